@@ -1,8 +1,14 @@
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use husako_bench::{LARGE_TS, MEDIUM_TS, SMALL_TS};
-use husako_compile_oxc::compile;
+use husako_compile_oxc::{CompileCache, compile};
 
 fn bench_compile(c: &mut Criterion) {
+    // `cached/*` measure a hit in the on-disk compile cache, as for every
+    // unchanged module after the first render.
+    let cache_dir =
+        std::env::temp_dir().join(format!("husako-bench-compile-{}", std::process::id()));
+    let cache = CompileCache::new(&cache_dir);
+
     let mut group = c.benchmark_group("compile");
     for (id, src) in [
         ("small", SMALL_TS),
@@ -12,8 +18,12 @@ fn bench_compile(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::from_parameter(id), src, |b, src| {
             b.iter(|| compile(src, "bench.ts").unwrap())
         });
+        group.bench_with_input(BenchmarkId::new("cached", id), src, |b, src| {
+            b.iter(|| cache.compile(src, "bench.ts").unwrap())
+        });
     }
     group.finish();
+    let _ = std::fs::remove_dir_all(&cache_dir);
 }
 
 criterion_group!(benches, bench_compile);
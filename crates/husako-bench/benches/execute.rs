@@ -17,6 +17,10 @@ fn make_opts(
         max_heap_mb: None,
         generated_types_dir,
         plugin_modules: HashMap::new(),
+        chart_renderer: None,
+        compile_cache_dir: None,
+        module_cache: None,
+        loaded_files: None,
     }
 }
 
@@ -1,31 +1,9 @@
 use std::collections::HashMap;
-use std::path::Path;
 
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
-use husako_bench::dts_fixtures_dir;
+use husako_bench::{dts_fixtures_dir, load_spec};
 use husako_dts::{GenerateOptions, generate};
 
-fn load_spec(dir: &Path, rel: &str) -> (String, serde_json::Value) {
-    let content = std::fs::read_to_string(dir.join(rel))
-        .unwrap_or_else(|e| panic!("failed to read {rel}: {e}"));
-    let value: serde_json::Value =
-        serde_json::from_str(&content).unwrap_or_else(|e| panic!("failed to parse {rel}: {e}"));
-    // Discovery key: strip the vendor prefix and ".json" suffix.
-    // e.g. "k8s/api/v1.json" → "api/v1"
-    //      "crds/cert-manager/apis/cert-manager.io/v1.json" → "apis/cert-manager.io/v1"
-    let key = rel
-        .strip_prefix("k8s/")
-        .or_else(|| {
-            // "crds/<vendor>/apis/..." → "apis/..."
-            let after_crds = rel.strip_prefix("crds/")?;
-            after_crds.find('/').map(|i| &after_crds[i + 1..])
-        })
-        .unwrap_or(rel)
-        .trim_end_matches(".json")
-        .to_owned();
-    (key, value)
-}
-
 // Measures: husako_dts::generate() — OpenAPI JSON → .d.ts + .js codegen only.
 //
 // NOT measured:
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use husako_bench::{dts_fixtures_dir, load_spec};
+use husako_core::{load_schema_store, validate::validate};
+use husako_dts::schema_store::generate_schema_store;
+
+// Measures: husako_core::load_schema_store() — the per-render cost of
+// loading `.husako/types/k8s/_schema.json` or `_schema.bin`, alone and
+// followed by validating one Deployment.
+//
+// The JSON store parses every schema up front. The binary store maps the
+// file and decodes only the schemas validation reaches, so its cost stays
+// flat as CRDs are added.
+//
+// Both stores are built from the full k8s + CRD fixture specs and written to
+// a temporary project before the benchmark loop.
+fn bench_schema_store(c: &mut Criterion) {
+    let dir = dts_fixtures_dir();
+    let specs: HashMap<String, serde_json::Value> = [
+        load_spec(&dir, "k8s/api/v1.json"),
+        load_spec(&dir, "k8s/apis/apps/v1.json"),
+        load_spec(&dir, "k8s/apis/batch/v1.json"),
+        load_spec(&dir, "k8s/apis/networking.k8s.io/v1.json"),
+        load_spec(&dir, "crds/cert-manager/apis/cert-manager.io/v1.json"),
+        load_spec(&dir, "crds/fluxcd/apis/source.toolkit.fluxcd.io/v1.json"),
+        load_spec(&dir, "crds/fluxcd/apis/kustomize.toolkit.fluxcd.io/v1.json"),
+        load_spec(&dir, "crds/fluxcd/apis/helm.toolkit.fluxcd.io/v2.json"),
+        load_spec(&dir, "crds/cnpg/apis/postgresql.cnpg.io/v1.json"),
+    ]
+    .into_iter()
+    .collect();
+    let store = generate_schema_store(&specs);
+
+    let tmp =
+        std::env::temp_dir().join(format!("husako-bench-schema-store-{}", std::process::id()));
+    let json_root = tmp.join("json");
+    let binary_root = tmp.join("binary");
+    write_store(&json_root, &store, false);
+    write_store(&binary_root, &store, true);
+
+    let doc = serde_json::json!([{
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"name": "bench"},
+        "spec": {
+            "replicas": 2,
+            "selector": {"matchLabels": {"app": "bench"}},
+            "template": {
+                "metadata": {"labels": {"app": "bench"}},
+                "spec": {
+                    "containers": [{
+                        "name": "app",
+                        "image": "nginx:1.27",
+                        "resources": {"limits": {"cpu": "500m", "memory": "128Mi"}}
+                    }]
+                }
+            }
+        }
+    }]);
+
+    let mut group = c.benchmark_group("schema_store");
+
+    for (id, root) in [("json", &json_root), ("binary", &binary_root)] {
+        group.bench_with_input(BenchmarkId::new("load", id), root, |b, root| {
+            b.iter(|| load_schema_store(root).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("load_validate", id), root, |b, root| {
+            b.iter(|| {
+                let store = load_schema_store(root).unwrap();
+                validate(&doc, Some(&store)).unwrap()
+            })
+        });
+    }
+
+    group.finish();
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+/// Write `_schema.json`, and with `binary` also `_schema.bin`, under `root`.
+fn write_store(root: &Path, store: &serde_json::Value, binary: bool) {
+    let k8s_dir = root.join(".husako/types/k8s");
+    std::fs::create_dir_all(&k8s_dir).unwrap();
+    std::fs::write(
+        k8s_dir.join("_schema.json"),
+        serde_json::to_string_pretty(store).unwrap(),
+    )
+    .unwrap();
+    if binary {
+        std::fs::write(
+            k8s_dir.join("_schema.bin"),
+            husako_dts::schema_bin::encode(store).unwrap(),
+        )
+        .unwrap();
+    }
+}
+
+criterion_group!(benches, bench_schema_store);
+criterion_main!(benches);
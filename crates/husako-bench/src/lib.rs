@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Returns the absolute path to `crates/husako-bench/fixtures/`.
 pub fn bench_fixtures_dir() -> PathBuf {
@@ -16,6 +16,29 @@ pub fn dts_fixtures_dir() -> PathBuf {
         .expect("dts fixtures dir not found")
 }
 
+/// Read the OpenAPI spec at `rel` under `dir` (e.g. [`dts_fixtures_dir`]),
+/// keyed by its discovery path.
+pub fn load_spec(dir: &Path, rel: &str) -> (String, serde_json::Value) {
+    let content = std::fs::read_to_string(dir.join(rel))
+        .unwrap_or_else(|e| panic!("failed to read {rel}: {e}"));
+    let value: serde_json::Value =
+        serde_json::from_str(&content).unwrap_or_else(|e| panic!("failed to parse {rel}: {e}"));
+    // Discovery key: strip the vendor prefix and ".json" suffix.
+    // e.g. "k8s/api/v1.json" → "api/v1"
+    //      "crds/cert-manager/apis/cert-manager.io/v1.json" → "apis/cert-manager.io/v1"
+    let key = rel
+        .strip_prefix("k8s/")
+        .or_else(|| {
+            // "crds/<vendor>/apis/..." → "apis/..."
+            let after_crds = rel.strip_prefix("crds/")?;
+            after_crds.find('/').map(|i| &after_crds[i + 1..])
+        })
+        .unwrap_or(rel)
+        .trim_end_matches(".json")
+        .to_owned();
+    (key, value)
+}
+
 /// Small TS: 1 ConfigMap, builtin imports only. (~10 lines)
 pub const SMALL_TS: &str = r#"
 import { metadata, build } from "husako";
@@ -8,6 +8,7 @@ use std::path::PathBuf;
 use std::process::ExitCode;
 
 use clap::Parser;
+use husako_config::{FetchContext, GitRef, credentials, network, shared_cache};
 use husako_core::{
     GenerateOptions, HusakoError, RenderOptions, ScaffoldOptions, TemplateName, TestOptions,
 };
@@ -20,6 +21,11 @@ const DEFAULT_K8S_VERSION: &str = "1.35";
 #[derive(Parser)]
 #[command(name = "husako")]
 struct Cli {
+    /// Never touch the network: serve everything from .husako/cache
+    /// (also enabled by HUSAKO_OFFLINE=1)
+    #[arg(long, global = true)]
+    offline: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -28,13 +34,16 @@ struct Cli {
 enum Commands {
     /// Render TypeScript to Kubernetes YAML
     Render {
-        /// Path to the TypeScript entry file, or an entry alias from husako.toml
-        file: String,
+        /// Path to the TypeScript entry file, or an entry alias from husako.toml.
+        /// Several entries can be given together with --watch
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<String>,
 
         /// Write output to a file or directory instead of stdout.
         /// If path ends with .yaml/.yml, writes to that file directly.
         /// Otherwise treated as a directory: writes <dir>/<name>.yaml
         /// where <name> is the entry alias or the entry file's stem.
+        /// Must be a directory when several entries are given.
         #[arg(long, short = 'o', value_name = "PATH")]
         output: Option<PathBuf>,
 
@@ -54,7 +63,7 @@ enum Commands {
         #[arg(short, long)]
         verbose: bool,
 
-        /// Watch for changes and re-render automatically
+        /// Watch the files the entries read and re-render automatically
         #[arg(short = 'w', long)]
         watch: bool,
     },
@@ -66,6 +75,15 @@ enum Commands {
         #[arg(long)]
         api_server: Option<String>,
 
+        /// Fetch specs from the cluster of a kubeconfig context
+        /// (defaults to $KUBECONFIG or ~/.kube/config)
+        #[arg(long, conflicts_with = "api_server")]
+        kubeconfig: Option<PathBuf>,
+
+        /// Kubeconfig context to use (defaults to the current context)
+        #[arg(long, conflicts_with = "api_server")]
+        context: Option<String>,
+
         /// Local directory with pre-fetched OpenAPI spec JSON files
         #[arg(long)]
         spec_dir: Option<PathBuf>,
@@ -78,6 +96,15 @@ enum Commands {
         /// Use when a git plugin's remote changed or a tag was moved upstream.
         #[arg(long)]
         no_incremental: bool,
+
+        /// Fail if husako.lock is out of date instead of updating it (for CI)
+        #[arg(long)]
+        locked: bool,
+
+        /// Like --locked, and fail if anything is missing from .husako/cache
+        /// instead of fetching it over the network
+        #[arg(long)]
+        frozen: bool,
     },
 
     /// Create a new project from a template
@@ -112,6 +139,12 @@ enum Commands {
         all: bool,
     },
 
+    /// Manage the shared cache used across projects
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
     /// List configured dependencies
     #[command(alias = "ls")]
     List {
@@ -185,6 +218,21 @@ enum Commands {
         /// Show what would be updated without applying
         #[arg(long)]
         dry_run: bool,
+
+        /// Move past semver requirements to the newest version, rewriting
+        /// them in husako.toml
+        #[arg(long)]
+        breaking: bool,
+
+        /// Report breaking schema changes between the current and new
+        /// version of each resource and chart
+        #[arg(long)]
+        diff: bool,
+
+        /// After updating, run `check` on every [entries] alias against the
+        /// regenerated types. Exits with code 7 if any entry fails.
+        #[arg(long, conflicts_with = "dry_run")]
+        check: bool,
     },
 
     /// Show project summary or dependency details
@@ -247,6 +295,18 @@ enum PluginAction {
         /// Local directory path
         #[arg(long)]
         path: Option<String>,
+
+        /// Pin a git plugin to a tag
+        #[arg(long, requires = "url", conflicts_with_all = ["version", "rev"])]
+        tag: Option<String>,
+
+        /// Pin a git plugin to a release version (tag `v<version>` or `<version>`)
+        #[arg(long, requires = "url", conflicts_with = "rev")]
+        version: Option<String>,
+
+        /// Pin a git plugin to a commit SHA
+        #[arg(long, requires = "url")]
+        rev: Option<String>,
     },
 
     /// Remove a plugin
@@ -259,13 +319,47 @@ enum PluginAction {
     List,
 }
 
+#[derive(clap::Subcommand)]
+enum CacheAction {
+    /// List shared cache entries, most recently used first
+    List,
+
+    /// Remove stale entries from the shared cache
+    #[command(group = clap::ArgGroup::new("limit").required(true).multiple(true))]
+    Prune {
+        /// Remove entries not used within this long (e.g. 30d, 12h, 2w)
+        #[arg(long, value_parser = parse_age, group = "limit")]
+        older_than: Option<std::time::Duration>,
+
+        /// Evict least recently used entries until the cache fits (e.g. 500M, 2G)
+        #[arg(long, value_parser = parse_size, group = "limit")]
+        max_size: Option<u64>,
+    },
+}
+
 #[tokio::main]
 async fn main() -> ExitCode {
     let cli = Cli::parse();
+    let network = match network::user_config_path() {
+        Some(path) => match network::load_network(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("{} {e}", style::error_prefix());
+                return ExitCode::from(2);
+            }
+        },
+        None => Default::default(),
+    };
+    let fetch = FetchContext {
+        offline: cli.offline || husako_config::offline_from_env(),
+        shared_cache: shared_cache::default_dir(),
+        network,
+        credentials: credentials::load_credentials(&cwd()),
+    };
 
     match cli.command {
         Commands::Render {
-            file,
+            files,
             output,
             allow_outside_root,
             timeout_ms,
@@ -275,30 +369,50 @@ async fn main() -> ExitCode {
         } => {
             let project_root = cwd();
 
-            let resolved = match resolve_entry(&file, &project_root) {
-                Ok(p) => p,
-                Err(msg) => {
+            let output_is_file = output
+                .as_ref()
+                .and_then(|out| out.extension())
+                .is_some_and(|e| e == "yaml" || e == "yml");
+            if files.len() > 1 {
+                let msg = if !watch {
+                    Some("several entries can only be rendered with --watch")
+                } else if output_is_file {
+                    Some("--output must be a directory when rendering several entries")
+                } else {
+                    None
+                };
+                if let Some(msg) = msg {
                     eprintln!("{} {msg}", style::error_prefix());
                     return ExitCode::from(2);
                 }
-            };
+            }
 
-            let abs_file = match resolved.canonicalize() {
-                Ok(p) => p,
-                Err(e) => {
-                    eprintln!(
-                        "{} could not resolve {}: {e}",
-                        style::error_prefix(),
-                        resolved.display()
-                    );
-                    return ExitCode::from(1);
+            let mut abs_files = Vec::with_capacity(files.len());
+            for file in &files {
+                let resolved = match resolve_entry(file, &project_root) {
+                    Ok(p) => p,
+                    Err(msg) => {
+                        eprintln!("{} {msg}", style::error_prefix());
+                        return ExitCode::from(2);
+                    }
+                };
+                match resolved.canonicalize() {
+                    Ok(p) => abs_files.push(p),
+                    Err(e) => {
+                        eprintln!(
+                            "{} could not resolve {}: {e}",
+                            style::error_prefix(),
+                            resolved.display()
+                        );
+                        return ExitCode::from(1);
+                    }
                 }
-            };
+            }
 
             // Pre-flight: if types are missing, auto-generate before rendering.
             let types_dir = project_root.join(".husako").join("types");
             if !types_dir.exists()
-                && let Err(e) = run_auto_generate(&project_root).await
+                && let Err(e) = run_auto_generate(&project_root, &fetch).await
             {
                 eprintln!("{} Could not generate types: {e}", style::error_prefix());
                 return ExitCode::from(exit_code(&e));
@@ -316,24 +430,30 @@ async fn main() -> ExitCode {
             };
 
             // Pre-compute the output file path (shared by watch and non-watch paths).
-            let watch_output: Option<PathBuf> = output.map(|out_path| {
-                if out_path
-                    .extension()
-                    .is_some_and(|e| e == "yaml" || e == "yml")
-                {
-                    out_path
-                } else {
-                    let name = derive_out_name(&file, &project_root);
-                    out_path.join(format!("{name}.yaml"))
-                }
-            });
+            let output_path = |file: &str| -> Option<PathBuf> {
+                output.as_ref().map(|out_path| {
+                    if output_is_file {
+                        out_path.clone()
+                    } else {
+                        let name = derive_out_name(file, &project_root);
+                        out_path.join(format!("{name}.yaml"))
+                    }
+                })
+            };
 
             if watch {
-                return run_watch_loop(&abs_file, &project_root, &options, watch_output).await;
+                let entries = files
+                    .iter()
+                    .zip(abs_files)
+                    .map(|(file, abs_file)| WatchEntry::new(abs_file, output_path(file)))
+                    .collect();
+                return run_watch_loop(entries, &project_root, options, &fetch).await;
             }
+            let abs_file = &abs_files[0];
+            let watch_output = output_path(&files[0]);
 
             // Non-watch: single render.
-            let source = match std::fs::read_to_string(&abs_file) {
+            let source = match std::fs::read_to_string(abs_file) {
                 Ok(s) => s,
                 Err(e) => {
                     eprintln!(
@@ -371,9 +491,13 @@ async fn main() -> ExitCode {
         }
         Commands::Generate {
             api_server,
+            kubeconfig,
+            context,
             spec_dir,
             skip_k8s,
             no_incremental,
+            locked,
+            frozen,
         } => {
             let project_root = cwd();
             let progress = IndicatifReporter::new();
@@ -384,13 +508,22 @@ async fn main() -> ExitCode {
                 Some(husako_openapi::FetchOptions {
                     source: husako_openapi::OpenApiSource::Directory(dir),
                     cache_dir: project_root.join(".husako/cache"),
-                    offline: true,
+                    context: fetch.clone(),
+                })
+            } else if kubeconfig.is_some() || context.is_some() {
+                Some(husako_openapi::FetchOptions {
+                    source: husako_openapi::OpenApiSource::Kubeconfig {
+                        path: kubeconfig,
+                        context,
+                    },
+                    cache_dir: project_root.join(".husako/cache"),
+                    context: fetch.clone(),
                 })
             } else {
                 api_server.map(|url| husako_openapi::FetchOptions {
                     source: husako_openapi::OpenApiSource::Url { base_url: url },
                     cache_dir: project_root.join(".husako/cache"),
-                    offline: false,
+                    context: fetch.clone(),
                 })
             };
 
@@ -409,6 +542,9 @@ async fn main() -> ExitCode {
                 config,
                 husako_version: env!("CARGO_PKG_VERSION").to_string(),
                 no_incremental,
+                locked,
+                frozen,
+                fetch,
             };
 
             match husako_core::generate(&options, &progress).await {
@@ -427,7 +563,7 @@ async fn main() -> ExitCode {
             directory,
             template,
         } => {
-            let k8s_version = latest_k8s_version();
+            let k8s_version = latest_k8s_version(&fetch);
 
             let options = ScaffoldOptions {
                 directory: directory.clone(),
@@ -464,7 +600,7 @@ async fn main() -> ExitCode {
         // --- M16 ---
         Commands::Init { template } => {
             let project_root = cwd();
-            let k8s_version = latest_k8s_version();
+            let k8s_version = latest_k8s_version(&fetch);
 
             let options = husako_core::InitOptions {
                 directory: project_root,
@@ -616,7 +752,11 @@ async fn main() -> ExitCode {
         } => {
             let project_root = cwd();
 
-            match resolve_add_target(url, extra, name, release, version, tag, branch, path).await {
+            match resolve_add_target(
+                url, extra, name, release, version, tag, branch, path, &fetch,
+            )
+            .await
+            {
                 Ok(None) => ExitCode::SUCCESS,
                 Ok(Some(result)) => {
                     let target = match &result {
@@ -646,7 +786,7 @@ async fn main() -> ExitCode {
                                 style::dim(&format_source_detail(&result))
                             );
                             eprintln!();
-                            if let Err(e) = run_auto_generate(&project_root).await {
+                            if let Err(e) = run_auto_generate(&project_root, &fetch).await {
                                 eprintln!(
                                     "{} Type generation failed: {e}",
                                     style::warning_prefix()
@@ -720,7 +860,7 @@ async fn main() -> ExitCode {
                         result.section
                     );
                     eprintln!();
-                    if let Err(e) = run_auto_generate(&project_root).await {
+                    if let Err(e) = run_auto_generate(&project_root, &fetch).await {
                         eprintln!("{} Type generation failed: {e}", style::warning_prefix());
                     }
                     ExitCode::SUCCESS
@@ -737,7 +877,7 @@ async fn main() -> ExitCode {
             let project_root = cwd();
             let progress = IndicatifReporter::new();
 
-            match husako_core::check_outdated(&project_root, &progress).await {
+            match husako_core::check_outdated(&project_root, &progress, &fetch).await {
                 Ok(entries) => {
                     if entries.is_empty() {
                         eprintln!("No versioned dependencies found");
@@ -749,21 +889,33 @@ async fn main() -> ExitCode {
                         eprintln!("{} All dependencies are up to date", style::check_mark());
                     } else {
                         eprintln!(
-                            "{:<16} {:<10} {:<12} {:<10} {:<10}",
+                            "{:<16} {:<10} {:<12} {:<14} {:<10} {:<12} {:<10}",
                             style::bold("Name"),
                             style::bold("Kind"),
                             style::bold("Source"),
+                            style::bold("Requirement"),
                             style::bold("Current"),
+                            style::bold("Compatible"),
                             style::bold("Latest"),
                         );
                         for entry in outdated {
                             let latest = entry.latest.as_deref().unwrap_or("?");
+                            // Exact pins have no range to stay within
+                            let (requirement, compatible) = match &entry.requirement {
+                                Some(requirement) => (
+                                    requirement.as_str(),
+                                    entry.compatible.as_deref().unwrap_or("?"),
+                                ),
+                                None => ("-", "-"),
+                            };
                             eprintln!(
-                                "{:<16} {:<10} {:<12} {:<10} {:<10} {}",
+                                "{:<16} {:<10} {:<12} {:<14} {:<10} {:<12} {:<10} {}",
                                 style::dep_name(&entry.name),
                                 entry.kind,
                                 entry.source_type,
+                                requirement,
                                 entry.current,
+                                compatible,
                                 latest,
                                 style::arrow_mark(),
                             );
@@ -784,6 +936,9 @@ async fn main() -> ExitCode {
             resources_only,
             charts_only,
             dry_run,
+            breaking,
+            diff,
+            check,
         } => {
             let project_root = cwd();
             let progress = IndicatifReporter::new();
@@ -794,22 +949,46 @@ async fn main() -> ExitCode {
                 resources_only,
                 charts_only,
                 dry_run,
+                breaking,
+                diff,
+                check,
                 husako_version: env!("CARGO_PKG_VERSION").to_string(),
+                fetch,
             };
 
             match husako_core::update_dependencies(&options, &progress).await {
                 Ok(result) => {
                     for entry in &result.updated {
                         let prefix = if dry_run { "Would update" } else { "Updated" };
+                        let detail = match &entry.requirement {
+                            Some(requirement) => format!("{}, {requirement}", entry.kind),
+                            None => entry.kind.to_string(),
+                        };
                         eprintln!(
-                            "{} {prefix} {}: {} {} {} ({})",
+                            "{} {prefix} {}: {} {} {} ({detail})",
                             style::check_mark(),
                             style::dep_name(&entry.name),
                             entry.old_version,
                             style::arrow_mark(),
                             entry.new_version,
-                            entry.kind
                         );
+                        match &entry.schema_diff {
+                            Some(Ok(changes)) if changes.is_empty() => {
+                                eprintln!("    {}", style::dim("no breaking schema changes"));
+                            }
+                            Some(Ok(changes)) => {
+                                for change in changes {
+                                    eprintln!("    {} {change}", style::cross_mark());
+                                }
+                            }
+                            Some(Err(e)) => {
+                                eprintln!(
+                                    "    {} could not compare schemas: {e}",
+                                    style::warning_prefix()
+                                );
+                            }
+                            None => {}
+                        }
                     }
                     for name in &result.skipped {
                         eprintln!(
@@ -827,7 +1006,24 @@ async fn main() -> ExitCode {
                     {
                         eprintln!("No versioned dependencies found");
                     }
-                    ExitCode::SUCCESS
+                    let mut check_failed = false;
+                    for (alias, outcome) in &result.checks {
+                        match outcome {
+                            Ok(count) => eprintln!(
+                                "{} check {alias}: {count} resource(s) OK",
+                                style::check_mark()
+                            ),
+                            Err(e) => {
+                                check_failed = true;
+                                eprintln!("{} check {alias}: {e}", style::cross_mark());
+                            }
+                        }
+                    }
+                    if check_failed {
+                        ExitCode::from(7)
+                    } else {
+                        ExitCode::SUCCESS
+                    }
                 }
                 Err(e) => {
                     eprintln!("{} {e}", style::error_prefix());
@@ -1035,13 +1231,88 @@ async fn main() -> ExitCode {
                 }
             }
         }
+        Commands::Cache { action } => {
+            let Some(dir) = fetch.shared_cache else {
+                eprintln!(
+                    "Shared cache is disabled ({} is empty)",
+                    shared_cache::CACHE_DIR_ENV
+                );
+                return ExitCode::SUCCESS;
+            };
+
+            match action {
+                CacheAction::List => match shared_cache::list(&dir) {
+                    Ok(entries) if entries.is_empty() => {
+                        eprintln!("Shared cache at {} is empty", dir.display());
+                        ExitCode::SUCCESS
+                    }
+                    Ok(entries) => {
+                        let total: u64 = entries.iter().map(|e| e.size).sum();
+                        eprintln!(
+                            "{} {}",
+                            style::bold("Shared cache:"),
+                            style::dim(&dir.display().to_string())
+                        );
+                        for entry in &entries {
+                            eprintln!(
+                                "  {:<48} {:>10}  {}",
+                                style::dep_name(&entry.key),
+                                format_size(entry.size),
+                                style::dim(&format_age(entry.last_used))
+                            );
+                        }
+                        eprintln!("{} entries, {}", entries.len(), format_size(total));
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("{} {e}", style::error_prefix());
+                        ExitCode::from(1)
+                    }
+                },
+                CacheAction::Prune {
+                    older_than,
+                    max_size,
+                } => match shared_cache::prune(&dir, older_than, max_size) {
+                    Ok(result) if result.entries_removed == 0 && result.bytes_freed == 0 => {
+                        eprintln!("Nothing to prune");
+                        ExitCode::SUCCESS
+                    }
+                    Ok(result) => {
+                        eprintln!(
+                            "{} Removed {} entries ({})",
+                            style::check_mark(),
+                            result.entries_removed,
+                            format_size(result.bytes_freed)
+                        );
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("{} {e}", style::error_prefix());
+                        ExitCode::from(1)
+                    }
+                },
+            }
+        }
         Commands::Plugin { action } => {
             let project_root = cwd();
 
             match action {
-                PluginAction::Add { name, url, path } => {
+                PluginAction::Add {
+                    name,
+                    url,
+                    path,
+                    tag,
+                    version,
+                    rev,
+                } => {
                     let source = if let Some(url) = url {
-                        husako_config::PluginSource::Git { url, path: None }
+                        husako_config::PluginSource::Git {
+                            url,
+                            path: None,
+                            tag,
+                            version,
+                            rev,
+                        }
                     } else if let Some(path) = path {
                         husako_config::PluginSource::Path { path }
                     } else {
@@ -1069,7 +1340,16 @@ async fn main() -> ExitCode {
                     }
 
                     let source_detail = match &source {
-                        husako_config::PluginSource::Git { url, .. } => url.clone(),
+                        husako_config::PluginSource::Git {
+                            url,
+                            tag,
+                            version,
+                            rev,
+                            ..
+                        } => match tag.as_ref().or(version.as_ref()).or(rev.as_ref()) {
+                            Some(pin) => format!("{url} @ {pin}"),
+                            None => url.clone(),
+                        },
                         husako_config::PluginSource::Path { path } => path.clone(),
                     };
                     eprintln!(
@@ -1079,7 +1359,7 @@ async fn main() -> ExitCode {
                         style::dim(&source_detail),
                     );
                     eprintln!();
-                    if let Err(e) = run_auto_generate(&project_root).await {
+                    if let Err(e) = run_auto_generate(&project_root, &fetch).await {
                         eprintln!("{} Type generation failed: {e}", style::warning_prefix());
                     }
                     ExitCode::SUCCESS
@@ -1121,7 +1401,7 @@ async fn main() -> ExitCode {
                             style::dep_name(&name)
                         );
                         eprintln!();
-                        if let Err(e) = run_auto_generate(&project_root).await {
+                        if let Err(e) = run_auto_generate(&project_root, &fetch).await {
                             eprintln!("{} Type generation failed: {e}", style::warning_prefix());
                         }
                     } else {
@@ -1405,10 +1685,11 @@ fn derive_out_name(file_arg: &str, project_root: &std::path::Path) -> String {
 /// Returns `Ok(version)` on success, `Err(None)` on Escape (abort),
 /// Fetches the latest Kubernetes version from GitHub.
 /// Falls back to `DEFAULT_K8S_VERSION` silently on any network failure.
-fn latest_k8s_version() -> String {
+fn latest_k8s_version(fetch: &FetchContext) -> String {
     let result = tokio::task::block_in_place(|| {
-        tokio::runtime::Handle::current()
-            .block_on(husako_core::version_check::discover_recent_releases(1, 0))
+        tokio::runtime::Handle::current().block_on(
+            husako_core::version_check::discover_recent_releases(1, 0, fetch),
+        )
     });
     match result {
         Ok(versions) if !versions.is_empty() => versions.into_iter().next().unwrap(),
@@ -1419,7 +1700,10 @@ fn latest_k8s_version() -> String {
 /// Run generate with default options derived from husako.toml.
 /// Returns Ok(()) on success, or a HusakoError on failure.
 /// Used by commands that need fresh types after config changes.
-async fn run_auto_generate(project_root: &std::path::Path) -> Result<(), HusakoError> {
+async fn run_auto_generate(
+    project_root: &std::path::Path,
+    fetch: &FetchContext,
+) -> Result<(), HusakoError> {
     let progress = IndicatifReporter::new();
     let config = husako_config::load(project_root).ok().flatten();
     let options = GenerateOptions {
@@ -1429,6 +1713,9 @@ async fn run_auto_generate(project_root: &std::path::Path) -> Result<(), HusakoE
         config,
         husako_version: env!("CARGO_PKG_VERSION").to_string(),
         no_incremental: false,
+        locked: false,
+        frozen: false,
+        fetch: fetch.clone(),
     };
     husako_core::generate(&options, &progress).await.map(|_| ())
 }
@@ -1456,6 +1743,7 @@ async fn resolve_add_target(
     tag: Option<String>,
     branch: Option<String>,
     path_override: Option<String>,
+    fetch: &FetchContext,
 ) -> Result<Option<AddResult>, String> {
     use husako_config::{ChartSource, SchemaSource};
     use url_detect::{SourceKind, UrlDetected, detect_url};
@@ -1476,9 +1764,11 @@ async fn resolve_add_target(
         match detect_url(&input) {
             Some(UrlDetected::ArtifactHub { package }) => {
                 let dep_name = name.unwrap_or_else(|| after_slash(&package));
-                let ver = husako_core::version_check::discover_latest_artifacthub(&package, prefix)
-                    .await
-                    .map_err(|e| e.to_string())?;
+                let ver = husako_core::version_check::discover_latest_artifacthub(
+                    &package, prefix, fetch,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
                 Ok(Some(AddResult::Chart {
                     name: dep_name,
                     source: ChartSource::ArtifactHub {
@@ -1493,7 +1783,7 @@ async fn resolve_add_target(
                 let ver = if let Some(v) = version {
                     v
                 } else {
-                    husako_core::version_check::discover_latest_oci(&reference)
+                    husako_core::version_check::discover_latest_oci(&reference, fetch)
                         .await
                         .map_err(|e| e.to_string())?
                         .ok_or_else(|| {
@@ -1520,12 +1810,14 @@ async fn resolve_add_target(
                 let dep_name = name.unwrap_or_else(|| repo_name(&repo));
                 let tempdir = tempfile::tempdir().map_err(|e| e.to_string())?;
 
+                let look_in = path_override
+                    .as_deref()
+                    .or(sub_path.as_deref())
+                    .unwrap_or(".");
+
                 if let Some(br) = effective_branch {
-                    git_clone_sparse(&repo, &br, tempdir.path()).await?;
-                    let look_in = path_override
-                        .as_deref()
-                        .or(sub_path.as_deref())
-                        .unwrap_or(".");
+                    git_fetch_path(&repo, GitRef::Branch(&br), look_in, tempdir.path(), fetch)
+                        .await?;
                     let kind = url_detect::detect_git_kind(tempdir.path(), look_in)?;
                     let path = path_override
                         .or(sub_path)
@@ -1535,7 +1827,9 @@ async fn resolve_add_target(
                             name: dep_name,
                             source: SchemaSource::Git {
                                 repo,
-                                tag: br,
+                                tag: String::new(),
+                                branch: Some(br),
+                                rev: None,
                                 path,
                             },
                         })),
@@ -1543,7 +1837,9 @@ async fn resolve_add_target(
                             name: dep_name,
                             source: ChartSource::Git {
                                 repo,
-                                tag: br,
+                                tag: String::new(),
+                                branch: Some(br),
+                                rev: None,
                                 path,
                             },
                         })),
@@ -1552,17 +1848,20 @@ async fn resolve_add_target(
                     let resolved_tag = if let Some(t) = tag {
                         t
                     } else {
-                        husako_core::version_check::discover_latest_git_tag(&repo, prefix)
+                        husako_core::version_check::discover_latest_git_tag(&repo, prefix, fetch)
                             .map_err(|e| e.to_string())?
                             .ok_or_else(|| {
                                 format!("no release tags found in '{repo}'; use --tag or --branch")
                             })?
                     };
-                    git_clone_sparse(&repo, &resolved_tag, tempdir.path()).await?;
-                    let look_in = path_override
-                        .as_deref()
-                        .or(sub_path.as_deref())
-                        .unwrap_or(".");
+                    git_fetch_path(
+                        &repo,
+                        GitRef::Tag(&resolved_tag),
+                        look_in,
+                        tempdir.path(),
+                        fetch,
+                    )
+                    .await?;
                     let kind = url_detect::detect_git_kind(tempdir.path(), look_in)?;
                     let path = path_override
                         .or(sub_path)
@@ -1573,6 +1872,8 @@ async fn resolve_add_target(
                             source: SchemaSource::Git {
                                 repo,
                                 tag: resolved_tag,
+                                branch: None,
+                                rev: None,
                                 path,
                             },
                         })),
@@ -1581,6 +1882,8 @@ async fn resolve_add_target(
                             source: ChartSource::Git {
                                 repo,
                                 tag: resolved_tag,
+                                branch: None,
+                                rev: None,
                                 path,
                             },
                         })),
@@ -1599,6 +1902,7 @@ async fn resolve_add_target(
                     &repo,
                     &chart_name,
                     prefix,
+                    fetch,
                 )
                 .await
                 .map_err(|e| e.to_string())?;
@@ -1612,6 +1916,22 @@ async fn resolve_add_target(
                 }))
             }
 
+            Some(UrlDetected::Manifest { url }) => {
+                let cache_dir = cwd().join(".husako/cache");
+                let (_, digest) =
+                    husako_core::schema_source::fetch_manifest(&url, None, &cache_dir, fetch)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                let dep_name = name.unwrap_or_else(|| manifest_name(&url));
+                Ok(Some(AddResult::Resource {
+                    name: dep_name,
+                    source: SchemaSource::Url {
+                        url,
+                        sha256: Some(digest),
+                    },
+                }))
+            }
+
             Some(UrlDetected::LocalPath { path }) => {
                 let kind = url_detect::detect_local_kind(&path)?;
                 let dep_name = name.unwrap_or_else(|| file_stem(&path));
@@ -1620,6 +1940,12 @@ async fn resolve_add_target(
                         name: dep_name,
                         source: SchemaSource::File { path },
                     })),
+                    SourceKind::Chart if url_detect::is_local_chart(&path) => {
+                        Ok(Some(AddResult::Chart {
+                            name: dep_name,
+                            source: ChartSource::Chart { path },
+                        }))
+                    }
                     SourceKind::Chart => Ok(Some(AddResult::Chart {
                         name: dep_name,
                         source: ChartSource::File { path },
@@ -1636,22 +1962,40 @@ async fn resolve_add_target(
     }
 }
 
-/// Shallow-clone a git repo at a specific tag or branch (depth 1).
-async fn git_clone_sparse(repo: &str, tag: &str, dir: &std::path::Path) -> Result<(), String> {
-    let output = tokio::process::Command::new("git")
-        .args(["clone", "--depth", "1", "--branch", tag, repo])
-        .arg(dir)
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::piped())
-        .output()
-        .await
-        .map_err(|e| format!("git clone: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("git clone failed for '{repo}' @ '{tag}': {stderr}"));
+/// Shallow-fetch only `path` of a git repo at a specific tag or branch.
+async fn git_fetch_path(
+    repo: &str,
+    git_ref: GitRef<'_>,
+    path: &str,
+    dir: &std::path::Path,
+    fetch: &FetchContext,
+) -> Result<(), String> {
+    if fetch.offline {
+        return Err(format!(
+            "offline mode: cannot clone '{repo}' without network access"
+        ));
+    }
+    let cache_dir = cwd().join(".husako/cache");
+    match husako_git::fetch(
+        repo,
+        Some(git_ref),
+        Some(path),
+        &cache_dir,
+        dir,
+        None,
+        fetch,
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(husako_git::GitError::PathNotFound(_)) => Err(format!(
+            "path '{path}' not found in '{repo}' at {git_ref}; check the path or use --path"
+        )),
+        Err(e) => Err(format!(
+            "git clone failed for '{repo}' @ '{}': {e}",
+            git_ref.name()
+        )),
     }
-    Ok(())
 }
 
 fn format_source_detail(result: &AddResult) -> String {
@@ -1664,10 +2008,11 @@ fn format_source_detail(result: &AddResult) -> String {
             format!("release  {version}")
         }
         AddResult::Resource {
-            source: SchemaSource::Git { repo, tag, .. },
+            source: source @ SchemaSource::Git { repo, .. },
             ..
         } => {
-            format!("git  {repo} @ {tag}")
+            let git_ref = source.git_ref().map(|r| r.name()).unwrap_or_default();
+            format!("git  {repo} @ {git_ref}")
         }
         AddResult::Resource {
             source: SchemaSource::File { path },
@@ -1675,6 +2020,25 @@ fn format_source_detail(result: &AddResult) -> String {
         } => {
             format!("file  {path}")
         }
+        AddResult::Resource {
+            source: SchemaSource::Url { url, .. },
+            ..
+        } => {
+            format!("url  {url}")
+        }
+        AddResult::Resource {
+            source: SchemaSource::Chart { chart, .. },
+            ..
+        } => {
+            format!("chart  {chart}")
+        }
+        AddResult::Resource {
+            source: SchemaSource::Cluster { context, .. },
+            ..
+        } => match context {
+            Some(context) => format!("cluster  {context}"),
+            None => "cluster  current context".to_string(),
+        },
         AddResult::Chart {
             source: ChartSource::ArtifactHub { package, version },
             ..
@@ -1699,10 +2063,11 @@ fn format_source_detail(result: &AddResult) -> String {
             format!("oci  {reference} @ {version}")
         }
         AddResult::Chart {
-            source: ChartSource::Git { repo, tag, .. },
+            source: source @ ChartSource::Git { repo, .. },
             ..
         } => {
-            format!("git  {repo} @ {tag}")
+            let git_ref = source.git_ref().map(|r| r.name()).unwrap_or_default();
+            format!("git  {repo} @ {git_ref}")
         }
         AddResult::Chart {
             source: ChartSource::File { path },
@@ -1710,6 +2075,12 @@ fn format_source_detail(result: &AddResult) -> String {
         } => {
             format!("file  {path}")
         }
+        AddResult::Chart {
+            source: ChartSource::Chart { path },
+            ..
+        } => {
+            format!("chart  {path}")
+        }
     }
 }
 
@@ -1743,6 +2114,19 @@ fn repo_name(url: &str) -> String {
         .to_string()
 }
 
+/// Dependency name for a manifest URL: the repository of a
+/// `/<org>/<repo>/releases/download/...` asset, else the file stem.
+fn manifest_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    if let Some((repo, _)) = path.split_once("/releases/download/") {
+        return repo_name(repo);
+    }
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.trim_end_matches(".yaml")
+        .trim_end_matches(".yml")
+        .to_string()
+}
+
 fn file_stem(path: &str) -> String {
     let p = std::path::Path::new(path);
     if p.is_dir() {
@@ -1768,6 +2152,59 @@ fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Parse a `--older-than` age such as `30d`, `12h`, `2w`, `90m` or `45s`.
+fn parse_age(value: &str) -> Result<std::time::Duration, String> {
+    let value = value.trim();
+    let split = value.len() - value.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid age '{value}' (expected e.g. 30d, 12h, 2w)"))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => {
+            return Err(format!(
+                "invalid age '{value}' (expected e.g. 30d, 12h, 2w)"
+            ));
+        }
+    };
+    Ok(std::time::Duration::from_secs(amount.saturating_mul(secs)))
+}
+
+/// Parse a `--max-size` such as `500M`, `2G`, `100K` or a plain byte count.
+/// Units are decimal, matching how sizes are printed.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.len() - value.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid size '{value}' (expected e.g. 500M, 2G)"))?;
+    let factor = match unit.to_ascii_uppercase().trim_end_matches('B') {
+        "" => 1,
+        "K" => 1_000,
+        "M" => 1_000_000,
+        "G" => 1_000_000_000,
+        _ => return Err(format!("invalid size '{value}' (expected e.g. 500M, 2G)")),
+    };
+    Ok(amount.saturating_mul(factor))
+}
+
+/// Describe how long ago `time` was, e.g. `3d ago`.
+fn format_age(time: std::time::SystemTime) -> String {
+    let secs = time.elapsed().map(|d| d.as_secs()).unwrap_or(0);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3_600 => format!("{}m ago", secs / 60),
+        3_600..86_400 => format!("{}h ago", secs / 3_600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
 // ── Watch mode ──────────────────────────────────────────────────────────────
 
 /// Returns `true` if `path` is under `project_root` but not inside an excluded
@@ -1795,33 +2232,64 @@ fn write_output_atomic(content: &str, dest: &std::path::Path) -> std::io::Result
     Ok(())
 }
 
-/// Execute a single render cycle: re-read source, call `husako_core::render`,
-/// write output (atomically to file, or directly to stdout).
-/// Returns `true` on success, `false` on any error (error is printed to stderr).
-async fn render_once(
-    abs_file: &std::path::Path,
-    options: &RenderOptions,
-    watch_output: &Option<PathBuf>,
-) -> bool {
-    let source = match std::fs::read_to_string(abs_file) {
+/// An entry `husako render --watch` keeps rendering.
+struct WatchEntry {
+    /// Canonical path of the entry file.
+    file: PathBuf,
+    output: Option<PathBuf>,
+    /// What the last render read, see [`husako_core::RenderSession::dependencies`].
+    dependencies: std::collections::BTreeSet<PathBuf>,
+    failed: bool,
+}
+
+impl WatchEntry {
+    fn new(file: PathBuf, output: Option<PathBuf>) -> Self {
+        Self {
+            file,
+            output,
+            dependencies: Default::default(),
+            failed: false,
+        }
+    }
+
+    /// Whether a change to `path` calls for rendering the entry again: the
+    /// path is a file it read or lies inside a chart directory it read.
+    ///
+    /// After a failed render any relevant change qualifies, since the failure
+    /// may be an import of a file that doesn't exist yet.
+    fn affected_by(&self, path: &std::path::Path, project_root: &std::path::Path) -> bool {
+        (self.failed && is_relevant_path(path, project_root))
+            || self.dependencies.iter().any(|dep| path.starts_with(dep))
+    }
+}
+
+/// Execute a single render cycle of `entry`: re-read source, render it in
+/// `session`, write output (atomically to file, or directly to stdout) and
+/// record what the render read. Errors are printed to stderr.
+async fn render_once(entry: &mut WatchEntry, session: &mut husako_core::RenderSession) {
+    let source = match std::fs::read_to_string(&entry.file) {
         Ok(s) => s,
         Err(e) => {
             eprintln!(
                 "{} could not read {}: {e}",
                 style::error_prefix(),
-                abs_file.display()
+                entry.file.display()
             );
-            return false;
+            entry.dependencies = [entry.file.clone()].into();
+            entry.failed = true;
+            return;
         }
     };
-    let filename = abs_file.to_string_lossy();
+    let filename = entry.file.to_string_lossy().into_owned();
     let progress = IndicatifReporter::new();
-    match husako_core::render(&source, &filename, options, &progress).await {
+    let result = session.render(&source, &filename, &progress).await;
+    entry.dependencies = session.dependencies().clone();
+    entry.failed = match result {
         Ok(yaml) => {
-            if let Some(file_path) = watch_output {
+            if let Some(file_path) = &entry.output {
                 if let Err(e) = write_output_atomic(&yaml, file_path) {
                     eprintln!("{} {e}", style::error_prefix());
-                    return false;
+                    return;
                 }
                 eprintln!(
                     "{} Written to {}",
@@ -1831,16 +2299,33 @@ async fn render_once(
             } else {
                 print!("{yaml}");
             }
-            true
+            false
         }
         Err(e) => {
             eprintln!("{} {e}", style::error_prefix());
-            false
+            true
         }
-    }
+    };
+}
+
+/// The files `husako gen` reads, canonicalized to match watcher events.
+fn generate_inputs(project_root: &std::path::Path) -> Vec<PathBuf> {
+    husako_core::generate_inputs(project_root)
+        .into_iter()
+        .map(|p| p.canonicalize().unwrap_or(p))
+        .collect()
 }
 
-/// Watch loop: re-render on every file-system change inside `project_root`.
+/// Watch loop: re-render `entries` when a file one of them read changes.
+///
+/// Each render records the entry's dependencies: the files it imported, the
+/// charts `helmTemplate()` rendered and the schema store. A change to any
+/// other file is ignored. A change to `husako.toml` or another input of
+/// `husako gen` re-runs an incremental generate first and then re-renders
+/// every entry. Dependencies outside `project_root` are watched as well.
+///
+/// Renders share a [`husako_core::RenderSession`], so only the modules whose
+/// files changed are loaded again, and the schema store is kept loaded.
 ///
 /// Uses `tokio::sync::Notify` to coalesce events: `notify_one()` is idempotent
 /// when a permit is already queued, so bursts of events collapse to one
@@ -1850,14 +2335,37 @@ async fn render_once(
 /// if both arms are simultaneously ready `select!` may pick the change arm and
 /// permanently discard the shutdown notification.
 async fn run_watch_loop(
-    abs_file: &std::path::Path,
+    mut entries: Vec<WatchEntry>,
     project_root: &std::path::Path,
-    options: &RenderOptions,
-    watch_output: Option<PathBuf>,
+    options: RenderOptions,
+    fetch: &FetchContext,
 ) -> ExitCode {
-    use std::sync::Arc;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
     use tokio::sync::Notify;
 
+    let project_root = project_root
+        .canonicalize()
+        .unwrap_or_else(|_| project_root.to_path_buf());
+    let mut session = husako_core::RenderSession::new(options);
+    // Writes of our own output never trigger a render
+    let outputs: HashSet<PathBuf> = entries
+        .iter()
+        .filter_map(|e| e.output.as_ref())
+        .map(|out| {
+            let out = project_root.join(out);
+            match (
+                out.parent().and_then(|p| p.canonicalize().ok()),
+                out.file_name(),
+            ) {
+                (Some(dir), Some(name)) => dir.join(name),
+                _ => out,
+            }
+        })
+        .collect();
+    // Where a regenerate writes, already rendered against once it finishes
+    let types_dir = project_root.join(".husako/types");
+
     // --- Shutdown: register SIGINT listener exactly once ----
     let shutdown = Arc::new(Notify::new());
     let shutdown_clone = Arc::clone(&shutdown);
@@ -1869,7 +2377,9 @@ async fn run_watch_loop(
     // --- File change signal ---------------------------------
     let change_signal = Arc::new(Notify::new());
     let signal_clone = Arc::clone(&change_signal);
-    let root_clone = project_root.to_path_buf();
+    // Paths changed since the last render, whose modules the session drops
+    let changed: Arc<Mutex<Vec<PathBuf>>> = Arc::default();
+    let changed_clone = Arc::clone(&changed);
 
     use notify::{EventKind, RecursiveMode, Watcher, event::ModifyKind, recommended_watcher};
 
@@ -1882,11 +2392,12 @@ async fn run_watch_loop(
                     | EventKind::Remove(_)
                     | EventKind::Modify(ModifyKind::Name(_))
             );
-            if kind_relevant {
-                let path_relevant = event.paths.iter().any(|p| is_relevant_path(p, &root_clone));
-                if path_relevant {
-                    signal_clone.notify_one(); // idempotent: at most one pending
-                }
+            if kind_relevant && !event.paths.is_empty() {
+                changed_clone
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .extend(event.paths);
+                signal_clone.notify_one(); // idempotent: at most one pending
             }
         }
     }) {
@@ -1900,13 +2411,38 @@ async fn run_watch_loop(
         }
     };
 
-    if let Err(e) = watcher.watch(project_root, RecursiveMode::Recursive) {
+    if let Err(e) = watcher.watch(&project_root, RecursiveMode::Recursive) {
         eprintln!("{} {e}", style::error_prefix());
         return ExitCode::from(1);
     }
+    // Directories outside the root watched for dependencies or gen inputs
+    let mut watched_outside: HashSet<PathBuf> = HashSet::new();
+    let mut watch_outside = |paths: &mut dyn Iterator<Item = &PathBuf>| {
+        for path in paths.filter(|p| !p.starts_with(&project_root)) {
+            let (dir, mode) = if path.is_dir() {
+                (path.as_path(), RecursiveMode::Recursive)
+            } else {
+                let Some(parent) = path.parent() else {
+                    continue;
+                };
+                (parent, RecursiveMode::NonRecursive)
+            };
+            if watched_outside.insert(dir.to_path_buf()) {
+                let _ = watcher.watch(dir, mode);
+            }
+        }
+    };
 
+    let mut inputs = generate_inputs(&project_root);
     eprintln!("Watching for changes. Press Ctrl+C to stop.");
-    render_once(abs_file, options, &watch_output).await;
+    for entry in &mut entries {
+        render_once(entry, &mut session).await;
+    }
+    watch_outside(
+        &mut inputs
+            .iter()
+            .chain(entries.iter().flat_map(|e| &e.dependencies)),
+    );
 
     loop {
         tokio::select! {
@@ -1918,8 +2454,51 @@ async fn run_watch_loop(
             _ = change_signal.notified() => {
                 // Debounce: coalesce rapid saves (e.g. editor write + rename)
                 tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-                eprintln!("Change detected, re-rendering...");
-                render_once(abs_file, options, &watch_output).await;
+                let mut paths = std::mem::take(&mut *changed.lock().unwrap_or_else(|e| e.into_inner()));
+                paths.retain(|p| !outputs.contains(p));
+
+                let regenerate = paths
+                    .iter()
+                    .any(|p| inputs.iter().any(|input| p.starts_with(input)));
+                let due: Vec<usize> = if regenerate {
+                    (0..entries.len()).collect()
+                } else {
+                    (0..entries.len())
+                        .filter(|&i| paths.iter().any(|p| entries[i].affected_by(p, &project_root)))
+                        .collect()
+                };
+                if due.is_empty() {
+                    continue;
+                }
+
+                session.invalidate(paths.iter().map(PathBuf::as_path));
+                if regenerate {
+                    eprintln!("Configuration changed, regenerating types...");
+                    let result = run_auto_generate(&project_root, fetch).await;
+                    inputs = generate_inputs(&project_root);
+                    if let Err(e) = result {
+                        eprintln!("{} Could not generate types: {e}", style::error_prefix());
+                        continue;
+                    }
+                } else {
+                    eprintln!("Change detected, re-rendering...");
+                }
+                for i in due {
+                    render_once(&mut entries[i], &mut session).await;
+                }
+                if regenerate {
+                    // Drop the events of gen's own writes; edits made while it
+                    // ran are still pending
+                    changed
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .retain(|p| !p.starts_with(&types_dir));
+                }
+                watch_outside(
+                    &mut inputs
+                        .iter()
+                        .chain(entries.iter().flat_map(|e| &e.dependencies)),
+                );
             }
         }
     }
@@ -1945,10 +2524,15 @@ fn exit_code(err: &HusakoError) -> u8 {
         HusakoError::Emit(_) => 7,
         HusakoError::Validation(_) => 7,
         HusakoError::Dts(_) => 5,
+        HusakoError::OpenApi(husako_openapi::OpenApiError::NoCachedData(_))
+        | HusakoError::Chart(husako_helm::HelmError::Offline(_))
+        | HusakoError::Offline(_) => 9,
         HusakoError::OpenApi(_) => 6,
         HusakoError::Chart(_) => 6,
         HusakoError::Config(_) => 2,
         HusakoError::GenerateIo(_) => 1,
+        HusakoError::LockOutOfDate(_) => 8,
+        HusakoError::FrozenFetch(_) => 9,
     }
 }
 
@@ -1958,7 +2542,45 @@ mod tests {
     use std::sync::Arc;
     use std::time::Duration;
 
-    use super::is_relevant_path;
+    use super::{is_relevant_path, manifest_name, parse_age, parse_size};
+
+    // ── manifest_name ───────────────────────────────────────────────────────
+
+    #[test]
+    fn manifest_name_prefers_release_repo() {
+        assert_eq!(
+            manifest_name("https://github.com/example/operator/releases/download/v1.2.3/crds.yaml"),
+            "operator"
+        );
+        assert_eq!(
+            manifest_name("https://example.com/files/widgets.yml?raw=1"),
+            "widgets"
+        );
+    }
+
+    // ── cache prune limits ──────────────────────────────────────────────────
+
+    #[test]
+    fn parse_age_units() {
+        assert_eq!(parse_age("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3_600));
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86_400));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86_400));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+    }
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("100K").unwrap(), 100_000);
+        assert_eq!(parse_size("500M").unwrap(), 500_000_000);
+        assert_eq!(parse_size("2GB").unwrap(), 2_000_000_000);
+        assert_eq!(parse_size("2g").unwrap(), 2_000_000_000);
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("5T").is_err());
+    }
 
     // ── is_relevant_path ────────────────────────────────────────────────────
 
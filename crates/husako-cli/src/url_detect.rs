@@ -17,6 +17,9 @@ pub enum UrlDetected {
     HelmRegistry {
         repo: String,
     },
+    Manifest {
+        url: String,
+    },
     LocalPath {
         path: String,
     },
@@ -27,10 +30,11 @@ pub enum UrlDetected {
 /// Detection order:
 /// 1. `oci://` → OCI
 /// 2. `org/chart` (exactly one `/`, no protocol) → ArtifactHub
-/// 3. `https?://github.com/`, `gitlab.com/`, `bitbucket.org/` → Git
-/// 4. Other `https?://` → HelmRegistry
-/// 5. `./`, `../`, `/` prefix → LocalPath
-/// 6. Otherwise → None
+/// 3. `https?://` ending in `.yaml`/`.yml` → Manifest (e.g. a release asset)
+/// 4. `https?://github.com/`, `gitlab.com/`, `bitbucket.org/` → Git
+/// 5. Other `https?://` → HelmRegistry
+/// 6. `./`, `../`, `/` prefix → LocalPath
+/// 7. Otherwise → None
 pub fn detect_url(input: &str) -> Option<UrlDetected> {
     // 1. OCI
     if input.starts_with("oci://") {
@@ -46,19 +50,26 @@ pub fn detect_url(input: &str) -> Option<UrlDetected> {
         });
     }
 
-    // 3. Git hosts
+    // 3. Manifest file
+    if is_manifest_url(input) {
+        return Some(UrlDetected::Manifest {
+            url: input.to_string(),
+        });
+    }
+
+    // 4. Git hosts
     if let Some(git) = try_git_url(input) {
         return Some(git);
     }
 
-    // 4. Other https/http → HelmRegistry
+    // 5. Other https/http → HelmRegistry
     if input.starts_with("https://") || input.starts_with("http://") {
         return Some(UrlDetected::HelmRegistry {
             repo: input.to_string(),
         });
     }
 
-    // 5. Local path
+    // 6. Local path
     if input.starts_with("./") || input.starts_with("../") || input.starts_with('/') {
         return Some(UrlDetected::LocalPath {
             path: input.to_string(),
@@ -68,6 +79,14 @@ pub fn detect_url(input: &str) -> Option<UrlDetected> {
     None
 }
 
+fn is_manifest_url(input: &str) -> bool {
+    if !(input.starts_with("https://") || input.starts_with("http://")) {
+        return false;
+    }
+    let path = input.split(['?', '#']).next().unwrap_or(input);
+    path.ends_with(".yaml") || path.ends_with(".yml")
+}
+
 fn is_artifacthub_package(input: &str) -> bool {
     if input.contains("://") {
         return false;
@@ -214,10 +233,24 @@ fn content_is_crd(content: &str) -> bool {
     content.contains("kind: CustomResourceDefinition")
 }
 
+/// Whether `path` is a chart directory (has `Chart.yaml`) or a packaged
+/// `.tgz` chart, as opposed to a bare `values.schema.json`.
+pub fn is_local_chart(path: &str) -> bool {
+    let p = Path::new(path);
+    if p.is_dir() {
+        return p.join("Chart.yaml").exists();
+    }
+    p.is_file() && path.ends_with(".tgz")
+}
+
 /// Detect source kind from a local file or directory by reading content.
 pub fn detect_local_kind(path: &str) -> Result<SourceKind, String> {
     let p = Path::new(path);
 
+    if is_local_chart(path) {
+        return Ok(SourceKind::Chart);
+    }
+
     if p.is_file() {
         let content =
             std::fs::read_to_string(p).map_err(|e| format!("could not read {path}: {e}"))?;
@@ -270,6 +303,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn detect_manifest_url() {
+        let url = "https://github.com/example/operator/releases/download/v1.2.3/crds.yaml";
+        assert_eq!(
+            detect_url(url),
+            Some(UrlDetected::Manifest {
+                url: url.to_string()
+            })
+        );
+        assert!(matches!(
+            detect_url("https://example.com/crds.yml?token=x"),
+            Some(UrlDetected::Manifest { .. })
+        ));
+        assert!(matches!(
+            detect_url("https://charts.example.com"),
+            Some(UrlDetected::HelmRegistry { .. })
+        ));
+    }
+
     #[test]
     fn detect_git_simple() {
         assert_eq!(
@@ -487,5 +539,22 @@ mod tests {
             detect_local_kind(dir.path().to_str().unwrap()).unwrap(),
             SourceKind::Chart
         );
+        assert!(is_local_chart(dir.path().to_str().unwrap()));
+    }
+
+    #[test]
+    fn detect_local_chart_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let f = dir.path().join("my-chart-1.0.0.tgz");
+        std::fs::write(&f, [0x1f, 0x8b]).unwrap();
+        assert_eq!(
+            detect_local_kind(f.to_str().unwrap()).unwrap(),
+            SourceKind::Chart
+        );
+        assert!(is_local_chart(f.to_str().unwrap()));
+
+        let schema = dir.path().join("values.schema.json");
+        std::fs::write(&schema, r#"{"$schema": "x"}"#).unwrap();
+        assert!(!is_local_chart(schema.to_str().unwrap()));
     }
 }
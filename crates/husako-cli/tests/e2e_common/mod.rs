@@ -12,6 +12,8 @@ use tempfile::TempDir;
 pub fn husako_at(dir: &Path) -> assert_cmd::Command {
     let mut cmd = cargo_bin_cmd!("husako");
     cmd.current_dir(dir);
+    // Keep tests out of the user's shared cache
+    cmd.env("HUSAKO_CACHE_DIR", "");
     cmd
 }
 
@@ -138,14 +138,21 @@ test("in subdir", () => { expect(true).toBeTruthy(); });
 
 /// G5: SDK unit tests — verifies all builtin modules (husako, husako/_base, husako/test)
 /// against the TypeScript test files in crates/husako-sdk/tests/.
-/// No network, no tempdir, no husako gen — builtin modules need no generated types.
+/// No network, no husako gen — builtin modules need no generated types. The files
+/// run from a copy so the compile cache lands in the tempdir, not the source tree.
 #[test]
 fn g5_sdk_unit_tests() {
     let sdk_test_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
         .join("../../crates/husako-sdk/tests")
         .canonicalize()
         .expect("crates/husako-sdk/tests/ must exist");
-    let output = husako_at(&sdk_test_dir).args(["test"]).output().unwrap();
+    let dir = e2e_tmpdir();
+    for entry in std::fs::read_dir(&sdk_test_dir).unwrap().flatten() {
+        if entry.path().is_file() {
+            std::fs::copy(entry.path(), dir.path().join(entry.file_name())).unwrap();
+        }
+    }
+    let output = husako_at(dir.path()).args(["test"]).output().unwrap();
     let combined = output_combined(&output);
     assert!(
         output.status.success(),
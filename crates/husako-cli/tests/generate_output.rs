@@ -18,6 +18,8 @@ use serde_json::Value;
 fn husako_at(dir: &Path) -> assert_cmd::Command {
     let mut cmd = cargo_bin_cmd!("husako");
     cmd.current_dir(dir);
+    // Keep tests out of the user's shared cache
+    cmd.env("HUSAKO_CACHE_DIR", "");
     cmd
 }
 
@@ -4,13 +4,21 @@ use std::path::Path;
 use assert_cmd::cargo::cargo_bin_cmd;
 use tempfile::NamedTempFile;
 
+/// Runs in a scratch directory: rendering a standalone file generates types and
+/// fills the compile cache in the working directory, which mustn't be the
+/// source tree.
 fn husako() -> assert_cmd::Command {
-    cargo_bin_cmd!("husako")
+    static SCRATCH: std::sync::OnceLock<tempfile::TempDir> = std::sync::OnceLock::new();
+    let mut cmd = cargo_bin_cmd!("husako");
+    cmd.current_dir(SCRATCH.get_or_init(|| tempfile::tempdir().unwrap()).path());
+    cmd
 }
 
 fn husako_at(dir: &std::path::Path) -> assert_cmd::Command {
     let mut cmd = cargo_bin_cmd!("husako");
     cmd.current_dir(dir);
+    // Keep tests out of the user's shared cache
+    cmd.env("HUSAKO_CACHE_DIR", "");
     cmd
 }
 
@@ -208,6 +216,62 @@ fn compile_error() {
 
 // --- Milestone 2: Module Loader + Project Imports ---
 
+#[test]
+fn render_reuses_compile_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("name.ts"),
+        r#"export const name: string = "first";"#,
+    )
+    .unwrap();
+    let entry = root.join("entry.ts");
+    std::fs::write(
+        &entry,
+        r#"import { build } from "husako";
+import { name } from "./name";
+build([{ _render() { return { apiVersion: "v1", kind: "ConfigMap", metadata: { name } }; } }]);
+"#,
+    )
+    .unwrap();
+
+    husako_at(root)
+        .args(["render", entry.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("name: first"));
+
+    // The entry and its import are both cached
+    let cache_dir = root.join(".husako/cache/compile");
+    let count = |dir: &Path| {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|sub| std::fs::read_dir(sub.path()).unwrap().count())
+            .sum::<usize>()
+    };
+    assert_eq!(count(&cache_dir), 2);
+
+    // An edited module misses and is compiled again
+    std::fs::write(
+        root.join("name.ts"),
+        r#"export const name: string = "second";"#,
+    )
+    .unwrap();
+    husako_at(root)
+        .args(["render", entry.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("name: second"));
+    assert_eq!(count(&cache_dir), 3);
+
+    husako_at(root)
+        .args(["clean", "--cache"])
+        .assert()
+        .success();
+    assert!(!cache_dir.exists());
+}
+
 #[test]
 fn render_project_imports() {
     let dir = tempfile::tempdir().unwrap();
@@ -268,6 +332,90 @@ build([nginx]);
         .stdout(predicates::str::contains("app: nginx"));
 }
 
+#[test]
+fn render_helm_template_from_local_chart() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[charts]\nweb = { source = \"file\", path = \"chart/values.schema.json\" }\n",
+    )
+    .unwrap();
+
+    let chart = root.join("chart");
+    std::fs::create_dir_all(chart.join("templates")).unwrap();
+    std::fs::write(
+        chart.join("Chart.yaml"),
+        "apiVersion: v2\nname: web\nversion: 1.0.0\n",
+    )
+    .unwrap();
+    std::fs::write(chart.join("values.yaml"), "port: 80\n").unwrap();
+    std::fs::write(
+        chart.join("values.schema.json"),
+        r#"{"type":"object","properties":{"port":{"type":"integer"}}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        chart.join("templates/service.yaml"),
+        r#"apiVersion: v1
+kind: Service
+metadata:
+  name: {{ .Release.Name }}-web
+  namespace: {{ .Release.Namespace }}
+spec:
+  ports:
+    - port: {{ .Values.port }}
+"#,
+    )
+    .unwrap();
+
+    let entry = root.join("entry.ts");
+    std::fs::write(
+        &entry,
+        r#"import { build, helmTemplate } from "husako";
+import { serviceName } from "helm/web";
+const docs = helmTemplate("web", { port: 8080 }, { releaseName: "prod", namespace: "apps" });
+docs[0].metadata.annotations = { "example.com/service": serviceName("prod") };
+build(docs);
+"#,
+    )
+    .unwrap();
+
+    husako_at(root)
+        .args(["render", entry.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("kind: Service"))
+        .stdout(predicates::str::contains("name: prod-web"))
+        .stdout(predicates::str::contains("namespace: apps"))
+        .stdout(predicates::str::contains("example.com/service: prod-web"))
+        .stdout(predicates::str::contains("port: 8080"));
+}
+
+#[test]
+fn render_helm_template_reports_broken_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(root.join("husako.toml"), "[charts\nweb = 1\n").unwrap();
+
+    let entry = root.join("entry.ts");
+    std::fs::write(
+        &entry,
+        r#"import { build, helmTemplate } from "husako";
+build(helmTemplate("web", {}));
+"#,
+    )
+    .unwrap();
+
+    husako_at(root)
+        .args(["render", entry.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "helmTemplate(): failed to parse husako.toml",
+        ));
+}
+
 #[test]
 fn render_project_snapshot() {
     let dir = tempfile::tempdir().unwrap();
@@ -824,6 +972,48 @@ fn write_schema_store(root: &Path) {
     std::fs::write(schema_dir.join("_schema.json"), json).unwrap();
 }
 
+#[test]
+fn generate_spec_dir_openapi_v2() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+
+    let spec_dir = root.join("specs");
+    std::fs::create_dir_all(&spec_dir).unwrap();
+    let swagger = serde_json::json!({
+        "swagger": "2.0",
+        "definitions": {
+            "io.k8s.api.apps.v1.Deployment": {
+                "type": "object",
+                "properties": {
+                    "metadata": { "$ref": "#/definitions/io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta" },
+                    "spec": { "$ref": "#/definitions/io.k8s.api.apps.v1.DeploymentSpec" }
+                },
+                "x-kubernetes-group-version-kind": [
+                    { "group": "apps", "kind": "Deployment", "version": "v1" }
+                ]
+            },
+            "io.k8s.api.apps.v1.DeploymentSpec": {
+                "type": "object",
+                "properties": { "replicas": { "type": "integer" } }
+            },
+            "io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta": {
+                "type": "object",
+                "properties": { "name": { "type": "string" } }
+            }
+        }
+    });
+    std::fs::write(spec_dir.join("swagger.json"), swagger.to_string()).unwrap();
+
+    husako_at(root)
+        .args(["gen", "--spec-dir", spec_dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let apps_v1 = std::fs::read_to_string(root.join(".husako/types/k8s/apps/v1.d.ts")).unwrap();
+    assert!(apps_v1.contains("interface Deployment"));
+    assert!(apps_v1.contains("replicas"));
+}
+
 #[test]
 fn generate_spec_dir() {
     let dir = tempfile::tempdir().unwrap();
@@ -1171,6 +1361,51 @@ fn generate_creates_schema_json() {
     assert!(parsed["schemas"].is_object());
 }
 
+#[test]
+fn generate_creates_schema_bin_used_by_render() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+
+    let spec_dir = root.join("specs");
+    std::fs::create_dir_all(&spec_dir).unwrap();
+    write_mock_spec(&spec_dir, "apis/apps/v1");
+
+    husako_at(root)
+        .args(["gen", "--spec-dir", spec_dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let k8s_dir = root.join(".husako/types/k8s");
+    let bin = std::fs::read(k8s_dir.join("_schema.bin")).unwrap();
+    assert_eq!(&bin[..4], b"HSKB");
+    assert!(!k8s_dir.join("_schema.bin.tmp").exists());
+
+    // Validation works from the binary store alone
+    std::fs::remove_file(k8s_dir.join("_schema.json")).unwrap();
+    let entry = root.join("entry.ts");
+    std::fs::write(
+        &entry,
+        r#"
+import { build } from "husako";
+build([{ _render() { return {
+    apiVersion: "apps/v1",
+    kind: "Deployment",
+    spec: {
+        selector: {},
+        replicas: "abc"
+    }
+}; } }]);
+"#,
+    )
+    .unwrap();
+
+    husako_at(root)
+        .args(["render", entry.to_str().unwrap()])
+        .assert()
+        .code(7)
+        .stderr(predicates::str::contains("expected type integer"));
+}
+
 // --- husako new ---
 
 #[test]
@@ -1719,6 +1954,27 @@ staging = "env/staging.ts"
         .stderr(predicates::str::contains("staging"));
 }
 
+#[test]
+fn render_several_entries_requires_watch() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(root.join("a.ts"), "").unwrap();
+    std::fs::write(root.join("b.ts"), "").unwrap();
+
+    husako_at(root)
+        .args(["render", "a.ts", "b.ts"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("--watch"));
+
+    // Outputs of several entries can't share one file
+    husako_at(root)
+        .args(["render", "a.ts", "b.ts", "--watch", "-o", "out.yaml"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("must be a directory"));
+}
+
 #[test]
 fn render_direct_path_still_works_with_config() {
     let dir = tempfile::tempdir().unwrap();
@@ -2183,6 +2439,175 @@ fn plugin_cli_add_path_and_remove() {
     assert!(!config_content.contains("test"));
 }
 
+/// Create a git repository under `dir` whose plugin manifest is tagged at
+/// each of `versions` (`v<version>`). Returns the commit SHA of each tag.
+fn make_plugin_repo(dir: &Path, versions: &[&str]) -> Vec<String> {
+    let git = |args: &[&str]| {
+        let out = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    };
+    std::fs::create_dir_all(dir).unwrap();
+    git(&["init", "--quiet"]);
+    versions
+        .iter()
+        .map(|version| {
+            std::fs::write(
+                dir.join("plugin.toml"),
+                format!("[plugin]\nname = \"test\"\nversion = \"{version}\"\n"),
+            )
+            .unwrap();
+            git(&["add", "-A"]);
+            git(&["commit", "--quiet", "-m", version]);
+            git(&["tag", &format!("v{version}")]);
+            git(&["rev-parse", "HEAD"])
+        })
+        .collect()
+}
+
+#[test]
+fn plugin_version_pin_and_update() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let repo = root.join("plugin-repo");
+    let commits = make_plugin_repo(&repo, &["0.1.0", "0.2.0"]);
+    let url = format!("file://{}", repo.display());
+    std::fs::write(root.join("husako.toml"), "").unwrap();
+
+    husako_at(root)
+        .args(["plugin", "add", "test", "--url", &url, "--version", "0.1.0"])
+        .assert()
+        .success();
+
+    // The pinned tag is installed, not HEAD
+    let installed = std::fs::read_to_string(root.join(".husako/plugins/test/plugin.toml")).unwrap();
+    assert!(installed.contains("version = \"0.1.0\""));
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains("version = \"0.1.0\""));
+    assert!(lock.contains(&format!("commit = \"{}\"", commits[0])));
+
+    husako_at(root)
+        .args(["outdated"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("plugin"))
+        .stderr(predicates::str::contains("0.2.0"));
+
+    husako_at(root).args(["update"]).assert().success();
+    let config = std::fs::read_to_string(root.join("husako.toml")).unwrap();
+    assert!(config.contains("version = \"0.2.0\""));
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains(&format!("commit = \"{}\"", commits[1])));
+}
+
+#[test]
+fn plugin_rev_pin_is_not_updated() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let repo = root.join("plugin-repo");
+    let commits = make_plugin_repo(&repo, &["0.1.0", "0.2.0"]);
+    let config = format!(
+        "[plugins]\ntest = {{ source = \"git\", url = \"file://{}\", rev = \"{}\" }}\n",
+        repo.display(),
+        commits[0]
+    );
+    std::fs::write(root.join("husako.toml"), &config).unwrap();
+
+    husako_at(root)
+        .args(["update", "test"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(format!(
+            "test: pinned to rev {}; change `rev` in husako.toml",
+            commits[0]
+        )));
+    assert_eq!(
+        std::fs::read_to_string(root.join("husako.toml")).unwrap(),
+        config
+    );
+}
+
+#[test]
+fn plugin_git_install_without_git_binary() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let repo = root.join("plugin-repo");
+    let commits = make_plugin_repo(&repo, &["0.1.0", "0.2.0"]);
+    let url = format!("file://{}", repo.display());
+    std::fs::write(root.join("husako.toml"), "").unwrap();
+    // No `git` on PATH: tag listing and fetching run in process
+    let empty_path = root.join("empty-path");
+    std::fs::create_dir_all(&empty_path).unwrap();
+
+    husako_at(root)
+        .args(["plugin", "add", "test", "--url", &url, "--version", "0.1.0"])
+        .env("PATH", &empty_path)
+        .assert()
+        .success();
+    let installed = std::fs::read_to_string(root.join(".husako/plugins/test/plugin.toml")).unwrap();
+    assert!(installed.contains("version = \"0.1.0\""));
+    assert!(!root.join(".husako/plugins/test/.git").exists());
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains(&format!("commit = \"{}\"", commits[0])));
+
+    husako_at(root)
+        .args(["update"])
+        .env("PATH", &empty_path)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("0.1.0 → 0.2.0"));
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains(&format!("commit = \"{}\"", commits[1])));
+}
+
+#[test]
+fn plugin_reinstall_verifies_locked_digest() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let repo = root.join("plugin-repo");
+    make_plugin_repo(&repo, &["0.1.0"]);
+    let url = format!("file://{}", repo.display());
+    std::fs::write(root.join("husako.toml"), "").unwrap();
+
+    husako_at(root)
+        .args(["plugin", "add", "test", "--url", &url, "--version", "0.1.0"])
+        .assert()
+        .success();
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains("format_version = 2"));
+    let digest = lock
+        .lines()
+        .find_map(|line| line.strip_prefix("digest = "))
+        .expect("plugin digest recorded")
+        .trim_matches('"')
+        .to_string();
+    assert!(digest.starts_with("sha256:"));
+
+    // Refilling the cache with identical content passes verification
+    std::fs::remove_dir_all(root.join(".husako/plugins")).unwrap();
+    husako_at(root).args(["gen"]).assert().success();
+
+    // Content that no longer matches the locked digest is rejected
+    let tampered = lock.replace(&digest, &format!("sha256:{}", "0".repeat(64)));
+    std::fs::write(root.join("husako.lock"), tampered).unwrap();
+    std::fs::remove_dir_all(root.join(".husako/plugins")).unwrap();
+    husako_at(root)
+        .args(["gen"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("plugin 'test': digest mismatch"));
+}
+
 // --- FluxCD Plugin ---
 
 /// Install the bundled fluxcd plugin into `.husako/plugins/fluxcd/` for test isolation.
@@ -3181,52 +3606,351 @@ fn gen_no_incremental_ignores_lock() {
     );
 }
 
-// --- husako render: -o short flag ---
-
 #[test]
-fn render_short_o_flag_writes_file() {
+fn gen_rejects_lock_from_newer_husako() {
     let dir = tempfile::tempdir().unwrap();
     let root = dir.path();
-    let entry = root.join("entry.ts");
+
     std::fs::write(
-        &entry,
-        r#"import { build } from "husako";
-build([{ _render() { return { apiVersion: "v1", kind: "Namespace", metadata: { name: "dev" } }; } }]);
-"#,
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n",
     )
     .unwrap();
+    let lock = "format_version = 99\n\
+                husako_version = \"9.0.0\"\n\n\
+                [resources.k8s]\n\
+                source = \"release\"\n\
+                version = \"1.35\"\n\
+                generated_at = \"2024-01-01T00:00:00Z\"\n";
+    std::fs::write(root.join("husako.lock"), lock).unwrap();
+    write_release_cache(root, "1.35");
 
-    let out = root.join("out.yaml");
     husako_at(root)
-        .args([
-            "render",
-            entry.to_str().unwrap(),
-            "-o",
-            out.to_str().unwrap(),
-        ])
+        .args(["gen"])
         .assert()
-        .success();
+        .failure()
+        .stderr(predicates::str::contains("upgrade husako"));
 
-    assert!(out.exists(), "out.yaml should be written");
-    assert!(
-        out.metadata().unwrap().len() > 0,
-        "out.yaml should not be empty"
+    // The newer lock is left as it was, not downgraded
+    assert_eq!(
+        std::fs::read_to_string(root.join("husako.lock")).unwrap(),
+        lock
     );
 }
 
-// --- husako add: duplicate cases (no network needed) ---
-
 #[test]
-fn add_duplicate_resource_shows_message() {
+fn gen_regenerates_only_changed_resources() {
+    let crd = |property: &str| {
+        format!(
+            "apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: widgets.example.com
+spec:
+  group: example.com
+  names:
+    kind: Widget
+    plural: widgets
+  scope: Namespaced
+  versions:
+    - name: v1
+      served: true
+      storage: true
+      schema:
+        openAPIV3Schema:
+          type: object
+          properties:
+            spec:
+              type: object
+              properties:
+                {property}:
+                  type: integer
+"
+        )
+    };
     let dir = tempfile::tempdir().unwrap();
     let root = dir.path();
+    let config = "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n";
     std::fs::write(
         root.join("husako.toml"),
-        "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n",
+        format!("{config}widgets = {{ source = \"file\", path = \"widgets.yaml\" }}\n"),
     )
     .unwrap();
+    std::fs::write(root.join("widgets.yaml"), crd("size")).unwrap();
+    write_release_cache(root, "1.35");
 
-    // --release branch returns without network; AlreadyExists does not regen
+    husako_at(root).args(["gen"]).assert().success();
+    let k8s_dir = root.join(".husako/types/k8s");
+    assert!(k8s_dir.join("example.com/v1.d.ts").exists());
+    assert!(root.join(".husako/cache/k8s/k8s.json").exists());
+
+    // Only the changed CRD is resolved and emitted again: the release is
+    // no longer in the cache and its group-version file is left alone
+    std::fs::remove_dir_all(root.join(".husako/cache/release")).unwrap();
+    let apps = k8s_dir.join("apps/v1.d.ts");
+    let untouched = format!("{}// untouched\n", std::fs::read_to_string(&apps).unwrap());
+    std::fs::write(&apps, &untouched).unwrap();
+    let widgets = k8s_dir.join("example.com/v1.d.ts");
+    std::fs::write(&widgets, "// stale\n").unwrap();
+    std::fs::write(root.join("widgets.yaml"), crd("replicas")).unwrap();
+
+    husako_at(root).args(["gen"]).assert().success();
+    let content = std::fs::read_to_string(&widgets).unwrap();
+    assert!(content.contains("interface Widget"), "{content}");
+    assert_eq!(std::fs::read_to_string(&apps).unwrap(), untouched);
+    let schema = std::fs::read_to_string(k8s_dir.join("_schema.json")).unwrap();
+    assert!(
+        schema.contains("replicas") && !schema.contains("size"),
+        "{schema}"
+    );
+    assert!(schema.contains("apps/v1:Deployment"), "{schema}");
+    assert!(schema.contains("example.com/v1:Widget"), "{schema}");
+
+    // Removing a source removes its group-versions
+    std::fs::write(root.join("husako.toml"), config).unwrap();
+    husako_at(root).args(["gen"]).assert().success();
+    assert!(!widgets.exists());
+    assert!(!root.join(".husako/cache/k8s/widgets.json").exists());
+    assert_eq!(std::fs::read_to_string(&apps).unwrap(), untouched);
+    let schema = std::fs::read_to_string(k8s_dir.join("_schema.json")).unwrap();
+    assert!(!schema.contains("example.com/v1:Widget"), "{schema}");
+}
+
+#[test]
+fn gen_locked_and_frozen() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n",
+    )
+    .unwrap();
+    write_release_cache(root, "1.35");
+
+    // No lock to check against
+    husako_at(root)
+        .args(["gen", "--locked"])
+        .assert()
+        .code(8)
+        .stderr(predicates::str::contains("husako.lock not found"));
+    assert!(!root.join("husako.lock").exists());
+
+    husako_at(root).args(["gen"]).assert().success();
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+
+    // Everything cached: --frozen regenerates without touching the lock
+    std::fs::remove_dir_all(root.join(".husako/types")).unwrap();
+    husako_at(root).args(["gen", "--frozen"]).assert().success();
+    assert!(root.join(".husako/types/k8s").exists());
+    assert_eq!(
+        std::fs::read_to_string(root.join("husako.lock")).unwrap(),
+        lock
+    );
+
+    // Version bump in husako.toml is out of date with the lock
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \"1.36\" }\n",
+    )
+    .unwrap();
+    husako_at(root)
+        .args(["gen", "--locked"])
+        .assert()
+        .code(8)
+        .stderr(predicates::str::contains(
+            "resource 'k8s': changed since husako.lock was written",
+        ));
+    assert_eq!(
+        std::fs::read_to_string(root.join("husako.lock")).unwrap(),
+        lock
+    );
+
+    // Once the lock is updated, --frozen still needs 1.36 in the cache
+    husako_at(root).args(["gen", "--frozen"]).assert().code(8);
+    std::fs::write(
+        root.join("husako.lock"),
+        lock.replace("version = \"1.35\"", "version = \"1.36\""),
+    )
+    .unwrap();
+    std::fs::remove_dir_all(root.join(".husako/types")).unwrap();
+    husako_at(root)
+        .args(["gen", "--frozen"])
+        .assert()
+        .code(9)
+        .stderr(predicates::str::contains("resource 'k8s'"));
+}
+
+#[test]
+fn offline_mode_serves_only_from_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n\n\
+         [charts]\ningress = { source = \"registry\", repo = \"https://charts.invalid\", chart = \"ingress\", version = \"1.0.0\" }\n",
+    )
+    .unwrap();
+
+    // Nothing cached: the missing entry is named
+    husako_at(root)
+        .args(["gen", "--offline"])
+        .assert()
+        .code(9)
+        .stderr(predicates::str::contains(
+            "offline mode: Kubernetes release 1.35 is not cached at",
+        ))
+        .stderr(predicates::str::contains("release/v1.35.0"));
+
+    // Release cached: the chart is the next missing entry
+    write_release_cache(root, "1.35");
+    husako_at(root)
+        .env("HUSAKO_OFFLINE", "1")
+        .args(["gen"])
+        .assert()
+        .code(9)
+        .stderr(predicates::str::contains(
+            "offline mode: chart 'ingress' is not cached at",
+        ));
+    assert!(root.join(".husako/types/k8s").exists());
+
+    // Version discovery has no cache to fall back on
+    husako_at(root)
+        .args(["outdated", "--offline"])
+        .assert()
+        .code(9)
+        .stderr(predicates::str::contains("needs network access"));
+    husako_at(root)
+        .env("HUSAKO_OFFLINE", "1")
+        .args([
+            "plugin",
+            "add",
+            "test",
+            "--url",
+            "https://example.invalid/plugin.git",
+        ])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("offline mode: plugin 'test'"));
+}
+
+#[test]
+fn shared_cache_is_reused_across_projects() {
+    let dir = tempfile::tempdir().unwrap();
+    let shared = dir.path().join("shared");
+    let config = "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n\n\
+                  [charts]\nredis = { source = \"artifacthub\", package = \"bitnami/redis\", version = \"20.0.0\" }\n";
+
+    // Project A has everything cached locally and publishes it
+    let a = dir.path().join("a");
+    std::fs::create_dir_all(&a).unwrap();
+    std::fs::write(a.join("husako.toml"), config).unwrap();
+    write_release_cache(&a, "1.35");
+    write_artifacthub_chart_cache(&a, "bitnami/redis", "20.0.0");
+    husako_at(&a)
+        .env("HUSAKO_CACHE_DIR", &shared)
+        .args(["gen"])
+        .assert()
+        .success();
+    husako_at(&a)
+        .env("HUSAKO_CACHE_DIR", &shared)
+        .args(["cache", "list"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("release/v1.35.0"))
+        .stderr(predicates::str::contains("helm/artifacthub/"))
+        .stderr(predicates::str::contains("2 entries"));
+
+    // Project B has an empty cache but resolves offline from the shared one
+    let b = dir.path().join("b");
+    std::fs::create_dir_all(&b).unwrap();
+    std::fs::write(b.join("husako.toml"), config).unwrap();
+    husako_at(&b)
+        .env("HUSAKO_CACHE_DIR", &shared)
+        .args(["gen", "--offline"])
+        .assert()
+        .success();
+    assert!(
+        b.join(".husako/cache/release/v1.35.0/_manifest.json")
+            .exists()
+    );
+    assert!(b.join(".husako/types/helm/redis.d.ts").exists());
+
+    // Pruning needs a limit; recently used entries survive an age limit
+    husako_at(&b)
+        .env("HUSAKO_CACHE_DIR", &shared)
+        .args(["cache", "prune"])
+        .assert()
+        .code(2);
+    husako_at(&b)
+        .env("HUSAKO_CACHE_DIR", &shared)
+        .args(["cache", "prune", "--older-than", "30d"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Nothing to prune"));
+    husako_at(&b)
+        .env("HUSAKO_CACHE_DIR", &shared)
+        .args(["cache", "prune", "--max-size", "0"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Removed 2 entries"));
+
+    // With the shared cache emptied, a fresh project has nothing to serve
+    let c = dir.path().join("c");
+    std::fs::create_dir_all(&c).unwrap();
+    std::fs::write(c.join("husako.toml"), config).unwrap();
+    husako_at(&c)
+        .env("HUSAKO_CACHE_DIR", &shared)
+        .args(["gen", "--offline"])
+        .assert()
+        .code(9);
+}
+
+// --- husako render: -o short flag ---
+
+#[test]
+fn render_short_o_flag_writes_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let entry = root.join("entry.ts");
+    std::fs::write(
+        &entry,
+        r#"import { build } from "husako";
+build([{ _render() { return { apiVersion: "v1", kind: "Namespace", metadata: { name: "dev" } }; } }]);
+"#,
+    )
+    .unwrap();
+
+    let out = root.join("out.yaml");
+    husako_at(root)
+        .args([
+            "render",
+            entry.to_str().unwrap(),
+            "-o",
+            out.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    assert!(out.exists(), "out.yaml should be written");
+    assert!(
+        out.metadata().unwrap().len() > 0,
+        "out.yaml should not be empty"
+    );
+}
+
+// --- husako add: duplicate cases (no network needed) ---
+
+#[test]
+fn add_duplicate_resource_shows_message() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n",
+    )
+    .unwrap();
+
+    // --release branch returns without network; AlreadyExists does not regen
     husako_at(root)
         .args(["add", "--release", "1.35", "--name", "k8s"])
         .assert()
@@ -3257,6 +3981,65 @@ fn outdated_no_config_shows_no_deps() {
 // Track B: tests using mockito + env var URL injection
 // ============================================================
 
+#[tokio::test]
+async fn generate_from_cluster_source_with_kubeconfig() {
+    let mut server = mockito::Server::new_async().await;
+    let discovery = serde_json::json!({
+        "paths": {
+            "apis/apps/v1": { "serverRelativeURL": "/openapi/v3/apis/apps/v1?hash=A" },
+            "apis/example.com/v1": { "serverRelativeURL": "/openapi/v3/apis/example.com/v1?hash=B" }
+        }
+    });
+    let _discovery = server
+        .mock("GET", "/openapi/v3")
+        .match_header("authorization", "Bearer cluster-token")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(discovery.to_string())
+        .create_async()
+        .await;
+    let _apps = server
+        .mock("GET", "/openapi/v3/apis/apps/v1?hash=A")
+        .match_header("authorization", "Bearer cluster-token")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(rich_mock_spec().to_string())
+        .create_async()
+        .await;
+    let _example = server
+        .mock("GET", "/openapi/v3/apis/example.com/v1?hash=B")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"components":{"schemas":{}}}"#)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("kubeconfig"),
+        format!(
+            "current-context: mock\nclusters:\n  - name: mock\n    cluster:\n      server: {}\n\
+             contexts:\n  - name: mock\n    context: {{ cluster: mock, user: bot }}\n\
+             users:\n  - name: bot\n    user: {{ token: cluster-token }}\n",
+            server.url()
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\ninstalled = { source = \"cluster\", kubeconfig = \"kubeconfig\", groups = [\"apps\"] }\n",
+    )
+    .unwrap();
+
+    husako_at(root).args(["gen"]).assert().success();
+
+    assert!(root.join(".husako/types/k8s/apps/v1.d.ts").exists());
+    assert!(!root.join(".husako/types/k8s/example.com").exists());
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains("source = \"cluster\""));
+}
+
 #[tokio::test]
 async fn outdated_release_source_newer_available() {
     let mut server = mockito::Server::new_async().await;
@@ -3285,6 +4068,133 @@ async fn outdated_release_source_newer_available() {
         .stderr(predicates::str::contains("1.35"));
 }
 
+#[tokio::test]
+async fn user_config_rewrites_upstream_urls() {
+    let mut server = mockito::Server::new_async().await;
+    let _m = server
+        .mock(
+            "GET",
+            "/mirror/github/repos/kubernetes/kubernetes/tags?per_page=100",
+        )
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"name":"v1.35.0"}]"#)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \"1.35\" }\n",
+    )
+    .unwrap();
+    let user_config = root.join("user-config.toml");
+    std::fs::write(
+        &user_config,
+        format!(
+            "[[network.rewrite]]\nfrom = \"https://api.github.com/\"\nto = \"{}/mirror/github/\"\n",
+            server.url()
+        ),
+    )
+    .unwrap();
+
+    husako_at(root)
+        .args(["outdated"])
+        .env("HUSAKO_CONFIG", &user_config)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("up to date"));
+
+    // Unreadable CA files are reported before any command runs
+    std::fs::write(&user_config, "[network]\nca_certs = [\"missing.pem\"]\n").unwrap();
+    husako_at(root)
+        .args(["outdated"])
+        .env("HUSAKO_CONFIG", &user_config)
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("invalid user config"))
+        .stderr(predicates::str::contains("missing.pem"));
+}
+
+#[tokio::test]
+async fn outdated_private_registry_uses_credentials() {
+    let mut server = mockito::Server::new_async().await;
+    // "ci:s3cret" and "helm:pw"
+    for auth in ["Basic Y2k6czNjcmV0", "Basic aGVsbTpwdw=="] {
+        server
+            .mock("GET", "/index.yaml")
+            .match_header("authorization", auth)
+            .with_status(200)
+            .with_body(
+                "apiVersion: v1\nentries:\n  app:\n    - version: 2.0.0\n    - version: 1.0.0\n",
+            )
+            .create_async()
+            .await;
+    }
+    server
+        .mock("GET", "/index.yaml")
+        .match_header("authorization", mockito::Matcher::Missing)
+        .with_status(401)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let chart = format!(
+        "[charts]\napp = {{ source = \"registry\", repo = \"{}\", chart = \"app\", version = \"1.0.0\" }}\n",
+        server.url()
+    );
+    let credentials = format!(
+        "\n[credentials]\n\"{}\" = {{ username_env = \"CORP_USER\", password_env = \"CORP_PASS\" }}\n",
+        server.host_with_port()
+    );
+
+    // Anonymous requests are rejected with a pointer to the credentials
+    std::fs::write(root.join("husako.toml"), &chart).unwrap();
+    husako_at(root)
+        .args(["gen", "--skip-k8s"])
+        .env("HELM_REPOSITORY_CONFIG", root.join("none.yaml"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("add credentials for"));
+
+    // Credentials from Helm's repositories.yaml
+    let repositories = root.join("repositories.yaml");
+    std::fs::write(
+        &repositories,
+        format!(
+            "repositories:\n- name: corp\n  url: {}\n  username: helm\n  password: pw\n",
+            server.url()
+        ),
+    )
+    .unwrap();
+    husako_at(root)
+        .args(["outdated"])
+        .env("HELM_REPOSITORY_CONFIG", &repositories)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("2.0.0"));
+
+    // husako.toml credentials read from the environment take precedence
+    std::fs::write(root.join("husako.toml"), format!("{chart}{credentials}")).unwrap();
+    husako_at(root)
+        .args(["outdated"])
+        .env("HELM_REPOSITORY_CONFIG", &repositories)
+        .env("CORP_USER", "ci")
+        .env("CORP_PASS", "s3cret")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("2.0.0"));
+    husako_at(root)
+        .args(["gen", "--skip-k8s"])
+        .env_remove("CORP_PASS")
+        .env("CORP_USER", "ci")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("$CORP_PASS, which is not set"));
+}
+
 #[tokio::test]
 async fn outdated_release_source_up_to_date() {
     let mut server = mockito::Server::new_async().await;
@@ -3556,6 +4466,194 @@ async fn update_all_current_no_changes() {
         .stderr(predicates::str::contains("up to date"));
 }
 
+#[tokio::test]
+async fn version_requirement_is_locked_and_updated_in_range() {
+    let mut old = mockito::Server::new_async().await;
+    let _old = old
+        .mock(
+            "GET",
+            "/repos/kubernetes/kubernetes/tags?per_page=100&page=1",
+        )
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"name":"v1.34.0"},{"name":"v1.33.1"}]"#)
+        .create_async()
+        .await;
+    let mut new = mockito::Server::new_async().await;
+    let _new = new
+        .mock(
+            "GET",
+            "/repos/kubernetes/kubernetes/tags?per_page=100&page=1",
+        )
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"name":"v1.36.0"},{"name":"v1.35.2"},{"name":"v1.34.0"}]"#)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \">=1.33, <1.36\" }\n",
+    )
+    .unwrap();
+    write_release_cache(root, "1.34");
+    write_release_cache(root, "1.35");
+
+    // The newest matching release is resolved and locked with its requirement
+    husako_at(root)
+        .args(["gen"])
+        .env("HUSAKO_GITHUB_API_URL", old.url())
+        .assert()
+        .success();
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains("version = \"1.34\""), "{lock}");
+    assert!(lock.contains("requirement = \">=1.33, <1.36\""), "{lock}");
+
+    // Newer upstream releases do not move the locked version on their own
+    husako_at(root)
+        .args(["gen"])
+        .env("HUSAKO_GITHUB_API_URL", new.url())
+        .assert()
+        .success();
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains("version = \"1.34\""), "{lock}");
+
+    husako_at(root)
+        .args(["outdated"])
+        .env("HUSAKO_GITHUB_API_URL", new.url())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(">=1.33, <1.36"))
+        .stderr(predicates::str::contains("1.35"))
+        .stderr(predicates::str::contains("1.36"));
+
+    // update stays within the requirement and only moves the lock
+    husako_at(root)
+        .args(["update"])
+        .env("HUSAKO_GITHUB_API_URL", new.url())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "1.34 → 1.35 (resource, >=1.33, <1.36)",
+        ));
+    let toml = std::fs::read_to_string(root.join("husako.toml")).unwrap();
+    assert!(toml.contains(">=1.33, <1.36"), "{toml}");
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains("version = \"1.35\""), "{lock}");
+
+    // --breaking moves past it and rewrites the requirement
+    husako_at(root)
+        .args(["update", "--breaking", "--dry-run"])
+        .env("HUSAKO_GITHUB_API_URL", new.url())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("1.35 → 1.36 (resource, ^1.36)"));
+
+    // The locked version satisfies the requirement without network access
+    husako_at(root)
+        .args(["gen", "--locked", "--offline"])
+        .assert()
+        .success();
+
+    std::fs::write(
+        root.join("husako.toml"),
+        "[resources]\nk8s = { source = \"release\", version = \"~1.36\" }\n",
+    )
+    .unwrap();
+    husako_at(root)
+        .args(["gen", "--locked"])
+        .assert()
+        .code(8)
+        .stderr(predicates::str::contains(
+            "no locked version satisfies '~1.36'",
+        ));
+}
+
+#[tokio::test]
+async fn update_diff_reports_breaking_schema_changes_and_check_runs_entries() {
+    let mut server = mockito::Server::new_async().await;
+    let _m = server
+        .mock("GET", "/api/v1/packages/helm/metallb/metallb")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"version":"0.15.3","available_versions":[]}"#)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(
+        root.join("husako.toml"),
+        "[entries]
+         good = \"good.ts\"
+         bad = \"bad.ts\"
+
+         [charts]
+         metallb = { source = \"artifacthub\", package = \"metallb/metallb\", version = \"0.14.0\" }
+",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("good.ts"),
+        r#"import { build } from "husako";
+build([{ _render() { return { apiVersion: "v1", kind: "Namespace", metadata: { name: "dev" } }; } }]);
+"#,
+    )
+    .unwrap();
+    std::fs::write(root.join("bad.ts"), "const x = 1;\n").unwrap();
+
+    let cache_dir = root.join(format!(
+        ".husako/cache/helm/artifacthub/{}",
+        chart_djb2("metallb/metallb")
+    ));
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    let old = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "replicaCount": { "type": "integer" },
+            "speaker": { "type": "object", "properties": { "enabled": { "type": "boolean" } } }
+        }
+    });
+    let new = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "replicaCount": { "type": "string" },
+            "speaker": { "type": "object", "properties": {} }
+        }
+    });
+    std::fs::write(cache_dir.join("0.14.0.json"), old.to_string()).unwrap();
+    std::fs::write(cache_dir.join("0.15.3.json"), new.to_string()).unwrap();
+
+    husako_at(root)
+        .args(["update", "--dry-run", "--diff"])
+        .env("HUSAKO_ARTIFACTHUB_URL", server.url())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("0.14.0 → 0.15.3"))
+        .stderr(predicates::str::contains(
+            "replicaCount: type changed from integer to string",
+        ))
+        .stderr(predicates::str::contains("speaker.enabled: removed"));
+
+    husako_at(root)
+        .args(["update", "--dry-run", "--check"])
+        .assert()
+        .code(2);
+
+    // Entries are checked against the regenerated types after the update
+    husako_at(root)
+        .args(["update", "--check"])
+        .env("HUSAKO_ARTIFACTHUB_URL", server.url())
+        .assert()
+        .code(7)
+        .stderr(predicates::str::contains("check good: 1 resource(s) OK"))
+        .stderr(predicates::str::contains("check bad:"));
+    let toml = std::fs::read_to_string(root.join("husako.toml")).unwrap();
+    assert!(toml.contains("0.15.3"), "{toml}");
+}
+
 #[tokio::test]
 async fn add_artifacthub_chart_writes_toml() {
     let mut server = mockito::Server::new_async().await;
@@ -3589,6 +4687,63 @@ async fn add_artifacthub_chart_writes_toml() {
     );
 }
 
+#[tokio::test]
+async fn add_manifest_url_pins_sha256() {
+    let crd = r#"apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: widgets.example.com
+spec:
+  group: example.com
+  names:
+    kind: Widget
+    plural: widgets
+  scope: Namespaced
+  versions:
+    - name: v1
+      served: true
+      storage: true
+      schema:
+        openAPIV3Schema:
+          type: object
+          properties:
+            spec:
+              type: object
+              properties:
+                size:
+                  type: integer
+"#;
+    let mut server = mockito::Server::new_async().await;
+    let _m = server
+        .mock("GET", "/example/widgets/releases/download/v1.0.0/crds.yaml")
+        .with_status(200)
+        .with_body(crd)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::write(root.join("husako.toml"), "").unwrap();
+
+    let url = format!(
+        "{}/example/widgets/releases/download/v1.0.0/crds.yaml",
+        server.url()
+    );
+    husako_at(root)
+        .args(["add", &url])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Added widgets"));
+
+    let digest = husako_digest::sha256_hex(crd.as_bytes());
+    let content = std::fs::read_to_string(root.join("husako.toml")).unwrap();
+    assert!(content.contains("source = \"url\""), "{content}");
+    assert!(content.contains(&digest), "{content}");
+    assert!(root.join(".husako/types/k8s/example.com/v1.d.ts").exists());
+    let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+    assert!(lock.contains(&digest), "{lock}");
+}
+
 #[tokio::test]
 async fn add_release_resource_writes_toml() {
     // --release provides version directly — no GitHub network call for discovery.
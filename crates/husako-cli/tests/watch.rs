@@ -3,6 +3,9 @@
 /// Verifies the full watch lifecycle using a real subprocess:
 ///   1. Initial render on startup
 ///   2. Re-render when the source file changes
+///   3. Re-render when a module it imports changes
+///   4. Only entries whose imports changed re-render; other files are ignored
+///   5. Re-generate when husako.toml changes
 ///
 /// No network access or pre-generated k8s types required — the entry files use
 /// only the builtin `"husako"` module.
@@ -109,3 +112,150 @@ fn watch_rerenders_on_file_change() {
         "re-rendered output should contain 'watch-test':\n{final_content}"
     );
 }
+
+/// An edit to an imported module re-renders with the new module, even though
+/// the session keeps the modules of earlier renders.
+#[test]
+fn watch_reloads_changed_import() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let root = dir.path();
+
+    std::fs::write(root.join("husako.toml"), "[entries]\n").unwrap();
+    let name = root.join("name.ts");
+    // Same length, so only the watcher's invalidation tells the edits apart
+    std::fs::write(&name, r#"export const name = "first";"#).unwrap();
+    std::fs::write(
+        root.join("entry.ts"),
+        r#"import { build } from "husako";
+import { name } from "./name";
+build([{ _render() { return { apiVersion: "v1", kind: "ConfigMap", metadata: { name } }; } }]);
+"#,
+    )
+    .unwrap();
+
+    let out_yaml = root.join("out.yaml");
+    let child = std::process::Command::new(env!("CARGO_BIN_EXE_husako"))
+        .args(["render", "entry.ts", "--watch", "-o", "out.yaml"])
+        .current_dir(root)
+        .spawn()
+        .expect("failed to spawn husako render --watch");
+    let _guard = KillOnDrop(child);
+
+    let poll = Duration::from_millis(100);
+    let rendered = |expected: &'static str| {
+        poll_until(Instant::now() + Duration::from_secs(5), poll, || {
+            std::fs::read_to_string(&out_yaml)
+                .ok()
+                .filter(|s| s.contains(expected))
+        })
+    };
+    rendered("name: first").expect("initial render did not produce out.yaml within 5 s");
+
+    std::fs::write(&name, r#"export const name = "other";"#).unwrap();
+    rendered("name: other").expect("watch did not pick up the changed import within 5 s");
+
+    std::fs::write(&name, r#"export const name = "third";"#).unwrap();
+    rendered("name: third").expect("watch did not pick up the second change within 5 s");
+}
+
+/// Spawns `husako render --watch` with `args` in `root`, stderr going to the
+/// returned file.
+fn spawn_watch(root: &std::path::Path, args: &[&str]) -> (KillOnDrop, std::path::PathBuf) {
+    let stderr_path = root.join("stderr.log");
+    let stderr = std::fs::File::create(&stderr_path).unwrap();
+    let child = std::process::Command::new(env!("CARGO_BIN_EXE_husako"))
+        .args(["render", "--watch"])
+        .args(args)
+        .current_dir(root)
+        .stderr(stderr)
+        .spawn()
+        .expect("failed to spawn husako render --watch");
+    (KillOnDrop(child), stderr_path)
+}
+
+fn entry_importing(module: &str) -> String {
+    format!(
+        r#"import {{ build }} from "husako";
+import {{ name }} from "./{module}";
+build([{{ _render() {{ return {{ apiVersion: "v1", kind: "ConfigMap", metadata: {{ name }} }}; }} }}]);
+"#
+    )
+}
+
+/// Several entries are watched at once, each re-rendering only for the files
+/// it imports.
+#[test]
+fn watch_rerenders_affected_entries_only() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let root = dir.path();
+
+    std::fs::write(root.join("husako.toml"), "[entries]\n").unwrap();
+    let a_name = root.join("a-name.ts");
+    std::fs::write(&a_name, r#"export const name = "a-first";"#).unwrap();
+    std::fs::write(root.join("b-name.ts"), r#"export const name = "b-first";"#).unwrap();
+    std::fs::write(root.join("a.ts"), entry_importing("a-name")).unwrap();
+    std::fs::write(root.join("b.ts"), entry_importing("b-name")).unwrap();
+
+    let (_guard, stderr) = spawn_watch(root, &["a.ts", "b.ts", "-o", "out"]);
+    let written = || {
+        std::fs::read_to_string(&stderr)
+            .unwrap_or_default()
+            .matches("Written to")
+            .count()
+    };
+
+    let poll = Duration::from_millis(100);
+    let deadline = || Instant::now() + Duration::from_secs(5);
+    poll_until(deadline(), poll, || (written() == 2).then_some(()))
+        .expect("initial render of both entries did not finish within 5 s");
+    let b_yaml = std::fs::read_to_string(root.join("out/b.yaml")).unwrap();
+    assert!(b_yaml.contains("name: b-first"), "{b_yaml}");
+
+    std::fs::write(&a_name, r#"export const name = "a-other";"#).unwrap();
+    poll_until(deadline(), poll, || {
+        std::fs::read_to_string(root.join("out/a.yaml"))
+            .ok()
+            .filter(|s| s.contains("name: a-other"))
+    })
+    .expect("watch did not re-render a.ts within 5 s");
+    // b.ts doesn't import a-name.ts
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(written(), 3);
+
+    // Files no entry reads are ignored
+    std::fs::write(root.join("unrelated.ts"), "export {};").unwrap();
+    std::fs::write(root.join("notes.txt"), "hello").unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(written(), 3);
+}
+
+/// A change to husako.toml re-runs `husako gen` before re-rendering.
+#[test]
+fn watch_regenerates_on_config_change() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let root = dir.path();
+
+    std::fs::write(root.join("husako.toml"), "[entries]\n").unwrap();
+    std::fs::write(root.join("entry.ts"), ENTRY_V1).unwrap();
+
+    let (_guard, stderr) = spawn_watch(root, &["entry.ts", "-o", "out.yaml"]);
+    let poll = Duration::from_millis(100);
+    let logged = |expected: &'static str| {
+        poll_until(Instant::now() + Duration::from_secs(5), poll, || {
+            std::fs::read_to_string(&stderr)
+                .ok()
+                .filter(|s| s.contains(expected))
+        })
+    };
+    logged("Written to").expect("initial render did not finish within 5 s");
+
+    std::fs::write(root.join("husako.toml"), "[entries]\nmain = \"entry.ts\"\n").unwrap();
+    let log = logged("regenerating types").expect("watch did not regenerate within 5 s");
+    assert!(!log.contains("Could not generate types"), "{log}");
+    poll_until(Instant::now() + Duration::from_secs(5), poll, || {
+        std::fs::read_to_string(&stderr)
+            .ok()
+            .filter(|s| s.matches("Written to").count() == 2)
+    })
+    .expect("watch did not re-render after regenerating within 5 s");
+}
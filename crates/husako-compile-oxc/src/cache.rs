@@ -0,0 +1,246 @@
+//! Compiled modules kept on disk between runs.
+//!
+//! Entries are named by a hash of the source, its filename (which picks the
+//! source type and is embedded as `sourceURL`), the compiler and the options
+//! [`crate::compile`] uses, so an entry never needs invalidating: a changed
+//! source or compiler simply misses. Compile errors are not cached.
+//!
+//! Entries left behind by edited sources or older compilers are removed by
+//! [`CompileCache::prune`], least recently used first. A hit refreshes the
+//! entry's modification time to mark it used.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use husako_digest::Sha256;
+
+use crate::{CompileError, compile};
+
+/// Identifies the output of [`compile`] together with [`OXC_VERSION`]. Bump
+/// `format` whenever the output changes without either version changing, e.g.
+/// new transform or codegen options or an oxc patch release that changes
+/// codegen.
+const COMPILER: &str = concat!(
+    "format=1 husako-compile-oxc=",
+    env!("CARGO_PKG_VERSION"),
+    " transform=default codegen=default"
+);
+
+/// The `oxc_*` version requirement in the workspace `Cargo.toml`; a test keeps
+/// the two in step.
+const OXC_VERSION: &str = "0.115";
+
+/// A directory of compiled modules, e.g. `.husako/cache/compile`.
+#[derive(Debug, Clone)]
+pub struct CompileCache {
+    dir: PathBuf,
+}
+
+impl CompileCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// [`compile`], reusing the output of an earlier identical compile.
+    ///
+    /// The cache is best effort: entries that can't be read or written are
+    /// compiled as if the cache weren't there.
+    pub fn compile(&self, source: &str, filename: &str) -> Result<String, CompileError> {
+        let path = self.entry(source, filename);
+        if let Ok(js) = std::fs::read_to_string(&path) {
+            let _ = std::fs::File::open(&path).and_then(|f| f.set_modified(SystemTime::now()));
+            return Ok(js);
+        }
+        let js = compile(source, filename)?;
+        let _ = write(&path, &js);
+        Ok(js)
+    }
+
+    /// Remove the least recently used entries until at most `max_bytes`
+    /// remain. Returns the number of bytes removed; entries that can't be
+    /// read or removed are skipped.
+    pub fn prune(&self, max_bytes: u64) -> u64 {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+        for sub in std::fs::read_dir(&self.dir).into_iter().flatten().flatten() {
+            for entry in std::fs::read_dir(sub.path())
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                if let Ok(meta) = entry.metadata()
+                    && meta.is_file()
+                {
+                    let used = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    entries.push((used, meta.len(), entry.path()));
+                }
+            }
+        }
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        let mut removed = 0;
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total -= len;
+                removed += len;
+                if let Some(dir) = path.parent() {
+                    // Only succeeds once the directory is empty
+                    let _ = std::fs::remove_dir(dir);
+                }
+            }
+        }
+        removed
+    }
+
+    fn entry(&self, source: &str, filename: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        for part in [COMPILER, OXC_VERSION, filename, source] {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        let hex = hasher.finish_hex();
+        self.dir.join(&hex[..2]).join(format!("{}.js", &hex[2..]))
+    }
+}
+
+/// Write `js` to `path` by replacing it, so that concurrent runs never read
+/// a partial entry.
+fn write(path: &Path, js: &str) -> std::io::Result<()> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!(
+        ".{}.{}-{}.tmp",
+        path.file_name().and_then(|n| n.to_str()).unwrap_or("entry"),
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, js)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiler_key_tracks_oxc_requirement() {
+        let manifest = include_str!("../../../Cargo.toml");
+        let requirements: Vec<_> = manifest
+            .lines()
+            .filter(|line| line.starts_with("oxc_"))
+            .collect();
+        assert!(!requirements.is_empty());
+        for line in requirements {
+            assert!(
+                line.ends_with(&format!("\"{}\"", OXC_VERSION)),
+                "bump OXC_VERSION with the oxc upgrade: {line}"
+            );
+        }
+    }
+
+    fn entries(dir: &Path) -> Vec<PathBuf> {
+        let mut out = Vec::new();
+        for sub in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            for entry in std::fs::read_dir(sub.path())
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                out.push(entry.path());
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn reuses_compiled_output() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(tmp.path());
+        let ts = "const x: number = 42; export { x };";
+
+        let js = cache.compile(ts, "a.ts").unwrap();
+        assert_eq!(js, compile(ts, "a.ts").unwrap());
+        let stored = entries(tmp.path());
+        assert_eq!(stored.len(), 1);
+
+        // A hit is served from disk
+        std::fs::write(&stored[0], "// cached").unwrap();
+        assert_eq!(cache.compile(ts, "a.ts").unwrap(), "// cached");
+    }
+
+    #[test]
+    fn keyed_by_source_and_filename() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(tmp.path());
+        let ts = "const x: number = 42; export { x };";
+
+        cache.compile(ts, "a.ts").unwrap();
+        let js = cache.compile(ts, "b.ts").unwrap();
+        assert!(js.contains("//# sourceURL=b.ts"));
+        cache.compile("export const y = 1;", "a.ts").unwrap();
+        assert_eq!(entries(tmp.path()).len(), 3);
+    }
+
+    #[test]
+    fn prune_removes_least_recently_used() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(tmp.path());
+        let sources = [
+            "export const a = 1;",
+            "export const b = 2;",
+            "export const c = 3;",
+        ];
+        for (i, ts) in sources.iter().enumerate() {
+            cache.compile(ts, "a.ts").unwrap();
+            let used =
+                SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000 * (i as u64 + 1));
+            std::fs::File::options()
+                .write(true)
+                .open(cache.entry(ts, "a.ts"))
+                .unwrap()
+                .set_modified(used)
+                .unwrap();
+        }
+        // A hit marks the oldest entry as the most recently used
+        cache.compile(sources[0], "a.ts").unwrap();
+
+        let size = |ts: &str| std::fs::metadata(cache.entry(ts, "a.ts")).unwrap().len();
+        let keep = size(sources[0]) + size(sources[2]);
+        let evicted = size(sources[1]);
+        assert_eq!(cache.prune(keep), evicted);
+        assert!(cache.entry(sources[0], "a.ts").exists());
+        assert!(!cache.entry(sources[1], "a.ts").exists());
+        assert!(cache.entry(sources[2], "a.ts").exists());
+
+        assert_eq!(cache.prune(keep), 0);
+        cache.prune(0);
+        assert!(entries(tmp.path()).is_empty());
+    }
+
+    #[test]
+    fn errors_not_cached() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(tmp.path());
+        assert!(cache.compile("const = ;", "bad.ts").is_err());
+        assert!(entries(tmp.path()).is_empty());
+    }
+
+    #[test]
+    fn unwritable_dir_still_compiles() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("not-a-dir");
+        std::fs::write(&file, "").unwrap();
+        let cache = CompileCache::new(&file);
+        let js = cache
+            .compile("export const x: number = 1;", "a.ts")
+            .unwrap();
+        assert!(js.contains("export const x = 1;"));
+    }
+}
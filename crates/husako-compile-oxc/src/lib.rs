@@ -1,3 +1,5 @@
+mod cache;
+
 use std::path::Path;
 
 use oxc_allocator::Allocator;
@@ -7,6 +9,8 @@ use oxc_semantic::SemanticBuilder;
 use oxc_span::SourceType;
 use oxc_transformer::{TransformOptions, Transformer};
 
+pub use cache::CompileCache;
+
 #[derive(Debug, thiserror::Error)]
 pub enum CompileError {
     #[error("parse error: {0}")]
@@ -0,0 +1,66 @@
+//! How husako reaches upstream sources.
+//!
+//! The CLI builds one [`FetchContext`] at startup from its flags, the
+//! environment, the user config and the project's `[credentials]`, and passes
+//! it down to every resolver that fetches.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::CredentialRef;
+use crate::credentials::{self, Credentials};
+use crate::network::Network;
+use crate::shared_cache;
+
+/// Settings every fetch runs with.
+#[derive(Debug, Clone, Default)]
+pub struct FetchContext {
+    /// Serve from `.husako/cache` only (`--offline`, `--frozen` or
+    /// `HUSAKO_OFFLINE`).
+    pub offline: bool,
+    /// Shared user-level cache directory; `None` disables the shared cache.
+    pub shared_cache: Option<PathBuf>,
+    /// Proxy, CA certificates and URL rewrites from the user config.
+    pub network: Network,
+    /// `[credentials]` references from `husako.toml`, by host.
+    pub credentials: HashMap<String, CredentialRef>,
+}
+
+impl FetchContext {
+    /// A `reqwest::ClientBuilder` with the configured proxy and CA certificates.
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        self.network.client_builder()
+    }
+
+    /// `url` after the configured rewrite rules.
+    pub fn rewrite_url(&self, url: &str) -> String {
+        self.network.rewrite_url(url)
+    }
+
+    /// Credentials configured for `host`; see [`credentials::lookup`].
+    pub fn credentials_for(&self, host: &str) -> Result<Option<Credentials>, String> {
+        credentials::lookup(&self.credentials, host)
+    }
+
+    /// Whether the shared cache holds an entry for `key`.
+    pub fn shared_contains(&self, key: &str) -> bool {
+        self.shared_cache
+            .as_deref()
+            .is_some_and(|dir| shared_cache::contains(dir, key))
+    }
+
+    /// Copy the shared cache entry for `key` into `cache_dir`. Returns `false`
+    /// when the shared cache is disabled or has no valid entry.
+    pub fn restore_shared(&self, cache_dir: &Path, key: &str) -> bool {
+        self.shared_cache
+            .as_deref()
+            .is_some_and(|dir| shared_cache::restore(dir, cache_dir, key))
+    }
+
+    /// Record `paths` under `cache_dir` as the shared cache entry for `key`.
+    pub fn publish_shared(&self, cache_dir: &Path, key: &str, paths: &[&str]) {
+        if let Some(dir) = &self.shared_cache {
+            shared_cache::publish(dir, cache_dir, key, paths);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! `[credentials]` from `husako.toml`.
+//!
+//! The CLI loads the project's credential references at startup with
+//! [`load_credentials`] into its [`crate::FetchContext`]; chart fetchers and
+//! version checks then look them up by host with [`lookup`]. Secrets
+//! themselves are only ever read from the environment.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{CONFIG_FILENAME, CredentialRef};
+
+/// A username and password resolved from the environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Read only the `[credentials]` table of `project_root/husako.toml`.
+///
+/// Errors are ignored here: a broken `husako.toml` is reported by the command
+/// that loads it in full.
+pub fn load_credentials(project_root: &Path) -> HashMap<String, CredentialRef> {
+    #[derive(Deserialize)]
+    struct CredentialsOnly {
+        #[serde(default)]
+        credentials: HashMap<String, CredentialRef>,
+    }
+
+    std::fs::read_to_string(project_root.join(CONFIG_FILENAME))
+        .ok()
+        .and_then(|content| toml::from_str::<CredentialsOnly>(&content).ok())
+        .map(|config| config.credentials)
+        .unwrap_or_default()
+}
+
+/// Credentials configured in `credentials` for `host` (`host` or
+/// `host:port`).
+///
+/// `Ok(None)` when no entry matches. An entry naming an unset environment
+/// variable is an error rather than a silent anonymous request.
+pub fn lookup(
+    credentials: &HashMap<String, CredentialRef>,
+    host: &str,
+) -> Result<Option<Credentials>, String> {
+    let Some((key, credential)) = find(credentials, host) else {
+        return Ok(None);
+    };
+    let var = |name: &str| {
+        std::env::var(name)
+            .map_err(|_| format!("credentials for '{key}' read ${name}, which is not set"))
+    };
+    Ok(Some(Credentials {
+        username: var(&credential.username_env)?,
+        password: var(&credential.password_env)?,
+    }))
+}
+
+/// The entry for `host`, preferring an exact `host:port` key over a bare
+/// host. Keys may be written as URLs; only their host part is compared.
+fn find<'a>(
+    credentials: &'a HashMap<String, CredentialRef>,
+    host: &str,
+) -> Option<(&'a str, &'a CredentialRef)> {
+    let bare = host.split(':').next().unwrap_or(host);
+    let mut fallback = None;
+    for (key, credential) in credentials {
+        let key_host = host_of(key);
+        if key_host.eq_ignore_ascii_case(host) {
+            return Some((key, credential));
+        }
+        if key_host.eq_ignore_ascii_case(bare) {
+            fallback = Some((key.as_str(), credential));
+        }
+    }
+    fallback
+}
+
+/// `host[:port]` of a URL or reference such as `https://host/path` or
+/// `oci://host:5000/repo`.
+pub fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    rest.rsplit_once('@').map_or(rest, |(_, host)| host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_of_urls() {
+        assert_eq!(
+            host_of("https://charts.example.com/stable"),
+            "charts.example.com"
+        );
+        assert_eq!(
+            host_of("oci://harbor.corp:5000/library/app"),
+            "harbor.corp:5000"
+        );
+        assert_eq!(host_of("harbor.corp"), "harbor.corp");
+        assert_eq!(
+            host_of("https://user@charts.example.com"),
+            "charts.example.com"
+        );
+    }
+
+    #[test]
+    fn find_prefers_exact_port() {
+        let credentials = HashMap::from([
+            (
+                "harbor.corp".to_string(),
+                CredentialRef::new("A_USER", "A_PASS"),
+            ),
+            (
+                "https://harbor.corp:5000".to_string(),
+                CredentialRef::new("B_USER", "B_PASS"),
+            ),
+        ]);
+        assert_eq!(
+            find(&credentials, "harbor.corp:5000")
+                .unwrap()
+                .1
+                .username_env,
+            "B_USER"
+        );
+        assert_eq!(
+            find(&credentials, "harbor.corp:443")
+                .unwrap()
+                .1
+                .username_env,
+            "A_USER"
+        );
+        assert_eq!(
+            find(&credentials, "HARBOR.corp").unwrap().1.username_env,
+            "A_USER"
+        );
+        assert!(find(&credentials, "other.corp").is_none());
+    }
+
+    #[test]
+    fn load_reads_only_credentials() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join(CONFIG_FILENAME),
+            r#"
+[charts]
+app = { source = "registry", repo = "https://charts.corp", chart = "app", version = "1.0.0" }
+
+[credentials]
+"charts.corp" = { username_env = "CORP_USER", password_env = "CORP_PASS" }
+"#,
+        )
+        .unwrap();
+        let credentials = load_credentials(tmp.path());
+        assert_eq!(
+            credentials["charts.corp"],
+            CredentialRef::new("CORP_USER", "CORP_PASS")
+        );
+        assert!(load_credentials(&tmp.path().join("missing")).is_empty());
+    }
+}
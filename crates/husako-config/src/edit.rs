@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 
 use toml_edit::{DocumentMut, Item, Table, value};
 
-use crate::{CONFIG_FILENAME, ChartSource, ConfigError, PluginSource, SchemaSource};
+use crate::{CONFIG_FILENAME, ChartRef, ChartSource, ConfigError, PluginSource, SchemaSource};
 
 /// Load the husako.toml as a format-preserving TOML document.
 pub fn load_document(project_root: &Path) -> Result<(DocumentMut, PathBuf), ConfigError> {
@@ -91,6 +91,16 @@ pub fn add_plugin(doc: &mut DocumentMut, name: &str, source: &PluginSource) {
     doc["plugins"][name] = Item::Value(toml_edit::Value::InlineTable(inline));
 }
 
+/// Update the version (or tag) of a plugin entry. Returns true if found and updated.
+pub fn update_plugin_version(doc: &mut DocumentMut, name: &str, new_version: &str) -> bool {
+    if let Some(table) = doc.get_mut("plugins").and_then(|t| t.as_table_like_mut())
+        && let Some(entry) = table.get_mut(name)
+    {
+        return update_version_in_item(entry, new_version);
+    }
+    false
+}
+
 /// Remove a plugin entry. Returns true if found and removed.
 pub fn remove_plugin(doc: &mut DocumentMut, name: &str) -> bool {
     if let Some(table) = doc.get_mut("plugins").and_then(|t| t.as_table_like_mut()) {
@@ -138,16 +148,62 @@ fn source_to_inline_table(source: &SchemaSource) -> toml_edit::InlineTable {
             t.insert("source", "release".into());
             t.insert("version", version.as_str().into());
         }
-        SchemaSource::Git { repo, tag, path } => {
+        SchemaSource::Git {
+            repo,
+            tag,
+            branch,
+            rev,
+            path,
+        } => {
             t.insert("source", "git".into());
             t.insert("repo", repo.as_str().into());
-            t.insert("tag", tag.as_str().into());
+            insert_git_ref(&mut t, tag, branch, rev);
             t.insert("path", path.as_str().into());
         }
         SchemaSource::File { path } => {
             t.insert("source", "file".into());
             t.insert("path", path.as_str().into());
         }
+        SchemaSource::Url { url, sha256 } => {
+            t.insert("source", "url".into());
+            t.insert("url", url.as_str().into());
+            if let Some(sha256) = sha256 {
+                t.insert("sha256", sha256.as_str().into());
+            }
+        }
+        SchemaSource::Chart { chart, values } => {
+            t.insert("source", "chart".into());
+            match chart {
+                ChartRef::Name(name) => t.insert("chart", name.as_str().into()),
+                ChartRef::Inline(source) => t.insert(
+                    "chart",
+                    toml_edit::Value::InlineTable(chart_source_to_inline_table(source)),
+                ),
+            };
+            if let Some(values) = values
+                && let Ok(v) = toml::Value::Table(values.clone())
+                    .to_string()
+                    .parse::<toml_edit::Value>()
+            {
+                t.insert("values", v);
+            }
+        }
+        SchemaSource::Cluster {
+            context,
+            kubeconfig,
+            groups,
+        } => {
+            t.insert("source", "cluster".into());
+            if let Some(context) = context {
+                t.insert("context", context.as_str().into());
+            }
+            if let Some(kubeconfig) = kubeconfig {
+                t.insert("kubeconfig", kubeconfig.as_str().into());
+            }
+            if !groups.is_empty() {
+                t.insert("groups", groups.iter().collect::<toml_edit::Array>().into());
+            }
+        }
     }
     t
 }
@@ -174,10 +230,20 @@ fn chart_source_to_inline_table(source: &ChartSource) -> toml_edit::InlineTable
             t.insert("source", "file".into());
             t.insert("path", path.as_str().into());
         }
-        ChartSource::Git { repo, tag, path } => {
+        ChartSource::Chart { path } => {
+            t.insert("source", "chart".into());
+            t.insert("path", path.as_str().into());
+        }
+        ChartSource::Git {
+            repo,
+            tag,
+            branch,
+            rev,
+            path,
+        } => {
             t.insert("source", "git".into());
             t.insert("repo", repo.as_str().into());
-            t.insert("tag", tag.as_str().into());
+            insert_git_ref(&mut t, tag, branch, rev);
             t.insert("path", path.as_str().into());
         }
         ChartSource::Oci { reference, version } => {
@@ -189,15 +255,44 @@ fn chart_source_to_inline_table(source: &ChartSource) -> toml_edit::InlineTable
     t
 }
 
+/// Write whichever of `tag`, `branch` or `rev` the source uses.
+fn insert_git_ref(
+    t: &mut toml_edit::InlineTable,
+    tag: &str,
+    branch: &Option<String>,
+    rev: &Option<String>,
+) {
+    if !tag.is_empty() {
+        t.insert("tag", tag.into());
+    }
+    if let Some(branch) = branch {
+        t.insert("branch", branch.as_str().into());
+    }
+    if let Some(rev) = rev {
+        t.insert("rev", rev.as_str().into());
+    }
+}
+
 fn plugin_source_to_inline_table(source: &PluginSource) -> toml_edit::InlineTable {
     let mut t = toml_edit::InlineTable::new();
     match source {
-        PluginSource::Git { url, path } => {
+        PluginSource::Git {
+            url,
+            path,
+            tag,
+            version,
+            rev,
+        } => {
             t.insert("source", "git".into());
             t.insert("url", url.as_str().into());
             if let Some(p) = path {
                 t.insert("path", p.as_str().into());
             }
+            for (key, value) in [("tag", tag), ("version", version), ("rev", rev)] {
+                if let Some(value) = value {
+                    t.insert(key, value.as_str().into());
+                }
+            }
         }
         PluginSource::Path { path } => {
             t.insert("source", "path".into());
@@ -218,6 +313,52 @@ mod tests {
         (tmp, path)
     }
 
+    #[test]
+    fn add_resource_chart_with_values() {
+        let (_tmp, path) = create_test_toml("");
+        let mut doc: DocumentMut = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+
+        let values: toml::Table = toml::from_str("crds = { enabled = true }").unwrap();
+        add_resource(
+            &mut doc,
+            "cert-manager-crds",
+            &SchemaSource::Chart {
+                chart: ChartRef::Name("cert-manager".to_string()),
+                values: Some(values),
+            },
+        );
+
+        let output = doc.to_string();
+        let config: crate::HusakoConfig = toml::from_str(&output).unwrap();
+        let SchemaSource::Chart { chart, values } = &config.resources["cert-manager-crds"] else {
+            panic!("expected chart source: {output}");
+        };
+        assert_eq!(chart, &ChartRef::Name("cert-manager".to_string()));
+        assert_eq!(
+            values.as_ref().unwrap()["crds"]["enabled"].as_bool(),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn add_resource_inline_chart() {
+        let (_tmp, path) = create_test_toml("");
+        let mut doc: DocumentMut = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+        let source = SchemaSource::Chart {
+            chart: ChartRef::Inline(ChartSource::Registry {
+                repo: "https://charts.example.com".to_string(),
+                chart: "operator".to_string(),
+                version: "1.2.3".to_string(),
+            }),
+            values: None,
+        };
+        add_resource(&mut doc, "operator-crds", &source);
+
+        let output = doc.to_string();
+        let config: crate::HusakoConfig = toml::from_str(&output).unwrap();
+        assert_eq!(config.resources["operator-crds"], source);
+    }
+
     #[test]
     fn add_resource_release() {
         let (_tmp, path) = create_test_toml("[entries]\ndev = \"env/dev.ts\"\n");
@@ -281,6 +422,23 @@ mod tests {
         assert!(output.contains("1.2.3"));
     }
 
+    #[test]
+    fn add_chart_local() {
+        let (_tmp, path) = create_test_toml("");
+        let mut doc: DocumentMut = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+
+        add_chart(
+            &mut doc,
+            "my-app",
+            &ChartSource::Chart {
+                path: "./charts/my-app".to_string(),
+            },
+        );
+
+        let output = doc.to_string();
+        assert!(output.contains(r#"my-app = { source = "chart", path = "./charts/my-app" }"#));
+    }
+
     #[test]
     fn remove_resource_existing() {
         let (_tmp, path) = create_test_toml(
@@ -364,6 +522,39 @@ mod tests {
         assert!(!output.contains("v1.17.2"));
     }
 
+    #[test]
+    fn add_resource_git_branch() {
+        let (_tmp, path) = create_test_toml("");
+        let mut doc: DocumentMut = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+        add_resource(
+            &mut doc,
+            "my-crds",
+            &SchemaSource::Git {
+                repo: "https://github.com/example/operator".to_string(),
+                tag: String::new(),
+                branch: Some("main".to_string()),
+                rev: None,
+                path: "config/crd".to_string(),
+            },
+        );
+        let output = doc.to_string();
+        assert!(output.contains("branch = \"main\""));
+        assert!(!output.contains("tag"));
+    }
+
+    #[test]
+    fn update_plugin_version() {
+        let (_tmp, path) = create_test_toml(
+            "[plugins]\nflux = { source = \"git\", url = \"https://github.com/nanazt/husako-plugin-flux\", version = \"0.2.0\" }\n",
+        );
+        let mut doc: DocumentMut = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+
+        assert!(super::update_plugin_version(&mut doc, "flux", "0.3.0"));
+        assert!(!super::update_plugin_version(&mut doc, "missing", "0.3.0"));
+        let output = doc.to_string();
+        assert!(output.contains("version = \"0.3.0\""));
+    }
+
     #[test]
     fn preserve_comments() {
         let content = "# Project config\n\n[entries]\n# Entry aliases\ndev = \"env/dev.ts\"\n\n[resources]\n# K8s resources\nkubernetes = { source = \"release\", version = \"1.35\" }\n";
@@ -419,6 +610,9 @@ mod tests {
             &PluginSource::Git {
                 url: "https://github.com/nanazt/husako-plugin-flux".to_string(),
                 path: None,
+                tag: None,
+                version: Some("0.2.0".to_string()),
+                rev: None,
             },
         );
 
@@ -427,6 +621,7 @@ mod tests {
         assert!(output.contains("flux"));
         assert!(output.contains("git"));
         assert!(output.contains("husako-plugin-flux"));
+        assert!(output.contains("version = \"0.2.0\""));
         // No path field when None
         assert!(!output.contains("\"path\""));
     }
@@ -442,6 +637,9 @@ mod tests {
             &PluginSource::Git {
                 url: "https://github.com/nanazt/husako".to_string(),
                 path: Some("plugins/flux".to_string()),
+                tag: None,
+                version: None,
+                rev: None,
             },
         );
 
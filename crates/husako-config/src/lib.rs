@@ -1,14 +1,23 @@
+mod context;
+pub mod credentials;
 pub mod edit;
 pub mod lock;
+pub mod network;
+mod offline;
+pub mod shared_cache;
+pub mod version_req;
 
+pub use context::FetchContext;
 pub use lock::{
-    ChartLockEntry, HusakoLock, PluginLockEntry, ResourceLockEntry, load_lock, save_lock,
+    ChartLockEntry, HusakoLock, LOCK_FORMAT_VERSION, PluginLockEntry, ResourceLockEntry,
+    digest_mismatch, load_lock, read_lock, save_lock,
 };
+pub use offline::{OFFLINE_ENV, offline_from_env};
 
 use std::collections::HashMap;
 use std::path::Path;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 pub const CONFIG_FILENAME: &str = "husako.toml";
 
@@ -23,6 +32,8 @@ pub enum ConfigError {
     Parse(String),
     #[error("config validation error: {0}")]
     Validation(String),
+    #[error("invalid user config {path}: {message}")]
+    UserConfig { path: String, message: String },
 }
 
 /// Full `husako.toml` configuration.
@@ -43,6 +54,35 @@ pub struct HusakoConfig {
     /// Plugin dependencies.
     #[serde(default)]
     pub plugins: HashMap<String, PluginSource>,
+
+    /// Credentials for private chart repositories and OCI registries, by host.
+    #[serde(default)]
+    pub credentials: HashMap<String, CredentialRef>,
+}
+
+/// Credentials for a private chart repository or OCI registry. Both values
+/// are read from environment variables so secrets never live in `husako.toml`.
+/// `"harbor.corp.example" = { username_env = "HARBOR_USER", password_env = "HARBOR_TOKEN" }`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct CredentialRef {
+    pub username_env: String,
+    pub password_env: String,
+    /// Only present to reject inline secrets with a clear error.
+    #[serde(default)]
+    username: Option<toml::Value>,
+    #[serde(default)]
+    password: Option<toml::Value>,
+}
+
+impl CredentialRef {
+    pub fn new(username_env: &str, password_env: &str) -> Self {
+        Self {
+            username_env: username_env.to_string(),
+            password_env: password_env.to_string(),
+            username: None,
+            password: None,
+        }
+    }
 }
 
 /// A schema dependency entry. Every entry must specify `source` explicitly.
@@ -54,12 +94,18 @@ pub enum SchemaSource {
     #[serde(rename = "release")]
     Release { version: String },
 
-    /// Clone a git repo at a tag and extract CRD YAML manifests.
+    /// Clone a git repo at a tag, branch or commit and extract CRD YAML manifests.
     /// `cert-manager = { source = "git", repo = "...", tag = "v1.17.2", path = "deploy/crds" }`
+    /// `cert-manager = { source = "git", repo = "...", rev = "8f3c...", path = "deploy/crds" }`
     #[serde(rename = "git")]
     Git {
         repo: String,
+        #[serde(default)]
         tag: String,
+        #[serde(default)]
+        branch: Option<String>,
+        #[serde(default)]
+        rev: Option<String>,
         path: String,
     },
 
@@ -67,10 +113,44 @@ pub enum SchemaSource {
     /// `my-crd = { source = "file", path = "./crds/my-crd.yaml" }`
     #[serde(rename = "file")]
     File { path: String },
+
+    /// Download CRD YAML from a URL, optionally pinned to a SHA-256 digest.
+    /// `my-crds = { source = "url", url = "https://.../crds.yaml", sha256 = "..." }`
+    #[serde(rename = "url")]
+    Url {
+        url: String,
+        #[serde(default)]
+        sha256: Option<String>,
+    },
+
+    /// Extract the CRDs bundled in a chart declared under `[charts]` or given
+    /// inline, rendered with optional `values` (for charts that gate CRDs
+    /// behind a flag).
+    /// `cert-manager-crds = { source = "chart", chart = "cert-manager", values = { crds = { enabled = true } } }`
+    /// `operator-crds = { source = "chart", chart = { source = "oci", reference = "oci://...", version = "1.2.3" } }`
+    #[serde(rename = "chart")]
+    Chart {
+        chart: ChartRef,
+        #[serde(default)]
+        values: Option<toml::Table>,
+    },
+
+    /// Fetch OpenAPI v3 specs from a live cluster through a kubeconfig context,
+    /// optionally limited to some API groups.
+    /// `installed = { source = "cluster", context = "prod", groups = ["cert-manager.io"] }`
+    #[serde(rename = "cluster")]
+    Cluster {
+        #[serde(default)]
+        context: Option<String>,
+        #[serde(default)]
+        kubeconfig: Option<String>,
+        #[serde(default)]
+        groups: Vec<String>,
+    },
 }
 
 /// A chart values schema source. Specifies where to find `values.schema.json`.
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(tag = "source")]
 pub enum ChartSource {
     /// Fetch from an HTTP Helm chart repository.
@@ -92,12 +172,22 @@ pub enum ChartSource {
     #[serde(rename = "file")]
     File { path: String },
 
-    /// Clone a git repo at a tag and extract `values.schema.json`.
+    /// Read a local chart directory or packaged `.tgz` archive.
+    /// `my-chart = { source = "chart", path = "./charts/my-chart" }`
+    #[serde(rename = "chart")]
+    Chart { path: String },
+
+    /// Clone a git repo at a tag, branch or commit and extract `values.schema.json`.
     /// `my-chart = { source = "git", repo = "https://...", tag = "v1.0.0", path = "charts/my-chart" }`
     #[serde(rename = "git")]
     Git {
         repo: String,
+        #[serde(default)]
         tag: String,
+        #[serde(default)]
+        branch: Option<String>,
+        #[serde(default)]
+        rev: Option<String>,
         path: String,
     },
 
@@ -107,8 +197,139 @@ pub enum ChartSource {
     Oci { reference: String, version: String },
 }
 
+/// The chart a `chart` resource source extracts CRDs from.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ChartRef {
+    /// Name of an entry under `[charts]`, whose version bumps then update both
+    /// its values types and its CRD types.
+    Name(String),
+    /// A chart coordinate written in place, used as is: version requirements
+    /// are not resolved.
+    Inline(ChartSource),
+}
+
+impl ChartRef {
+    /// The chart source, looked up under `[charts]` for a named reference.
+    pub fn source<'a>(&'a self, config: &'a HusakoConfig) -> Option<&'a ChartSource> {
+        match self {
+            Self::Name(name) => config.charts.get(name),
+            Self::Inline(source) => Some(source),
+        }
+    }
+}
+
+impl std::fmt::Display for ChartRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name(name) => f.write_str(name),
+            Self::Inline(ChartSource::Registry {
+                repo,
+                chart,
+                version,
+            }) => write!(f, "{chart} {version} from {repo}"),
+            Self::Inline(ChartSource::ArtifactHub { package, version }) => {
+                write!(f, "{package} {version}")
+            }
+            Self::Inline(ChartSource::Oci { reference, version }) => {
+                write!(f, "{reference} {version}")
+            }
+            Self::Inline(ChartSource::Git {
+                repo,
+                tag,
+                branch,
+                rev,
+                path,
+            }) => {
+                let git_ref = GitRef::new(tag, branch.as_deref(), rev.as_deref());
+                write!(f, "{repo}/{path} at {git_ref}")
+            }
+            Self::Inline(ChartSource::File { path } | ChartSource::Chart { path }) => {
+                f.write_str(path)
+            }
+        }
+    }
+}
+
+/// The revision a git source is checked out at: exactly one of `tag`,
+/// `branch` or `rev` is set in `husako.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitRef<'a> {
+    Tag(&'a str),
+    Branch(&'a str),
+    Rev(&'a str),
+}
+
+impl<'a> GitRef<'a> {
+    /// Build from the `tag` / `branch` / `rev` fields of a git source.
+    /// `rev` wins over `branch`, which wins over `tag`.
+    pub fn new(tag: &'a str, branch: Option<&'a str>, rev: Option<&'a str>) -> Self {
+        match (rev, branch) {
+            (Some(rev), _) => Self::Rev(rev),
+            (None, Some(branch)) => Self::Branch(branch),
+            (None, None) => Self::Tag(tag),
+        }
+    }
+
+    /// The tag, branch name or commit SHA.
+    pub fn name(&self) -> &'a str {
+        match self {
+            Self::Tag(s) | Self::Branch(s) | Self::Rev(s) => s,
+        }
+    }
+
+    /// Directory/file name used to cache a checkout of this ref.
+    /// Tags keep their bare name so existing caches stay valid.
+    pub fn cache_key(&self) -> String {
+        match self {
+            Self::Tag(tag) => tag.to_string(),
+            Self::Branch(branch) => format!("branch-{}", branch.replace('/', "-")),
+            Self::Rev(rev) => format!("rev-{rev}"),
+        }
+    }
+
+    /// Branches move, so their checkouts are never reused from cache.
+    pub fn is_mutable(&self) -> bool {
+        matches!(self, Self::Branch(_))
+    }
+}
+
+impl std::fmt::Display for GitRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tag(tag) => write!(f, "tag {tag}"),
+            Self::Branch(branch) => write!(f, "branch {branch}"),
+            Self::Rev(rev) => write!(f, "rev {rev}"),
+        }
+    }
+}
+
+impl SchemaSource {
+    /// The git ref of a `git` source; `None` for every other source type.
+    pub fn git_ref(&self) -> Option<GitRef<'_>> {
+        match self {
+            Self::Git {
+                tag, branch, rev, ..
+            } => Some(GitRef::new(tag, branch.as_deref(), rev.as_deref())),
+            _ => None,
+        }
+    }
+}
+
+impl ChartSource {
+    /// The git ref of a `git` source; `None` for every other source type.
+    pub fn git_ref(&self) -> Option<GitRef<'_>> {
+        match self {
+            Self::Git {
+                tag, branch, rev, ..
+            } => Some(GitRef::new(tag, branch.as_deref(), rev.as_deref())),
+            _ => None,
+        }
+    }
+}
+
 /// A plugin dependency entry in `husako.toml`.
-/// `fluxcd = { source = "git", url = "https://github.com/nanazt/husako-plugin-fluxcd" }`
+/// `fluxcd = { source = "git", url = "https://github.com/nanazt/husako-plugin-fluxcd", version = "0.2.0" }`
 /// `fluxcd = { source = "git", url = "https://github.com/nanazt/husako", path = "plugins/fluxcd" }`
 /// `my-plugin = { source = "path", path = "./plugins/my-plugin" }`
 #[derive(Debug, Clone, Deserialize)]
@@ -116,11 +337,20 @@ pub enum ChartSource {
 pub enum PluginSource {
     /// Clone from a git repository. If `path` is set, only that subdirectory is checked out
     /// via sparse-checkout (useful for plugins bundled inside a monorepo).
+    ///
+    /// At most one of `tag`, `version` (checks out tag `v<version>` or `<version>`)
+    /// and `rev` may be set; without any, the default branch HEAD is installed.
     #[serde(rename = "git")]
     Git {
         url: String,
         #[serde(default)]
         path: Option<String>,
+        #[serde(default)]
+        tag: Option<String>,
+        #[serde(default)]
+        version: Option<String>,
+        #[serde(default)]
+        rev: Option<String>,
     },
 
     /// Use a local directory.
@@ -219,11 +449,36 @@ fn validate(config: &HusakoConfig) -> Result<(), ConfigError> {
                 "schema '{name}' has absolute path '{path}'; use a relative path"
             )));
         }
+        if let SchemaSource::Chart {
+            chart: ChartRef::Inline(chart),
+            ..
+        } = source
+        {
+            check_inline_chart(name, chart)?;
+        }
+    }
+
+    // Git sources must name exactly one of tag, branch or rev
+    for (name, source) in &config.resources {
+        if let SchemaSource::Git {
+            tag, branch, rev, ..
+        } = source
+        {
+            check_git_ref(&format!("schema '{name}'"), tag, branch, rev)?;
+        }
+    }
+    for (name, source) in &config.charts {
+        if let ChartSource::Git {
+            tag, branch, rev, ..
+        } = source
+        {
+            check_git_ref(&format!("chart '{name}'"), tag, branch, rev)?;
+        }
     }
 
     // Chart file paths must be relative
     for (name, source) in &config.charts {
-        if let ChartSource::File { path } = source
+        if let ChartSource::File { path } | ChartSource::Chart { path } = source
             && Path::new(path).is_absolute()
         {
             return Err(ConfigError::Validation(format!(
@@ -232,6 +487,66 @@ fn validate(config: &HusakoConfig) -> Result<(), ConfigError> {
         }
     }
 
+    // Version requirements must parse
+    for (name, source) in &config.resources {
+        if let SchemaSource::Release { version } = source {
+            check_version(&format!("schema '{name}'"), version)?;
+        }
+    }
+    for (name, source) in &config.charts {
+        if let ChartSource::Registry { version, .. }
+        | ChartSource::ArtifactHub { version, .. }
+        | ChartSource::Oci { version, .. } = source
+        {
+            check_version(&format!("chart '{name}'"), version)?;
+        }
+    }
+    for (name, source) in &config.plugins {
+        if let PluginSource::Git {
+            version: Some(version),
+            ..
+        } = source
+        {
+            check_version(&format!("plugin '{name}'"), version)?;
+        }
+    }
+
+    // Git plugins may pin at most one of tag, version or rev
+    for (name, source) in &config.plugins {
+        if let PluginSource::Git {
+            tag, version, rev, ..
+        } = source
+        {
+            let what = format!("plugin '{name}'");
+            match (tag, version, rev) {
+                (None, None, None) => {}
+                (Some(tag), None, None) => check_git_ref(&what, tag, &None, &None)?,
+                (None, Some(version), None) => check_git_ref(&what, version, &None, &None)?,
+                (None, None, rev @ Some(_)) => check_git_ref(&what, "", &None, rev)?,
+                _ => {
+                    return Err(ConfigError::Validation(format!(
+                        "{what} must set at most one of 'tag', 'version' or 'rev'"
+                    )));
+                }
+            }
+        }
+    }
+
+    // Credentials are referenced by environment variable, never stored inline
+    for (host, credential) in &config.credentials {
+        if credential.username.is_some() || credential.password.is_some() {
+            return Err(ConfigError::Validation(format!(
+                "credentials for '{host}' must not be stored in husako.toml; \
+                 use username_env and password_env to name environment variables"
+            )));
+        }
+        if credential.username_env.is_empty() || credential.password_env.is_empty() {
+            return Err(ConfigError::Validation(format!(
+                "credentials for '{host}' must set username_env and password_env"
+            )));
+        }
+    }
+
     // Plugin path sources must be relative
     for (name, source) in &config.plugins {
         if let PluginSource::Path { path } = source
@@ -246,6 +561,71 @@ fn validate(config: &HusakoConfig) -> Result<(), ConfigError> {
     Ok(())
 }
 
+/// An inline chart of a `chart` resource source follows the rules of
+/// `[charts]` entries, except that its version must be exact.
+fn check_inline_chart(name: &str, chart: &ChartSource) -> Result<(), ConfigError> {
+    let what = format!("chart of schema '{name}'");
+    match chart {
+        ChartSource::Git {
+            tag, branch, rev, ..
+        } => check_git_ref(&what, tag, branch, rev),
+        ChartSource::File { path } | ChartSource::Chart { path }
+            if Path::new(path).is_absolute() =>
+        {
+            Err(ConfigError::Validation(format!(
+                "{what} has absolute path '{path}'; use a relative path"
+            )))
+        }
+        ChartSource::Registry { version, .. }
+        | ChartSource::ArtifactHub { version, .. }
+        | ChartSource::Oci { version, .. }
+            if version_req::is_requirement(version) =>
+        {
+            Err(ConfigError::Validation(format!(
+                "{what} has version requirement '{version}'; inline charts need an exact \
+                 version, or declare the chart under [charts]"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_version(what: &str, version: &str) -> Result<(), ConfigError> {
+    if version_req::is_requirement(version)
+        && let Err(e) = version_req::parse_requirement(version)
+    {
+        return Err(ConfigError::Validation(format!(
+            "{what} has invalid version requirement '{version}': {e}"
+        )));
+    }
+    Ok(())
+}
+
+fn check_git_ref(
+    what: &str,
+    tag: &str,
+    branch: &Option<String>,
+    rev: &Option<String>,
+) -> Result<(), ConfigError> {
+    let set = [!tag.is_empty(), branch.is_some(), rev.is_some()]
+        .into_iter()
+        .filter(|s| *s)
+        .count();
+    if set != 1 {
+        return Err(ConfigError::Validation(format!(
+            "{what} must set exactly one of 'tag', 'branch' or 'rev'"
+        )));
+    }
+    if let Some(rev) = rev
+        && (!matches!(rev.len(), 40 | 64) || !rev.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return Err(ConfigError::Validation(format!(
+            "{what} has invalid rev '{rev}'; use a full commit SHA"
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -305,6 +685,31 @@ dev = "env/dev.ts"
         assert!(result.is_err());
     }
 
+    #[test]
+    fn parse_credentials() {
+        let toml = r#"
+[credentials]
+"harbor.corp.example" = { username_env = "HARBOR_USER", password_env = "HARBOR_TOKEN" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        validate(&config).unwrap();
+        assert_eq!(
+            config.credentials["harbor.corp.example"],
+            CredentialRef::new("HARBOR_USER", "HARBOR_TOKEN")
+        );
+    }
+
+    #[test]
+    fn reject_inline_credentials() {
+        let toml = r#"
+[credentials]
+"harbor.corp.example" = { username_env = "HARBOR_USER", password_env = "HARBOR_TOKEN", password = "hunter2" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        let err = validate(&config).unwrap_err().to_string();
+        assert!(err.contains("must not be stored in husako.toml"), "{err}");
+    }
+
     #[test]
     fn reject_absolute_entry_path() {
         let config = HusakoConfig {
@@ -436,6 +841,138 @@ postgresql = { source = "oci", reference = "oci://ghcr.io/org/postgresql", versi
         ));
     }
 
+    #[test]
+    fn parse_local_chart_source() {
+        let toml = r#"[charts]
+my-app = { source = "chart", path = "./charts/my-app" }
+packaged = { source = "chart", path = "./dist/packaged-1.0.0.tgz" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.charts["my-app"],
+            ChartSource::Chart {
+                path: "./charts/my-app".to_string()
+            }
+        );
+        assert!(matches!(
+            config.charts["packaged"],
+            ChartSource::Chart { .. }
+        ));
+
+        let config = HusakoConfig {
+            charts: HashMap::from([(
+                "my-app".to_string(),
+                ChartSource::Chart {
+                    path: "/abs/charts/my-app".to_string(),
+                },
+            )]),
+            ..Default::default()
+        };
+        assert!(validate(&config).is_err());
+    }
+
+    #[test]
+    fn parse_chart_schema_source() {
+        let toml = r#"[charts]
+cert-manager = { source = "registry", repo = "https://charts.jetstack.io", chart = "cert-manager", version = "v1.16.0" }
+
+[resources]
+cert-manager-crds = { source = "chart", chart = "cert-manager", values = { crds = { enabled = true } } }
+operator-crds = { source = "chart", chart = "operator" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        let SchemaSource::Chart { chart, values } = &config.resources["cert-manager-crds"] else {
+            panic!("expected chart source");
+        };
+        assert_eq!(chart, &ChartRef::Name("cert-manager".to_string()));
+        assert_eq!(chart.to_string(), "cert-manager");
+        assert!(chart.source(&config).is_some());
+        let values = values.as_ref().unwrap();
+        assert_eq!(values["crds"]["enabled"].as_bool(), Some(true));
+        assert_eq!(
+            config.resources["operator-crds"],
+            SchemaSource::Chart {
+                chart: ChartRef::Name("operator".to_string()),
+                values: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_inline_chart_schema_source() {
+        let toml = r#"[resources]
+operator-crds = { source = "chart", chart = { source = "oci", reference = "oci://ghcr.io/org/operator", version = "1.2.3" } }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        validate(&config).unwrap();
+        let SchemaSource::Chart { chart, values } = &config.resources["operator-crds"] else {
+            panic!("expected chart source");
+        };
+        let inline = ChartSource::Oci {
+            reference: "oci://ghcr.io/org/operator".to_string(),
+            version: "1.2.3".to_string(),
+        };
+        assert_eq!(chart, &ChartRef::Inline(inline.clone()));
+        assert_eq!(chart.source(&config), Some(&inline));
+        assert_eq!(chart.to_string(), "oci://ghcr.io/org/operator 1.2.3");
+        assert!(values.is_none());
+    }
+
+    #[test]
+    fn reject_inline_chart_version_requirement() {
+        let toml = r#"[resources]
+operator-crds = { source = "chart", chart = { source = "registry", repo = "https://charts.example.com", chart = "operator", version = "^1.2" } }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        let err = validate(&config).unwrap_err().to_string();
+        assert!(err.contains("exact"), "{err}");
+    }
+
+    #[test]
+    fn parse_url_schema_source() {
+        let toml = r#"[resources]
+pinned = { source = "url", url = "https://example.com/v1.2.3/crds.yaml", sha256 = "abc123" }
+floating = { source = "url", url = "https://example.com/latest/crds.yaml" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.resources["pinned"],
+            SchemaSource::Url {
+                url: "https://example.com/v1.2.3/crds.yaml".to_string(),
+                sha256: Some("abc123".to_string()),
+            }
+        );
+        assert!(matches!(
+            &config.resources["floating"],
+            SchemaSource::Url { sha256: None, .. }
+        ));
+    }
+
+    #[test]
+    fn parse_cluster_schema_source() {
+        let toml = r#"[resources]
+installed = { source = "cluster", context = "prod", groups = ["cert-manager.io"] }
+current = { source = "cluster" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.resources["installed"],
+            SchemaSource::Cluster {
+                context: Some("prod".to_string()),
+                kubeconfig: None,
+                groups: vec!["cert-manager.io".to_string()],
+            }
+        );
+        assert_eq!(
+            config.resources["current"],
+            SchemaSource::Cluster {
+                context: None,
+                kubeconfig: None,
+                groups: Vec::new(),
+            }
+        );
+    }
+
     #[test]
     fn reject_absolute_chart_file_path() {
         let config = HusakoConfig {
@@ -462,7 +999,7 @@ my-plugin = { source = "path", path = "./plugins/my-plugin" }
         assert_eq!(config.plugins.len(), 2);
         assert!(matches!(
             config.plugins["fluxcd"],
-            PluginSource::Git { ref url, path: None } if url == "https://github.com/nanazt/husako-plugin-fluxcd"
+            PluginSource::Git { ref url, path: None, .. } if url == "https://github.com/nanazt/husako-plugin-fluxcd"
         ));
         assert!(matches!(
             config.plugins["my-plugin"],
@@ -479,11 +1016,63 @@ fluxcd = { source = "git", url = "https://github.com/nanazt/husako", path = "plu
         let config: HusakoConfig = toml::from_str(toml).unwrap();
         assert!(matches!(
             config.plugins["fluxcd"],
-            PluginSource::Git { ref url, path: Some(ref p) }
+            PluginSource::Git { ref url, path: Some(ref p), .. }
                 if url == "https://github.com/nanazt/husako" && p == "plugins/fluxcd"
         ));
     }
 
+    #[test]
+    fn parse_plugin_git_version() {
+        let toml = r#"
+[plugins]
+fluxcd = { source = "git", url = "https://github.com/nanazt/husako-plugin-fluxcd", version = "0.2.0" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        validate(&config).unwrap();
+        assert!(matches!(
+            config.plugins["fluxcd"],
+            PluginSource::Git { version: Some(ref v), tag: None, rev: None, .. } if v == "0.2.0"
+        ));
+    }
+
+    #[test]
+    fn reject_plugin_with_tag_and_version() {
+        let toml = r#"
+[plugins]
+fluxcd = { source = "git", url = "https://github.com/nanazt/husako-plugin-fluxcd", tag = "v0.2.0", version = "0.2.0" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        let err = validate(&config).unwrap_err();
+        assert!(err.to_string().contains("at most one"));
+    }
+
+    #[test]
+    fn version_requirements() {
+        let toml = r#"
+[resources]
+kubernetes = { source = "release", version = ">=1.33, <1.36" }
+
+[charts]
+ingress-nginx = { source = "registry", repo = "https://kubernetes.github.io/ingress-nginx", chart = "ingress-nginx", version = "^4.12" }
+
+[plugins]
+fluxcd = { source = "git", url = "https://github.com/nanazt/husako-plugin-fluxcd", version = "~0.2" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        validate(&config).unwrap();
+
+        let toml = r#"
+[charts]
+app = { source = "oci", reference = "oci://ghcr.io/org/app", version = "^1.x.y" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        let err = validate(&config).unwrap_err().to_string();
+        assert!(
+            err.contains("chart 'app' has invalid version requirement"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn reject_absolute_plugin_path() {
         let config = HusakoConfig {
@@ -499,6 +1088,47 @@ fluxcd = { source = "git", url = "https://github.com/nanazt/husako", path = "plu
         assert!(err.to_string().contains("absolute path"));
     }
 
+    #[test]
+    fn parse_git_rev_and_branch() {
+        let toml = r#"[resources]
+pinned = { source = "git", repo = "https://github.com/example/op", rev = "0123456789abcdef0123456789abcdef01234567", path = "crds" }
+
+[charts]
+edge = { source = "git", repo = "https://github.com/example/charts", branch = "main", path = "charts/app" }
+"#;
+        let config: HusakoConfig = toml::from_str(toml).unwrap();
+        validate(&config).unwrap();
+        assert_eq!(
+            config.resources["pinned"].git_ref(),
+            Some(GitRef::Rev("0123456789abcdef0123456789abcdef01234567"))
+        );
+        assert_eq!(
+            config.charts["edge"].git_ref(),
+            Some(GitRef::Branch("main"))
+        );
+        assert_eq!(
+            GitRef::Branch("release/1.x").cache_key(),
+            "branch-release-1.x"
+        );
+    }
+
+    #[test]
+    fn reject_git_source_with_conflicting_refs() {
+        for fields in [
+            r#"tag = "v1.0.0", branch = "main""#,
+            r#"rev = "abc123""#,
+            "",
+        ] {
+            let toml = format!(
+                "[resources]\nop = {{ source = \"git\", repo = \"https://x\", path = \"crds\", {fields} }}\n"
+            )
+            .replace(", }", " }");
+            let config: HusakoConfig = toml::from_str(&toml).unwrap();
+            let err = validate(&config).unwrap_err();
+            assert!(err.to_string().contains("schema 'op'"), "{fields}: {err}");
+        }
+    }
+
     #[test]
     fn parse_plugin_manifest() {
         let toml = r#"
@@ -7,6 +7,12 @@ use crate::ConfigError;
 
 pub const LOCK_FILENAME: &str = "husako.lock";
 
+/// Current `husako.lock` format.
+///
+/// - 1: djb2 content hashes
+/// - 2: `sha256:` content hashes and artifact digests
+pub const LOCK_FORMAT_VERSION: u32 = 2;
+
 const LOCK_HEADER: &str = "# This file is generated by husako. Commit it to version control.\n# Do not edit it manually.\n\n";
 
 /// Project-root lock file that enables incremental type generation.
@@ -27,21 +33,36 @@ pub struct HusakoLock {
 
 /// Lock entry for a k8s resource schema source.
 ///
-/// k8s resources are regenerated all-or-nothing; individual entries cannot
-/// be skipped in isolation (because `resolve_all()` merges them before codegen).
+/// Each entry is checked on its own: only sources whose entry changed are
+/// resolved again, and only the group-versions they contribute to are
+/// regenerated.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "source")]
 pub enum ResourceLockEntry {
+    /// `digest` covers the downloaded spec files.
     #[serde(rename = "release")]
     Release {
         version: String,
+        /// Semver requirement from `husako.toml` that `version` was resolved from.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        requirement: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digest: Option<String>,
         generated_at: String,
     },
     #[serde(rename = "git")]
     Git {
         repo: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
         tag: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
         path: String,
+        /// Commit SHA the ref resolved to when the entry was generated.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        commit: Option<String>,
         generated_at: String,
     },
     #[serde(rename = "file")]
@@ -50,6 +71,31 @@ pub enum ResourceLockEntry {
         content_hash: String,
         generated_at: String,
     },
+    /// `sha256` is the digest of the downloaded manifest.
+    #[serde(rename = "url")]
+    Url {
+        url: String,
+        sha256: String,
+        generated_at: String,
+    },
+    /// `source_hash` covers the referenced chart's source, its values and,
+    /// for local charts, their content — so bumping the chart regenerates.
+    #[serde(rename = "chart")]
+    Chart {
+        chart: String,
+        source_hash: String,
+        generated_at: String,
+    },
+    #[serde(rename = "cluster")]
+    Cluster {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        kubeconfig: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        groups: Vec<String>,
+        generated_at: String,
+    },
 }
 
 /// Lock entry for a Helm chart values schema source.
@@ -59,17 +105,28 @@ pub enum ResourceLockEntry {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "source")]
 pub enum ChartLockEntry {
+    /// `digest` is the SHA-256 of the chart `.tgz` archive.
     #[serde(rename = "registry")]
     Registry {
         repo: String,
         chart: String,
         version: String,
+        /// Semver requirement from `husako.toml` that `version` was resolved from.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        requirement: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digest: Option<String>,
         generated_at: String,
     },
     #[serde(rename = "artifacthub")]
     ArtifactHub {
         package: String,
         version: String,
+        /// Semver requirement from `husako.toml` that `version` was resolved from.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        requirement: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digest: Option<String>,
         generated_at: String,
     },
     #[serde(rename = "file")]
@@ -78,17 +135,37 @@ pub enum ChartLockEntry {
         content_hash: String,
         generated_at: String,
     },
+    #[serde(rename = "chart")]
+    Chart {
+        path: String,
+        content_hash: String,
+        generated_at: String,
+    },
     #[serde(rename = "git")]
     Git {
         repo: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
         tag: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
         path: String,
+        /// Commit SHA the ref resolved to when the entry was generated.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        commit: Option<String>,
         generated_at: String,
     },
     #[serde(rename = "oci")]
     Oci {
         reference: String,
         version: String,
+        /// Semver requirement from `husako.toml` that `version` was resolved from.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        requirement: Option<String>,
+        /// Digest of the chart layer blob named in the OCI manifest.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digest: Option<String>,
         generated_at: String,
     },
 }
@@ -102,6 +179,21 @@ pub enum PluginLockEntry {
         url: String,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         path: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        /// Semver requirement from `husako.toml` that `version` was resolved from.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        requirement: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
+        /// Commit SHA that was checked out when the plugin was installed.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        commit: Option<String>,
+        /// Digest of the installed plugin directory.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digest: Option<String>,
         plugin_version: String,
         generated_at: String,
     },
@@ -116,9 +208,27 @@ pub enum PluginLockEntry {
 
 /// Load `husako.lock` from the project root.
 ///
+/// Lock files written in an older format are migrated and rewritten in place.
+///
 /// Returns `Ok(None)` if the file does not exist.
-/// Returns `Err` if the file exists but cannot be parsed.
+/// Returns `Err` if the file exists but cannot be parsed, or was written by a
+/// newer husako.
 pub fn load_lock(project_root: &Path) -> Result<Option<HusakoLock>, ConfigError> {
+    let Some(mut lock) = read_lock(project_root)? else {
+        return Ok(None);
+    };
+    if lock.format_version < LOCK_FORMAT_VERSION {
+        migrate(&mut lock);
+        save_lock(project_root, &lock)?;
+    }
+    Ok(Some(lock))
+}
+
+/// Read `husako.lock` as stored, without migrating or rewriting it.
+///
+/// Used where the lock must not be modified (`husako gen --locked`).
+/// Errors like [`load_lock`].
+pub fn read_lock(project_root: &Path) -> Result<Option<HusakoLock>, ConfigError> {
     let path = project_root.join(LOCK_FILENAME);
     if !path.exists() {
         return Ok(None);
@@ -129,9 +239,61 @@ pub fn load_lock(project_root: &Path) -> Result<Option<HusakoLock>, ConfigError>
     })?;
     let lock: HusakoLock =
         toml::from_str(&content).map_err(|e| ConfigError::Parse(e.to_string()))?;
+    if lock.format_version > LOCK_FORMAT_VERSION {
+        return Err(ConfigError::Parse(format!(
+            "{LOCK_FILENAME} has format_version {}, but this husako supports up to {LOCK_FORMAT_VERSION}; upgrade husako",
+            lock.format_version
+        )));
+    }
     Ok(Some(lock))
 }
 
+/// Upgrade a lock to [`LOCK_FORMAT_VERSION`].
+///
+/// Format 1 content hashes were djb2 and cannot be compared with sha256
+/// hashes, so they are cleared: the affected entries regenerate once and
+/// record fresh digests. Everything else carries over.
+fn migrate(lock: &mut HusakoLock) {
+    if lock.format_version < 2 {
+        for entry in lock.resources.values_mut() {
+            match entry {
+                ResourceLockEntry::File { content_hash, .. } => content_hash.clear(),
+                ResourceLockEntry::Chart { source_hash, .. } => source_hash.clear(),
+                _ => {}
+            }
+        }
+        for entry in lock.charts.values_mut() {
+            if let ChartLockEntry::File { content_hash, .. }
+            | ChartLockEntry::Chart { content_hash, .. } = entry
+            {
+                content_hash.clear();
+            }
+        }
+        for entry in lock.plugins.values_mut() {
+            if let PluginLockEntry::Path { content_hash, .. } = entry {
+                content_hash.clear();
+            }
+        }
+    }
+    lock.format_version = LOCK_FORMAT_VERSION;
+}
+
+/// If `current` and `locked` describe the same source — equal in every field
+/// except `generated_at` and `digest` — but record different digests, returns
+/// `(locked, current)` digests.
+pub fn digest_mismatch<T: Serialize>(current: &T, locked: &T) -> Option<(String, String)> {
+    let split = |entry: &T| {
+        let mut table = toml::Table::try_from(entry).ok()?;
+        table.remove("generated_at");
+        let digest = table.remove("digest")?;
+        Some((table, digest.as_str()?.to_string()))
+    };
+    let (current_source, current_digest) = split(current)?;
+    let (locked_source, locked_digest) = split(locked)?;
+    (current_source == locked_source && current_digest != locked_digest)
+        .then_some((locked_digest, current_digest))
+}
+
 /// Write `husako.lock` to the project root.
 pub fn save_lock(project_root: &Path, lock: &HusakoLock) -> Result<(), ConfigError> {
     let path = project_root.join(LOCK_FILENAME);
@@ -154,6 +316,8 @@ mod tests {
             "kubernetes".to_string(),
             ResourceLockEntry::Release {
                 version: "1.35".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-02-26T14:03:00Z".to_string(),
             },
         );
@@ -162,7 +326,10 @@ mod tests {
             ResourceLockEntry::Git {
                 repo: "https://github.com/cert-manager/cert-manager".to_string(),
                 tag: "v1.17.2".to_string(),
+                branch: None,
+                rev: None,
                 path: "deploy/crds".to_string(),
+                commit: Some("4c1e0a7f9b2d3e8a6c5b4a39281706f5e4d3c2b1".to_string()),
                 generated_at: "2026-02-26T14:03:05Z".to_string(),
             },
         );
@@ -182,6 +349,11 @@ mod tests {
                 repo: "https://kubernetes.github.io/ingress-nginx".to_string(),
                 chart: "ingress-nginx".to_string(),
                 version: "4.12.0".to_string(),
+                requirement: None,
+                digest: Some(
+                    "sha256:2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae"
+                        .to_string(),
+                ),
                 generated_at: "2026-02-26T14:03:10Z".to_string(),
             },
         );
@@ -190,6 +362,8 @@ mod tests {
             ChartLockEntry::ArtifactHub {
                 package: "bitnami/postgresql".to_string(),
                 version: "16.4.0".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-02-26T14:03:12Z".to_string(),
             },
         );
@@ -201,12 +375,23 @@ mod tests {
                 generated_at: "2026-02-26T14:03:05Z".to_string(),
             },
         );
+        charts.insert(
+            "my-local-chart".to_string(),
+            ChartLockEntry::Chart {
+                path: "./charts/my-app".to_string(),
+                content_hash: "0f1e2d3c4b5a6978".to_string(),
+                generated_at: "2026-02-26T14:03:06Z".to_string(),
+            },
+        );
         charts.insert(
             "my-git-chart".to_string(),
             ChartLockEntry::Git {
                 repo: "https://github.com/example/charts".to_string(),
-                tag: "v1.0.0".to_string(),
+                tag: String::new(),
+                branch: Some("main".to_string()),
+                rev: None,
                 path: "charts/my-chart".to_string(),
+                commit: Some("9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b".to_string()),
                 generated_at: "2026-02-26T14:03:08Z".to_string(),
             },
         );
@@ -215,6 +400,8 @@ mod tests {
             ChartLockEntry::Oci {
                 reference: "oci://ghcr.io/org/postgresql".to_string(),
                 version: "1.2.3".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-02-26T14:03:14Z".to_string(),
             },
         );
@@ -225,7 +412,13 @@ mod tests {
             PluginLockEntry::Git {
                 url: "https://github.com/nanazt/husako".to_string(),
                 path: Some("plugins/fluxcd".to_string()),
+                tag: None,
+                version: Some("0.2.0".to_string()),
+                requirement: None,
+                rev: None,
+                commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
                 plugin_version: "0.2.0".to_string(),
+                digest: None,
                 generated_at: "2026-02-26T14:03:02Z".to_string(),
             },
         );
@@ -240,7 +433,7 @@ mod tests {
         );
 
         HusakoLock {
-            format_version: 1,
+            format_version: LOCK_FORMAT_VERSION,
             husako_version: "0.3.0".to_string(),
             resources,
             charts,
@@ -271,6 +464,93 @@ mod tests {
         assert!(matches!(err, ConfigError::Parse(_)));
     }
 
+    #[test]
+    fn load_migrates_format_1_in_place() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join(LOCK_FILENAME),
+            "format_version = 1\nhusako_version = \"0.1.0\"\n\n\
+             [resources.k8s]\nsource = \"release\"\nversion = \"1.35\"\ngenerated_at = \"2026-01-01T00:00:00Z\"\n\n\
+             [resources.my-crd]\nsource = \"file\"\npath = \"crds\"\ncontent_hash = \"a1b2c3d4e5f67890\"\ngenerated_at = \"2026-01-01T00:00:00Z\"\n",
+        )
+        .unwrap();
+
+        let lock = load_lock(tmp.path()).unwrap().unwrap();
+        assert_eq!(lock.format_version, LOCK_FORMAT_VERSION);
+        assert!(matches!(
+            &lock.resources["my-crd"],
+            ResourceLockEntry::File { content_hash, .. } if content_hash.is_empty()
+        ));
+        assert!(matches!(
+            &lock.resources["k8s"],
+            ResourceLockEntry::Release { version, .. } if version == "1.35"
+        ));
+
+        // Rewritten on disk
+        let content = std::fs::read_to_string(tmp.path().join(LOCK_FILENAME)).unwrap();
+        assert!(content.starts_with(LOCK_HEADER));
+        assert!(content.contains("format_version = 2"));
+    }
+
+    #[test]
+    fn read_lock_leaves_old_format_untouched() {
+        let tmp = tempfile::tempdir().unwrap();
+        let content = "format_version = 1\nhusako_version = \"0.1.0\"\n";
+        std::fs::write(tmp.path().join(LOCK_FILENAME), content).unwrap();
+
+        let lock = read_lock(tmp.path()).unwrap().unwrap();
+        assert_eq!(lock.format_version, 1);
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join(LOCK_FILENAME)).unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn load_newer_format_returns_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join(LOCK_FILENAME),
+            "format_version = 99\nhusako_version = \"9.0.0\"\n",
+        )
+        .unwrap();
+        let err = load_lock(tmp.path()).unwrap_err();
+        assert!(err.to_string().contains("upgrade husako"));
+    }
+
+    #[test]
+    fn digest_mismatch_only_for_same_source() {
+        let registry = |version: &str, digest: &str| ChartLockEntry::Registry {
+            repo: "https://charts.example.com".to_string(),
+            chart: "app".to_string(),
+            version: version.to_string(),
+            requirement: None,
+            digest: Some(digest.to_string()),
+            generated_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        assert_eq!(
+            digest_mismatch(
+                &registry("1.0.0", "sha256:b"),
+                &registry("1.0.0", "sha256:a")
+            ),
+            Some(("sha256:a".to_string(), "sha256:b".to_string()))
+        );
+        assert_eq!(
+            digest_mismatch(
+                &registry("1.0.0", "sha256:a"),
+                &registry("1.0.0", "sha256:a")
+            ),
+            None
+        );
+        assert_eq!(
+            digest_mismatch(
+                &registry("1.1.0", "sha256:b"),
+                &registry("1.0.0", "sha256:a")
+            ),
+            None
+        );
+    }
+
     #[test]
     fn load_unknown_source_tag_returns_error() {
         let tmp = tempfile::tempdir().unwrap();
@@ -301,7 +581,7 @@ mod tests {
     #[test]
     fn empty_lock_serializes_without_empty_sections() {
         let lock = HusakoLock {
-            format_version: 1,
+            format_version: LOCK_FORMAT_VERSION,
             husako_version: "0.1.0".to_string(),
             resources: BTreeMap::new(),
             charts: BTreeMap::new(),
@@ -321,12 +601,18 @@ mod tests {
             PluginLockEntry::Git {
                 url: "https://github.com/example/plugin".to_string(),
                 path: None,
+                tag: None,
+                version: None,
+                requirement: None,
+                rev: None,
+                commit: None,
                 plugin_version: "1.0.0".to_string(),
+                digest: None,
                 generated_at: "2026-02-26T14:00:00Z".to_string(),
             },
         );
         let lock = HusakoLock {
-            format_version: 1,
+            format_version: LOCK_FORMAT_VERSION,
             husako_version: "0.1.0".to_string(),
             resources: BTreeMap::new(),
             charts: BTreeMap::new(),
@@ -0,0 +1,270 @@
+//! Network settings from the user config (`~/.config/husako/config.toml`).
+//!
+//! ```toml
+//! [network]
+//! proxy = "http://proxy.corp.example:3128"
+//! no_proxy = "localhost,.corp.example"
+//! ca_certs = ["/etc/ssl/certs/corp-root.pem"]
+//!
+//! [[network.rewrite]]
+//! from = "https://api.github.com/"
+//! to = "https://github-mirror.corp.example/api/"
+//! ```
+//!
+//! The CLI loads these settings at startup with [`load_network`] into its
+//! [`crate::FetchContext`]. Every HTTP client husako builds starts from
+//! [`Network::client_builder`], and every remote URL passes through
+//! [`Network::rewrite_url`] before it is requested.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::ConfigError;
+
+/// Environment variable pointing at an alternative user config file.
+pub const USER_CONFIG_ENV: &str = "HUSAKO_CONFIG";
+
+/// The user-level config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UserConfig {
+    #[serde(default)]
+    pub network: NetworkConfig,
+}
+
+/// `[network]` section of the user config.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct NetworkConfig {
+    /// Proxy for all HTTP and HTTPS requests. Without it, the usual
+    /// `HTTPS_PROXY` / `HTTP_PROXY` / `NO_PROXY` variables apply.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Comma-separated hosts and domains that bypass `proxy`.
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// PEM files with extra CA certificates to trust, e.g. for a proxy that
+    /// intercepts TLS. Relative paths are resolved against the config file.
+    #[serde(default)]
+    pub ca_certs: Vec<PathBuf>,
+    /// URL prefix rewrites, e.g. to point upstream hosts at internal mirrors.
+    #[serde(default, rename = "rewrite")]
+    pub rewrites: Vec<UrlRewrite>,
+}
+
+/// Requests whose URL starts with `from` go to `to` + the rest of the URL.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct UrlRewrite {
+    pub from: String,
+    pub to: String,
+}
+
+/// Network settings in effect, with the proxy and certificates parsed.
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    proxy: Option<reqwest::Proxy>,
+    certs: Vec<reqwest::Certificate>,
+    rewrites: Vec<UrlRewrite>,
+}
+
+/// Location of the user config: `$HUSAKO_CONFIG`, else
+/// `$XDG_CONFIG_HOME/husako/config.toml`, else `~/.config/husako/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(USER_CONFIG_ENV).filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(xdg).join("husako/config.toml"));
+    }
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(|home| PathBuf::from(home).join(".config/husako/config.toml"))
+}
+
+/// Load the user config and resolve its network settings. A missing file
+/// yields the defaults.
+pub fn load_network(path: &Path) -> Result<Network, ConfigError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Network::default()),
+        Err(e) => {
+            return Err(ConfigError::Io {
+                path: path.display().to_string(),
+                source: e,
+            });
+        }
+    };
+    let config: UserConfig = toml::from_str(&content).map_err(|e| invalid(path, e))?;
+    resolve(&config.network, path.parent().unwrap_or(Path::new(".")))
+        .map_err(|message| invalid(path, message))
+}
+
+fn invalid(path: &Path, message: impl std::fmt::Display) -> ConfigError {
+    ConfigError::UserConfig {
+        path: path.display().to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Parse the proxy URL and read the CA files of `config`.
+fn resolve(config: &NetworkConfig, base: &Path) -> Result<Network, String> {
+    let proxy = match &config.proxy {
+        Some(url) => {
+            let proxy =
+                reqwest::Proxy::all(url).map_err(|e| format!("invalid proxy '{url}': {e}"))?;
+            Some(
+                proxy.no_proxy(
+                    config
+                        .no_proxy
+                        .as_deref()
+                        .and_then(reqwest::NoProxy::from_string),
+                ),
+            )
+        }
+        None => None,
+    };
+
+    let mut certs = Vec::new();
+    for path in &config.ca_certs {
+        let path = base.join(path);
+        let pem = std::fs::read(&path)
+            .map_err(|e| format!("cannot read CA file {}: {e}", path.display()))?;
+        let bundle = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("invalid CA file {}: {e}", path.display()))?;
+        if bundle.is_empty() {
+            return Err(format!("no certificates in CA file {}", path.display()));
+        }
+        certs.extend(bundle);
+    }
+
+    for rewrite in &config.rewrites {
+        if rewrite.from.is_empty() {
+            return Err("rewrite rule with an empty 'from'".to_string());
+        }
+    }
+
+    Ok(Network {
+        proxy,
+        certs,
+        rewrites: config.rewrites.clone(),
+    })
+}
+
+impl Network {
+    /// A `reqwest::ClientBuilder` with the configured proxy and CA certificates.
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if !self.certs.is_empty() {
+            builder = builder.tls_certs_merge(self.certs.iter().cloned());
+        }
+        builder
+    }
+
+    /// `url` after the configured rewrite rules; the longest matching `from`
+    /// wins. Unchanged when no rule matches.
+    pub fn rewrite_url(&self, url: &str) -> String {
+        self.rewrites
+            .iter()
+            .filter(|r| url.starts_with(&r.from))
+            .max_by_key(|r| r.from.len())
+            .map(|r| format!("{}{}", r.to, &url[r.from.len()..]))
+            .unwrap_or_else(|| url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(from: &str, to: &str) -> UrlRewrite {
+        UrlRewrite {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn rewrite_prefers_longest_prefix() {
+        let network = Network {
+            rewrites: vec![
+                rewrite("oci://ghcr.io/", "oci://registry.corp/ghcr/"),
+                rewrite("oci://ghcr.io/acme/", "oci://registry.corp/acme/"),
+                rewrite("https://artifacthub.io", "https://hub.corp"),
+            ],
+            ..Network::default()
+        };
+        assert_eq!(
+            network.rewrite_url("oci://ghcr.io/stefanprodan/charts/podinfo"),
+            "oci://registry.corp/ghcr/stefanprodan/charts/podinfo"
+        );
+        assert_eq!(
+            network.rewrite_url("oci://ghcr.io/acme/app"),
+            "oci://registry.corp/acme/app"
+        );
+        assert_eq!(
+            network.rewrite_url("https://artifacthub.io/api/v1/packages/helm/bitnami/redis"),
+            "https://hub.corp/api/v1/packages/helm/bitnami/redis"
+        );
+        assert_eq!(
+            network.rewrite_url("https://charts.example.com/index.yaml"),
+            "https://charts.example.com/index.yaml"
+        );
+    }
+
+    #[test]
+    fn load_missing_file_is_default() {
+        let tmp = tempfile::tempdir().unwrap();
+        let network = load_network(&tmp.path().join("config.toml")).unwrap();
+        assert!(network.proxy.is_none());
+        assert!(network.rewrites.is_empty());
+    }
+
+    #[test]
+    fn load_parses_network_section() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+[network]
+proxy = "http://proxy.corp:3128"
+no_proxy = "localhost,.corp"
+
+[[network.rewrite]]
+from = "https://api.github.com/"
+to = "https://mirror.corp/github/"
+"#,
+        )
+        .unwrap();
+        let network = load_network(&path).unwrap();
+        assert!(network.proxy.is_some());
+        assert_eq!(
+            network.rewrite_url("https://api.github.com/repos/kubernetes/kubernetes"),
+            "https://mirror.corp/github/repos/kubernetes/kubernetes"
+        );
+    }
+
+    #[test]
+    fn load_rejects_bad_settings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.toml");
+
+        std::fs::write(&path, "[network]\nca_certs = [\"missing.pem\"]\n").unwrap();
+        let err = load_network(&path).unwrap_err().to_string();
+        assert!(err.contains("cannot read CA file"), "{err}");
+        assert!(err.contains("missing.pem"), "{err}");
+
+        std::fs::write(tmp.path().join("empty.pem"), "").unwrap();
+        std::fs::write(&path, "[network]\nca_certs = [\"empty.pem\"]\n").unwrap();
+        let err = load_network(&path).unwrap_err().to_string();
+        assert!(err.contains("no certificates"), "{err}");
+
+        std::fs::write(&path, "[network]\nproxy = 3128\n").unwrap();
+        assert!(matches!(
+            load_network(&path),
+            Err(ConfigError::UserConfig { .. })
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+//! Offline mode (`--offline` / `HUSAKO_OFFLINE`).
+//!
+//! Carried by [`crate::FetchContext::offline`]: every resolver that would
+//! reach the network checks it first and serves from `.husako/cache` only,
+//! failing with the missing cache entry when there is nothing to serve.
+
+/// Environment variable that enables offline mode when set to anything other
+/// than an empty string, `0` or `false`.
+pub const OFFLINE_ENV: &str = "HUSAKO_OFFLINE";
+
+/// Whether `HUSAKO_OFFLINE` enables offline mode.
+pub fn offline_from_env() -> bool {
+    std::env::var(OFFLINE_ENV).is_ok_and(|v| env_enabled(&v))
+}
+
+fn env_enabled(value: &str) -> bool {
+    !matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "" | "0" | "false"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_values() {
+        for value in ["1", "true", "yes", "TRUE"] {
+            assert!(env_enabled(value), "{value}");
+        }
+        for value in ["", "0", "false", "False"] {
+            assert!(!env_enabled(value), "{value}");
+        }
+    }
+}
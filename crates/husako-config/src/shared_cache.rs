@@ -0,0 +1,463 @@
+//! Shared user-level cache (`$XDG_CACHE_HOME/husako`).
+//!
+//! Fetchers consult it before the network and publish what they download, so
+//! a Kubernetes release or chart fetched by one project is reused by every
+//! other project on the machine. Content is addressed by SHA-256:
+//!
+//! ```text
+//! <dir>/blobs/sha256/<hex>        file contents, shared between entries
+//! <dir>/entries/<hex(key)>.toml   key → project-cache-relative files → blob
+//! ```
+//!
+//! An entry's key is the path it occupies under `.husako/cache` (e.g.
+//! `release/v1.35.0`), and restoring it copies its files back to that path.
+//! Blobs are verified against their digest on restore, and a corrupted entry
+//! is dropped so the caller falls back to fetching.
+//!
+//! Fetchers reach it through [`crate::FetchContext`], whose `shared_cache`
+//! the CLI sets to [`default_dir`]; without a directory the cache is off.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigError;
+use husako_digest::{sha256_digest, sha256_hex};
+
+/// Environment variable overriding the shared cache location. An empty value
+/// disables the shared cache.
+pub const CACHE_DIR_ENV: &str = "HUSAKO_CACHE_DIR";
+
+/// Default location: `$HUSAKO_CACHE_DIR`, else `$XDG_CACHE_HOME/husako`,
+/// else `~/.cache/husako`. `None` when `HUSAKO_CACHE_DIR` is empty or no
+/// home directory is known.
+pub fn default_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV) {
+        return (!dir.is_empty()).then(|| PathBuf::from(dir));
+    }
+    if let Some(xdg) = std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(xdg).join("husako"));
+    }
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(|home| PathBuf::from(home).join(".cache/husako"))
+}
+
+/// A cached entry as recorded on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    key: String,
+    /// Project-cache-relative path → `sha256:<hex>` blob digest.
+    files: BTreeMap<String, String>,
+    created: u64,
+    last_used: u64,
+}
+
+/// Summary of a shared cache entry for `husako cache list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub key: String,
+    /// Total size of the entry's files in bytes.
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+/// What [`prune`] removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PruneResult {
+    pub entries_removed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Whether the shared cache at `dir` holds an entry for `key`.
+pub fn contains(dir: &Path, key: &str) -> bool {
+    entry_path(dir, key).is_file()
+}
+
+/// Record `paths` (files or directories relative to `cache_dir`) as the entry
+/// for `key` in the shared cache at `dir`. Best-effort: a failed write only
+/// means the next project fetches again.
+pub fn publish(dir: &Path, cache_dir: &Path, key: &str, paths: &[&str]) {
+    let _ = publish_to(dir, cache_dir, key, paths);
+}
+
+/// List entries, most recently used first.
+pub fn list(dir: &Path) -> Result<Vec<CacheEntry>, ConfigError> {
+    let mut entries: Vec<CacheEntry> = read_manifests(dir)?
+        .into_iter()
+        .map(|(_, manifest)| CacheEntry {
+            size: manifest
+                .files
+                .values()
+                .map(|digest| blob_size(dir, digest))
+                .sum(),
+            last_used: UNIX_EPOCH + Duration::from_secs(manifest.last_used),
+            key: manifest.key,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.last_used.cmp(&a.last_used).then(a.key.cmp(&b.key)));
+    Ok(entries)
+}
+
+/// Remove entries unused for longer than `older_than`, then evict the least
+/// recently used entries until the blobs total at most `max_size` bytes.
+/// Blobs no longer referenced by any entry are deleted.
+pub fn prune(
+    dir: &Path,
+    older_than: Option<Duration>,
+    max_size: Option<u64>,
+) -> Result<PruneResult, ConfigError> {
+    let mut manifests = read_manifests(dir)?;
+    manifests.sort_by_key(|(_, m)| m.last_used);
+
+    let now = now_secs();
+    let mut removed = vec![false; manifests.len()];
+    if let Some(age) = older_than {
+        let cutoff = now.saturating_sub(age.as_secs());
+        for (i, (_, manifest)) in manifests.iter().enumerate() {
+            removed[i] = manifest.last_used < cutoff;
+        }
+    }
+
+    if let Some(max) = max_size {
+        let mut refs: HashMap<&str, usize> = HashMap::new();
+        for ((_, manifest), _) in manifests.iter().zip(&removed).filter(|(_, r)| !**r) {
+            for digest in manifest.files.values() {
+                *refs.entry(digest.as_str()).or_default() += 1;
+            }
+        }
+        let mut total: u64 = refs.keys().map(|digest| blob_size(dir, digest)).sum();
+        for (i, (_, manifest)) in manifests.iter().enumerate() {
+            if total <= max {
+                break;
+            }
+            if removed[i] {
+                continue;
+            }
+            removed[i] = true;
+            for digest in manifest.files.values() {
+                if let Some(count) = refs.get_mut(digest.as_str()) {
+                    *count -= 1;
+                    if *count == 0 {
+                        total = total.saturating_sub(blob_size(dir, digest));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut result = PruneResult::default();
+    for ((path, _), removed) in manifests.iter().zip(&removed) {
+        if *removed {
+            remove_file(path)?;
+            result.entries_removed += 1;
+        }
+    }
+    result.bytes_freed = collect_garbage(dir)?;
+    Ok(result)
+}
+
+/// Copy the entry for `key` in the shared cache at `dir` into `cache_dir`.
+/// Returns `false` when there is no such entry or it failed verification.
+pub fn restore(dir: &Path, cache_dir: &Path, key: &str) -> bool {
+    let path = entry_path(dir, key);
+    let Some(mut manifest) = read_manifest(&path) else {
+        return false;
+    };
+
+    // Read and verify everything before writing, so a bad blob never leaves
+    // a half-restored entry in the project cache.
+    let mut files = Vec::with_capacity(manifest.files.len());
+    for (rel, digest) in &manifest.files {
+        match std::fs::read(blob_path(dir, digest)) {
+            Ok(data) if sha256_digest(&data) == *digest => files.push((rel, data)),
+            _ => {
+                let _ = std::fs::remove_file(&path);
+                let _ = std::fs::remove_file(blob_path(dir, digest));
+                return false;
+            }
+        }
+    }
+    for (rel, data) in files {
+        let target = cache_dir.join(rel);
+        if let Some(parent) = target.parent()
+            && std::fs::create_dir_all(parent).is_err()
+        {
+            return false;
+        }
+        if std::fs::write(&target, data).is_err() {
+            return false;
+        }
+    }
+
+    manifest.last_used = now_secs();
+    let _ = write_manifest(&path, &manifest);
+    true
+}
+
+fn publish_to(dir: &Path, cache_dir: &Path, key: &str, paths: &[&str]) -> std::io::Result<()> {
+    let mut files = BTreeMap::new();
+    for rel in paths {
+        let full = cache_dir.join(rel);
+        if full.is_dir() {
+            collect_files(cache_dir, &full, &mut files)?;
+        } else if full.is_file() {
+            files.insert(rel.to_string(), full);
+        }
+    }
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let mut digests = BTreeMap::new();
+    for (rel, full) in files {
+        let data = std::fs::read(&full)?;
+        let digest = sha256_digest(&data);
+        let blob = blob_path(dir, &digest);
+        if !blob.is_file() {
+            write_atomic(&blob, &data)?;
+        }
+        digests.insert(rel, digest);
+    }
+
+    let now = now_secs();
+    let manifest = Manifest {
+        key: key.to_string(),
+        files: digests,
+        created: now,
+        last_used: now,
+    };
+    write_manifest(&entry_path(dir, key), &manifest)
+}
+
+/// Insert every file under `root` into `files`, keyed by its path relative
+/// to `base` with `/` separators.
+fn collect_files(
+    base: &Path,
+    root: &Path,
+    files: &mut BTreeMap<String, PathBuf>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(base, &path, files)?;
+        } else if let Ok(rel) = path.strip_prefix(base) {
+            let rel = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(rel, path);
+        }
+    }
+    Ok(())
+}
+
+fn read_manifests(dir: &Path) -> Result<Vec<(PathBuf, Manifest)>, ConfigError> {
+    let entries_dir = dir.join("entries");
+    let read_dir = match std::fs::read_dir(&entries_dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error(&entries_dir, e)),
+    };
+    let mut manifests = Vec::new();
+    for entry in read_dir {
+        let path = entry.map_err(|e| io_error(&entries_dir, e))?.path();
+        if path.extension().is_some_and(|ext| ext == "toml")
+            && let Some(manifest) = read_manifest(&path)
+        {
+            manifests.push((path, manifest));
+        }
+    }
+    Ok(manifests)
+}
+
+fn read_manifest(path: &Path) -> Option<Manifest> {
+    let content = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+fn write_manifest(path: &Path, manifest: &Manifest) -> std::io::Result<()> {
+    let content = toml::to_string(manifest).map_err(std::io::Error::other)?;
+    write_atomic(path, content.as_bytes())
+}
+
+/// Delete blobs not referenced by any entry. Returns the bytes freed.
+fn collect_garbage(dir: &Path) -> Result<u64, ConfigError> {
+    let referenced: std::collections::HashSet<String> = read_manifests(dir)?
+        .into_iter()
+        .flat_map(|(_, m)| m.files.into_values())
+        .filter_map(|digest| digest.strip_prefix("sha256:").map(str::to_string))
+        .collect();
+
+    let blobs_dir = dir.join("blobs/sha256");
+    let read_dir = match std::fs::read_dir(&blobs_dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(io_error(&blobs_dir, e)),
+    };
+    let mut freed = 0;
+    for entry in read_dir {
+        let entry = entry.map_err(|e| io_error(&blobs_dir, e))?;
+        if referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+            continue;
+        }
+        freed += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        remove_file(&entry.path())?;
+    }
+    Ok(freed)
+}
+
+fn remove_file(path: &Path) -> Result<(), ConfigError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error(path, e)),
+        _ => Ok(()),
+    }
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> ConfigError {
+    ConfigError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+/// Write via a temporary sibling and rename, so concurrent readers never see
+/// a partial file.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+fn entry_path(dir: &Path, key: &str) -> PathBuf {
+    let hex = sha256_hex(key.as_bytes());
+    dir.join(format!("entries/{hex}.toml"))
+}
+
+fn blob_path(dir: &Path, digest: &str) -> PathBuf {
+    dir.join("blobs/sha256")
+        .join(digest.trim_start_matches("sha256:"))
+}
+
+fn blob_size(dir: &Path, digest: &str) -> u64 {
+    std::fs::metadata(blob_path(dir, digest))
+        .map(|m| m.len())
+        .unwrap_or(0)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(project: &Path, rel: &str, content: &str) {
+        let path = project.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn set_last_used(dir: &Path, key: &str, last_used: u64) {
+        let path = entry_path(dir, key);
+        let mut manifest = read_manifest(&path).unwrap();
+        manifest.last_used = last_used;
+        write_manifest(&path, &manifest).unwrap();
+    }
+
+    #[test]
+    fn publish_then_restore_into_another_project() {
+        let tmp = tempfile::tempdir().unwrap();
+        let shared = tmp.path().join("shared");
+        let a = tmp.path().join("a");
+        seed(&a, "release/v1.35.0/api__v1.json", "{}");
+        seed(&a, "release/v1.35.0/apis__apps__v1.json", "{\"a\":1}");
+
+        publish_to(&shared, &a, "release/v1.35.0", &["release/v1.35.0"]).unwrap();
+        assert!(entry_path(&shared, "release/v1.35.0").is_file());
+
+        let b = tmp.path().join("b");
+        assert!(restore(&shared, &b, "release/v1.35.0"));
+        assert_eq!(
+            std::fs::read_to_string(b.join("release/v1.35.0/apis__apps__v1.json")).unwrap(),
+            "{\"a\":1}"
+        );
+        assert!(!restore(&shared, &b, "release/v1.36.0"));
+    }
+
+    #[test]
+    fn identical_files_share_a_blob() {
+        let tmp = tempfile::tempdir().unwrap();
+        let shared = tmp.path().join("shared");
+        seed(tmp.path(), "p/x.json", "same");
+        seed(tmp.path(), "p/y.json", "same");
+        publish_to(&shared, tmp.path(), "x", &["p/x.json"]).unwrap();
+        publish_to(&shared, tmp.path(), "y", &["p/y.json"]).unwrap();
+
+        let blobs = std::fs::read_dir(shared.join("blobs/sha256")).unwrap();
+        assert_eq!(blobs.count(), 1);
+        let entries = list(&shared).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.size == 4));
+    }
+
+    #[test]
+    fn corrupted_blob_drops_the_entry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let shared = tmp.path().join("shared");
+        seed(tmp.path(), "a/f.json", "original");
+        publish_to(&shared, &tmp.path().join("a"), "k", &["f.json"]).unwrap();
+
+        let digest = sha256_digest(b"original");
+        std::fs::write(blob_path(&shared, &digest), "tampered").unwrap();
+
+        let b = tmp.path().join("b");
+        assert!(!restore(&shared, &b, "k"));
+        assert!(!b.join("f.json").exists());
+        assert!(!entry_path(&shared, "k").exists());
+    }
+
+    #[test]
+    fn prune_by_age_and_size() {
+        let tmp = tempfile::tempdir().unwrap();
+        let shared = tmp.path().join("shared");
+        seed(tmp.path(), "old.json", "0123456789");
+        seed(tmp.path(), "mid.json", "abcdefghij");
+        seed(tmp.path(), "new.json", "ABCDEFGHIJ");
+        for key in ["old", "mid", "new"] {
+            publish_to(&shared, tmp.path(), key, &[&format!("{key}.json")]).unwrap();
+        }
+        let now = now_secs();
+        set_last_used(&shared, "old", now - 40 * 86_400);
+        set_last_used(&shared, "mid", now - 86_400);
+
+        let result = prune(&shared, Some(Duration::from_secs(30 * 86_400)), None).unwrap();
+        assert_eq!(result.entries_removed, 1);
+        assert_eq!(result.bytes_freed, 10);
+
+        // 20 bytes remain; capping at 15 evicts the least recently used.
+        let result = prune(&shared, None, Some(15)).unwrap();
+        assert_eq!(result.entries_removed, 1);
+        let keys: Vec<_> = list(&shared).unwrap().into_iter().map(|e| e.key).collect();
+        assert_eq!(keys, vec!["new"]);
+    }
+
+    #[test]
+    fn prune_empty_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let result = prune(&tmp.path().join("missing"), None, Some(0)).unwrap();
+        assert_eq!(result, PruneResult::default());
+    }
+}
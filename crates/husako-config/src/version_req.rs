@@ -0,0 +1,130 @@
+//! Semver requirements in `husako.toml` version fields.
+//!
+//! A `version` is either an exact pin (`"4.12.0"`, or `"1.35"` for a
+//! Kubernetes release) or a requirement such as `"^4.12"`, `"~1.17"` or
+//! `">=1.33, <1.36"`. Requirements are resolved to a concrete version that
+//! is recorded in `husako.lock`.
+
+use semver::{Version, VersionReq};
+
+/// Whether `spec` is a semver requirement rather than an exact pin.
+pub fn is_requirement(spec: &str) -> bool {
+    let spec = spec.trim();
+    spec.starts_with(['^', '~', '<', '>', '=', '*']) || spec.contains([',', '*'])
+}
+
+/// Parse a requirement such as `^4.12` or `>=1.33, <1.36`.
+pub fn parse_requirement(spec: &str) -> Result<VersionReq, String> {
+    VersionReq::parse(spec.trim()).map_err(|e| e.to_string())
+}
+
+/// Parse a version leniently: a leading `v` is ignored and missing minor or
+/// patch components count as zero, so `"v1.35"` is `1.35.0`.
+pub fn parse_version(version: &str) -> Option<Version> {
+    let v = version.trim();
+    let v = v.strip_prefix('v').unwrap_or(v);
+    if v.contains(['-', '+']) {
+        return Version::parse(v).ok();
+    }
+    match v.matches('.').count() {
+        0 => Version::parse(&format!("{v}.0.0")).ok(),
+        1 => Version::parse(&format!("{v}.0")).ok(),
+        _ => Version::parse(v).ok(),
+    }
+}
+
+/// Whether `version` satisfies `req`.
+pub fn satisfies(req: &VersionReq, version: &str) -> bool {
+    parse_version(version).is_some_and(|v| req.matches(&v))
+}
+
+/// The newest of `versions` that satisfies `req`, as written in `versions`.
+pub fn newest_matching<'a>(
+    req: &VersionReq,
+    versions: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    versions
+        .into_iter()
+        .filter_map(|v| Some((parse_version(v)?, v)))
+        .filter(|(parsed, _)| req.matches(parsed))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, v)| v)
+}
+
+/// A requirement of the same kind as `spec` that admits `version`.
+///
+/// `^` and `~` requirements keep their operator and precision
+/// (`^4.12` → `^5.1`); any other requirement becomes `^<version>`.
+pub fn bump_requirement(spec: &str, version: &str) -> String {
+    let spec = spec.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    if let Some(op) = spec.chars().next().filter(|c| matches!(c, '^' | '~'))
+        && !spec.contains(',')
+    {
+        let precision = spec[1..].trim().split('.').count();
+        let parts: Vec<&str> = version.split('.').take(precision).collect();
+        return format!("{op}{}", parts.join("."));
+    }
+    format!("^{version}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirement_or_pin() {
+        for spec in [
+            "^4.12",
+            "~1.17",
+            ">=1.33, <1.36",
+            ">=1.33,<1.36",
+            "=1.2.3",
+            "*",
+            "1.*",
+        ] {
+            assert!(is_requirement(spec), "{spec}");
+            assert!(parse_requirement(spec).is_ok(), "{spec}");
+        }
+        for spec in ["4.12.0", "1.35", "v1.17.2"] {
+            assert!(!is_requirement(spec), "{spec}");
+        }
+        assert!(parse_requirement("^4.x.y").is_err());
+    }
+
+    #[test]
+    fn lenient_versions() {
+        assert_eq!(parse_version("v1.35"), Some(Version::new(1, 35, 0)));
+        assert_eq!(parse_version("2"), Some(Version::new(2, 0, 0)));
+        assert_eq!(parse_version("4.12.3"), Some(Version::new(4, 12, 3)));
+        assert!(parse_version("1.0.0-rc.1").is_some_and(|v| !v.pre.is_empty()));
+        assert_eq!(parse_version("latest"), None);
+    }
+
+    #[test]
+    fn newest_matching_respects_range() {
+        let versions = ["1.32", "1.33", "1.35", "1.36", "2.0"];
+        let req = parse_requirement(">=1.33, <1.36").unwrap();
+        assert_eq!(newest_matching(&req, versions), Some("1.35"));
+        let req = parse_requirement("~1.32").unwrap();
+        assert_eq!(newest_matching(&req, versions), Some("1.32"));
+        let req = parse_requirement("^3").unwrap();
+        assert_eq!(newest_matching(&req, versions), None);
+
+        let req = parse_requirement("^4.12").unwrap();
+        assert_eq!(
+            newest_matching(&req, ["4.12.0", "4.13.1", "5.0.0", "4.14.0-rc.1"]),
+            Some("4.13.1")
+        );
+        assert!(satisfies(&req, "v4.12.2"));
+        assert!(!satisfies(&req, "5.0.0"));
+    }
+
+    #[test]
+    fn bump_keeps_operator_and_precision() {
+        assert_eq!(bump_requirement("^4.12", "5.1.3"), "^5.1");
+        assert_eq!(bump_requirement("~1.17.2", "v2.0.1"), "~2.0.1");
+        assert_eq!(bump_requirement("^1.33", "1.36"), "^1.36");
+        assert_eq!(bump_requirement(">=1.33, <1.36", "1.37"), "^1.37");
+    }
+}
@@ -0,0 +1,182 @@
+//! Per-source k8s type generation.
+//!
+//! The specs each `[resources]` entry resolved to are kept, with the
+//! group-versions and `_schema.json` fragment they produce, in
+//! `.husako/cache/k8s/<name>.json`. A run then resolves only the sources
+//! whose lock entry no longer matches and emits only the group-version files
+//! they touch, merging everything into `_common` and `_schema.json` at the
+//! end.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use husako_config::{FetchContext, HusakoConfig};
+use husako_dts::SourceOutput;
+
+use crate::progress::ProgressReporter;
+use crate::{HusakoError, schema_source, write_file, write_schema_bin};
+
+/// What to regenerate, decided from the lock before anything is fetched.
+pub struct Plan<'a> {
+    /// Sources whose lock entry no longer matches.
+    pub changed: BTreeSet<String>,
+    /// Sources in the previous lock that are no longer configured.
+    pub removed: Vec<&'a String>,
+    /// Emit every group-version, e.g. after an upgrade or when the generated
+    /// files are missing.
+    pub full: bool,
+}
+
+/// Outputs of every source, and which group-versions to emit from them.
+pub struct Resolved {
+    cache_dir: PathBuf,
+    outputs: BTreeMap<String, SourceOutput>,
+    /// Sources resolved in this run, whose outputs are stored on writing.
+    fresh: BTreeSet<String>,
+    removed: Vec<String>,
+    /// `None` to emit every group-version.
+    affected: Option<BTreeSet<String>>,
+}
+
+/// Resolve the changed sources of `config` and load the stored outputs of
+/// the others.
+pub async fn resolve(
+    config: &HusakoConfig,
+    plan: Plan<'_>,
+    frozen: bool,
+    project_root: &Path,
+    progress: &dyn ProgressReporter,
+    ctx: &FetchContext,
+) -> Result<Resolved, HusakoError> {
+    let cache_dir = project_root.join(".husako/cache");
+
+    // Reuse the stored outputs of unchanged sources; one that is missing
+    // is resolved again, which is cheap from the fetch caches.
+    let mut outputs: BTreeMap<String, SourceOutput> = BTreeMap::new();
+    let mut to_resolve = plan.changed;
+    for name in config.resources.keys() {
+        if to_resolve.contains(name) {
+            continue;
+        }
+        match load(&cache_dir, name) {
+            Some(output) => {
+                outputs.insert(name.clone(), output);
+            }
+            None => {
+                to_resolve.insert(name.clone());
+            }
+        }
+    }
+
+    // Group-versions the previous outputs of changed and removed sources
+    // were in, and the `_common` types as of the previous run
+    let mut affected = (!plan.full).then(BTreeSet::new);
+    let mut old_common: BTreeSet<String> =
+        outputs.values().flat_map(|o| o.common.clone()).collect();
+    for name in to_resolve.iter().chain(plan.removed.iter().copied()) {
+        match load(&cache_dir, name) {
+            Some(old) => {
+                if let Some(affected) = &mut affected {
+                    affected.extend(old.group_versions);
+                }
+                old_common.extend(old.common);
+            }
+            None => affected = None,
+        }
+    }
+
+    if frozen {
+        let mut uncached: Vec<String> = to_resolve
+            .iter()
+            .filter(|name| {
+                !schema_source::is_cached(&config.resources[name.as_str()], config, &cache_dir, ctx)
+            })
+            .map(|name| format!("resource '{name}'"))
+            .collect();
+        if !uncached.is_empty() {
+            uncached.sort();
+            return Err(HusakoError::FrozenFetch(uncached));
+        }
+    }
+
+    let resolved =
+        schema_source::resolve_each(config, &to_resolve, project_root, &cache_dir, progress, ctx)
+            .await?;
+    let mut fresh = BTreeSet::new();
+    for (name, specs) in resolved {
+        let output = SourceOutput::new(specs);
+        if let Some(affected) = &mut affected {
+            affected.extend(output.group_versions.iter().cloned());
+        }
+        fresh.insert(name.clone());
+        outputs.insert(name, output);
+    }
+
+    // Every group-version file names the `_common` types it may use
+    let common: BTreeSet<String> = outputs.values().flat_map(|o| o.common.clone()).collect();
+    if common != old_common {
+        affected = None;
+    }
+
+    Ok(Resolved {
+        cache_dir,
+        outputs,
+        fresh,
+        removed: plan.removed.into_iter().cloned().collect(),
+        affected,
+    })
+}
+
+impl Resolved {
+    /// Write the affected group-version files, `_common`, `_schema.json` and
+    /// `_schema.bin` under `types_dir`, and remove those of group-versions no
+    /// source has any more. The outputs of sources resolved in this run are
+    /// stored for the next. Returns the number of group-versions emitted and the total.
+    pub fn write(self, types_dir: &Path) -> Result<(usize, usize), HusakoError> {
+        let sources: Vec<&SourceOutput> = self.outputs.values().collect();
+        let result = husako_dts::generate_from_sources(&sources, self.affected.as_ref())?;
+        for (rel_path, content) in &result.files {
+            write_file(&types_dir.join(rel_path), content)?;
+        }
+        write_schema_bin(types_dir, &result.schema_bin)?;
+
+        if let Some(affected) = &self.affected {
+            for gv in affected {
+                for ext in ["d.ts", "js"] {
+                    let rel_path = format!("k8s/{gv}.{ext}");
+                    if !result.files.contains_key(&rel_path) {
+                        let _ = std::fs::remove_file(types_dir.join(rel_path));
+                    }
+                }
+            }
+        }
+
+        for name in &self.fresh {
+            save(&self.cache_dir, name, &self.outputs[name])?;
+        }
+        for name in &self.removed {
+            let _ = std::fs::remove_file(path(&self.cache_dir, name));
+        }
+
+        let total = result.group_versions.len();
+        let emitted = self.affected.map_or(total, |affected| {
+            affected.intersection(&result.group_versions).count()
+        });
+        Ok((emitted, total))
+    }
+}
+
+fn path(cache_dir: &Path, name: &str) -> PathBuf {
+    cache_dir.join(format!("k8s/{}.json", name.replace(['/', '\\'], "_")))
+}
+
+fn load(cache_dir: &Path, name: &str) -> Option<SourceOutput> {
+    let content = std::fs::read(path(cache_dir, name)).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn save(cache_dir: &Path, name: &str, output: &SourceOutput) -> Result<(), HusakoError> {
+    let content = serde_json::to_string(output)
+        .map_err(|e| HusakoError::GenerateIo(format!("serialize resource '{name}': {e}")))?;
+    write_file(&path(cache_dir, name), &content)
+}
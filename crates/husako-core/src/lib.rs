@@ -1,9 +1,13 @@
 pub mod emit;
+mod k8s_sources;
 pub mod lock_check;
 pub mod plugin;
 pub mod progress;
 pub mod quantity;
+pub mod requirements;
+pub mod schema_diff;
 pub mod schema_source;
+mod session;
 pub mod validate;
 pub mod version_check;
 
@@ -15,6 +19,7 @@ use std::str::FromStr;
 
 use husako_runtime_qjs::ExecuteOptions;
 
+use husako_config::version_req;
 use progress::ProgressReporter;
 
 #[derive(Debug, thiserror::Error)]
@@ -37,6 +42,18 @@ pub enum HusakoError {
     Validation(String),
     #[error("generate I/O error: {0}")]
     GenerateIo(String),
+    #[error(
+        "husako.lock is out of date:\n  {}\nrun `husako gen` without --locked/--frozen to update it",
+        .0.join("\n  ")
+    )]
+    LockOutOfDate(Vec<String>),
+    #[error(
+        "--frozen forbids network access, but these are not cached in .husako/cache:\n  {}",
+        .0.join("\n  ")
+    )]
+    FrozenFetch(Vec<String>),
+    #[error("offline mode: {0}")]
+    Offline(String),
 }
 
 pub struct RenderOptions {
@@ -49,6 +66,7 @@ pub struct RenderOptions {
 }
 
 pub use husako_runtime_qjs::TestCaseResult;
+pub use session::{RenderSession, generate_inputs};
 
 pub struct TestOptions {
     pub project_root: PathBuf,
@@ -70,12 +88,25 @@ pub async fn render(
     filename: &str,
     options: &RenderOptions,
     progress: &dyn ProgressReporter,
+) -> Result<String, HusakoError> {
+    render_with_modules(source, filename, options, None, None, progress).await
+}
+
+/// [`render`], loading unchanged file modules from `modules` and recording
+/// the files read into `loaded` when given.
+async fn render_with_modules(
+    source: &str,
+    filename: &str,
+    options: &RenderOptions,
+    modules: Option<&husako_runtime_qjs::ModuleCache>,
+    loaded: Option<&husako_runtime_qjs::LoadedFiles>,
+    progress: &dyn ProgressReporter,
 ) -> Result<String, HusakoError> {
     progress.set_total(4);
 
     // Phase 1: Compile
     let compile_task = progress.start_task(&format!("Compiling {}...", filename));
-    let js = match husako_compile_oxc::compile(source, filename) {
+    let js = match compile_cache(&options.project_root).compile(source, filename) {
         Ok(js) => js,
         Err(e) => {
             compile_task.finish_err(&format!("Compile failed: {e}"));
@@ -112,6 +143,10 @@ pub async fn render(
         max_heap_mb: options.max_heap_mb,
         generated_types_dir,
         plugin_modules,
+        chart_renderer: chart_renderer(&options.project_root, loaded.cloned()),
+        compile_cache_dir: Some(compile_cache_dir(&options.project_root)),
+        module_cache: modules.cloned(),
+        loaded_files: loaded.cloned(),
     };
 
     // Phase 2: Execute
@@ -192,7 +227,20 @@ pub async fn render(
     Ok(yaml)
 }
 
-/// Load a `SchemaStore` from `.husako/types/k8s/_schema.json` if it exists.
+/// Size the compile cache is pruned back to by each `husako gen`.
+const COMPILE_CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Where `.ts` modules compiled by earlier runs are kept.
+fn compile_cache_dir(project_root: &Path) -> PathBuf {
+    project_root.join(".husako/cache/compile")
+}
+
+fn compile_cache(project_root: &Path) -> husako_compile_oxc::CompileCache {
+    husako_compile_oxc::CompileCache::new(compile_cache_dir(project_root))
+}
+
+/// Load a `SchemaStore` from `.husako/types/k8s/`, preferring `_schema.bin`
+/// over `_schema.json`.
 pub fn load_schema_store(project_root: &Path) -> Option<validate::SchemaStore> {
     validate::load_schema_store(project_root)
 }
@@ -210,6 +258,14 @@ pub struct GenerateOptions {
     /// When true, skip all lock-file checks and regenerate everything.
     /// The lock is still written at the end so the next run is incremental.
     pub no_incremental: bool,
+    /// Fail instead of updating `husako.lock` when it disagrees with the
+    /// config; the lock is never written.
+    pub locked: bool,
+    /// Like `locked`, and additionally fail when anything would have to be
+    /// fetched over the network instead of read from `.husako/cache`.
+    pub frozen: bool,
+    /// Settings for fetching schema sources, charts and plugins.
+    pub fetch: husako_config::FetchContext,
 }
 
 pub async fn generate(
@@ -219,17 +275,72 @@ pub async fn generate(
     let types_dir = options.project_root.join(".husako/types");
     let mut any_work_done = false;
 
-    // Load existing lock (None if --no-incremental or file absent / unreadable)
-    let old_lock: Option<husako_config::HusakoLock> = if options.no_incremental {
+    // Drop compiled modules of edited sources and older compilers
+    compile_cache(&options.project_root).prune(COMPILE_CACHE_MAX_BYTES);
+
+    // Fetch branches afresh in each run, such as every regeneration in watch
+    husako_git::forget_moving_refs();
+
+    // --frozen implies --locked: the lock is read as stored and never written
+    let locked_mode = options.locked || options.frozen;
+    // ...and --offline: nothing is fetched
+    let fetch = husako_config::FetchContext {
+        offline: options.fetch.offline || options.frozen,
+        ..options.fetch.clone()
+    };
+
+    // Lock on disk, used to verify digests and report git tag drift even
+    // under --no-incremental.
+    let locked = if locked_mode {
+        husako_config::read_lock(&options.project_root)?
+    } else {
+        husako_config::load_lock(&options.project_root)?
+    };
+
+    // Resolve version requirements against the lock before anything else,
+    // so everything below sees concrete versions.
+    let (config, mut requirements) = match &options.config {
+        Some(config) => {
+            let (config, requirements) =
+                requirements::resolve(config, locked.as_ref(), locked_mode, progress, &fetch)
+                    .await?;
+            (Some(config), requirements)
+        }
+        None => (None, requirements::Requirements::default()),
+    };
+
+    // Existing lock for skip decisions (None if --no-incremental)
+    let old_lock = if options.no_incremental {
         None
     } else {
-        husako_config::load_lock(&options.project_root)
-            .ok()
-            .flatten()
+        locked.clone()
     };
 
+    if locked_mode && let Some(config) = &config {
+        let stale = match &locked {
+            None if config.resources.is_empty()
+                && config.charts.is_empty()
+                && config.plugins.is_empty() =>
+            {
+                Vec::new()
+            }
+            None => vec!["husako.lock not found".to_string()],
+            Some(lock) if lock.format_version < husako_config::LOCK_FORMAT_VERSION => {
+                vec![format!(
+                    "husako.lock has format_version {}, expected {}",
+                    lock.format_version,
+                    husako_config::LOCK_FORMAT_VERSION
+                )]
+            }
+            Some(lock) => lock_check::plugin_lock_mismatches(config, lock, &options.project_root),
+        };
+        if !stale.is_empty() {
+            return Err(HusakoError::LockOutOfDate(stale));
+        }
+    }
+
     let mut new_lock = husako_config::HusakoLock {
-        format_version: 1,
+        format_version: husako_config::LOCK_FORMAT_VERSION,
         husako_version: options.husako_version.clone(),
         resources: std::collections::BTreeMap::new(),
         charts: std::collections::BTreeMap::new(),
@@ -237,11 +348,12 @@ pub async fn generate(
     };
 
     // 1. Process plugins: install or reuse from existing cache
-    let installed_plugins = if let Some(config) = &options.config
+    let installed_plugins = if let Some(config) = &config
         && !config.plugins.is_empty()
     {
         let plugins_dir = options.project_root.join(".husako/plugins");
         let mut installed = Vec::new();
+        let mut uncached = Vec::new();
 
         for (name, source) in &config.plugins {
             let plugin_dir = plugins_dir.join(name);
@@ -268,21 +380,36 @@ pub async fn generate(
                 // If manifest load fails, fall through to reinstall
             }
 
+            if options.frozen && matches!(source, husako_config::PluginSource::Git { .. }) {
+                uncached.push(format!("plugin '{name}'"));
+                continue;
+            }
+
             any_work_done = true;
             let task = progress.start_task(&format!("Installing plugin {name}..."));
-            match plugin::install_plugin(name, source, &options.project_root, &plugin_dir).await {
-                Ok(()) => match husako_config::load_plugin_manifest(&plugin_dir) {
+            match plugin::install_plugin(name, source, &options.project_root, &plugin_dir, &fetch)
+                .await
+            {
+                Ok(commit) => match husako_config::load_plugin_manifest(&plugin_dir) {
                     Ok(manifest) => {
-                        task.finish_ok(&format!(
-                            "{name}: installed (v{})",
-                            manifest.plugin.version
-                        ));
                         let lock_entry = lock_check::build_plugin_entry(
                             source,
                             &plugin_dir,
                             &manifest,
+                            commit,
                             &options.project_root,
                         );
+                        let locked_entry = locked.as_ref().and_then(|l| l.plugins.get(name));
+                        if let Some(msg) =
+                            lock_check::digest_error("plugin", name, &lock_entry, locked_entry)
+                        {
+                            task.finish_err(&format!("{name}: digest mismatch"));
+                            return Err(HusakoError::Validation(msg));
+                        }
+                        task.finish_ok(&format!(
+                            "{name}: installed (v{})",
+                            manifest.plugin.version
+                        ));
                         new_lock.plugins.insert(name.clone(), lock_entry);
                         installed.push(plugin::InstalledPlugin {
                             name: name.clone(),
@@ -302,17 +429,56 @@ pub async fn generate(
             }
         }
 
+        if !uncached.is_empty() {
+            return Err(HusakoError::FrozenFetch(uncached));
+        }
         installed
     } else {
         Vec::new()
     };
 
     // Clone config and merge plugin presets (resources + charts)
-    let mut merged_config = options.config.clone();
+    let mut merged_config = config.clone();
     if !installed_plugins.is_empty()
         && let Some(ref mut cfg) = merged_config
     {
         plugin::merge_plugin_presets(cfg, &installed_plugins);
+        let (resolved, preset_requirements) =
+            requirements::resolve(cfg, locked.as_ref(), locked_mode, progress, &fetch).await?;
+        *cfg = resolved;
+        requirements.extend(preset_requirements);
+    }
+
+    if locked_mode
+        && let Some(config) = &config
+        && let Some(lock) = &locked
+    {
+        let charts = merged_config.as_ref().map_or(&config.charts, |c| &c.charts);
+        let stale = lock_check::lock_mismatches(config, charts, lock, &options.project_root);
+        if !stale.is_empty() {
+            return Err(HusakoError::LockOutOfDate(stale));
+        }
+    }
+
+    // A tag that now points to a different commit than the one locked
+    // (re-tagged release or force-push) is an error before anything is
+    // fetched. The remote is asked, as the fetch cache keeps the old commit.
+    if let Some(config) = &merged_config
+        && let Some(lock) = &locked
+        && !fetch.offline
+    {
+        let drifts = lock_check::git_tag_drift(config, lock, |repo, tag| {
+            husako_git::tag_commit(repo, tag, &fetch).ok().flatten()
+        });
+        if !drifts.is_empty() {
+            if locked_mode {
+                return Err(HusakoError::LockOutOfDate(drifts));
+            }
+            return Err(HusakoError::Validation(format!(
+                "{}\nif the move is expected, remove these entries from husako.lock and re-run",
+                drifts.join("\n")
+            )));
+        }
     }
 
     // 2. Write static husako.d.ts
@@ -342,7 +508,7 @@ pub async fn generate(
         }
     } else {
         let k8s_skipped = lock_check::should_skip_k8s(
-            options.config.as_ref(),
+            config.as_ref(),
             old_lock.as_ref(),
             &options.husako_version,
             &types_dir,
@@ -356,7 +522,7 @@ pub async fn generate(
                 }
             }
         } else {
-            let specs = if let Some(openapi_opts) = &options.openapi {
+            if let Some(openapi_opts) = &options.openapi {
                 // Legacy CLI mode
                 any_work_done = true;
                 let task = progress.start_task("Fetching OpenAPI specs...");
@@ -367,31 +533,27 @@ pub async fn generate(
                                 base_url: base_url.clone(),
                             }
                         }
+                        husako_openapi::OpenApiSource::Kubeconfig { path, context } => {
+                            husako_openapi::OpenApiSource::Kubeconfig {
+                                path: path.clone(),
+                                context: context.clone(),
+                            }
+                        }
                         husako_openapi::OpenApiSource::Directory(p) => {
                             husako_openapi::OpenApiSource::Directory(p.clone())
                         }
                     },
                     cache_dir: options.project_root.join(".husako/cache"),
-                    offline: openapi_opts.offline,
+                    context: fetch.clone(),
                 })?;
-                let result = client.fetch_all_specs().await?;
+                let specs = client.fetch_all_specs().await?;
                 task.finish_ok("Fetched OpenAPI specs");
-                Some(result)
-            } else if let Some(config) = &merged_config
-                && !config.resources.is_empty()
-            {
-                // Config-driven mode (includes merged plugin presets)
-                let cache_dir = options.project_root.join(".husako/cache");
-                Some(
-                    schema_source::resolve_all(config, &options.project_root, &cache_dir, progress)
-                        .await?,
-                )
-            } else {
-                None
-            };
 
-            if let Some(specs) = specs {
-                any_work_done = true;
+                if let Some(config) = &config {
+                    new_lock.resources =
+                        lock_check::build_resource_entries(config, &options.project_root);
+                }
+
                 progress.set_total(1);
                 let task = progress.start_task("Generating types...");
                 let gen_options = husako_dts::GenerateOptions { specs };
@@ -400,15 +562,88 @@ pub async fn generate(
                 for (rel_path, content) in &result.files {
                     write_file(&types_dir.join(rel_path), content)?;
                 }
+                write_schema_bin(&types_dir, &result.schema_bin)?;
                 task.finish_ok("Generated k8s types");
                 // Drop the large generated-file map off the async executor.
                 drop_in_background(result);
-            }
+            } else if let Some(merged) = &merged_config
+                && !merged.resources.is_empty()
+            {
+                // Config-driven mode (includes merged plugin presets): only
+                // sources whose lock entry changed are resolved again.
+                // Plugin presets are not locked, so they always are.
+                let k8s_dir = types_dir.join("k8s");
+                let full = options.no_incremental
+                    || old_lock
+                        .as_ref()
+                        .is_none_or(|l| l.husako_version != options.husako_version)
+                    || std::fs::read_dir(&k8s_dir).map_or(true, |mut d| d.next().is_none());
+                let changed: std::collections::BTreeSet<String> = merged
+                    .resources
+                    .iter()
+                    .filter(|(name, source)| {
+                        full || !lock_check::should_skip_resource(
+                            name,
+                            source,
+                            old_lock.as_ref(),
+                            &options.husako_version,
+                            merged,
+                            &options.project_root,
+                        )
+                    })
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                let removed = old_lock
+                    .iter()
+                    .flat_map(|l| l.resources.keys())
+                    .filter(|name| !merged.resources.contains_key(*name))
+                    .collect();
+                let plan = k8s_sources::Plan {
+                    changed: changed.clone(),
+                    removed,
+                    full,
+                };
+                let resolved = k8s_sources::resolve(
+                    merged,
+                    plan,
+                    options.frozen,
+                    &options.project_root,
+                    progress,
+                    &fetch,
+                )
+                .await?;
+
+                // Lock entries of unchanged sources are kept as they were;
+                // refetched release specs are verified against the locked
+                // digests before anything is written.
+                if let Some(config) = &config {
+                    new_lock.resources =
+                        lock_check::build_resource_entries(config, &options.project_root);
+                    for (name, entry) in &mut new_lock.resources {
+                        let locked_entry = locked.as_ref().and_then(|l| l.resources.get(name));
+                        if !changed.contains(name)
+                            && let Some(old) = old_lock.as_ref().and_then(|l| l.resources.get(name))
+                        {
+                            *entry = old.clone();
+                        } else if let Some(msg) =
+                            lock_check::digest_error("resource", name, entry, locked_entry)
+                        {
+                            return Err(HusakoError::Validation(msg));
+                        }
+                    }
+                }
 
-            // Build lock entries for all configured resources
-            if let Some(config) = &options.config {
-                new_lock.resources =
-                    lock_check::build_resource_entries(config, &options.project_root);
+                any_work_done = true;
+                progress.set_total(1);
+                let task = progress.start_task("Generating types...");
+                let (emitted, total) = resolved.write(&types_dir)?;
+                if emitted == total {
+                    task.finish_ok("Generated k8s types");
+                } else {
+                    task.finish_ok(&format!(
+                        "Generated k8s types ({emitted} of {total} group-versions changed)"
+                    ));
+                }
             }
         }
     }
@@ -438,6 +673,18 @@ pub async fn generate(
             }
         }
 
+        if options.frozen {
+            let mut uncached: Vec<String> = charts_to_generate
+                .iter()
+                .filter(|(_, source)| !husako_helm::is_cached(source, &cache_dir, &fetch))
+                .map(|(name, _)| format!("chart '{name}'"))
+                .collect();
+            if !uncached.is_empty() {
+                uncached.sort();
+                return Err(HusakoError::FrozenFetch(uncached));
+            }
+        }
+
         if !charts_to_generate.is_empty() {
             any_work_done = true;
             // Resolve and generate each chart sequentially to support per-chart progress.
@@ -457,18 +704,39 @@ pub async fn generate(
                     &options.project_root,
                     &cache_dir,
                     Some(&on_progress_cb),
+                    &fetch,
                 )
                 .await
                 .map_err(|e| {
                     task.finish_err(&format!("{chart_name}: {e}"));
                     HusakoError::Chart(e)
                 })?;
-                let (dts, js) = husako_dts::json_schema::generate_chart_types(chart_name, &schema)?;
+                let entry = lock_check::build_chart_entry(source, &options.project_root);
+                let locked_entry = locked.as_ref().and_then(|l| l.charts.get(chart_name));
+                if let Some(msg) =
+                    lock_check::digest_error("chart", chart_name, &entry, locked_entry)
+                {
+                    task.finish_err(&format!("{chart_name}: digest mismatch"));
+                    return Err(HusakoError::Validation(msg));
+                }
+                let (mut dts, mut js) =
+                    husako_dts::json_schema::generate_chart_types(chart_name, &schema)?;
+                let meta = husako_helm::chart_metadata(
+                    chart_name,
+                    source,
+                    &options.project_root,
+                    &cache_dir,
+                );
+                let (refs_dts, refs_js) = husako_dts::json_schema::generate_chart_refs(
+                    &meta.name,
+                    meta.version.as_deref(),
+                    meta.app_version.as_deref(),
+                );
+                dts.push_str(&refs_dts);
+                js.push_str(&refs_js);
                 write_file(&types_dir.join(format!("helm/{chart_name}.d.ts")), &dts)?;
                 write_file(&types_dir.join(format!("helm/{chart_name}.js")), &js)?;
                 task.finish_ok(&format!("{chart_name}: chart types generated"));
-
-                let entry = lock_check::build_chart_entry(source, &options.project_root);
                 new_lock.charts.insert(chart_name.clone(), entry);
                 chart_schemas.insert(chart_name.clone(), schema);
             }
@@ -482,7 +750,8 @@ pub async fn generate(
     write_tsconfig(&options.project_root, merged_config.as_ref(), &plugin_paths)?;
 
     // 7. Save lock (non-fatal on failure — types are already written)
-    if let Err(e) = husako_config::save_lock(&options.project_root, &new_lock) {
+    requirements.apply(&mut new_lock);
+    if !locked_mode && let Err(e) = husako_config::save_lock(&options.project_root, &new_lock) {
         eprintln!("warning: failed to write husako.lock: {e}");
     }
 
@@ -504,6 +773,16 @@ fn write_file(path: &std::path::Path, content: &str) -> Result<(), HusakoError>
         .map_err(|e| HusakoError::GenerateIo(format!("write {}: {e}", path.display())))
 }
 
+/// Write `k8s/_schema.bin` under `types_dir` by replacing it, as renders
+/// running meanwhile (e.g. under `husako watch`) may have it mapped.
+fn write_schema_bin(types_dir: &std::path::Path, content: &[u8]) -> Result<(), HusakoError> {
+    let path = types_dir.join("k8s/_schema.bin");
+    let tmp = types_dir.join("k8s/_schema.bin.tmp");
+    std::fs::write(&tmp, content)
+        .and_then(|()| std::fs::rename(&tmp, &path))
+        .map_err(|e| HusakoError::GenerateIo(format!("write {}: {e}", path.display())))
+}
+
 fn write_tsconfig(
     project_root: &std::path::Path,
     config: Option<&husako_config::HusakoConfig>,
@@ -937,7 +1216,7 @@ pub fn list_dependencies(project_root: &Path) -> Result<DependencyList, HusakoEr
         let mut chart_entries: Vec<_> = cfg.charts.iter().collect();
         chart_entries.sort_by_key(|(k, _)| k.as_str());
         for (name, source) in chart_entries {
-            charts.push(chart_info(name, source));
+            charts.push(chart_info(name, source, project_root));
         }
     }
 
@@ -966,10 +1245,10 @@ fn resource_info(name: &str, source: &husako_config::SchemaSource) -> Dependency
             version: Some(version.clone()),
             details: String::new(),
         },
-        husako_config::SchemaSource::Git { repo, tag, path } => DependencyInfo {
+        husako_config::SchemaSource::Git { repo, path, .. } => DependencyInfo {
             name: name.to_string(),
             source_type: "git",
-            version: Some(tag.clone()),
+            version: source.git_ref().map(|r| r.name().to_string()),
             details: format!("{repo} ({})", path),
         },
         husako_config::SchemaSource::File { path } => DependencyInfo {
@@ -978,10 +1257,42 @@ fn resource_info(name: &str, source: &husako_config::SchemaSource) -> Dependency
             version: None,
             details: path.clone(),
         },
+        husako_config::SchemaSource::Url { url, sha256 } => DependencyInfo {
+            name: name.to_string(),
+            source_type: "url",
+            version: None,
+            details: match sha256 {
+                Some(digest) => format!("{url} (sha256 {})", &digest[..digest.len().min(12)]),
+                None => url.clone(),
+            },
+        },
+        husako_config::SchemaSource::Chart { chart, .. } => DependencyInfo {
+            name: name.to_string(),
+            source_type: "chart",
+            version: None,
+            details: format!("CRDs from chart '{chart}'"),
+        },
+        husako_config::SchemaSource::Cluster {
+            context, groups, ..
+        } => DependencyInfo {
+            name: name.to_string(),
+            source_type: "cluster",
+            version: None,
+            details: match (context, groups.is_empty()) {
+                (Some(c), true) => format!("context {c}"),
+                (Some(c), false) => format!("context {c} ({})", groups.join(", ")),
+                (None, true) => "current context".to_string(),
+                (None, false) => format!("current context ({})", groups.join(", ")),
+            },
+        },
     }
 }
 
-fn chart_info(name: &str, source: &husako_config::ChartSource) -> DependencyInfo {
+fn chart_info(
+    name: &str,
+    source: &husako_config::ChartSource,
+    project_root: &Path,
+) -> DependencyInfo {
     match source {
         husako_config::ChartSource::Registry {
             repo,
@@ -1005,10 +1316,22 @@ fn chart_info(name: &str, source: &husako_config::ChartSource) -> DependencyInfo
             version: None,
             details: path.clone(),
         },
-        husako_config::ChartSource::Git { repo, tag, path } => DependencyInfo {
+        husako_config::ChartSource::Chart { path } => DependencyInfo {
+            name: name.to_string(),
+            source_type: "chart",
+            version: husako_helm::chart_metadata(
+                name,
+                source,
+                project_root,
+                &project_root.join(".husako/cache"),
+            )
+            .version,
+            details: path.clone(),
+        },
+        husako_config::ChartSource::Git { repo, path, .. } => DependencyInfo {
             name: name.to_string(),
             source_type: "git",
-            version: Some(tag.clone()),
+            version: source.git_ref().map(|r| r.name().to_string()),
             details: format!("{repo} ({})", path),
         },
         husako_config::ChartSource::Oci { reference, version } => DependencyInfo {
@@ -1104,7 +1427,13 @@ pub struct OutdatedEntry {
     pub name: String,
     pub kind: &'static str,
     pub source_type: &'static str,
+    /// The pinned version, or for a requirement the version it is locked to.
     pub current: String,
+    /// The semver requirement in `husako.toml`; `None` for exact pins.
+    pub requirement: Option<String>,
+    /// Newest version satisfying `requirement`.
+    pub compatible: Option<String>,
+    /// Newest version overall.
     pub latest: Option<String>,
     pub up_to_date: bool,
 }
@@ -1112,19 +1441,38 @@ pub struct OutdatedEntry {
 pub async fn check_outdated(
     project_root: &Path,
     progress: &dyn ProgressReporter,
+    ctx: &husako_config::FetchContext,
 ) -> Result<Vec<OutdatedEntry>, HusakoError> {
     let config = husako_config::load(project_root)?;
     let Some(cfg) = config else {
         return Ok(Vec::new());
     };
+    if ctx.offline {
+        return Err(HusakoError::Offline(
+            "checking for newer versions needs network access".to_string(),
+        ));
+    }
 
+    let lock = husako_config::read_lock(project_root)?;
     let mut entries = Vec::new();
 
     for (name, source) in &cfg.resources {
         match source {
+            husako_config::SchemaSource::Release { version }
+                if version_req::is_requirement(version) =>
+            {
+                let locked = requirements::locked_resource(lock.as_ref(), name);
+                let upstream = requirements::Upstream::Release;
+                entries.push(
+                    check_requirement(
+                        name, "resource", "release", version, locked, upstream, progress, ctx,
+                    )
+                    .await,
+                );
+            }
             husako_config::SchemaSource::Release { version } => {
                 let task = progress.start_task(&format!("Checking {name}..."));
-                match version_check::discover_latest_release().await {
+                match version_check::discover_latest_release(ctx).await {
                     Ok(latest) => {
                         let up_to_date = version_check::versions_match(version, &latest);
                         task.finish_ok(&format!("{name}: {version} → {latest}"));
@@ -1133,6 +1481,8 @@ pub async fn check_outdated(
                             kind: "resource",
                             source_type: "release",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: Some(latest),
                             up_to_date,
                         });
@@ -1144,15 +1494,17 @@ pub async fn check_outdated(
                             kind: "resource",
                             source_type: "release",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: None,
                             up_to_date: false,
                         });
                     }
                 }
             }
-            husako_config::SchemaSource::Git { tag, repo, .. } => {
+            husako_config::SchemaSource::Git { tag, repo, .. } if !tag.is_empty() => {
                 let task = progress.start_task(&format!("Checking {name}..."));
-                match version_check::discover_latest_git_tag(repo, None) {
+                match version_check::discover_latest_git_tag(repo, None, ctx) {
                     Ok(Some(latest)) => {
                         let up_to_date = tag == &latest;
                         task.finish_ok(&format!("{name}: {tag} → {latest}"));
@@ -1161,6 +1513,8 @@ pub async fn check_outdated(
                             kind: "resource",
                             source_type: "git",
                             current: tag.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: Some(latest),
                             up_to_date,
                         });
@@ -1172,6 +1526,8 @@ pub async fn check_outdated(
                             kind: "resource",
                             source_type: "git",
                             current: tag.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: None,
                             up_to_date: false,
                         });
@@ -1183,6 +1539,8 @@ pub async fn check_outdated(
                             kind: "resource",
                             source_type: "git",
                             current: tag.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: None,
                             up_to_date: false,
                         });
@@ -1195,6 +1553,45 @@ pub async fn check_outdated(
     }
 
     for (name, source) in &cfg.charts {
+        let locked = requirements::locked_chart(lock.as_ref(), name, source);
+        let ranged = match source {
+            husako_config::ChartSource::Registry {
+                repo,
+                chart,
+                version,
+            } => Some((
+                version,
+                "registry",
+                requirements::Upstream::Registry { repo, chart },
+            )),
+            husako_config::ChartSource::ArtifactHub { package, version } => Some((
+                version,
+                "artifacthub",
+                requirements::Upstream::ArtifactHub { package },
+            )),
+            husako_config::ChartSource::Oci { reference, version } => {
+                Some((version, "oci", requirements::Upstream::Oci { reference }))
+            }
+            _ => None,
+        };
+        if let Some((version, source_type, upstream)) = ranged
+            && version_req::is_requirement(version)
+        {
+            entries.push(
+                check_requirement(
+                    name,
+                    "chart",
+                    source_type,
+                    version,
+                    locked,
+                    upstream,
+                    progress,
+                    ctx,
+                )
+                .await,
+            );
+            continue;
+        }
         match source {
             husako_config::ChartSource::Registry {
                 repo,
@@ -1202,7 +1599,7 @@ pub async fn check_outdated(
                 version,
             } => {
                 let task = progress.start_task(&format!("Checking {name}..."));
-                match version_check::discover_latest_registry(repo, chart, None).await {
+                match version_check::discover_latest_registry(repo, chart, None, ctx).await {
                     Ok(latest) => {
                         let up_to_date = version == &latest;
                         task.finish_ok(&format!("{name}: {version} → {latest}"));
@@ -1211,6 +1608,8 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "registry",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: Some(latest),
                             up_to_date,
                         });
@@ -1222,6 +1621,8 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "registry",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: None,
                             up_to_date: false,
                         });
@@ -1230,7 +1631,7 @@ pub async fn check_outdated(
             }
             husako_config::ChartSource::ArtifactHub { package, version } => {
                 let task = progress.start_task(&format!("Checking {name}..."));
-                match version_check::discover_latest_artifacthub(package, None).await {
+                match version_check::discover_latest_artifacthub(package, None, ctx).await {
                     Ok(latest) => {
                         let up_to_date = version == &latest;
                         task.finish_ok(&format!("{name}: {version} → {latest}"));
@@ -1239,6 +1640,8 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "artifacthub",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: Some(latest),
                             up_to_date,
                         });
@@ -1250,15 +1653,17 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "artifacthub",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: None,
                             up_to_date: false,
                         });
                     }
                 }
             }
-            husako_config::ChartSource::Git { tag, repo, .. } => {
+            husako_config::ChartSource::Git { tag, repo, .. } if !tag.is_empty() => {
                 let task = progress.start_task(&format!("Checking {name}..."));
-                match version_check::discover_latest_git_tag(repo, None) {
+                match version_check::discover_latest_git_tag(repo, None, ctx) {
                     Ok(Some(latest)) => {
                         let up_to_date = tag == &latest;
                         task.finish_ok(&format!("{name}: {tag} → {latest}"));
@@ -1267,6 +1672,8 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "git",
                             current: tag.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: Some(latest),
                             up_to_date,
                         });
@@ -1281,6 +1688,8 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "git",
                             current: tag.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: None,
                             up_to_date: false,
                         });
@@ -1289,7 +1698,7 @@ pub async fn check_outdated(
             }
             husako_config::ChartSource::Oci { reference, version } => {
                 let task = progress.start_task(&format!("Checking {name}..."));
-                match version_check::discover_latest_oci(reference).await {
+                match version_check::discover_latest_oci(reference, ctx).await {
                     Ok(Some(latest)) => {
                         let up_to_date = version == &latest;
                         task.finish_ok(&format!("{name}: {version} → {latest}"));
@@ -1298,6 +1707,8 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "oci",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: Some(latest),
                             up_to_date,
                         });
@@ -1312,6 +1723,8 @@ pub async fn check_outdated(
                             kind: "chart",
                             source_type: "oci",
                             current: version.clone(),
+                            requirement: None,
+                            compatible: None,
                             latest: None,
                             up_to_date: false,
                         });
@@ -1323,9 +1736,135 @@ pub async fn check_outdated(
         }
     }
 
+    for (name, source) in &cfg.plugins {
+        // Only tag/version-pinned git plugins can be bumped; rev and HEAD are not versioned
+        if let husako_config::PluginSource::Git { rev: Some(rev), .. } = source {
+            progress
+                .start_task(&format!("Checking {name}..."))
+                .finish_ok(&format!("{name}: {}", rev_pin_note(rev)));
+            continue;
+        }
+        let husako_config::PluginSource::Git {
+            url, tag, version, ..
+        } = source
+        else {
+            continue;
+        };
+        let Some(current) = tag.as_ref().or(version.as_ref()) else {
+            continue;
+        };
+        if let Some(version) = version
+            && version_req::is_requirement(version)
+        {
+            let locked = requirements::locked_plugin(lock.as_ref(), name, source);
+            let upstream = requirements::Upstream::PluginTags { url };
+            entries.push(
+                check_requirement(
+                    name, "plugin", "git", version, locked, upstream, progress, ctx,
+                )
+                .await,
+            );
+            continue;
+        }
+        let task = progress.start_task(&format!("Checking {name}..."));
+        match version_check::discover_git_tags(url, 1, 0, ctx) {
+            Ok(tags) => {
+                // `version` pins are written without the tag's `v` prefix
+                let latest = tags.into_iter().next().map(|t| match version {
+                    Some(_) => t.strip_prefix('v').map(str::to_string).unwrap_or(t),
+                    None => t,
+                });
+                match &latest {
+                    Some(latest) => task.finish_ok(&format!("{name}: {current} → {latest}")),
+                    None => task.finish_ok(&format!("{name}: no tags")),
+                }
+                entries.push(OutdatedEntry {
+                    name: name.clone(),
+                    kind: "plugin",
+                    source_type: "git",
+                    current: current.clone(),
+                    requirement: None,
+                    compatible: None,
+                    up_to_date: latest.as_ref().is_some_and(|l| l == current),
+                    latest,
+                });
+            }
+            Err(e) => {
+                task.finish_err(&format!("{name}: {e}"));
+                entries.push(OutdatedEntry {
+                    name: name.clone(),
+                    kind: "plugin",
+                    source_type: "git",
+                    current: current.clone(),
+                    requirement: None,
+                    compatible: None,
+                    latest: None,
+                    up_to_date: false,
+                });
+            }
+        }
+    }
+
     Ok(entries)
 }
 
+/// Check a dependency whose version is a semver requirement, reporting both
+/// the newest compatible and the newest overall version.
+#[allow(clippy::too_many_arguments)]
+async fn check_requirement(
+    name: &str,
+    kind: &'static str,
+    source_type: &'static str,
+    spec: &str,
+    locked: Option<(&str, Option<&str>)>,
+    upstream: requirements::Upstream<'_>,
+    progress: &dyn ProgressReporter,
+    ctx: &husako_config::FetchContext,
+) -> OutdatedEntry {
+    let task = progress.start_task(&format!("Checking {name}..."));
+    let req = version_req::parse_requirement(spec).ok();
+    let current = locked
+        .map(|(version, _)| version)
+        .filter(|version| {
+            req.as_ref()
+                .is_some_and(|r| version_req::satisfies(r, version))
+        })
+        .unwrap_or(spec)
+        .to_string();
+    let mut entry = OutdatedEntry {
+        name: name.to_string(),
+        kind,
+        source_type,
+        current,
+        requirement: Some(spec.to_string()),
+        compatible: None,
+        latest: None,
+        up_to_date: false,
+    };
+    match upstream.versions(ctx).await {
+        Ok(versions) => {
+            let newest = |req: &semver::VersionReq| {
+                version_req::newest_matching(req, versions.iter().map(String::as_str))
+                    .map(str::to_string)
+            };
+            entry.compatible = req.as_ref().and_then(newest);
+            entry.latest = newest(&semver::VersionReq::STAR);
+            entry.up_to_date = entry
+                .latest
+                .as_ref()
+                .is_some_and(|l| version_check::versions_match(&entry.current, l));
+            task.finish_ok(&format!(
+                "{name}: {} → {} (latest {})",
+                entry.current,
+                entry.compatible.as_deref().unwrap_or("none"),
+                entry.latest.as_deref().unwrap_or("none"),
+            ));
+        }
+        Err(e) => task.finish_err(&format!("{name}: {e}")),
+    }
+    entry
+}
+
 // --- husako update (M19) ---
 
 #[derive(Debug)]
@@ -1335,9 +1874,20 @@ pub struct UpdateOptions {
     pub resources_only: bool,
     pub charts_only: bool,
     pub dry_run: bool,
+    /// Move dependencies with a semver requirement past it to the newest
+    /// version, rewriting the requirement. Without it they stay in range.
+    pub breaking: bool,
+    /// Compare the schemas of the current and new version of each updated
+    /// resource and chart, and report breaking changes.
+    pub diff: bool,
+    /// After regenerating, run `check` on every `[entries]` alias against the
+    /// new types.
+    pub check: bool,
     /// Current husako binary version — forwarded to the auto-gen call so the
     /// lock file records the correct version after an update.
     pub husako_version: String,
+    /// Settings for looking up versions and fetching schemas.
+    pub fetch: husako_config::FetchContext,
 }
 
 #[derive(Debug)]
@@ -1346,6 +1896,12 @@ pub struct UpdatedEntry {
     pub kind: &'static str,
     pub old_version: String,
     pub new_version: String,
+    /// The semver requirement in `husako.toml` after the update; `None` for
+    /// exact pins.
+    pub requirement: Option<String>,
+    /// Breaking schema changes from the old to the new version, when
+    /// `UpdateOptions::diff` is set and the dependency has a schema.
+    pub schema_diff: Option<Result<Vec<schema_diff::SchemaChange>, String>>,
 }
 
 #[derive(Debug)]
@@ -1353,18 +1909,26 @@ pub struct UpdateResult {
     pub updated: Vec<UpdatedEntry>,
     pub skipped: Vec<String>,
     pub failed: Vec<(String, String)>,
+    /// `check` results by entry alias when `UpdateOptions::check` is set:
+    /// the number of resources built, or the error.
+    pub checks: Vec<(String, Result<usize, String>)>,
+}
+
+fn rev_pin_note(rev: &str) -> String {
+    format!("pinned to rev {rev}; change `rev` in husako.toml to move it")
 }
 
 pub async fn update_dependencies(
     options: &UpdateOptions,
     progress: &dyn ProgressReporter,
 ) -> Result<UpdateResult, HusakoError> {
-    let outdated = check_outdated(&options.project_root, progress).await?;
+    let outdated = check_outdated(&options.project_root, progress, &options.fetch).await?;
 
     let mut result = UpdateResult {
         updated: Vec::new(),
         skipped: Vec::new(),
         failed: Vec::new(),
+        checks: Vec::new(),
     };
 
     // Filter entries
@@ -1384,41 +1948,93 @@ pub async fn update_dependencies(
         })
         .collect();
 
+    // A plugin pinned to a commit has no newer version to move to
+    if let Some(target) = &options.name
+        && filtered.is_empty()
+        && let Some(config) = husako_config::load(&options.project_root)?
+        && let Some(husako_config::PluginSource::Git { rev: Some(rev), .. }) =
+            config.plugins.get(target)
+    {
+        result.failed.push((target.clone(), rev_pin_note(rev)));
+    }
+
     let mut doc_and_path = None;
+    // Entries moving within their requirement: only husako.lock changes
+    let mut relock = Vec::new();
 
     for entry in filtered {
-        let Some(ref latest) = entry.latest else {
-            result
-                .failed
-                .push((entry.name, "could not determine latest version".to_string()));
+        // Requirements stay in range unless --breaking
+        let target = match &entry.requirement {
+            Some(_) if !options.breaking => entry.compatible.as_ref(),
+            _ => entry.latest.as_ref(),
+        };
+        let Some(target) = target else {
+            let reason = match &entry.requirement {
+                Some(spec) if entry.latest.is_some() => {
+                    format!("no version matches {spec}; use --breaking to move past it")
+                }
+                _ => "could not determine latest version".to_string(),
+            };
+            result.failed.push((entry.name, reason));
             continue;
         };
 
-        if entry.up_to_date {
+        let up_to_date = match &entry.requirement {
+            Some(_) => &entry.current == target,
+            None => entry.up_to_date,
+        };
+        if up_to_date {
             result.skipped.push(entry.name);
             continue;
         }
 
+        // The version to write to husako.toml, if it changes
+        let new_spec = match &entry.requirement {
+            Some(spec)
+                if version_req::parse_requirement(spec)
+                    .is_ok_and(|req| version_req::satisfies(&req, target)) =>
+            {
+                None
+            }
+            Some(spec) => Some(version_req::bump_requirement(spec, target)),
+            None => Some(target.clone()),
+        };
+        let requirement = entry
+            .requirement
+            .as_ref()
+            .map(|spec| new_spec.clone().unwrap_or_else(|| spec.clone()));
+
         if options.dry_run {
             result.updated.push(UpdatedEntry {
                 name: entry.name,
                 kind: entry.kind,
                 old_version: entry.current,
-                new_version: latest.clone(),
+                new_version: target.clone(),
+                requirement,
+                schema_diff: None,
             });
             continue;
         }
 
-        // Load TOML document lazily
-        if doc_and_path.is_none() {
-            doc_and_path = Some(husako_config::edit::load_document(&options.project_root)?);
-        }
-        let (doc, _) = doc_and_path.as_mut().unwrap();
-
-        let updated = if entry.kind == "resource" {
-            husako_config::edit::update_resource_version(doc, &entry.name, latest)
-        } else {
-            husako_config::edit::update_chart_version(doc, &entry.name, latest)
+        let updated = match &new_spec {
+            Some(spec) => {
+                // Load TOML document lazily
+                if doc_and_path.is_none() {
+                    doc_and_path = Some(husako_config::edit::load_document(&options.project_root)?);
+                }
+                let (doc, _) = doc_and_path.as_mut().unwrap();
+                match entry.kind {
+                    "resource" => {
+                        husako_config::edit::update_resource_version(doc, &entry.name, spec)
+                    }
+                    "plugin" => husako_config::edit::update_plugin_version(doc, &entry.name, spec),
+                    _ => husako_config::edit::update_chart_version(doc, &entry.name, spec),
+                }
+            }
+            None => {
+                relock.push((entry.kind, entry.name.clone()));
+                true
+            }
         };
 
         if updated {
@@ -1426,11 +2042,54 @@ pub async fn update_dependencies(
                 name: entry.name,
                 kind: entry.kind,
                 old_version: entry.current,
-                new_version: latest.clone(),
+                new_version: target.clone(),
+                requirement,
+                schema_diff: None,
             });
+        } else {
+            result.failed.push((
+                entry.name,
+                "no version or tag to update in husako.toml".to_string(),
+            ));
         }
     }
 
+    if options.diff
+        && let Some(config) = husako_config::load(&options.project_root)?
+    {
+        for entry in &mut result.updated {
+            entry.schema_diff = diff_update(
+                &config,
+                entry,
+                &options.project_root,
+                progress,
+                &options.fetch,
+            )
+            .await;
+        }
+    }
+
+    // Drop the locked versions of entries moving within their requirement so
+    // regeneration resolves them afresh.
+    if !relock.is_empty()
+        && let Some(mut lock) = husako_config::read_lock(&options.project_root)?
+    {
+        for (kind, name) in &relock {
+            match *kind {
+                "resource" => {
+                    lock.resources.remove(name);
+                }
+                "plugin" => {
+                    lock.plugins.remove(name);
+                }
+                _ => {
+                    lock.charts.remove(name);
+                }
+            }
+        }
+        husako_config::save_lock(&options.project_root, &lock)?;
+    }
+
     // Save if we modified the document
     if let Some((doc, path)) = &doc_and_path
         && !result.updated.is_empty()
@@ -1448,17 +2107,150 @@ pub async fn update_dependencies(
             config,
             husako_version: options.husako_version.clone(),
             no_incremental: false,
+            locked: false,
+            frozen: false,
+            fetch: options.fetch.clone(),
         };
         // generate() manages its own spinners; running an outer task concurrently
         // would cause multiple standalone ProgressBars to corrupt each other's output.
-        if let Err(e) = generate(&gen_options, progress).await {
-            eprintln!("warning: type regeneration failed: {e}");
+        match generate(&gen_options, progress).await {
+            Ok(_) if options.check => {
+                if let Some(config) = &gen_options.config {
+                    result.checks = check_entries(config, &options.project_root).await;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("warning: type regeneration failed: {e}"),
         }
     }
 
     Ok(result)
 }
 
+/// Breaking schema changes between the old and new version of an updated
+/// resource or chart. `None` for dependencies without a schema.
+async fn diff_update(
+    config: &husako_config::HusakoConfig,
+    entry: &UpdatedEntry,
+    project_root: &Path,
+    progress: &dyn ProgressReporter,
+    ctx: &husako_config::FetchContext,
+) -> Option<Result<Vec<schema_diff::SchemaChange>, String>> {
+    use husako_config::{ChartSource, SchemaSource};
+
+    if version_req::is_requirement(&entry.old_version) {
+        return Some(Err(format!(
+            "{} is not locked to a version to compare against",
+            entry.name
+        )));
+    }
+    let cache_dir = project_root.join(".husako/cache");
+    let name = entry.name.as_str();
+
+    match entry.kind {
+        "resource" => {
+            let SchemaSource::Release { .. } = config.resources.get(name)? else {
+                return None;
+            };
+            let specs = |version: &str| {
+                let single = husako_config::HusakoConfig {
+                    resources: std::collections::HashMap::from([(
+                        name.to_string(),
+                        SchemaSource::Release {
+                            version: version.to_string(),
+                        },
+                    )]),
+                    ..Default::default()
+                };
+                let cache_dir = cache_dir.clone();
+                async move {
+                    schema_source::resolve_all(&single, project_root, &cache_dir, progress, ctx)
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+            };
+            let old = match specs(&entry.old_version).await {
+                Ok(old) => old,
+                Err(e) => return Some(Err(e)),
+            };
+            Some(
+                specs(&entry.new_version)
+                    .await
+                    .map(|new| schema_diff::diff_openapi(&old, &new)),
+            )
+        }
+        "chart" => {
+            let source = config.charts.get(name)?;
+            let at = |version: &str| {
+                let mut source = source.clone();
+                match &mut source {
+                    ChartSource::Registry { version: v, .. }
+                    | ChartSource::ArtifactHub { version: v, .. }
+                    | ChartSource::Oci { version: v, .. } => *v = version.to_string(),
+                    _ => return None,
+                }
+                Some(source)
+            };
+            let (old_source, new_source) = (at(&entry.old_version)?, at(&entry.new_version)?);
+            let task = progress.start_task(&format!("Comparing {name} schemas..."));
+            let schema = |source: ChartSource| {
+                let cache_dir = cache_dir.clone();
+                async move {
+                    husako_helm::resolve(name, &source, project_root, &cache_dir, None, ctx)
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+            };
+            let diff = match schema(old_source).await {
+                Ok(old) => schema(new_source)
+                    .await
+                    .map(|new| schema_diff::diff_schema(&old, &new)),
+                Err(e) => Err(e),
+            };
+            match &diff {
+                Ok(changes) => {
+                    task.finish_ok(&format!("{name}: {} breaking change(s)", changes.len()))
+                }
+                Err(e) => task.finish_err(&format!("{name}: {e}")),
+            }
+            Some(diff)
+        }
+        _ => None,
+    }
+}
+
+/// Run `check` on every `[entries]` alias, in alias order.
+async fn check_entries(
+    config: &husako_config::HusakoConfig,
+    project_root: &Path,
+) -> Vec<(String, Result<usize, String>)> {
+    let mut entries: Vec<_> = config.entries.iter().collect();
+    entries.sort();
+    let mut results = Vec::new();
+    for (alias, path) in entries {
+        let file = project_root.join(path);
+        let outcome = match std::fs::read_to_string(&file) {
+            Ok(source) => {
+                let options = RenderOptions {
+                    project_root: project_root.to_path_buf(),
+                    allow_outside_root: false,
+                    schema_store: load_schema_store(project_root),
+                    timeout_ms: None,
+                    max_heap_mb: None,
+                    verbose: false,
+                };
+                validate_file(&source, &file.to_string_lossy(), &options)
+                    .await
+                    .map(|r| r.resource_count)
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => Err(format!("could not read {}: {e}", file.display())),
+        };
+        results.push((alias.clone(), outcome));
+    }
+    results
+}
+
 // --- husako info (M20) ---
 
 #[derive(Debug)]
@@ -1549,7 +2341,7 @@ pub fn dependency_detail(project_root: &Path, name: &str) -> Result<DependencyDe
 
     // Check charts
     if let Some(source) = cfg.charts.get(name) {
-        let info = chart_info(name, source);
+        let info = chart_info(name, source, project_root);
         let types_dir = project_root.join(".husako/types/helm");
         let type_files = list_chart_type_files(&types_dir, name);
         let schema_property_count = read_chart_schema_props(project_root, name);
@@ -1810,7 +2602,7 @@ pub async fn validate_file(
     filename: &str,
     options: &RenderOptions,
 ) -> Result<ValidateResult, HusakoError> {
-    let js = husako_compile_oxc::compile(source, filename)?;
+    let js = compile_cache(&options.project_root).compile(source, filename)?;
 
     let entry_path = std::path::Path::new(filename)
         .canonicalize()
@@ -1832,6 +2624,10 @@ pub async fn validate_file(
         max_heap_mb: options.max_heap_mb,
         generated_types_dir,
         plugin_modules,
+        chart_renderer: chart_renderer(&options.project_root, None),
+        compile_cache_dir: Some(compile_cache_dir(&options.project_root)),
+        module_cache: None,
+        loaded_files: None,
     };
 
     let value = husako_runtime_qjs::execute(&js, &exec_options).await?;
@@ -1965,6 +2761,73 @@ fn load_plugin_modules(project_root: &Path) -> std::collections::HashMap<String,
     modules
 }
 
+/// Build the host side of `helmTemplate()` from the `[charts]` configured in
+/// `husako.toml`, including charts contributed by installed plugins.
+///
+/// Charts render from the archives `husako gen` cached, so no network access
+/// happens during `render`. `.Capabilities.KubeVersion` defaults to the
+/// version of a `release` resource source when one is configured. The chart
+/// directory or archive each render reads is recorded into `loaded`.
+fn chart_renderer(
+    project_root: &Path,
+    loaded: Option<husako_runtime_qjs::LoadedFiles>,
+) -> Option<husako_runtime_qjs::ChartRenderer> {
+    let mut config = match husako_config::load(project_root) {
+        Ok(config) => config?,
+        // Report a broken husako.toml from the call rather than as a missing chart
+        Err(e) => {
+            let message = format!("helmTemplate(): {e}");
+            return Some(std::sync::Arc::new(move |_, _, _| Err(message.clone())));
+        }
+    };
+    plugin::merge_plugin_presets(&mut config, &plugin::list_plugins(project_root));
+
+    let default_kube_version = config.resources.values().find_map(|s| match s {
+        husako_config::SchemaSource::Release { version } => {
+            Some(if version.matches('.').count() < 2 {
+                format!("{version}.0")
+            } else {
+                version.clone()
+            })
+        }
+        _ => None,
+    });
+    let charts = config.charts;
+    let project_root = project_root.to_path_buf();
+    let cache_dir = project_root.join(".husako/cache");
+
+    Some(std::sync::Arc::new(move |name, values, opts| {
+        let source = charts.get(name).ok_or_else(|| {
+            format!("helmTemplate(): chart '{name}' is not configured in [charts] of husako.toml")
+        })?;
+        if let Some(loaded) = &loaded {
+            loaded.record(husako_helm::render_input(source, &project_root, &cache_dir));
+        }
+        let str_opt = |key: &str| opts.get(key).and_then(|v| v.as_str()).map(String::from);
+        let options = husako_helm::RenderOptions {
+            release_name: str_opt("releaseName"),
+            namespace: str_opt("namespace"),
+            include_crds: opts
+                .get("includeCrds")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            kube_version: str_opt("kubeVersion").or_else(|| default_kube_version.clone()),
+            api_versions: opts
+                .get("apiVersions")
+                .and_then(|v| v.as_array())
+                .map(|a| {
+                    a.iter()
+                        .filter_map(|v| v.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        husako_helm::render(name, source, &project_root, &cache_dir, &values, &options)
+            .map(serde_json::Value::from)
+            .map_err(|e| e.to_string())
+    }))
+}
+
 // --- husako test ---
 
 /// Recursively discover `*.test.ts` and `*.spec.ts` files under `root`.
@@ -2010,7 +2873,7 @@ async fn run_test_file(
     filename: &str,
     options: &TestOptions,
 ) -> Result<Vec<TestCaseResult>, HusakoError> {
-    let js = husako_compile_oxc::compile(source, filename)?;
+    let js = compile_cache(&options.project_root).compile(source, filename)?;
 
     let entry_path = std::path::Path::new(filename)
         .canonicalize()
@@ -2032,6 +2895,10 @@ async fn run_test_file(
         max_heap_mb: options.max_heap_mb,
         generated_types_dir,
         plugin_modules,
+        chart_renderer: chart_renderer(&options.project_root, None),
+        compile_cache_dir: Some(compile_cache_dir(&options.project_root)),
+        module_cache: None,
+        loaded_files: None,
     };
 
     Ok(husako_runtime_qjs::execute_tests(&js, &exec_options).await?)
@@ -2154,6 +3021,9 @@ mod tests {
             config: None,
             husako_version: String::new(),
             no_incremental: true,
+            locked: false,
+            frozen: false,
+            fetch: Default::default(),
         };
         tokio::runtime::Runtime::new()
             .unwrap()
@@ -2203,6 +3073,9 @@ mod tests {
             config: None,
             husako_version: String::new(),
             no_incremental: true,
+            locked: false,
+            frozen: false,
+            fetch: Default::default(),
         };
         tokio::runtime::Runtime::new()
             .unwrap()
@@ -2372,6 +3245,213 @@ mod tests {
         assert!(dir.join("entry.ts").exists());
     }
 
+    #[test]
+    fn generate_locked_rejects_stale_lock() {
+        const SCHEMA: &str =
+            r#"{"type":"object","properties":{"replicaCount":{"type":"integer"}}}"#;
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        std::fs::write(root.join("a.schema.json"), SCHEMA).unwrap();
+        std::fs::write(root.join("b.schema.json"), SCHEMA).unwrap();
+
+        let chart = |path: &str| husako_config::HusakoConfig {
+            charts: std::collections::HashMap::from([(
+                "my-chart".to_string(),
+                husako_config::ChartSource::File {
+                    path: path.to_string(),
+                },
+            )]),
+            ..Default::default()
+        };
+        let opts = |config, locked| GenerateOptions {
+            project_root: root.clone(),
+            openapi: None,
+            skip_k8s: true,
+            config: Some(config),
+            husako_version: String::new(),
+            no_incremental: false,
+            locked,
+            frozen: false,
+            fetch: Default::default(),
+        };
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        // No lock yet
+        let err = rt
+            .block_on(generate(
+                &opts(chart("a.schema.json"), true),
+                &progress::SilentProgress,
+            ))
+            .unwrap_err();
+        assert!(
+            matches!(&err, HusakoError::LockOutOfDate(stale) if stale == &["husako.lock not found"])
+        );
+
+        rt.block_on(generate(
+            &opts(chart("a.schema.json"), false),
+            &progress::SilentProgress,
+        ))
+        .unwrap();
+        let lock = std::fs::read_to_string(root.join("husako.lock")).unwrap();
+
+        // Matching config: types are regenerated, the lock is left alone
+        std::fs::remove_dir_all(root.join(".husako")).unwrap();
+        rt.block_on(generate(
+            &opts(chart("a.schema.json"), true),
+            &progress::SilentProgress,
+        ))
+        .unwrap();
+        assert!(root.join(".husako/types/helm/my-chart.d.ts").exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join("husako.lock")).unwrap(),
+            lock
+        );
+
+        // Changed config is reported, not written
+        let err = rt
+            .block_on(generate(
+                &opts(chart("b.schema.json"), true),
+                &progress::SilentProgress,
+            ))
+            .unwrap_err();
+        assert!(
+            matches!(&err, HusakoError::LockOutOfDate(stale)
+                if stale == &["chart 'my-chart': changed since husako.lock was written"]),
+            "{err}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("husako.lock")).unwrap(),
+            lock
+        );
+    }
+
+    #[test]
+    fn generate_frozen_requires_cached_sources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        let config = husako_config::HusakoConfig {
+            charts: std::collections::HashMap::from([(
+                "ingress".to_string(),
+                husako_config::ChartSource::Oci {
+                    reference: "oci://ghcr.io/org/ingress".to_string(),
+                    version: "1.0.0".to_string(),
+                },
+            )]),
+            ..Default::default()
+        };
+        let lock = husako_config::HusakoLock {
+            format_version: husako_config::LOCK_FORMAT_VERSION,
+            husako_version: String::new(),
+            resources: Default::default(),
+            charts: std::collections::BTreeMap::from([(
+                "ingress".to_string(),
+                husako_config::ChartLockEntry::Oci {
+                    reference: "oci://ghcr.io/org/ingress".to_string(),
+                    version: "1.0.0".to_string(),
+                    requirement: None,
+                    digest: None,
+                    generated_at: String::new(),
+                },
+            )]),
+            plugins: Default::default(),
+        };
+        husako_config::save_lock(&root, &lock).unwrap();
+
+        let opts = GenerateOptions {
+            project_root: root.clone(),
+            openapi: None,
+            skip_k8s: true,
+            config: Some(config),
+            husako_version: String::new(),
+            no_incremental: false,
+            locked: false,
+            frozen: true,
+            fetch: Default::default(),
+        };
+        let err = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(generate(&opts, &progress::SilentProgress))
+            .unwrap_err();
+        assert!(
+            matches!(&err, HusakoError::FrozenFetch(missing) if missing == &["chart 'ingress'"]),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn generate_rejects_moved_git_tag() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("project");
+        std::fs::create_dir_all(&root).unwrap();
+        let repo_dir = tmp.path().join("repo");
+        let commits =
+            schema_source::tests::make_repo(&repo_dir, &[("v1", &[("crds/a.yaml", "a")])]);
+        let repo = format!("file://{}", repo_dir.display());
+
+        let config = husako_config::HusakoConfig {
+            resources: std::collections::HashMap::from([(
+                "op".to_string(),
+                husako_config::SchemaSource::Git {
+                    repo: repo.clone(),
+                    tag: "v1".to_string(),
+                    branch: None,
+                    rev: None,
+                    path: "crds".to_string(),
+                },
+            )]),
+            ..Default::default()
+        };
+        let locked_commit = "0".repeat(40);
+        let lock = husako_config::HusakoLock {
+            format_version: husako_config::LOCK_FORMAT_VERSION,
+            husako_version: String::new(),
+            resources: std::collections::BTreeMap::from([(
+                "op".to_string(),
+                husako_config::ResourceLockEntry::Git {
+                    repo,
+                    tag: "v1".to_string(),
+                    branch: None,
+                    rev: None,
+                    path: "crds".to_string(),
+                    commit: Some(locked_commit.clone()),
+                    generated_at: String::new(),
+                },
+            )]),
+            charts: Default::default(),
+            plugins: Default::default(),
+        };
+        husako_config::save_lock(&root, &lock).unwrap();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        for locked in [false, true] {
+            let opts = GenerateOptions {
+                project_root: root.clone(),
+                openapi: None,
+                skip_k8s: true,
+                config: Some(config.clone()),
+                husako_version: String::new(),
+                no_incremental: false,
+                locked,
+                frozen: false,
+                fetch: Default::default(),
+            };
+            let err = rt
+                .block_on(generate(&opts, &progress::SilentProgress))
+                .unwrap_err();
+            let expected = format!("now resolves to {} (locked {locked_commit})", commits[0]);
+            match &err {
+                HusakoError::LockOutOfDate(stale) if locked => {
+                    assert!(stale[0].contains(&expected), "{err}")
+                }
+                HusakoError::Validation(msg) if !locked => {
+                    assert!(msg.contains(&expected), "{err}")
+                }
+                _ => panic!("unexpected error: {err}"),
+            }
+        }
+        assert!(!root.join(".husako/types").exists());
+    }
+
     #[test]
     fn generate_chart_types_from_file_source() {
         let tmp = tempfile::tempdir().unwrap();
@@ -2413,6 +3493,9 @@ mod tests {
             config: Some(config),
             husako_version: String::new(),
             no_incremental: true,
+            locked: false,
+            frozen: false,
+            fetch: Default::default(),
         };
         tokio::runtime::Runtime::new()
             .unwrap()
@@ -2435,6 +3518,10 @@ mod tests {
         assert!(js.contains("class _MyChart extends _SchemaBuilder"));
         assert!(js.contains("export function MyChart()"));
 
+        // No Chart.yaml next to the schema: refs fall back to the config key
+        assert!(dts.contains("export function fullname(release: string"));
+        assert!(js.contains(r#"export const chart = { name: "my-chart", version: undefined"#));
+
         // Check tsconfig includes helm path
         let tsconfig = std::fs::read_to_string(root.join("tsconfig.json")).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&tsconfig).unwrap();
@@ -2453,6 +3540,9 @@ mod tests {
             config: None,
             husako_version: String::new(),
             no_incremental: true,
+            locked: false,
+            frozen: false,
+            fetch: Default::default(),
         };
         tokio::runtime::Runtime::new()
             .unwrap()
@@ -2557,6 +3647,9 @@ mod tests {
             config: None,
             husako_version: String::new(),
             no_incremental: true,
+            locked: false,
+            frozen: false,
+            fetch: Default::default(),
         };
         tokio::runtime::Runtime::new()
             .unwrap()
@@ -3012,6 +4105,9 @@ mod tests {
             config: None,
             husako_version: String::new(),
             no_incremental: true,
+            locked: false,
+            frozen: false,
+            fetch: Default::default(),
         };
         tokio::runtime::Runtime::new()
             .unwrap()
@@ -3170,6 +4266,9 @@ mod tests {
             config: None,
             husako_version: String::new(),
             no_incremental: true,
+            locked: false,
+            frozen: false,
+            fetch: Default::default(),
         };
         tokio::runtime::Runtime::new()
             .unwrap()
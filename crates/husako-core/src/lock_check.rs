@@ -1,10 +1,11 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 
 use husako_config::{
-    ChartLockEntry, ChartSource, HusakoConfig, HusakoLock, PluginLockEntry, PluginManifest,
-    PluginSource, ResourceLockEntry, SchemaSource,
+    ChartLockEntry, ChartRef, ChartSource, HusakoConfig, HusakoLock, PluginLockEntry,
+    PluginManifest, PluginSource, ResourceLockEntry, SchemaSource,
 };
+use husako_digest::{Sha256, sha256_digest, sha256_hex};
 
 /// Returns an RFC 3339 UTC timestamp string for lock entries.
 pub fn utc_now() -> String {
@@ -12,39 +13,30 @@ pub fn utc_now() -> String {
 }
 
 // ---------------------------------------------------------------------------
-// File / directory hashing (djb2)
+// File / directory hashing (sha256)
 // ---------------------------------------------------------------------------
 
-fn djb2_bytes(data: &[u8]) -> u64 {
-    let mut hash: u64 = 5381;
-    for &b in data {
-        hash = hash.wrapping_mul(33).wrapping_add(b as u64);
-    }
-    hash
-}
-
-/// Hash a single file using djb2.
+/// Hash a single file with SHA-256.
 pub fn hash_file(path: &Path) -> Result<String, std::io::Error> {
     let content = std::fs::read(path)?;
-    Ok(format!("{:016x}", djb2_bytes(&content)))
+    Ok(sha256_digest(&content))
 }
 
 /// Hash a directory by sorting all contained files lexicographically and
-/// hashing their relative path concatenated with their content.
+/// hashing each relative path, length and content with SHA-256.
 pub fn hash_dir(dir: &Path) -> Result<String, std::io::Error> {
     let mut files = collect_all_files(dir)?;
     files.sort();
-    let mut hash: u64 = 5381;
+    let mut hasher = Sha256::new();
     for file in &files {
         let rel = file.strip_prefix(dir).unwrap_or(file);
-        for b in rel.to_string_lossy().bytes() {
-            hash = hash.wrapping_mul(33).wrapping_add(b as u64);
-        }
-        for b in std::fs::read(file)? {
-            hash = hash.wrapping_mul(33).wrapping_add(b as u64);
-        }
+        let content = std::fs::read(file)?;
+        hasher.update(rel.to_string_lossy().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&(content.len() as u64).to_le_bytes());
+        hasher.update(&content);
     }
-    Ok(format!("{hash:016x}"))
+    Ok(hasher.finish())
 }
 
 fn collect_all_files(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
@@ -75,8 +67,8 @@ fn hash_path_source(path: &str, project_root: &Path) -> String {
 // ---------------------------------------------------------------------------
 
 /// Returns `true` if the entire k8s resource type generation block can be
-/// skipped. All resources are treated as one unit: if any resource fails its
-/// skip check, all k8s types are regenerated.
+/// skipped. Otherwise [`should_skip_resource`] decides which resources are
+/// resolved again.
 pub fn should_skip_k8s(
     config: Option<&HusakoConfig>,
     old_lock: Option<&HusakoLock>,
@@ -117,7 +109,7 @@ pub fn should_skip_k8s(
 
     // Every resource must pass its identity check
     for (name, source) in &config.resources {
-        if !resource_identity_matches(name, source, lock, project_root) {
+        if is_live(source) || !resource_identity_matches(name, source, lock, config, project_root) {
             return false;
         }
     }
@@ -125,10 +117,41 @@ pub fn should_skip_k8s(
     true
 }
 
+/// Returns `true` if the resolved specs of one k8s resource source are still
+/// current, so the source needs neither fetching nor its group-versions
+/// regenerating, provided its stored specs still exist.
+pub fn should_skip_resource(
+    name: &str,
+    source: &SchemaSource,
+    old_lock: Option<&HusakoLock>,
+    husako_version: &str,
+    config: &HusakoConfig,
+    project_root: &Path,
+) -> bool {
+    let Some(lock) = old_lock else {
+        return false;
+    };
+
+    // Binary version changed
+    if lock.husako_version != husako_version {
+        return false;
+    }
+
+    !is_live(source) && resource_identity_matches(name, source, lock, config, project_root)
+}
+
+/// Sources read from a running cluster, whose CRDs can be installed, upgraded
+/// or removed without anything in `husako.toml` changing. They are fetched on
+/// every `husako gen`.
+fn is_live(source: &SchemaSource) -> bool {
+    matches!(source, SchemaSource::Cluster { .. })
+}
+
 fn resource_identity_matches(
     name: &str,
     source: &SchemaSource,
     lock: &HusakoLock,
+    config: &HusakoConfig,
     project_root: &Path,
 ) -> bool {
     match source {
@@ -139,12 +162,20 @@ fn resource_identity_matches(
             )
         }
 
-        SchemaSource::Git { repo, tag, path } => {
-            matches!(
-                lock.resources.get(name),
-                Some(ResourceLockEntry::Git { repo: lr, tag: lt, path: lp, .. })
-                    if lr == repo && lt == tag && lp == path
-            )
+        SchemaSource::Git {
+            repo,
+            tag,
+            branch,
+            rev,
+            path,
+        } => {
+            // Branches move, so they are always re-fetched
+            branch.is_none()
+                && matches!(
+                    lock.resources.get(name),
+                    Some(ResourceLockEntry::Git { repo: lr, tag: lt, rev: lrev, path: lp, .. })
+                        if lr == repo && lt == tag && lrev == rev && lp == path
+                )
         }
 
         SchemaSource::File { path } => match lock.resources.get(name) {
@@ -161,9 +192,78 @@ fn resource_identity_matches(
             }
             _ => false,
         },
+
+        SchemaSource::Url { url, sha256 } => match lock.resources.get(name) {
+            Some(ResourceLockEntry::Url {
+                url: lu,
+                sha256: lh,
+                ..
+            }) => {
+                lu == url
+                    && sha256
+                        .as_ref()
+                        .is_none_or(|pin| pin.eq_ignore_ascii_case(lh))
+            }
+            _ => false,
+        },
+
+        SchemaSource::Chart { chart, values } => match lock.resources.get(name) {
+            Some(ResourceLockEntry::Chart {
+                chart: lc,
+                source_hash: lh,
+                ..
+            }) => {
+                let current_hash = hash_chart_schema_source(chart, values, config, project_root);
+                *lc == chart.to_string() && current_hash == *lh && !current_hash.is_empty()
+            }
+            _ => false,
+        },
+
+        SchemaSource::Cluster {
+            context,
+            kubeconfig,
+            groups,
+        } => {
+            matches!(
+                lock.resources.get(name),
+                Some(ResourceLockEntry::Cluster { context: lc, kubeconfig: lk, groups: lg, .. })
+                    if lc == context && lk == kubeconfig && lg == groups
+            )
+        }
     }
 }
 
+/// SHA-256 of the manifest cached for a URL schema source.
+fn manifest_digest(url: &str, project_root: &Path) -> Option<String> {
+    let cache_dir = project_root.join(".husako/cache");
+    let content = std::fs::read(crate::schema_source::url_cache_path(url, &cache_dir)).ok()?;
+    Some(sha256_hex(&content))
+}
+
+/// Hash the chart a `chart` schema source refers to (its `[charts]` entry or
+/// inline coordinate), together with its render values. Local charts also
+/// hash their content. Returns an empty string when the chart is not declared
+/// (e.g. provided by a plugin).
+fn hash_chart_schema_source(
+    chart: &ChartRef,
+    values: &Option<toml::Table>,
+    config: &HusakoConfig,
+    project_root: &Path,
+) -> String {
+    let Some(source) = chart.source(config) else {
+        return String::new();
+    };
+    // serde_json objects sort their keys, so the key does not depend on field
+    // or table order
+    let mut key = serde_json::to_value((source, values))
+        .and_then(|value| serde_json::to_vec(&value))
+        .unwrap_or_default();
+    if let ChartSource::File { path } | ChartSource::Chart { path } = source {
+        key.extend_from_slice(hash_path_source(path, project_root).as_bytes());
+    }
+    sha256_digest(&key)
+}
+
 /// Returns `true` if a single Helm chart's type generation can be skipped.
 pub fn should_skip_chart(
     name: &str,
@@ -232,12 +332,35 @@ fn chart_identity_matches(
             _ => false,
         },
 
-        ChartSource::Git { repo, tag, path } => {
-            matches!(
-                lock.charts.get(name),
-                Some(ChartLockEntry::Git { repo: lr, tag: lt, path: lp, .. })
-                    if lr == repo && lt == tag && lp == path
-            )
+        ChartSource::Chart { path } => match lock.charts.get(name) {
+            Some(ChartLockEntry::Chart {
+                path: lp,
+                content_hash: lh,
+                ..
+            }) => {
+                if lp != path {
+                    return false;
+                }
+                let current = hash_path_source(path, project_root);
+                current == *lh && !current.is_empty()
+            }
+            _ => false,
+        },
+
+        ChartSource::Git {
+            repo,
+            tag,
+            branch,
+            rev,
+            path,
+        } => {
+            // Branches move, so they are always re-fetched
+            branch.is_none()
+                && matches!(
+                    lock.charts.get(name),
+                    Some(ChartLockEntry::Git { repo: lr, tag: lt, rev: lrev, path: lp, .. })
+                        if lr == repo && lt == tag && lrev == rev && lp == path
+                )
         }
 
         ChartSource::Oci { reference, version } => {
@@ -272,35 +395,48 @@ pub fn should_skip_plugin(
         return false;
     }
 
+    if !plugin_identity_matches(source, lock_entry, project_root) {
+        return false;
+    }
+
+    // Plugin version in installed manifest must match lock
+    let (PluginLockEntry::Git { plugin_version, .. }
+    | PluginLockEntry::Path { plugin_version, .. }) = lock_entry;
+    match husako_config::load_plugin_manifest(&plugin_dir) {
+        Ok(m) => m.plugin.version == *plugin_version,
+        Err(_) => false,
+    }
+}
+
+fn plugin_identity_matches(
+    source: &PluginSource,
+    lock_entry: &PluginLockEntry,
+    project_root: &Path,
+) -> bool {
     match (source, lock_entry) {
         (
             PluginSource::Git {
                 url,
                 path: src_path,
+                tag,
+                version,
+                rev,
             },
             PluginLockEntry::Git {
                 url: lu,
                 path: lp,
-                plugin_version: lv,
+                tag: lt,
+                version: lver,
+                rev: lrev,
                 ..
             },
-        ) => {
-            if url != lu || src_path != lp {
-                return false;
-            }
-            // Plugin version in installed manifest must match lock
-            match husako_config::load_plugin_manifest(&plugin_dir) {
-                Ok(m) => m.plugin.version == *lv,
-                Err(_) => false,
-            }
-        }
+        ) => url == lu && src_path == lp && tag == lt && version == lver && rev == lrev,
 
         (
             PluginSource::Path { path },
             PluginLockEntry::Path {
                 path: lp,
                 content_hash: lh,
-                plugin_version: lv,
                 ..
             },
         ) => {
@@ -309,14 +445,7 @@ pub fn should_skip_plugin(
             }
             // Check directory content hash
             let current_hash = hash_dir(&project_root.join(path)).unwrap_or_default();
-            if current_hash != *lh || current_hash.is_empty() {
-                return false;
-            }
-            // Check installed plugin version matches lock
-            match husako_config::load_plugin_manifest(&plugin_dir) {
-                Ok(m) => m.plugin.version == *lv,
-                Err(_) => false,
-            }
+            current_hash == *lh && !current_hash.is_empty()
         }
 
         // Source type changed (e.g. was git, now path)
@@ -324,6 +453,166 @@ pub fn should_skip_plugin(
     }
 }
 
+/// Check a freshly built lock entry against the locked one.
+///
+/// Returns an error message when both describe the same source but the
+/// artifact fetched into the cache no longer hashes to the locked digest —
+/// the upstream content changed under an unchanged version.
+pub fn digest_error<T: serde::Serialize>(
+    kind: &str,
+    name: &str,
+    current: &T,
+    locked: Option<&T>,
+) -> Option<String> {
+    let (locked, current) = husako_config::digest_mismatch(current, locked?)?;
+    Some(format!(
+        "{kind} '{name}': digest mismatch: husako.lock records {locked}, fetched content is \
+         {current}; if the change is expected, remove the entry from husako.lock and re-run"
+    ))
+}
+
+/// Check the tags git resources and charts are pinned to against the commits
+/// locked for them.
+///
+/// `remote` returns the commit a tag of a repository points to now, asked of
+/// the remote rather than the fetch cache, or `None` when it cannot tell.
+/// Returns one message per source whose tag now points to a different commit
+/// than the one locked — a re-tagged release or a force-push. Sources whose
+/// repository or tag changed since they were locked, branches and `rev`
+/// sources are not reported.
+pub fn git_tag_drift(
+    config: &HusakoConfig,
+    locked: &HusakoLock,
+    remote: impl Fn(&str, &str) -> Option<String>,
+) -> Vec<String> {
+    let mut drifts = Vec::new();
+    let mut check =
+        |kind: &str, name: &str, repo: &str, tag: &str, locked: Option<(&str, &str, &str)>| {
+            if let Some((lr, lt, old)) = locked
+                && lr == repo
+                && lt == tag
+                && let Some(new) = remote(repo, tag)
+                && new != old
+            {
+                drifts.push(format!(
+                    "{kind} '{name}': tag {tag} of {repo} now resolves to {new} (locked {old})"
+                ));
+            }
+        };
+
+    for (name, source) in &config.resources {
+        if let SchemaSource::Git { repo, tag, .. } = source
+            && !tag.is_empty()
+        {
+            let entry = match locked.resources.get(name) {
+                Some(ResourceLockEntry::Git {
+                    repo,
+                    tag,
+                    commit: Some(commit),
+                    ..
+                }) => Some((repo.as_str(), tag.as_str(), commit.as_str())),
+                _ => None,
+            };
+            check("resource", name, repo, tag, entry);
+        }
+    }
+    for (name, source) in &config.charts {
+        if let ChartSource::Git { repo, tag, .. } = source
+            && !tag.is_empty()
+        {
+            let entry = match locked.charts.get(name) {
+                Some(ChartLockEntry::Git {
+                    repo,
+                    tag,
+                    commit: Some(commit),
+                    ..
+                }) => Some((repo.as_str(), tag.as_str(), commit.as_str())),
+                _ => None,
+            };
+            check("chart", name, repo, tag, entry);
+        }
+    }
+    drifts
+}
+
+// ---------------------------------------------------------------------------
+// Locked mode — report how husako.toml and husako.lock disagree
+// ---------------------------------------------------------------------------
+
+/// Describe each plugin whose `[plugins]` entry disagrees with the lock.
+///
+/// Checked before installation, so plugin presets are not yet merged.
+pub fn plugin_lock_mismatches(
+    config: &HusakoConfig,
+    lock: &HusakoLock,
+    project_root: &Path,
+) -> Vec<String> {
+    let mut out = Vec::new();
+    for (name, source) in sorted(&config.plugins) {
+        match lock.plugins.get(name) {
+            None => out.push(format!("plugin '{name}': not in husako.lock")),
+            Some(entry) if !plugin_identity_matches(source, entry, project_root) => out.push(
+                format!("plugin '{name}': changed since husako.lock was written"),
+            ),
+            Some(_) => {}
+        }
+    }
+    out.extend(removed("plugin", lock.plugins.keys(), &config.plugins));
+    out
+}
+
+/// Describe each resource and chart whose configuration disagrees with the
+/// lock, including entries the lock records but the config no longer has.
+///
+/// `charts` is the `[charts]` table with plugin presets merged in.
+pub fn lock_mismatches(
+    config: &HusakoConfig,
+    charts: &HashMap<String, ChartSource>,
+    lock: &HusakoLock,
+    project_root: &Path,
+) -> Vec<String> {
+    let mut out = Vec::new();
+    for (name, source) in sorted(&config.resources) {
+        if !lock.resources.contains_key(name) {
+            out.push(format!("resource '{name}': not in husako.lock"));
+        } else if !resource_identity_matches(name, source, lock, config, project_root) {
+            out.push(format!(
+                "resource '{name}': changed since husako.lock was written"
+            ));
+        }
+    }
+    out.extend(removed(
+        "resource",
+        lock.resources.keys(),
+        &config.resources,
+    ));
+    for (name, source) in sorted(charts) {
+        if !lock.charts.contains_key(name) {
+            out.push(format!("chart '{name}': not in husako.lock"));
+        } else if !chart_identity_matches(name, source, lock, project_root) {
+            out.push(format!(
+                "chart '{name}': changed since husako.lock was written"
+            ));
+        }
+    }
+    out.extend(removed("chart", lock.charts.keys(), charts));
+    out
+}
+
+fn sorted<V>(map: &HashMap<String, V>) -> BTreeMap<&String, &V> {
+    map.iter().collect()
+}
+
+fn removed<'a, V>(
+    kind: &'a str,
+    locked: impl Iterator<Item = &'a String> + 'a,
+    config: &'a HashMap<String, V>,
+) -> impl Iterator<Item = String> + 'a {
+    locked
+        .filter(|name| !config.contains_key(*name))
+        .map(move |name| format!("{kind} '{name}': in husako.lock but not in husako.toml"))
+}
+
 // ---------------------------------------------------------------------------
 // Entry builders — called after successful generation to populate new_lock
 // ---------------------------------------------------------------------------
@@ -334,17 +623,31 @@ pub fn build_resource_entries(
     project_root: &Path,
 ) -> BTreeMap<String, ResourceLockEntry> {
     let now = utc_now();
+    let cache_dir = project_root.join(".husako/cache");
     let mut entries = BTreeMap::new();
     for (name, source) in &config.resources {
         let entry = match source {
             SchemaSource::Release { version } => ResourceLockEntry::Release {
                 version: version.clone(),
+                requirement: None,
+                digest: release_digest(version, &cache_dir),
                 generated_at: now.clone(),
             },
-            SchemaSource::Git { repo, tag, path } => ResourceLockEntry::Git {
+            SchemaSource::Git {
+                repo,
+                tag,
+                branch,
+                rev,
+                path,
+            } => ResourceLockEntry::Git {
                 repo: repo.clone(),
                 tag: tag.clone(),
+                branch: branch.clone(),
+                rev: rev.clone(),
                 path: path.clone(),
+                commit: source.git_ref().and_then(|git_ref| {
+                    crate::schema_source::git_commit(repo, git_ref, &cache_dir)
+                }),
                 generated_at: now.clone(),
             },
             SchemaSource::File { path } => ResourceLockEntry::File {
@@ -352,15 +655,45 @@ pub fn build_resource_entries(
                 content_hash: hash_path_source(path, project_root),
                 generated_at: now.clone(),
             },
+            SchemaSource::Url { url, sha256 } => ResourceLockEntry::Url {
+                url: url.clone(),
+                sha256: manifest_digest(url, project_root)
+                    .or_else(|| sha256.clone())
+                    .unwrap_or_default(),
+                generated_at: now.clone(),
+            },
+            SchemaSource::Chart { chart, values } => ResourceLockEntry::Chart {
+                chart: chart.to_string(),
+                source_hash: hash_chart_schema_source(chart, values, config, project_root),
+                generated_at: now.clone(),
+            },
+            SchemaSource::Cluster {
+                context,
+                kubeconfig,
+                groups,
+            } => ResourceLockEntry::Cluster {
+                context: context.clone(),
+                kubeconfig: kubeconfig.clone(),
+                groups: groups.clone(),
+                generated_at: now.clone(),
+            },
         };
         entries.insert(name.clone(), entry);
     }
     entries
 }
 
+/// Digest of the cached spec files of a Kubernetes release, if fetched.
+fn release_digest(version: &str, cache_dir: &Path) -> Option<String> {
+    let tag = husako_openapi::release::version_to_tag(version);
+    let dir = cache_dir.join(format!("release/{tag}"));
+    dir.is_dir().then(|| hash_dir(&dir).ok()).flatten()
+}
+
 /// Build a lock entry for a single Helm chart source.
 pub fn build_chart_entry(source: &ChartSource, project_root: &Path) -> ChartLockEntry {
     let now = utc_now();
+    let digest = husako_helm::archive_digest(source, &project_root.join(".husako/cache"));
     match source {
         ChartSource::Registry {
             repo,
@@ -370,11 +703,15 @@ pub fn build_chart_entry(source: &ChartSource, project_root: &Path) -> ChartLock
             repo: repo.clone(),
             chart: chart.clone(),
             version: version.clone(),
+            requirement: None,
+            digest,
             generated_at: now,
         },
         ChartSource::ArtifactHub { package, version } => ChartLockEntry::ArtifactHub {
             package: package.clone(),
             version: version.clone(),
+            requirement: None,
+            digest,
             generated_at: now,
         },
         ChartSource::File { path } => ChartLockEntry::File {
@@ -382,34 +719,65 @@ pub fn build_chart_entry(source: &ChartSource, project_root: &Path) -> ChartLock
             content_hash: hash_path_source(path, project_root),
             generated_at: now,
         },
-        ChartSource::Git { repo, tag, path } => ChartLockEntry::Git {
+        ChartSource::Chart { path } => ChartLockEntry::Chart {
+            path: path.clone(),
+            content_hash: hash_path_source(path, project_root),
+            generated_at: now,
+        },
+        ChartSource::Git {
+            repo,
+            tag,
+            branch,
+            rev,
+            path,
+        } => ChartLockEntry::Git {
             repo: repo.clone(),
             tag: tag.clone(),
+            branch: branch.clone(),
+            rev: rev.clone(),
             path: path.clone(),
+            commit: husako_helm::git_commit(source, &project_root.join(".husako/cache")),
             generated_at: now,
         },
         ChartSource::Oci { reference, version } => ChartLockEntry::Oci {
             reference: reference.clone(),
             version: version.clone(),
+            requirement: None,
+            digest,
             generated_at: now,
         },
     }
 }
 
 /// Build a lock entry for a plugin after successful installation.
+///
+/// `commit` is the SHA a git plugin was checked out at.
 pub fn build_plugin_entry(
     source: &PluginSource,
     plugin_dir: &Path,
     manifest: &PluginManifest,
+    commit: Option<String>,
     project_root: &Path,
 ) -> PluginLockEntry {
     let now = utc_now();
-    let version = manifest.plugin.version.clone();
+    let plugin_version = manifest.plugin.version.clone();
     match source {
-        PluginSource::Git { url, path } => PluginLockEntry::Git {
+        PluginSource::Git {
+            url,
+            path,
+            tag,
+            version,
+            rev,
+        } => PluginLockEntry::Git {
             url: url.clone(),
             path: path.clone(),
-            plugin_version: version,
+            tag: tag.clone(),
+            version: version.clone(),
+            requirement: None,
+            rev: rev.clone(),
+            commit,
+            digest: hash_dir(plugin_dir).ok(),
+            plugin_version,
             generated_at: now,
         },
         PluginSource::Path { path } => {
@@ -424,7 +792,7 @@ pub fn build_plugin_entry(
             PluginLockEntry::Path {
                 path: path.clone(),
                 content_hash,
-                plugin_version: version,
+                plugin_version,
                 generated_at: now,
             }
         }
@@ -446,7 +814,7 @@ mod tests {
         plugins: BTreeMap<String, PluginLockEntry>,
     ) -> HusakoLock {
         HusakoLock {
-            format_version: 1,
+            format_version: husako_config::LOCK_FORMAT_VERSION,
             husako_version: "0.3.0".to_string(),
             resources,
             charts,
@@ -489,6 +857,8 @@ mod tests {
             "kubernetes",
             ResourceLockEntry::Release {
                 version: "1.35".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -516,6 +886,8 @@ mod tests {
             "kubernetes",
             ResourceLockEntry::Release {
                 version: "1.34".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -534,6 +906,190 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn skip_resource_checked_on_its_own() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let release = |version: &str| ResourceLockEntry::Release {
+            version: version.to_string(),
+            requirement: None,
+            digest: None,
+            generated_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        let mut resources = BTreeMap::new();
+        resources.insert("kubernetes".to_string(), release("1.35"));
+        resources.insert("other".to_string(), release("1.34"));
+        let lock = make_lock(resources, BTreeMap::new(), BTreeMap::new());
+        let source = SchemaSource::Release {
+            version: "1.35".to_string(),
+        };
+        let config = config_with_resource("kubernetes", source.clone());
+
+        let skip = |name: &str, lock: Option<&HusakoLock>, version: &str| {
+            should_skip_resource(name, &source, lock, version, &config, root)
+        };
+        assert!(skip("kubernetes", Some(&lock), "0.3.0"));
+        // Another entry's version differs
+        assert!(!skip("other", Some(&lock), "0.3.0"));
+        assert!(!skip("kubernetes", Some(&lock), "0.4.0"));
+        assert!(!skip("kubernetes", None, "0.3.0"));
+    }
+
+    #[test]
+    fn skip_url_until_url_or_pin_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        make_k8s_types_dir(root);
+        let url = "https://example.com/v1/crds.yaml";
+        let lock = lock_with_resource(
+            "widgets",
+            ResourceLockEntry::Url {
+                url: url.to_string(),
+                sha256: "abc123".to_string(),
+                generated_at: "2026-01-01T00:00:00Z".to_string(),
+            },
+        );
+        let check = |url: &str, sha256: Option<&str>| {
+            let config = config_with_resource(
+                "widgets",
+                SchemaSource::Url {
+                    url: url.to_string(),
+                    sha256: sha256.map(String::from),
+                },
+            );
+            should_skip_k8s(Some(&config), Some(&lock), "0.3.0", root, root)
+        };
+        assert!(check(url, None));
+        assert!(check(url, Some("ABC123")));
+        assert!(!check(url, Some("def456")));
+        assert!(!check("https://example.com/v2/crds.yaml", None));
+    }
+
+    #[test]
+    fn never_skip_cluster_sources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        make_k8s_types_dir(root);
+        let source = SchemaSource::Cluster {
+            context: Some("prod".to_string()),
+            kubeconfig: None,
+            groups: vec!["cert-manager.io".to_string()],
+        };
+        let config = config_with_resource("installed", source.clone());
+        let lock = make_lock(
+            build_resource_entries(&config, root),
+            BTreeMap::new(),
+            BTreeMap::new(),
+        );
+        assert!(!should_skip_k8s(
+            Some(&config),
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+        assert!(!should_skip_resource(
+            "installed",
+            &source,
+            Some(&lock),
+            "0.3.0",
+            &config,
+            root
+        ));
+        // The lock still describes the configured source for --locked
+        assert!(lock_mismatches(&config, &config.charts, &lock, root).is_empty());
+    }
+
+    #[test]
+    fn skip_chart_crds_until_chart_version_bumped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        make_k8s_types_dir(root);
+        let mut config = config_with_resource(
+            "cert-manager-crds",
+            SchemaSource::Chart {
+                chart: ChartRef::Name("cert-manager".to_string()),
+                values: None,
+            },
+        );
+        let registry = |version: &str| ChartSource::Registry {
+            repo: "https://charts.jetstack.io".to_string(),
+            chart: "cert-manager".to_string(),
+            version: version.to_string(),
+        };
+        config
+            .charts
+            .insert("cert-manager".to_string(), registry("v1.16.0"));
+        let lock = make_lock(
+            build_resource_entries(&config, root),
+            BTreeMap::new(),
+            BTreeMap::new(),
+        );
+        assert!(should_skip_k8s(
+            Some(&config),
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+
+        config
+            .charts
+            .insert("cert-manager".to_string(), registry("v1.17.0"));
+        assert!(!should_skip_k8s(
+            Some(&config),
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+
+        config.charts.clear();
+        assert!(!should_skip_k8s(
+            Some(&config),
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+    }
+
+    #[test]
+    fn skip_inline_chart_crds_until_version_bumped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        make_k8s_types_dir(root);
+        let inline = |version: &str| SchemaSource::Chart {
+            chart: ChartRef::Inline(ChartSource::Oci {
+                reference: "oci://ghcr.io/org/operator".to_string(),
+                version: version.to_string(),
+            }),
+            values: None,
+        };
+        let config = config_with_resource("operator-crds", inline("1.0.0"));
+        let lock = make_lock(
+            build_resource_entries(&config, root),
+            BTreeMap::new(),
+            BTreeMap::new(),
+        );
+        assert!(should_skip_k8s(
+            Some(&config),
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+
+        let config = config_with_resource("operator-crds", inline("1.1.0"));
+        assert!(!should_skip_k8s(
+            Some(&config),
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+    }
+
     #[test]
     fn skip_git_unchanged() {
         let tmp = tempfile::tempdir().unwrap();
@@ -544,7 +1100,10 @@ mod tests {
             ResourceLockEntry::Git {
                 repo: "https://github.com/cert-manager/cert-manager".to_string(),
                 tag: "v1.17.2".to_string(),
+                branch: None,
+                rev: None,
                 path: "deploy/crds".to_string(),
+                commit: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -553,6 +1112,8 @@ mod tests {
             SchemaSource::Git {
                 repo: "https://github.com/cert-manager/cert-manager".to_string(),
                 tag: "v1.17.2".to_string(),
+                branch: None,
+                rev: None,
                 path: "deploy/crds".to_string(),
             },
         );
@@ -575,7 +1136,10 @@ mod tests {
             ResourceLockEntry::Git {
                 repo: "https://github.com/cert-manager/cert-manager".to_string(),
                 tag: "v1.17.1".to_string(),
+                branch: None,
+                rev: None,
                 path: "deploy/crds".to_string(),
+                commit: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -584,6 +1148,8 @@ mod tests {
             SchemaSource::Git {
                 repo: "https://github.com/cert-manager/cert-manager".to_string(),
                 tag: "v1.17.2".to_string(),
+                branch: None,
+                rev: None,
                 path: "deploy/crds".to_string(),
             },
         );
@@ -596,6 +1162,118 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn never_skip_git_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        make_k8s_types_dir(root);
+        let lock = lock_with_resource(
+            "op",
+            ResourceLockEntry::Git {
+                repo: "https://github.com/example/op".to_string(),
+                tag: String::new(),
+                branch: Some("main".to_string()),
+                rev: None,
+                path: "crds".to_string(),
+                commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+                generated_at: "2026-01-01T00:00:00Z".to_string(),
+            },
+        );
+        let config = config_with_resource(
+            "op",
+            SchemaSource::Git {
+                repo: "https://github.com/example/op".to_string(),
+                tag: String::new(),
+                branch: Some("main".to_string()),
+                rev: None,
+                path: "crds".to_string(),
+            },
+        );
+        assert!(!should_skip_k8s(
+            Some(&config),
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+    }
+
+    #[test]
+    fn git_tag_drift_reports_moved_tags_only() {
+        let repo = "https://github.com/example/op";
+        let source = |tag: &str, branch: Option<&str>| SchemaSource::Git {
+            repo: repo.to_string(),
+            tag: tag.to_string(),
+            branch: branch.map(str::to_string),
+            rev: None,
+            path: "crds".to_string(),
+        };
+        let git = |tag: &str, branch: Option<&str>, commit: &str| ResourceLockEntry::Git {
+            repo: repo.to_string(),
+            tag: tag.to_string(),
+            branch: branch.map(str::to_string),
+            rev: None,
+            path: "crds".to_string(),
+            commit: Some(commit.to_string()),
+            generated_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        let mut locked = lock_with_resource("moved", git("v1.0.0", None, "aaaa"));
+        for (name, entry) in [
+            ("unchanged", git("v1.1.0", None, "bbbb")),
+            ("bumped", git("v1.0.0", None, "aaaa")),
+            ("edge", git("", Some("main"), "cccc")),
+        ] {
+            locked.resources.insert(name.to_string(), entry);
+        }
+        let mut config = HusakoConfig::default();
+        for (name, source) in [
+            ("moved", source("v1.0.0", None)),
+            ("unchanged", source("v1.1.0", None)),
+            ("bumped", source("v1.1.0", None)),
+            ("edge", source("", Some("main"))),
+            ("new", source("v1.0.0", None)),
+        ] {
+            config.resources.insert(name.to_string(), source);
+        }
+        config.charts.insert(
+            "chart".to_string(),
+            ChartSource::Git {
+                repo: repo.to_string(),
+                tag: "v1.0.0".to_string(),
+                branch: None,
+                rev: None,
+                path: "charts/op".to_string(),
+            },
+        );
+        locked.charts.insert(
+            "chart".to_string(),
+            ChartLockEntry::Git {
+                repo: repo.to_string(),
+                tag: "v1.0.0".to_string(),
+                branch: None,
+                rev: None,
+                path: "charts/op".to_string(),
+                commit: Some("aaaa".to_string()),
+                generated_at: "2026-01-01T00:00:00Z".to_string(),
+            },
+        );
+
+        // The remote has moved v1.0.0; the cache still holds the old commit
+        let remote = |_: &str, tag: &str| match tag {
+            "v1.0.0" => Some("dddd".to_string()),
+            "v1.1.0" => Some("bbbb".to_string()),
+            _ => None,
+        };
+        let drift = git_tag_drift(&config, &locked, remote);
+        assert_eq!(drift.len(), 2, "{drift:?}");
+        assert!(drift[0].contains("resource 'moved'"));
+        assert!(drift[0].contains("now resolves to dddd (locked aaaa)"));
+        assert!(drift[1].contains("chart 'chart'"));
+
+        // Nothing is reported when the remote cannot be asked
+        assert!(git_tag_drift(&config, &locked, |_, _| None).is_empty());
+    }
+
     #[test]
     fn skip_file_content_unchanged() {
         let tmp = tempfile::tempdir().unwrap();
@@ -668,6 +1346,8 @@ mod tests {
             "kubernetes",
             ResourceLockEntry::Release {
                 version: "1.35".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -706,6 +1386,8 @@ mod tests {
             "kubernetes",
             ResourceLockEntry::Release {
                 version: "1.35".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -733,6 +1415,8 @@ mod tests {
             "kubernetes",
             ResourceLockEntry::Release {
                 version: "1.35".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -779,6 +1463,8 @@ mod tests {
                 repo: "https://kubernetes.github.io/ingress-nginx".to_string(),
                 chart: "ingress-nginx".to_string(),
                 version: "4.12.0".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -808,6 +1494,8 @@ mod tests {
                 repo: "https://kubernetes.github.io/ingress-nginx".to_string(),
                 chart: "ingress-nginx".to_string(),
                 version: "4.11.0".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -836,6 +1524,8 @@ mod tests {
             ChartLockEntry::ArtifactHub {
                 package: "bitnami/postgresql".to_string(),
                 version: "16.4.0".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -863,6 +1553,8 @@ mod tests {
             ChartLockEntry::Oci {
                 reference: "oci://ghcr.io/org/postgresql".to_string(),
                 version: "1.2.3".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -880,6 +1572,40 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn skip_chart_local_content_unchanged() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        make_helm_types_dir(root, "my-app");
+        let chart_dir = root.join("charts/my-app");
+        std::fs::create_dir_all(&chart_dir).unwrap();
+        std::fs::write(chart_dir.join("Chart.yaml"), "name: my-app\n").unwrap();
+        std::fs::write(chart_dir.join("values.yaml"), "replicas: 1\n").unwrap();
+
+        let source = ChartSource::Chart {
+            path: "charts/my-app".to_string(),
+        };
+        let lock = make_chart_lock("my-app", build_chart_entry(&source, root));
+        assert!(should_skip_chart(
+            "my-app",
+            &source,
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+
+        std::fs::write(chart_dir.join("values.yaml"), "replicas: 2\n").unwrap();
+        assert!(!should_skip_chart(
+            "my-app",
+            &source,
+            Some(&lock),
+            "0.3.0",
+            root,
+            root
+        ));
+    }
+
     #[test]
     fn no_skip_chart_types_file_missing() {
         let tmp = tempfile::tempdir().unwrap();
@@ -891,6 +1617,8 @@ mod tests {
                 repo: "https://kubernetes.github.io/ingress-nginx".to_string(),
                 chart: "ingress-nginx".to_string(),
                 version: "4.12.0".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -919,6 +1647,8 @@ mod tests {
             ChartLockEntry::ArtifactHub {
                 package: "bitnami/postgresql".to_string(),
                 version: "16.4.0".to_string(),
+                requirement: None,
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
@@ -967,13 +1697,22 @@ mod tests {
             PluginLockEntry::Git {
                 url: "https://github.com/nanazt/husako".to_string(),
                 path: Some("plugins/fluxcd".to_string()),
+                tag: None,
+                version: None,
+                requirement: None,
+                rev: None,
+                commit: None,
                 plugin_version: "0.2.0".to_string(),
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
         let source = PluginSource::Git {
             url: "https://github.com/nanazt/husako".to_string(),
             path: Some("plugins/fluxcd".to_string()),
+            tag: None,
+            version: None,
+            rev: None,
         };
         assert!(should_skip_plugin(
             "fluxcd",
@@ -996,13 +1735,22 @@ mod tests {
             PluginLockEntry::Git {
                 url: "https://github.com/old/repo".to_string(),
                 path: None,
+                tag: None,
+                version: None,
+                requirement: None,
+                rev: None,
+                commit: None,
                 plugin_version: "0.2.0".to_string(),
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
         let source = PluginSource::Git {
             url: "https://github.com/new/repo".to_string(),
             path: None,
+            tag: None,
+            version: None,
+            rev: None,
         };
         assert!(!should_skip_plugin(
             "fluxcd",
@@ -1013,6 +1761,51 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn skip_plugin_git_pin_changed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let plugins_dir = tmp.path().join("plugins");
+        let plugin_dir = plugins_dir.join("fluxcd");
+        write_plugin_manifest(&plugin_dir, "0.2.0");
+
+        let lock = make_plugin_lock(
+            "fluxcd",
+            PluginLockEntry::Git {
+                url: "https://github.com/nanazt/husako-plugin-fluxcd".to_string(),
+                path: None,
+                tag: None,
+                version: Some("0.2.0".to_string()),
+                requirement: None,
+                rev: None,
+                commit: None,
+                plugin_version: "0.2.0".to_string(),
+                digest: None,
+                generated_at: "2026-01-01T00:00:00Z".to_string(),
+            },
+        );
+        let pinned = |version: &str| PluginSource::Git {
+            url: "https://github.com/nanazt/husako-plugin-fluxcd".to_string(),
+            path: None,
+            tag: None,
+            version: Some(version.to_string()),
+            rev: None,
+        };
+        assert!(should_skip_plugin(
+            "fluxcd",
+            &pinned("0.2.0"),
+            Some(&lock),
+            &plugins_dir,
+            tmp.path()
+        ));
+        assert!(!should_skip_plugin(
+            "fluxcd",
+            &pinned("0.3.0"),
+            Some(&lock),
+            &plugins_dir,
+            tmp.path()
+        ));
+    }
+
     #[test]
     fn skip_plugin_plugin_version_changed() {
         let tmp = tempfile::tempdir().unwrap();
@@ -1026,13 +1819,22 @@ mod tests {
             PluginLockEntry::Git {
                 url: "https://github.com/nanazt/husako".to_string(),
                 path: None,
+                tag: None,
+                version: None,
+                requirement: None,
+                rev: None,
+                commit: None,
                 plugin_version: "0.2.0".to_string(),
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
         let source = PluginSource::Git {
             url: "https://github.com/nanazt/husako".to_string(),
             path: None,
+            tag: None,
+            version: None,
+            rev: None,
         };
         assert!(!should_skip_plugin(
             "fluxcd",
@@ -1054,13 +1856,22 @@ mod tests {
             PluginLockEntry::Git {
                 url: "https://github.com/nanazt/husako".to_string(),
                 path: None,
+                tag: None,
+                version: None,
+                requirement: None,
+                rev: None,
+                commit: None,
                 plugin_version: "0.2.0".to_string(),
+                digest: None,
                 generated_at: "2026-01-01T00:00:00Z".to_string(),
             },
         );
         let source = PluginSource::Git {
             url: "https://github.com/nanazt/husako".to_string(),
             path: None,
+            tag: None,
+            version: None,
+            rev: None,
         };
         assert!(!should_skip_plugin(
             "fluxcd",
@@ -1075,6 +1886,28 @@ mod tests {
     // Hashing tests
     // -----------------------------------------------------------------------
 
+    #[test]
+    fn chart_schema_hash_is_canonical_json() {
+        let chart = ChartRef::Inline(ChartSource::Registry {
+            repo: "https://charts.example.com".to_string(),
+            chart: "app".to_string(),
+            version: "1.0.0".to_string(),
+        });
+        let values: toml::Table = toml::from_str("b = 2\na = 1\n").unwrap();
+        let hash = hash_chart_schema_source(
+            &chart,
+            &Some(values),
+            &HusakoConfig::default(),
+            Path::new("."),
+        );
+        assert_eq!(
+            hash,
+            sha256_digest(
+                br#"[{"chart":"app","repo":"https://charts.example.com","source":"registry","version":"1.0.0"},{"a":1,"b":2}]"#
+            )
+        );
+    }
+
     #[test]
     fn hash_dir_detects_content_change() {
         let tmp = tempfile::tempdir().unwrap();
@@ -1114,4 +1947,100 @@ mod tests {
         let h2 = hash_dir(tmp.path()).unwrap();
         assert_eq!(h1, h2);
     }
+
+    #[test]
+    fn hash_file_is_prefixed_sha256() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("a.txt");
+        std::fs::write(&file, b"abc").unwrap();
+        assert_eq!(
+            hash_file(&file).unwrap(),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let dir_hash = hash_dir(tmp.path()).unwrap();
+        assert!(dir_hash.starts_with("sha256:"));
+        assert_eq!(dir_hash.len(), "sha256:".len() + 64);
+    }
+
+    #[test]
+    fn digest_error_only_for_changed_content() {
+        let entry = |digest: &str, generated_at: &str| ChartLockEntry::Registry {
+            repo: "https://charts.example.com".to_string(),
+            chart: "ingress".to_string(),
+            version: "1.0.0".to_string(),
+            requirement: None,
+            digest: Some(digest.to_string()),
+            generated_at: generated_at.to_string(),
+        };
+        let locked = entry("sha256:aaa", "2026-01-01T00:00:00Z");
+
+        let same = entry("sha256:aaa", "2026-02-01T00:00:00Z");
+        assert!(digest_error("chart", "ingress", &same, Some(&locked)).is_none());
+
+        let changed = entry("sha256:bbb", "2026-02-01T00:00:00Z");
+        let msg = digest_error("chart", "ingress", &changed, Some(&locked)).unwrap();
+        assert!(msg.contains("chart 'ingress': digest mismatch"), "{msg}");
+        assert!(
+            msg.contains("sha256:aaa") && msg.contains("sha256:bbb"),
+            "{msg}"
+        );
+
+        assert!(digest_error("chart", "ingress", &changed, None).is_none());
+    }
+
+    #[test]
+    fn lock_mismatches_lists_added_changed_and_removed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let lock = HusakoLock {
+            format_version: husako_config::LOCK_FORMAT_VERSION,
+            husako_version: String::new(),
+            resources: BTreeMap::from([
+                (
+                    "k8s".to_string(),
+                    ResourceLockEntry::Release {
+                        version: "1.34".to_string(),
+                        requirement: None,
+                        digest: None,
+                        generated_at: String::new(),
+                    },
+                ),
+                (
+                    "old".to_string(),
+                    ResourceLockEntry::Release {
+                        version: "1.30".to_string(),
+                        requirement: None,
+                        digest: None,
+                        generated_at: String::new(),
+                    },
+                ),
+            ]),
+            charts: BTreeMap::new(),
+            plugins: BTreeMap::new(),
+        };
+        let config = HusakoConfig {
+            resources: HashMap::from([(
+                "k8s".to_string(),
+                SchemaSource::Release {
+                    version: "1.35".to_string(),
+                },
+            )]),
+            charts: HashMap::from([(
+                "ingress".to_string(),
+                ChartSource::ArtifactHub {
+                    package: "org/ingress".to_string(),
+                    version: "1.0.0".to_string(),
+                },
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(
+            lock_mismatches(&config, &config.charts, &lock, tmp.path()),
+            [
+                "resource 'k8s': changed since husako.lock was written",
+                "resource 'old': in husako.lock but not in husako.toml",
+                "chart 'ingress': not in husako.lock",
+            ]
+        );
+        assert!(plugin_lock_mismatches(&config, &lock, tmp.path()).is_empty());
+    }
 }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use husako_config::{HusakoConfig, PluginManifest, PluginSource};
+use husako_config::{FetchContext, GitRef, HusakoConfig, PluginManifest, PluginSource};
 
 use crate::HusakoError;
 use crate::progress::ProgressReporter;
@@ -24,6 +24,7 @@ pub async fn install_plugins(
     config: &HusakoConfig,
     project_root: &Path,
     progress: &dyn ProgressReporter,
+    ctx: &FetchContext,
 ) -> Result<Vec<InstalledPlugin>, HusakoError> {
     if config.plugins.is_empty() {
         return Ok(Vec::new());
@@ -37,8 +38,8 @@ pub async fn install_plugins(
         let plugin_dir = plugins_dir.join(name);
         let task = progress.start_task(&format!("Installing plugin {name}..."));
 
-        match install_plugin(name, source, project_root, &plugin_dir).await {
-            Ok(()) => match husako_config::load_plugin_manifest(&plugin_dir) {
+        match install_plugin(name, source, project_root, &plugin_dir, ctx).await {
+            Ok(_) => match husako_config::load_plugin_manifest(&plugin_dir) {
                 Ok(manifest) => {
                     task.finish_ok(&format!("{name}: installed (v{})", manifest.plugin.version));
                     installed.push(InstalledPlugin {
@@ -62,12 +63,25 @@ pub async fn install_plugins(
     Ok(installed)
 }
 
+/// Install a single plugin into `target_dir`, replacing any existing install.
+///
+/// Returns the commit SHA that was checked out for git sources.
 pub(crate) async fn install_plugin(
     name: &str,
     source: &PluginSource,
     project_root: &Path,
     target_dir: &Path,
-) -> Result<(), HusakoError> {
+    ctx: &FetchContext,
+) -> Result<Option<String>, HusakoError> {
+    if ctx.offline
+        && let PluginSource::Git { url, .. } = source
+    {
+        return Err(HusakoError::Offline(format!(
+            "plugin '{name}' ({url}) is missing or out of date at {}",
+            target_dir.display()
+        )));
+    }
+
     // Clean existing install
     if target_dir.exists() {
         std::fs::remove_dir_all(target_dir).map_err(|e| {
@@ -76,72 +90,115 @@ pub(crate) async fn install_plugin(
     }
 
     match source {
-        PluginSource::Git { url, path } => {
-            install_git(name, url, path.as_deref(), target_dir).await
+        PluginSource::Git {
+            url,
+            path,
+            tag,
+            version,
+            rev,
+        } => {
+            // A `version` may be published as `v<version>` or `<version>`
+            let version_tags = version.as_deref().map(version_tags).unwrap_or_default();
+            let cache_dir = project_root.join(".husako/cache");
+            let candidates: Vec<GitRef<'_>> = match (tag, rev) {
+                (Some(tag), _) => vec![GitRef::Tag(tag)],
+                (None, Some(rev)) => vec![GitRef::Rev(rev)],
+                (None, None) => version_tags.iter().map(|t| GitRef::Tag(t)).collect(),
+            };
+            if candidates.is_empty() {
+                return install_git(
+                    name,
+                    url,
+                    path.as_deref(),
+                    None,
+                    &cache_dir,
+                    target_dir,
+                    ctx,
+                )
+                .await;
+            }
+            let mut last_err = None;
+            for git_ref in candidates {
+                match install_git(
+                    name,
+                    url,
+                    path.as_deref(),
+                    Some(git_ref),
+                    &cache_dir,
+                    target_dir,
+                    ctx,
+                )
+                .await
+                {
+                    Ok(commit) => return Ok(commit),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+            Err(last_err.expect("at least one candidate ref"))
         }
         PluginSource::Path { path } => {
             let source_dir = project_root.join(path);
-            install_path(name, &source_dir, target_dir)
+            install_path(name, &source_dir, target_dir).map(|()| None)
         }
     }
 }
 
+/// Tag names a plugin `version` may be published under, in lookup order.
+fn version_tags(version: &str) -> Vec<String> {
+    if version.starts_with('v') {
+        vec![version.to_string()]
+    } else {
+        vec![format!("v{version}"), version.to_string()]
+    }
+}
+
 async fn install_git(
     name: &str,
     url: &str,
     subdir: Option<&str>,
+    git_ref: Option<GitRef<'_>>,
+    cache_dir: &Path,
     target_dir: &Path,
-) -> Result<(), HusakoError> {
+    ctx: &FetchContext,
+) -> Result<Option<String>, HusakoError> {
     match subdir {
-        None => install_git_full(name, url, target_dir).await,
-        Some(sub) => install_git_subdir(name, url, sub, target_dir).await,
+        None => install_git_full(name, url, git_ref, cache_dir, target_dir, ctx).await,
+        Some(sub) => install_git_subdir(name, url, sub, git_ref, cache_dir, target_dir, ctx).await,
     }
 }
 
-/// Shallow-clone the full repository into `target_dir`.
-async fn install_git_full(name: &str, url: &str, target_dir: &Path) -> Result<(), HusakoError> {
-    std::fs::create_dir_all(target_dir).map_err(|e| {
-        HusakoError::GenerateIo(format!("create dir {}: {e}", target_dir.display()))
-    })?;
-
-    let output = tokio::process::Command::new("git")
-        .args([
-            "clone",
-            "--depth",
-            "1",
-            "--single-branch",
-            url,
-            &target_dir.to_string_lossy(),
-        ])
-        .output()
-        .await
-        .map_err(|e| HusakoError::GenerateIo(format!("plugin '{name}': git clone failed: {e}")))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let _ = std::fs::remove_dir_all(target_dir);
-        return Err(HusakoError::GenerateIo(format!(
-            "plugin '{name}': git clone failed: {stderr}"
-        )));
-    }
-
-    // Remove .git directory to save space
-    let git_dir = target_dir.join(".git");
-    if git_dir.exists() {
-        let _ = std::fs::remove_dir_all(&git_dir);
+/// Shallow-fetch the full repository into `target_dir`, at `git_ref` or
+/// the default branch HEAD.
+async fn install_git_full(
+    name: &str,
+    url: &str,
+    git_ref: Option<GitRef<'_>>,
+    cache_dir: &Path,
+    target_dir: &Path,
+    ctx: &FetchContext,
+) -> Result<Option<String>, HusakoError> {
+    match husako_git::fetch(url, git_ref, None, cache_dir, target_dir, None, ctx).await {
+        Ok(commit) => Ok(Some(commit)),
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(target_dir);
+            Err(HusakoError::GenerateIo(format!(
+                "plugin '{name}': git clone failed: {e}"
+            )))
+        }
     }
-
-    Ok(())
 }
 
-/// Use sparse-checkout to fetch only `subdir` from the repository,
-/// then copy its contents into `target_dir`.
+/// Sparse-fetch only `subdir` from the repository, then copy its contents
+/// into `target_dir`.
 async fn install_git_subdir(
     name: &str,
     url: &str,
     subdir: &str,
+    git_ref: Option<GitRef<'_>>,
+    cache_dir: &Path,
     target_dir: &Path,
-) -> Result<(), HusakoError> {
+    ctx: &FetchContext,
+) -> Result<Option<String>, HusakoError> {
     // Use a sibling temp directory for staging
     let tmp_dir = {
         let mut p = target_dir.to_path_buf();
@@ -161,89 +218,37 @@ async fn install_git_subdir(
         std::fs::remove_dir_all(&tmp_dir)
             .map_err(|e| HusakoError::GenerateIo(format!("remove {}: {e}", tmp_dir.display())))?;
     }
-    std::fs::create_dir_all(&tmp_dir)
-        .map_err(|e| HusakoError::GenerateIo(format!("create dir {}: {e}", tmp_dir.display())))?;
 
     let cleanup = |msg: String| -> HusakoError {
         let _ = std::fs::remove_dir_all(&tmp_dir);
         HusakoError::GenerateIo(msg)
     };
 
-    let tmp = tmp_dir.to_string_lossy().into_owned();
-
-    // git init
-    let output = tokio::process::Command::new("git")
-        .args(["init", &tmp])
-        .output()
+    let commit = husako_git::fetch(url, git_ref, Some(subdir), cache_dir, &tmp_dir, None, ctx)
         .await
-        .map_err(|e| cleanup(format!("plugin '{name}': git init failed: {e}")))?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(cleanup(format!(
-            "plugin '{name}': git init failed: {stderr}"
-        )));
-    }
-
-    // git remote add origin <url>
-    let output = tokio::process::Command::new("git")
-        .args(["-C", &tmp, "remote", "add", "origin", url])
-        .output()
-        .await
-        .map_err(|e| cleanup(format!("plugin '{name}': git remote add failed: {e}")))?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(cleanup(format!(
-            "plugin '{name}': git remote add failed: {stderr}"
-        )));
-    }
-
-    // git sparse-checkout set <subdir>
-    let output = tokio::process::Command::new("git")
-        .args(["-C", &tmp, "sparse-checkout", "set", subdir])
-        .output()
-        .await
-        .map_err(|e| cleanup(format!("plugin '{name}': git sparse-checkout failed: {e}")))?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(cleanup(format!(
-            "plugin '{name}': git sparse-checkout failed: {stderr}"
-        )));
-    }
-
-    // git pull --depth 1 origin HEAD
-    let output = tokio::process::Command::new("git")
-        .args(["-C", &tmp, "pull", "--depth", "1", "origin", "HEAD"])
-        .output()
-        .await
-        .map_err(|e| cleanup(format!("plugin '{name}': git pull failed: {e}")))?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(cleanup(format!(
-            "plugin '{name}': git pull failed: {stderr}"
-        )));
-    }
+        .map_err(|e| match e {
+            husako_git::GitError::PathNotFound(_) => cleanup(format!(
+                "plugin '{name}': subdirectory '{subdir}' not found in repository"
+            )),
+            e => cleanup(format!("plugin '{name}': git fetch failed: {e}")),
+        })?;
 
     // Copy the subdirectory contents into target_dir
     let subdir_path = tmp_dir.join(subdir);
     if !subdir_path.is_dir() {
-        let _ = std::fs::remove_dir_all(&tmp_dir);
-        return Err(HusakoError::GenerateIo(format!(
+        return Err(cleanup(format!(
             "plugin '{name}': subdirectory '{subdir}' not found in repository"
         )));
     }
 
-    std::fs::create_dir_all(target_dir).map_err(|e| {
-        let _ = std::fs::remove_dir_all(&tmp_dir);
-        HusakoError::GenerateIo(format!("create dir {}: {e}", target_dir.display()))
-    })?;
+    std::fs::create_dir_all(target_dir)
+        .map_err(|e| cleanup(format!("create dir {}: {e}", target_dir.display())))?;
 
-    copy_dir_recursive(&subdir_path, target_dir).map_err(|e| {
-        let _ = std::fs::remove_dir_all(&tmp_dir);
-        HusakoError::GenerateIo(format!("plugin '{name}': copy from subdir failed: {e}"))
-    })?;
+    copy_dir_recursive(&subdir_path, target_dir)
+        .map_err(|e| cleanup(format!("plugin '{name}': copy from subdir failed: {e}")))?;
 
     let _ = std::fs::remove_dir_all(&tmp_dir);
-    Ok(())
+    Ok(Some(commit))
 }
 
 fn install_path(name: &str, source_dir: &Path, target_dir: &Path) -> Result<(), HusakoError> {
@@ -398,7 +403,12 @@ version = "0.1.0"
         let progress = crate::progress::SilentProgress;
         let rt = tokio::runtime::Runtime::new().unwrap();
         let installed = rt
-            .block_on(install_plugins(&config, project_root, &progress))
+            .block_on(install_plugins(
+                &config,
+                project_root,
+                &progress,
+                &FetchContext::default(),
+            ))
             .unwrap();
 
         assert_eq!(installed.len(), 1);
@@ -429,7 +439,12 @@ version = "0.1.0"
         let progress = crate::progress::SilentProgress;
         let rt = tokio::runtime::Runtime::new().unwrap();
         let err = rt
-            .block_on(install_plugins(&config, project_root, &progress))
+            .block_on(install_plugins(
+                &config,
+                project_root,
+                &progress,
+                &FetchContext::default(),
+            ))
             .unwrap_err();
         assert!(err.to_string().contains("not found"));
     }
@@ -470,7 +485,12 @@ version = "0.1.0"
         let progress = crate::progress::SilentProgress;
         let rt = tokio::runtime::Runtime::new().unwrap();
         let installed = rt
-            .block_on(install_plugins(&config, project_root, &progress))
+            .block_on(install_plugins(
+                &config,
+                project_root,
+                &progress,
+                &FetchContext::default(),
+            ))
             .unwrap();
 
         assert_eq!(installed[0].manifest.plugin.version, "0.2.0");
@@ -501,6 +521,8 @@ version = "0.1.0"
                     SchemaSource::Git {
                         repo: "https://github.com/fluxcd/source-controller".to_string(),
                         tag: "v1.5.0".to_string(),
+                        branch: None,
+                        rev: None,
                         path: "config/crd/bases".to_string(),
                     },
                 )]),
@@ -633,7 +655,12 @@ version = "0.1.0"
         let progress = crate::progress::SilentProgress;
         let rt = tokio::runtime::Runtime::new().unwrap();
         let installed = rt
-            .block_on(install_plugins(&config, tmp.path(), &progress))
+            .block_on(install_plugins(
+                &config,
+                tmp.path(),
+                &progress,
+                &FetchContext::default(),
+            ))
             .unwrap();
         assert!(installed.is_empty());
     }
@@ -0,0 +1,488 @@
+//! Resolve semver requirements in `husako.toml` to concrete versions.
+//!
+//! A requirement keeps the version recorded in `husako.lock` for as long as
+//! that version satisfies it, so `husako gen` never moves a dependency on its
+//! own. Without a usable locked version the newest matching upstream version
+//! is chosen. `husako update` moves locked versions forward within their
+//! requirements.
+
+use std::collections::BTreeMap;
+
+use husako_config::version_req;
+use husako_config::{
+    ChartLockEntry, ChartSource, ConfigError, FetchContext, HusakoConfig, HusakoLock,
+    PluginLockEntry, PluginSource, ResourceLockEntry, SchemaSource,
+};
+
+use crate::HusakoError;
+use crate::progress::ProgressReporter;
+use crate::version_check;
+
+/// Requirements written in `husako.toml`, by entry name. Entries pinned to an
+/// exact version are absent.
+#[derive(Debug, Default)]
+pub struct Requirements {
+    pub resources: BTreeMap<String, String>,
+    pub charts: BTreeMap<String, String>,
+    pub plugins: BTreeMap<String, String>,
+}
+
+impl Requirements {
+    pub fn extend(&mut self, other: Requirements) {
+        self.resources.extend(other.resources);
+        self.charts.extend(other.charts);
+        self.plugins.extend(other.plugins);
+    }
+
+    /// Record in `lock` the requirement each versioned entry was resolved
+    /// from, clearing it on entries that are now pinned exactly.
+    pub fn apply(&self, lock: &mut HusakoLock) {
+        for (name, entry) in &mut lock.resources {
+            if let ResourceLockEntry::Release { requirement, .. } = entry {
+                *requirement = self.resources.get(name).cloned();
+            }
+        }
+        for (name, entry) in &mut lock.charts {
+            if let ChartLockEntry::Registry { requirement, .. }
+            | ChartLockEntry::ArtifactHub { requirement, .. }
+            | ChartLockEntry::Oci { requirement, .. } = entry
+            {
+                *requirement = self.charts.get(name).cloned();
+            }
+        }
+        for (name, entry) in &mut lock.plugins {
+            if let PluginLockEntry::Git { requirement, .. } = entry {
+                *requirement = self.plugins.get(name).cloned();
+            }
+        }
+    }
+}
+
+/// Where the available versions of a dependency are listed.
+pub(crate) enum Upstream<'a> {
+    Release,
+    Registry {
+        repo: &'a str,
+        chart: &'a str,
+    },
+    ArtifactHub {
+        package: &'a str,
+    },
+    Oci {
+        reference: &'a str,
+    },
+    /// Git plugin tags, listed without their `v` prefix like plugin `version`s.
+    PluginTags {
+        url: &'a str,
+    },
+}
+
+impl Upstream<'_> {
+    /// Every stable version upstream, newest first.
+    pub(crate) async fn versions(&self, ctx: &FetchContext) -> Result<Vec<String>, HusakoError> {
+        match self {
+            Self::Release => version_check::discover_recent_releases(usize::MAX, 0, ctx).await,
+            Self::Registry { repo, chart } => {
+                version_check::discover_registry_versions(repo, chart, usize::MAX, 0, ctx).await
+            }
+            Self::ArtifactHub { package } => {
+                version_check::discover_artifacthub_versions(package, usize::MAX, 0, ctx).await
+            }
+            Self::Oci { reference } => {
+                version_check::discover_oci_tags(reference, usize::MAX, 0, ctx).await
+            }
+            Self::PluginTags { url } => {
+                Ok(version_check::discover_git_tags(url, usize::MAX, 0, ctx)?
+                    .into_iter()
+                    .map(|t| t.strip_prefix('v').map(str::to_string).unwrap_or(t))
+                    .collect())
+            }
+        }
+    }
+}
+
+/// The version and requirement `lock` records for a dependency.
+type Locked<'a> = Option<(&'a str, Option<&'a str>)>;
+
+/// The locked version of resource `name`.
+pub(crate) fn locked_resource<'a>(lock: Option<&'a HusakoLock>, name: &str) -> Locked<'a> {
+    match lock?.resources.get(name)? {
+        ResourceLockEntry::Release {
+            version,
+            requirement,
+            ..
+        } => Some((version, requirement.as_deref())),
+        _ => None,
+    }
+}
+
+/// The locked version of chart `name`, if it still names the same chart.
+pub(crate) fn locked_chart<'a>(
+    lock: Option<&'a HusakoLock>,
+    name: &str,
+    source: &ChartSource,
+) -> Locked<'a> {
+    match (source, lock?.charts.get(name)?) {
+        (
+            ChartSource::Registry { repo, chart, .. },
+            ChartLockEntry::Registry {
+                repo: lr,
+                chart: lc,
+                version,
+                requirement,
+                ..
+            },
+        ) if lr == repo && lc == chart => Some((version, requirement.as_deref())),
+        (
+            ChartSource::ArtifactHub { package, .. },
+            ChartLockEntry::ArtifactHub {
+                package: lp,
+                version,
+                requirement,
+                ..
+            },
+        ) if lp == package => Some((version, requirement.as_deref())),
+        (
+            ChartSource::Oci { reference, .. },
+            ChartLockEntry::Oci {
+                reference: lr,
+                version,
+                requirement,
+                ..
+            },
+        ) if lr == reference => Some((version, requirement.as_deref())),
+        _ => None,
+    }
+}
+
+/// The locked version of plugin `name`, if it still names the same plugin.
+pub(crate) fn locked_plugin<'a>(
+    lock: Option<&'a HusakoLock>,
+    name: &str,
+    source: &PluginSource,
+) -> Locked<'a> {
+    match (source, lock?.plugins.get(name)?) {
+        (
+            PluginSource::Git { url, path, .. },
+            PluginLockEntry::Git {
+                url: lu,
+                path: lp,
+                version: Some(version),
+                requirement,
+                ..
+            },
+        ) if lu == url && lp == path => Some((version, requirement.as_deref())),
+        _ => None,
+    }
+}
+
+/// Return a copy of `config` with every version requirement replaced by the
+/// concrete version it resolves to, and the requirements that were replaced.
+///
+/// Under `locked_mode` the locked versions must satisfy the requirements and
+/// nothing is looked up upstream.
+pub async fn resolve(
+    config: &HusakoConfig,
+    lock: Option<&HusakoLock>,
+    locked_mode: bool,
+    progress: &dyn ProgressReporter,
+    ctx: &FetchContext,
+) -> Result<(HusakoConfig, Requirements), HusakoError> {
+    let mut resolved = config.clone();
+    let mut requirements = Requirements::default();
+    let mut resolver = Resolver {
+        locked_mode,
+        progress,
+        ctx,
+        stale: Vec::new(),
+    };
+
+    for (name, source) in &mut resolved.resources {
+        if let SchemaSource::Release { version } = source {
+            let locked = locked_resource(lock, name);
+            if let Some(v) = resolver
+                .resolve("resource", name, version, locked, Upstream::Release)
+                .await?
+            {
+                requirements
+                    .resources
+                    .insert(name.clone(), std::mem::replace(version, v));
+            }
+        }
+    }
+
+    for (name, source) in &mut resolved.charts {
+        let locked = locked_chart(lock, name, source);
+        let (version, upstream) = match source {
+            ChartSource::Registry {
+                repo,
+                chart,
+                version,
+            } => (version, Upstream::Registry { repo, chart }),
+            ChartSource::ArtifactHub { package, version } => {
+                (version, Upstream::ArtifactHub { package })
+            }
+            ChartSource::Oci { reference, version } => (version, Upstream::Oci { reference }),
+            _ => continue,
+        };
+        let spec = version.clone();
+        if let Some(v) = resolver
+            .resolve("chart", name, &spec, locked, upstream)
+            .await?
+        {
+            *version = v;
+            requirements.charts.insert(name.clone(), spec);
+        }
+    }
+
+    for (name, source) in &mut resolved.plugins {
+        let locked = locked_plugin(lock, name, source);
+        if let PluginSource::Git {
+            url,
+            version: Some(version),
+            ..
+        } = source
+        {
+            let spec = version.clone();
+            if let Some(v) = resolver
+                .resolve("plugin", name, &spec, locked, Upstream::PluginTags { url })
+                .await?
+            {
+                *version = v;
+                requirements.plugins.insert(name.clone(), spec);
+            }
+        }
+    }
+
+    if !resolver.stale.is_empty() {
+        resolver.stale.sort();
+        return Err(HusakoError::LockOutOfDate(resolver.stale));
+    }
+    Ok((resolved, requirements))
+}
+
+struct Resolver<'a> {
+    locked_mode: bool,
+    progress: &'a dyn ProgressReporter,
+    ctx: &'a FetchContext,
+    stale: Vec<String>,
+}
+
+impl Resolver<'_> {
+    /// The concrete version for `spec`, or `None` when `spec` is an exact pin.
+    async fn resolve(
+        &mut self,
+        kind: &str,
+        name: &str,
+        spec: &str,
+        locked: Locked<'_>,
+        upstream: Upstream<'_>,
+    ) -> Result<Option<String>, HusakoError> {
+        let locked_requirement = locked.and_then(|(_, requirement)| requirement);
+        if !version_req::is_requirement(spec) {
+            if self.locked_mode
+                && let Some(requirement) = locked_requirement
+            {
+                self.stale.push(format!(
+                    "{kind} '{name}': husako.lock records requirement '{requirement}', \
+                     husako.toml pins {spec}"
+                ));
+            }
+            return Ok(None);
+        }
+        let req = version_req::parse_requirement(spec).map_err(|e| {
+            HusakoError::Config(ConfigError::Validation(format!(
+                "{kind} '{name}' has invalid version requirement '{spec}': {e}"
+            )))
+        })?;
+
+        if let Some((version, _)) = locked
+            && version_req::satisfies(&req, version)
+        {
+            if self.locked_mode && locked_requirement != Some(spec) {
+                self.stale.push(format!(
+                    "{kind} '{name}': requirement '{spec}' is not recorded in husako.lock"
+                ));
+            }
+            return Ok(Some(version.to_string()));
+        }
+        if self.locked_mode {
+            self.stale.push(format!(
+                "{kind} '{name}': no locked version satisfies '{spec}'"
+            ));
+            return Ok(Some(spec.to_string()));
+        }
+
+        let task = self
+            .progress
+            .start_task(&format!("Resolving {name} {spec}..."));
+        let versions = upstream.versions(self.ctx).await.inspect_err(|e| {
+            task.finish_err(&format!("{name}: {e}"));
+        })?;
+        match version_req::newest_matching(&req, versions.iter().map(String::as_str)) {
+            Some(version) => {
+                task.finish_ok(&format!("{name}: {spec} → {version}"));
+                Ok(Some(version.to_string()))
+            }
+            None => {
+                task.finish_err(&format!("{name}: no version matches {spec}"));
+                Err(HusakoError::Config(ConfigError::Validation(format!(
+                    "no version of {kind} '{name}' matches '{spec}'"
+                ))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::SilentProgress;
+
+    fn lock_with_chart(version: &str, requirement: Option<&str>) -> HusakoLock {
+        HusakoLock {
+            format_version: husako_config::LOCK_FORMAT_VERSION,
+            husako_version: "0.1.0".to_string(),
+            resources: BTreeMap::new(),
+            charts: BTreeMap::from([(
+                "ingress".to_string(),
+                ChartLockEntry::Registry {
+                    repo: "https://charts.example.com".to_string(),
+                    chart: "ingress-nginx".to_string(),
+                    version: version.to_string(),
+                    requirement: requirement.map(str::to_string),
+                    digest: None,
+                    generated_at: String::new(),
+                },
+            )]),
+            plugins: BTreeMap::new(),
+        }
+    }
+
+    fn config_with_chart(version: &str) -> HusakoConfig {
+        HusakoConfig {
+            charts: std::collections::HashMap::from([(
+                "ingress".to_string(),
+                ChartSource::Registry {
+                    repo: "https://charts.example.com".to_string(),
+                    chart: "ingress-nginx".to_string(),
+                    version: version.to_string(),
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn chart_version(config: &HusakoConfig) -> &str {
+        match &config.charts["ingress"] {
+            ChartSource::Registry { version, .. } => version,
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn locked_version_satisfying_requirement_is_kept() {
+        let lock = lock_with_chart("4.12.1", Some("^4.12"));
+        let (resolved, requirements) = resolve(
+            &config_with_chart("^4.12"),
+            Some(&lock),
+            true,
+            &SilentProgress,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chart_version(&resolved), "4.12.1");
+        assert_eq!(requirements.charts["ingress"], "^4.12");
+
+        // A widened requirement still accepts the locked version
+        let (resolved, _) = resolve(
+            &config_with_chart("^4"),
+            Some(&lock),
+            false,
+            &SilentProgress,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chart_version(&resolved), "4.12.1");
+    }
+
+    #[tokio::test]
+    async fn exact_pins_are_unchanged() {
+        let (resolved, requirements) = resolve(
+            &config_with_chart("4.12.0"),
+            None,
+            false,
+            &SilentProgress,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chart_version(&resolved), "4.12.0");
+        assert!(requirements.charts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn locked_mode_reports_unsatisfied_requirements() {
+        let lock = lock_with_chart("4.12.1", Some("^4.12"));
+        let err = resolve(
+            &config_with_chart("^5"),
+            Some(&lock),
+            true,
+            &SilentProgress,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("no locked version satisfies '^5'"),
+            "{err}"
+        );
+
+        let err = resolve(
+            &config_with_chart("^4"),
+            Some(&lock),
+            true,
+            &SilentProgress,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("not recorded"), "{err}");
+
+        let err = resolve(
+            &config_with_chart("4.12.1"),
+            Some(&lock),
+            true,
+            &SilentProgress,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("records requirement"), "{err}");
+    }
+
+    #[test]
+    fn apply_records_and_clears_requirements() {
+        let mut lock = lock_with_chart("4.12.1", Some("^4.12"));
+        Requirements::default().apply(&mut lock);
+        assert!(matches!(
+            &lock.charts["ingress"],
+            ChartLockEntry::Registry {
+                requirement: None,
+                ..
+            }
+        ));
+
+        let requirements = Requirements {
+            charts: BTreeMap::from([("ingress".to_string(), "~4.12".to_string())]),
+            ..Default::default()
+        };
+        requirements.apply(&mut lock);
+        assert!(matches!(
+            &lock.charts["ingress"],
+            ChartLockEntry::Registry { requirement: Some(r), .. } if r == "~4.12"
+        ));
+    }
+}
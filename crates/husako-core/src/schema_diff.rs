@@ -0,0 +1,325 @@
+//! Breaking changes between two versions of a schema.
+//!
+//! Compares the OpenAPI definitions of a resource dependency, or the
+//! `values.schema.json` of a chart, before and after an update. Only changes
+//! that can break existing code are reported: removed properties, type
+//! changes, newly required fields and narrowed enums.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+/// A breaking change at `path`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaChange {
+    /// Dotted property path, starting with the definition name for resources.
+    /// `[]` stands for array items and `*` for map values.
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    /// The property or definition no longer exists.
+    Removed,
+    /// The type (or referenced definition) changed.
+    TypeChanged { from: String, to: String },
+    /// The property became required.
+    NewRequired,
+    /// Values accepted before are no longer allowed. `removed` is empty when
+    /// a previously unrestricted property gained an enum.
+    EnumNarrowed {
+        removed: Vec<String>,
+        allowed: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = &self.path;
+        match &self.kind {
+            ChangeKind::Removed => write!(f, "{path}: removed"),
+            ChangeKind::TypeChanged { from, to } => {
+                write!(f, "{path}: type changed from {from} to {to}")
+            }
+            ChangeKind::NewRequired => write!(f, "{path}: now required"),
+            ChangeKind::EnumNarrowed { removed, .. } if !removed.is_empty() => {
+                write!(f, "{path}: enum no longer allows {}", removed.join(", "))
+            }
+            ChangeKind::EnumNarrowed { allowed, .. } => {
+                write!(f, "{path}: now restricted to {}", allowed.join(", "))
+            }
+        }
+    }
+}
+
+/// Compare the OpenAPI specs of two versions of a resource dependency, as
+/// returned by `schema_source::resolve_all`.
+///
+/// Definitions shared between spec files (e.g. `ObjectMeta`) are compared
+/// once.
+pub fn diff_openapi(
+    old: &HashMap<String, Value>,
+    new: &HashMap<String, Value>,
+) -> Vec<SchemaChange> {
+    let old = definitions(old);
+    let new = definitions(new);
+    let mut out = Vec::new();
+    for (name, old_schema) in &old {
+        match new.get(name) {
+            Some(new_schema) => walk(name, old_schema, new_schema, &mut out),
+            None => out.push(SchemaChange {
+                path: name.to_string(),
+                kind: ChangeKind::Removed,
+            }),
+        }
+    }
+    out
+}
+
+/// Compare two versions of a chart's `values.schema.json`. Paths are relative
+/// to the values root.
+pub fn diff_schema(old: &Value, new: &Value) -> Vec<SchemaChange> {
+    let mut out = Vec::new();
+    walk("", old, new, &mut out);
+    out
+}
+
+fn definitions(specs: &HashMap<String, Value>) -> BTreeMap<&str, &Value> {
+    specs
+        .values()
+        .filter_map(|spec| spec.pointer("/components/schemas")?.as_object())
+        .flatten()
+        .map(|(name, schema)| (name.as_str(), schema))
+        .collect()
+}
+
+fn walk(path: &str, old: &Value, new: &Value, out: &mut Vec<SchemaChange>) {
+    if let (Some(from), Some(to)) = (type_of(old), type_of(new))
+        && from != to
+    {
+        out.push(SchemaChange {
+            path: display_path(path),
+            kind: ChangeKind::TypeChanged { from, to },
+        });
+        return;
+    }
+
+    if let Some(allowed) = new.get("enum").and_then(Value::as_array) {
+        let narrowed = match old.get("enum").and_then(Value::as_array) {
+            Some(before) => {
+                let removed: Vec<String> = before
+                    .iter()
+                    .filter(|v| !allowed.contains(v))
+                    .map(render)
+                    .collect();
+                (!removed.is_empty()).then_some(removed)
+            }
+            None => Some(Vec::new()),
+        };
+        if let Some(removed) = narrowed {
+            out.push(SchemaChange {
+                path: display_path(path),
+                kind: ChangeKind::EnumNarrowed {
+                    removed,
+                    allowed: allowed.iter().map(render).collect(),
+                },
+            });
+        }
+    }
+
+    let old_required = required(old);
+    for name in required(new) {
+        if !old_required.contains(&name) {
+            out.push(SchemaChange {
+                path: child(path, name),
+                kind: ChangeKind::NewRequired,
+            });
+        }
+    }
+
+    if let Some(old_props) = old.get("properties").and_then(Value::as_object) {
+        let new_props = new.get("properties").and_then(Value::as_object);
+        for (name, old_prop) in old_props {
+            match new_props.and_then(|props| props.get(name)) {
+                Some(new_prop) => walk(&child(path, name), old_prop, new_prop, out),
+                None => out.push(SchemaChange {
+                    path: child(path, name),
+                    kind: ChangeKind::Removed,
+                }),
+            }
+        }
+    }
+
+    for (key, segment) in [("items", "[]"), ("additionalProperties", "*")] {
+        if let (Some(old_sub), Some(new_sub)) = (old.get(key), new.get(key))
+            && old_sub.is_object()
+            && new_sub.is_object()
+        {
+            walk(&child(path, segment), old_sub, new_sub, out);
+        }
+    }
+}
+
+/// The referenced definition or JSON type of a schema.
+fn type_of(schema: &Value) -> Option<String> {
+    let reference =
+        schema
+            .get("$ref")
+            .or_else(|| match schema.get("allOf")?.as_array()?.as_slice() {
+                [only] => only.get("$ref"),
+                _ => None,
+            });
+    if let Some(reference) = reference.and_then(Value::as_str) {
+        return Some(
+            reference
+                .rsplit('/')
+                .next()
+                .unwrap_or(reference)
+                .to_string(),
+        );
+    }
+    match schema.get("type")? {
+        Value::String(ty) => Some(ty.clone()),
+        Value::Array(types) => Some(
+            types
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("|"),
+        ),
+        _ => None,
+    }
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn child(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else if name == "[]" {
+        format!("{path}[]")
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        "(root)".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+fn render(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(changes: &[SchemaChange]) -> Vec<String> {
+        changes.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn chart_values_changes() {
+        let old = json!({
+            "type": "object",
+            "properties": {
+                "replicas": { "type": "integer" },
+                "image": {
+                    "type": "object",
+                    "properties": {
+                        "tag": { "type": "string" },
+                        "pullPolicy": { "type": "string", "enum": ["Always", "IfNotPresent", "Never"] }
+                    }
+                },
+                "legacy": { "type": "boolean" },
+                "mode": { "type": "string" },
+                "hosts": { "type": "array", "items": { "type": "object", "properties": { "name": { "type": "string" } } } }
+            }
+        });
+        let new = json!({
+            "type": "object",
+            "required": ["image"],
+            "properties": {
+                "replicas": { "type": "string" },
+                "image": {
+                    "type": "object",
+                    "properties": {
+                        "tag": { "type": "string" },
+                        "pullPolicy": { "type": "string", "enum": ["Always", "IfNotPresent"] }
+                    }
+                },
+                "mode": { "type": "string", "enum": ["a", "b"] },
+                "hosts": { "type": "array", "items": { "type": "object", "properties": {} } },
+                "added": { "type": "string" }
+            }
+        });
+        assert_eq!(
+            paths(&diff_schema(&old, &new)),
+            [
+                "image: now required",
+                "hosts[].name: removed",
+                "image.pullPolicy: enum no longer allows Never",
+                "legacy: removed",
+                "mode: now restricted to a, b",
+                "replicas: type changed from integer to string",
+            ]
+        );
+        assert!(diff_schema(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn openapi_definitions() {
+        let spec = |schemas: Value| {
+            HashMap::from([(
+                "apis/example.com/v1".to_string(),
+                json!({ "components": { "schemas": schemas } }),
+            )])
+        };
+        let old = spec(json!({
+            "com.example.v1.Widget": {
+                "properties": {
+                    "spec": { "allOf": [{ "$ref": "#/components/schemas/com.example.v1.WidgetSpec" }] }
+                }
+            },
+            "com.example.v1.WidgetSpec": {
+                "properties": { "size": { "type": "integer" } }
+            },
+            "com.example.v1.Gadget": { "type": "object" }
+        }));
+        let new = spec(json!({
+            "com.example.v1.Widget": {
+                "properties": {
+                    "spec": { "allOf": [{ "$ref": "#/components/schemas/com.example.v2.WidgetSpec" }] }
+                }
+            },
+            "com.example.v1.WidgetSpec": {
+                "required": ["size"],
+                "properties": { "size": { "type": "integer" } }
+            }
+        }));
+        assert_eq!(
+            paths(&diff_openapi(&old, &new)),
+            [
+                "com.example.v1.Gadget: removed",
+                "com.example.v1.Widget.spec: type changed from com.example.v1.WidgetSpec to com.example.v2.WidgetSpec",
+                "com.example.v1.WidgetSpec.size: now required",
+            ]
+        );
+    }
+}
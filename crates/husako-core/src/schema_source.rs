@@ -1,7 +1,8 @@
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 
-use husako_config::{HusakoConfig, SchemaSource};
+use husako_config::{ChartRef, FetchContext, GitRef, HusakoConfig, SchemaSource};
+use husako_digest::sha256_hex;
 use serde_json::Value;
 
 use crate::HusakoError;
@@ -13,18 +14,43 @@ type ProgressCb = dyn Fn(u64, Option<u64>, Option<u8>) + Sync;
 ///
 /// Returns a merged `HashMap<String, Value>` where keys are discovery paths
 /// (e.g., `"apis/cert-manager.io/v1"`) and values are OpenAPI spec JSON.
-/// Later sources override for the same key.
+/// Sources are merged in name order; later ones override for the same key.
 pub async fn resolve_all(
     config: &HusakoConfig,
     project_root: &Path,
     cache_dir: &Path,
     progress: &dyn ProgressReporter,
+    ctx: &FetchContext,
 ) -> Result<HashMap<String, Value>, HusakoError> {
+    let names = config.resources.keys().cloned().collect();
     let mut merged = HashMap::new();
+    for specs in resolve_each(config, &names, project_root, cache_dir, progress, ctx)
+        .await?
+        .into_values()
+    {
+        merged.extend(specs);
+    }
+    Ok(merged)
+}
 
-    progress.set_total(config.resources.len());
+/// Resolve the schema sources named in `names` separately, keeping the specs
+/// each one produced apart.
+pub async fn resolve_each(
+    config: &HusakoConfig,
+    names: &BTreeSet<String>,
+    project_root: &Path,
+    cache_dir: &Path,
+    progress: &dyn ProgressReporter,
+    ctx: &FetchContext,
+) -> Result<BTreeMap<String, HashMap<String, Value>>, HusakoError> {
+    let mut resolved = BTreeMap::new();
+
+    progress.set_total(names.len());
 
-    for (name, source) in &config.resources {
+    for name in names {
+        let Some(source) = config.resources.get(name) else {
+            continue;
+        };
         let task = std::sync::Arc::new(progress.start_task(&format!("Resolving {name}...")));
         let specs = match source {
             SchemaSource::File { path } => resolve_file(path, project_root)?,
@@ -33,10 +59,41 @@ pub async fn resolve_all(
                 let on_progress_cb = move |bytes: u64, total: Option<u64>, pct: Option<u8>| {
                     task_cb.set_progress(bytes, total, pct);
                 };
-                resolve_release(version, cache_dir, Some(&on_progress_cb)).await?
+                resolve_release(version, cache_dir, Some(&on_progress_cb), ctx).await?
+            }
+            SchemaSource::Git { repo, path, .. } => {
+                let git_ref = source.git_ref().expect("git source has a git ref");
+                resolve_git(repo, git_ref, path, cache_dir, ctx).await?
+            }
+            SchemaSource::Url { url, sha256 } => {
+                resolve_url(url, sha256.as_deref(), cache_dir, ctx).await?
+            }
+            SchemaSource::Chart { chart, values } => {
+                resolve_chart(
+                    name,
+                    chart,
+                    values.as_ref(),
+                    config,
+                    project_root,
+                    cache_dir,
+                    ctx,
+                )
+                .await?
             }
-            SchemaSource::Git { repo, tag, path } => {
-                resolve_git(repo, tag, path, cache_dir).await?
+            SchemaSource::Cluster {
+                context,
+                kubeconfig,
+                groups,
+            } => {
+                resolve_cluster(
+                    context.as_deref(),
+                    kubeconfig.as_deref(),
+                    groups,
+                    project_root,
+                    cache_dir,
+                    ctx,
+                )
+                .await?
             }
         };
 
@@ -45,10 +102,45 @@ pub async fn resolve_all(
         }
 
         task.finish_ok(&format!("{name}: {} group-versions", specs.len()));
-        merged.extend(specs);
+        resolved.insert(name.clone(), specs);
     }
 
-    Ok(merged)
+    Ok(resolved)
+}
+
+/// Whether `source` can be resolved from `cache_dir` alone, without network
+/// access, either from the project cache or the shared cache. `cluster`
+/// sources and `git` sources that follow a branch never can.
+pub fn is_cached(
+    source: &SchemaSource,
+    config: &HusakoConfig,
+    cache_dir: &Path,
+    ctx: &FetchContext,
+) -> bool {
+    match source {
+        SchemaSource::File { .. } => true,
+        SchemaSource::Release { version } => {
+            let key = format!(
+                "release/{}",
+                husako_openapi::release::version_to_tag(version)
+            );
+            cache_dir.join(&key).is_dir() || ctx.shared_contains(&key)
+        }
+        SchemaSource::Git { repo, .. } => source.git_ref().is_some_and(|git_ref| {
+            let key = git_cache_key(repo, git_ref);
+            !git_ref.is_mutable() && (cache_dir.join(&key).is_dir() || ctx.shared_contains(&key))
+        }),
+        SchemaSource::Url { url, sha256 } => std::fs::read(url_cache_path(url, cache_dir))
+            .is_ok_and(|cached| {
+                sha256
+                    .as_ref()
+                    .is_none_or(|pin| pin.eq_ignore_ascii_case(&sha256_hex(&cached)))
+            }),
+        SchemaSource::Chart { chart, .. } => chart
+            .source(config)
+            .is_some_and(|source| husako_helm::is_archive_cached(source, cache_dir, ctx)),
+        SchemaSource::Cluster { .. } => false,
+    }
 }
 
 /// Resolve a file-based schema source.
@@ -197,54 +289,274 @@ async fn resolve_release(
     version: &str,
     cache_dir: &Path,
     on_progress: Option<&ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<HashMap<String, Value>, HusakoError> {
+    let key = format!(
+        "release/{}",
+        husako_openapi::release::version_to_tag(version)
+    );
+    let tag_cache = cache_dir.join(&key);
+    let cached = tag_cache.is_dir() || ctx.restore_shared(cache_dir, &key);
+    if !cached && ctx.offline {
+        return Err(HusakoError::Offline(format!(
+            "Kubernetes release {version} is not cached at {}",
+            tag_cache.display()
+        )));
+    }
     let specs =
-        husako_openapi::release::fetch_release_specs(version, cache_dir, on_progress).await?;
+        husako_openapi::release::fetch_release_specs(version, cache_dir, on_progress, ctx).await?;
+    if !ctx.shared_contains(&key) {
+        ctx.publish_shared(cache_dir, &key, &[&key]);
+    }
+    Ok(specs)
+}
+
+/// Resolve a URL schema source: download the CRD manifest and convert it.
+async fn resolve_url(
+    url: &str,
+    sha256: Option<&str>,
+    cache_dir: &Path,
+    ctx: &FetchContext,
+) -> Result<HashMap<String, Value>, HusakoError> {
+    let (yaml, _) = fetch_manifest(url, sha256, cache_dir, ctx).await?;
+    let openapi = husako_openapi::crd::crd_yaml_to_openapi(&yaml)?;
+    crd_openapi_to_specs(&openapi)
+}
+
+/// Cache location of the manifest downloaded for a URL schema source.
+pub fn url_cache_path(url: &str, cache_dir: &Path) -> std::path::PathBuf {
+    cache_dir.join(format!("url/{}/manifest.yaml", simple_hash(url)))
+}
+
+/// Download a CRD manifest, returning its content and SHA-256 digest.
+///
+/// A cached copy is reused when it matches the pinned `sha256` (or when no
+/// digest is pinned). A download that does not match the pin is rejected.
+pub async fn fetch_manifest(
+    url: &str,
+    sha256: Option<&str>,
+    cache_dir: &Path,
+    ctx: &FetchContext,
+) -> Result<(String, String), HusakoError> {
+    let cache_path = url_cache_path(url, cache_dir);
+    if let Ok(cached) = std::fs::read(&cache_path) {
+        let digest = sha256_hex(&cached);
+        if sha256.is_none_or(|pin| pin.eq_ignore_ascii_case(&digest)) {
+            let yaml = String::from_utf8(cached).map_err(|e| {
+                HusakoError::GenerateIo(format!("read {}: {e}", cache_path.display()))
+            })?;
+            return Ok((yaml, digest));
+        }
+    }
+    if ctx.offline {
+        return Err(HusakoError::Offline(format!(
+            "{url} is not cached at {}",
+            cache_path.display()
+        )));
+    }
+
+    let client = ctx
+        .client_builder()
+        .timeout(std::time::Duration::from_secs(60))
+        .build()
+        .map_err(|e| HusakoError::GenerateIo(format!("build HTTP client: {e}")))?;
+    let resp = client
+        .get(ctx.rewrite_url(url))
+        .send()
+        .await
+        .map_err(|e| HusakoError::GenerateIo(format!("GET {url}: {e}")))?;
+    if !resp.status().is_success() {
+        return Err(HusakoError::GenerateIo(format!(
+            "GET {url}: status {}",
+            resp.status()
+        )));
+    }
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| HusakoError::GenerateIo(format!("GET {url}: {e}")))?;
+
+    let digest = sha256_hex(&bytes);
+    if let Some(pin) = sha256
+        && !pin.eq_ignore_ascii_case(&digest)
+    {
+        return Err(HusakoError::GenerateIo(format!(
+            "sha256 mismatch for {url}: expected {pin}, got {digest}"
+        )));
+    }
+    let yaml = String::from_utf8(bytes.to_vec())
+        .map_err(|e| HusakoError::GenerateIo(format!("{url} is not UTF-8 text: {e}")))?;
+
+    if let Some(parent) = cache_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(&cache_path, &yaml);
+
+    Ok((yaml, digest))
+}
+
+/// Resolve a chart-based schema source.
+///
+/// The chart — a `[charts]` entry or an inline coordinate — is fetched
+/// through the same resolvers as values schemas, without requiring one, then
+/// rendered with CRDs included. Only the rendered `CustomResourceDefinition`
+/// objects — from `crds/` or from templates — are converted.
+async fn resolve_chart(
+    name: &str,
+    chart: &ChartRef,
+    values: Option<&toml::Table>,
+    config: &HusakoConfig,
+    project_root: &Path,
+    cache_dir: &Path,
+    ctx: &FetchContext,
+) -> Result<HashMap<String, Value>, HusakoError> {
+    let source = chart.source(config).ok_or_else(|| {
+        HusakoError::GenerateIo(format!(
+            "schema source '{name}' references chart '{chart}', which is not declared in [charts]"
+        ))
+    })?;
+    // Inline charts go by the name of the resource
+    let chart = match chart {
+        ChartRef::Name(chart) => chart.as_str(),
+        ChartRef::Inline(_) => name,
+    };
+
+    husako_helm::fetch_archive(chart, source, project_root, cache_dir, ctx).await?;
+
+    let values = match values {
+        Some(table) => serde_json::to_value(table)
+            .map_err(|e| HusakoError::GenerateIo(format!("schema source '{name}': values: {e}")))?,
+        None => Value::Object(Default::default()),
+    };
+    let options = husako_helm::RenderOptions {
+        include_crds: true,
+        ..Default::default()
+    };
+    let objects = husako_helm::render(chart, source, project_root, cache_dir, &values, &options)?;
+
+    let crds: Vec<String> = objects
+        .iter()
+        .filter(|o| o["kind"] == "CustomResourceDefinition")
+        .filter_map(|o| serde_yaml_ng::to_string(o).ok())
+        .collect();
+    if crds.is_empty() {
+        return Err(HusakoError::GenerateIo(format!(
+            "schema source '{name}': chart '{chart}' contains no CustomResourceDefinitions"
+        )));
+    }
+
+    let openapi = husako_openapi::crd::crd_yaml_to_openapi(&crds.join("\n---\n"))?;
+    crd_openapi_to_specs(&openapi)
+}
+
+/// Resolve a cluster schema source.
+///
+/// Fetches the cluster's OpenAPI v3 specs through a kubeconfig context and
+/// keeps the group-versions of the listed API groups (all when empty). A
+/// relative `kubeconfig` resolves against the project root, and `~/` against
+/// the home directory.
+async fn resolve_cluster(
+    context: Option<&str>,
+    kubeconfig: Option<&str>,
+    groups: &[String],
+    project_root: &Path,
+    cache_dir: &Path,
+    ctx: &FetchContext,
+) -> Result<HashMap<String, Value>, HusakoError> {
+    let path = kubeconfig.map(|p| match p.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_default()
+            .join(rest),
+        None => project_root.join(p),
+    });
+    let client = husako_openapi::OpenApiClient::new(husako_openapi::FetchOptions {
+        source: husako_openapi::OpenApiSource::Kubeconfig {
+            path,
+            context: context.map(String::from),
+        },
+        cache_dir: cache_dir.to_path_buf(),
+        context: ctx.clone(),
+    })?;
+    let mut specs = client.fetch_all_specs().await?;
+    if !groups.is_empty() {
+        specs.retain(|key, _| {
+            key.strip_prefix("apis/")
+                .and_then(|rest| rest.split('/').next())
+                .is_some_and(|group| groups.iter().any(|g| g == group))
+        });
+    }
     Ok(specs)
 }
 
 /// Resolve a git-based schema source.
 async fn resolve_git(
     repo: &str,
-    tag: &str,
+    git_ref: GitRef<'_>,
     path: &str,
     cache_dir: &Path,
+    ctx: &FetchContext,
 ) -> Result<HashMap<String, Value>, HusakoError> {
-    let repo_hash = simple_hash(repo);
-    let git_cache = cache_dir.join(format!("git/{repo_hash}/{tag}"));
+    let git_cache = git_cache_dir(repo, git_ref, cache_dir);
+    let shared_key = (!git_ref.is_mutable()).then(|| git_cache_key(repo, git_ref));
+    if let Some(key) = &shared_key
+        && !git_cache.exists()
+    {
+        ctx.restore_shared(cache_dir, key);
+    }
 
-    // Check cache
+    // Check cache (branches move, so they are re-fetched unless offline; so
+    // is a tag the remote now has at another commit than the cached one)
     if git_cache.exists() {
-        return load_git_cache(&git_cache);
+        let moved = match (git_ref, git_commit(repo, git_ref, cache_dir)) {
+            (GitRef::Tag(tag), Some(cached)) if !ctx.offline => {
+                husako_git::tag_commit(repo, tag, ctx)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|c| c != cached)
+            }
+            _ => false,
+        };
+        if !git_ref.is_mutable() && !moved || ctx.offline {
+            if let Some(key) = &shared_key
+                && !ctx.shared_contains(key)
+            {
+                ctx.publish_shared(cache_dir, key, &[key]);
+            }
+            return load_git_cache(&git_cache);
+        }
+        let _ = std::fs::remove_dir_all(&git_cache);
+    }
+    if ctx.offline {
+        return Err(HusakoError::Offline(format!(
+            "git {repo} at {git_ref} is not cached at {}",
+            git_cache.display()
+        )));
     }
 
-    // Clone repo at specific tag
+    // Fetch repo at the requested ref
     let temp_dir = tempfile::tempdir()
         .map_err(|e| HusakoError::GenerateIo(format!("create temp dir: {e}")))?;
 
-    let output = tokio::process::Command::new("git")
-        .args(["clone", "--depth", "1", "--branch", tag, repo])
-        .arg(temp_dir.path())
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::piped())
-        .output()
-        .await
-        .map_err(|e| HusakoError::GenerateIo(format!("git clone failed: {e}")))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(HusakoError::GenerateIo(format!(
-            "git clone {repo} at tag {tag} failed: {stderr}"
-        )));
-    }
+    let commit = husako_git::fetch(
+        repo,
+        Some(git_ref),
+        Some(path),
+        cache_dir,
+        temp_dir.path(),
+        None,
+        ctx,
+    )
+    .await
+    .map_err(|e| match e {
+        husako_git::GitError::PathNotFound(_) => {
+            HusakoError::GenerateIo(format!("path '{path}' not found in repository"))
+        }
+        e => HusakoError::GenerateIo(format!("git clone {repo} at {git_ref} failed: {e}")),
+    })?;
 
     // Read CRD YAML files
     let crd_dir = temp_dir.path().join(path);
-    if !crd_dir.exists() {
-        return Err(HusakoError::GenerateIo(format!(
-            "path '{path}' not found in repository"
-        )));
-    }
 
     let yaml = read_crd_directory(&crd_dir)?;
     let openapi = husako_openapi::crd::crd_yaml_to_openapi(&yaml)?;
@@ -260,10 +572,35 @@ async fn resolve_git(
             serde_json::to_string(spec).unwrap_or_default(),
         );
     }
+    let _ = std::fs::write(git_cache.join(GIT_COMMIT_FILE), commit);
+    if let Some(key) = &shared_key {
+        ctx.publish_shared(cache_dir, key, &[key]);
+    }
 
     Ok(specs)
 }
 
+/// File recording the commit a cached git checkout resolved to.
+const GIT_COMMIT_FILE: &str = "commit";
+
+fn git_cache_dir(repo: &str, git_ref: GitRef<'_>, cache_dir: &Path) -> PathBuf {
+    cache_dir.join(git_cache_key(repo, git_ref))
+}
+
+/// Path of a git checkout's specs relative to the cache dir, which doubles
+/// as its shared cache key.
+fn git_cache_key(repo: &str, git_ref: GitRef<'_>) -> String {
+    format!("git/{}/{}", simple_hash(repo), git_ref.cache_key())
+}
+
+/// Commit SHA a `git` resource source was last fetched at, as recorded in
+/// its cache. `None` before the first fetch.
+pub fn git_commit(repo: &str, git_ref: GitRef<'_>, cache_dir: &Path) -> Option<String> {
+    let path = git_cache_dir(repo, git_ref, cache_dir).join(GIT_COMMIT_FILE);
+    let commit = std::fs::read_to_string(path).ok()?;
+    Some(commit.trim().to_string()).filter(|c| !c.is_empty())
+}
+
 fn load_git_cache(cache_dir: &Path) -> Result<HashMap<String, Value>, HusakoError> {
     let mut specs = HashMap::new();
     let entries = std::fs::read_dir(cache_dir)
@@ -296,9 +633,44 @@ fn simple_hash(s: &str) -> String {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
+    /// Create a local repository with one commit per `(tag, files)` step.
+    /// Returns the commit SHA of each step.
+    pub(crate) fn make_repo(dir: &Path, steps: &[(&str, &[(&str, &str)])]) -> Vec<String> {
+        let git = |args: &[&str]| {
+            let out = std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(
+                out.status.success(),
+                "{}",
+                String::from_utf8_lossy(&out.stderr)
+            );
+            String::from_utf8_lossy(&out.stdout).trim().to_string()
+        };
+        std::fs::create_dir_all(dir).unwrap();
+        git(&["init", "--quiet", "--initial-branch", "main"]);
+        let mut commits = Vec::new();
+        for (tag, files) in steps {
+            for (path, content) in *files {
+                let file = dir.join(path);
+                std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+                std::fs::write(file, content).unwrap();
+            }
+            git(&["add", "-A"]);
+            git(&["commit", "--quiet", "-m", tag]);
+            git(&["tag", "-f", tag]);
+            commits.push(git(&["rev-parse", "HEAD"]));
+        }
+        commits
+    }
+
     #[test]
     fn resolve_file_single() {
         let tmp = tempfile::tempdir().unwrap();
@@ -413,6 +785,185 @@ spec:
         assert!(schemas.contains_key("com.example.v1.Gadget"));
     }
 
+    #[tokio::test]
+    async fn resolve_chart_extracts_crds() {
+        let tmp = tempfile::tempdir().unwrap();
+        let chart_dir = tmp.path().join("charts/operator");
+        std::fs::create_dir_all(chart_dir.join("crds")).unwrap();
+        std::fs::create_dir_all(chart_dir.join("templates")).unwrap();
+        std::fs::write(
+            chart_dir.join("Chart.yaml"),
+            "apiVersion: v2\nname: operator\nversion: 1.0.0\n",
+        )
+        .unwrap();
+        std::fs::write(chart_dir.join("values.yaml"), "crds:\n  enabled: false\n").unwrap();
+        let crd = |kind: &str, plural: &str| {
+            format!(
+                "apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: {plural}.example.com
+spec:
+  group: example.com
+  names:
+    kind: {kind}
+    plural: {plural}
+  scope: Namespaced
+  versions:
+    - name: v1
+      served: true
+      storage: true
+      schema:
+        openAPIV3Schema:
+          type: object
+"
+            )
+        };
+        std::fs::write(chart_dir.join("crds/widget.yaml"), crd("Widget", "widgets")).unwrap();
+        std::fs::write(
+            chart_dir.join("templates/gadget.yaml"),
+            format!(
+                "{{{{- if .Values.crds.enabled }}}}\n{}{{{{- end }}}}\n",
+                crd("Gadget", "gadgets")
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            chart_dir.join("templates/deployment.yaml"),
+            "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: operator\n",
+        )
+        .unwrap();
+
+        let mut config = HusakoConfig::default();
+        config.charts.insert(
+            "operator".to_string(),
+            husako_config::ChartSource::Chart {
+                path: "charts/operator".to_string(),
+            },
+        );
+        let cache = tmp.path().join("cache");
+
+        let operator = ChartRef::Name("operator".to_string());
+        let specs = resolve_chart(
+            "crds",
+            &operator,
+            None,
+            &config,
+            tmp.path(),
+            &cache,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        let schemas = specs["apis/example.com/v1"]["components"]["schemas"]
+            .as_object()
+            .unwrap();
+        assert!(schemas.contains_key("com.example.v1.Widget"));
+        assert!(!schemas.contains_key("com.example.v1.Gadget"));
+
+        let values: toml::Table = toml::from_str("crds = { enabled = true }").unwrap();
+        let specs = resolve_chart(
+            "crds",
+            &operator,
+            Some(&values),
+            &config,
+            tmp.path(),
+            &cache,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        let schemas = specs["apis/example.com/v1"]["components"]["schemas"]
+            .as_object()
+            .unwrap();
+        assert!(schemas.contains_key("com.example.v1.Gadget"));
+
+        let inline = ChartRef::Inline(husako_config::ChartSource::Chart {
+            path: "charts/operator".to_string(),
+        });
+        let specs = resolve_chart(
+            "crds",
+            &inline,
+            None,
+            &HusakoConfig::default(),
+            tmp.path(),
+            &cache,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert!(
+            specs["apis/example.com/v1"]["components"]["schemas"]
+                .as_object()
+                .unwrap()
+                .contains_key("com.example.v1.Widget")
+        );
+
+        let missing = ChartRef::Name("missing".to_string());
+        let err = resolve_chart(
+            "crds",
+            &missing,
+            None,
+            &config,
+            tmp.path(),
+            &cache,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("not declared in [charts]"));
+    }
+
+    #[tokio::test]
+    async fn fetch_manifest_verifies_pinned_digest() {
+        let crd = "apiVersion: apiextensions.k8s.io/v1\nkind: CustomResourceDefinition\n";
+        let digest = sha256_hex(crd.as_bytes());
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/v1/crds.yaml")
+            .with_status(200)
+            .with_body(crd)
+            .expect(1)
+            .create_async()
+            .await;
+        let url = format!("{}/v1/crds.yaml", server.url());
+        let tmp = tempfile::tempdir().unwrap();
+
+        let err = fetch_manifest(&url, Some("00ff"), tmp.path(), &FetchContext::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("sha256 mismatch"));
+        assert!(!url_cache_path(&url, tmp.path()).exists());
+        mock.remove_async().await;
+
+        let mock = server
+            .mock("GET", "/v1/crds.yaml")
+            .with_status(200)
+            .with_body(crd)
+            .expect(1)
+            .create_async()
+            .await;
+        let (yaml, got) = fetch_manifest(
+            &url,
+            Some(&digest.to_uppercase()),
+            tmp.path(),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(yaml, crd);
+        assert_eq!(got, digest);
+
+        // Served from cache afterwards, pinned or not
+        fetch_manifest(&url, Some(&digest), tmp.path(), &FetchContext::default())
+            .await
+            .unwrap();
+        fetch_manifest(&url, None, tmp.path(), &FetchContext::default())
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
     #[test]
     fn resolve_file_not_found() {
         let tmp = tempfile::tempdir().unwrap();
@@ -466,4 +1017,93 @@ spec:
         assert_eq!(result.len(), 1);
         assert!(result.contains_key("apis/example.com/v1"));
     }
+
+    #[tokio::test]
+    async fn resolve_git_records_commit_per_ref() {
+        let crd = |kind: &str| {
+            format!(
+                "apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: {lower}s.example.com
+spec:
+  group: example.com
+  names:
+    kind: {kind}
+    plural: {lower}s
+  scope: Namespaced
+  versions:
+    - name: v1
+      served: true
+      storage: true
+      schema:
+        openAPIV3Schema:
+          type: object
+",
+                lower = kind.to_lowercase()
+            )
+        };
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        let widget = crd("Widget");
+        let gadget = crd("Gadget");
+        let commits = make_repo(
+            &repo_dir,
+            &[
+                ("v1", &[("crds/widget.yaml", widget.as_str())]),
+                ("v2", &[("crds/gadget.yaml", gadget.as_str())]),
+            ],
+        );
+        let repo = format!("file://{}", repo_dir.display());
+        let cache_dir = tmp.path().join("cache");
+
+        resolve_git(
+            &repo,
+            GitRef::Tag("v1"),
+            "crds",
+            &cache_dir,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            git_commit(&repo, GitRef::Tag("v1"), &cache_dir).as_ref(),
+            Some(&commits[0])
+        );
+
+        let rev = GitRef::Rev(&commits[1]);
+        let specs = resolve_git(&repo, rev, "crds", &cache_dir, &FetchContext::default())
+            .await
+            .unwrap();
+        let schemas = &specs["apis/example.com/v1"]["components"]["schemas"];
+        assert!(schemas.as_object().unwrap().len() >= 2);
+        assert_eq!(
+            git_commit(&repo, rev, &cache_dir).as_ref(),
+            Some(&commits[1])
+        );
+
+        // A moved tag is fetched again rather than served from the cache
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&repo_dir)
+            .args(["tag", "-f", "v1", &commits[1]])
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+        husako_git::forget_moving_refs();
+        resolve_git(
+            &repo,
+            GitRef::Tag("v1"),
+            "crds",
+            &cache_dir,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            git_commit(&repo, GitRef::Tag("v1"), &cache_dir).as_ref(),
+            Some(&commits[1])
+        );
+    }
 }
@@ -0,0 +1,312 @@
+//! Repeated renders of one project, as under `husako render --watch`.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use husako_runtime_qjs::{LoadedFiles, ModuleCache};
+
+use crate::progress::ProgressReporter;
+use crate::{HusakoError, RenderOptions, load_schema_store, render_with_modules};
+
+/// Renders that share state from one to the next.
+///
+/// Each render still runs in a fresh QuickJS runtime, so nothing a script
+/// does carries over. What does carry over is the schema store, reloaded
+/// only when `husako gen` rewrites it, and every file module loaded so far
+/// as bytecode, so an unchanged `k8s/*` or project module is not read,
+/// compiled or parsed again. Modules are dropped on [`Self::invalidate`] or
+/// when their file's modification time or size changes.
+///
+/// The session also records what the last render read, see
+/// [`Self::dependencies`].
+pub struct RenderSession {
+    options: RenderOptions,
+    modules: ModuleCache,
+    schema_stamp: SchemaStamp,
+    loaded: LoadedFiles,
+    dependencies: BTreeSet<PathBuf>,
+}
+
+/// Modification times of `_schema.bin` and `_schema.json`.
+type SchemaStamp = [Option<SystemTime>; 2];
+
+impl RenderSession {
+    /// Start a session rendering with `options`, whose `schema_store` is
+    /// taken to be the one currently in the project.
+    pub fn new(options: RenderOptions) -> Self {
+        let schema_stamp = schema_stamp(&options.project_root);
+        Self {
+            options,
+            modules: ModuleCache::new(),
+            schema_stamp,
+            loaded: LoadedFiles::new(),
+            dependencies: BTreeSet::new(),
+        }
+    }
+
+    pub fn project_root(&self) -> &Path {
+        &self.options.project_root
+    }
+
+    /// Forget the modules loaded from `paths`, e.g. files a watcher saw
+    /// change. Paths of files that aren't modules are ignored.
+    pub fn invalidate<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) {
+        for path in paths {
+            self.modules.invalidate(path);
+        }
+    }
+
+    /// Number of file modules kept for the next render.
+    pub fn cached_modules(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// Files the last render read: the entry file, every file module it
+    /// imported, the chart directories or archives `helmTemplate()` used and
+    /// the schema store. A render that fails part way records only what it
+    /// read up to the failure.
+    pub fn dependencies(&self) -> &BTreeSet<PathBuf> {
+        &self.dependencies
+    }
+
+    pub async fn render(
+        &mut self,
+        source: &str,
+        filename: &str,
+        progress: &dyn ProgressReporter,
+    ) -> Result<String, HusakoError> {
+        let stamp = schema_stamp(&self.options.project_root);
+        if stamp != self.schema_stamp {
+            self.options.schema_store = load_schema_store(&self.options.project_root);
+            self.schema_stamp = stamp;
+        }
+        self.loaded.take();
+        let result = render_with_modules(
+            source,
+            filename,
+            &self.options,
+            Some(&self.modules),
+            Some(&self.loaded),
+            progress,
+        )
+        .await;
+
+        let mut dependencies = self.loaded.take();
+        let entry = Path::new(filename);
+        dependencies.insert(entry.canonicalize().unwrap_or_else(|_| entry.to_path_buf()));
+        let k8s_dir = self.options.project_root.join(".husako/types/k8s");
+        dependencies.extend(SCHEMA_FILES.map(|name| k8s_dir.join(name)));
+        self.dependencies = dependencies;
+        result
+    }
+}
+
+/// The files `husako gen` reads from the project: `husako.toml` and the local
+/// paths of `file` resources, `file`/`chart` charts (also inline in `chart`
+/// resources) and `path` plugins.
+///
+/// `husako.lock` is left out since `gen` writes it. When `husako.toml` can't
+/// be loaded, only `husako.toml` itself is returned.
+pub fn generate_inputs(project_root: &Path) -> Vec<PathBuf> {
+    let mut inputs = vec![project_root.join(husako_config::CONFIG_FILENAME)];
+    let Ok(Some(config)) = husako_config::load(project_root) else {
+        return inputs;
+    };
+    let resources = config.resources.values().filter_map(|s| match s {
+        husako_config::SchemaSource::File { path }
+        | husako_config::SchemaSource::Chart {
+            chart:
+                husako_config::ChartRef::Inline(
+                    husako_config::ChartSource::File { path }
+                    | husako_config::ChartSource::Chart { path },
+                ),
+            ..
+        } => Some(path),
+        _ => None,
+    });
+    let charts = config.charts.values().filter_map(|s| match s {
+        husako_config::ChartSource::File { path } | husako_config::ChartSource::Chart { path } => {
+            Some(path)
+        }
+        _ => None,
+    });
+    let plugins = config.plugins.values().filter_map(|s| match s {
+        husako_config::PluginSource::Path { path } => Some(path),
+        _ => None,
+    });
+    inputs.extend(
+        resources
+            .chain(charts)
+            .chain(plugins)
+            .map(|path| project_root.join(path)),
+    );
+    inputs
+}
+
+const SCHEMA_FILES: [&str; 2] = ["_schema.bin", "_schema.json"];
+
+fn schema_stamp(project_root: &Path) -> SchemaStamp {
+    let dir = project_root.join(".husako/types/k8s");
+    SCHEMA_FILES.map(|name| {
+        std::fs::metadata(dir.join(name))
+            .and_then(|m| m.modified())
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::SilentProgress;
+
+    fn options(root: &Path) -> RenderOptions {
+        RenderOptions {
+            project_root: root.to_path_buf(),
+            allow_outside_root: false,
+            schema_store: load_schema_store(root),
+            timeout_ms: None,
+            max_heap_mb: None,
+            verbose: false,
+        }
+    }
+
+    const ENTRY: &str = r#"import { build } from "husako";
+import { name } from "./name";
+build([{ _render() { return { apiVersion: "v1", kind: "ConfigMap", metadata: { name }, data: { replicas: "1" } }; } }]);
+"#;
+
+    #[tokio::test]
+    async fn rerenders_invalidated_modules_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let name_path = root.join("name.ts");
+        std::fs::write(&name_path, r#"export const name = "first";"#).unwrap();
+        let entry = root.join("entry.ts");
+        std::fs::write(&entry, ENTRY).unwrap();
+        let filename = entry.to_string_lossy();
+
+        let mut session = RenderSession::new(options(&root));
+        let yaml = session
+            .render(ENTRY, &filename, &SilentProgress)
+            .await
+            .unwrap();
+        assert!(yaml.contains("name: first"));
+        assert_eq!(session.cached_modules(), 1);
+
+        // Served from the session while unchanged
+        let yaml = session
+            .render(ENTRY, &filename, &SilentProgress)
+            .await
+            .unwrap();
+        assert!(yaml.contains("name: first"));
+
+        std::fs::write(&name_path, r#"export const name = "second";"#).unwrap();
+        session.invalidate([name_path.as_path()]);
+        assert_eq!(session.cached_modules(), 0);
+        let yaml = session
+            .render(ENTRY, &filename, &SilentProgress)
+            .await
+            .unwrap();
+        assert!(yaml.contains("name: second"));
+    }
+
+    #[tokio::test]
+    async fn reloads_schema_store_after_gen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        std::fs::write(root.join("name.ts"), r#"export const name = "cm";"#).unwrap();
+        let entry = root.join("entry.ts");
+        std::fs::write(&entry, ENTRY).unwrap();
+        let filename = entry.to_string_lossy();
+
+        let mut session = RenderSession::new(options(&root));
+        assert!(
+            session
+                .render(ENTRY, &filename, &SilentProgress)
+                .await
+                .is_ok()
+        );
+
+        // A schema store appearing later is picked up
+        let k8s_dir = root.join(".husako/types/k8s");
+        std::fs::create_dir_all(&k8s_dir).unwrap();
+        let store = serde_json::json!({
+            "version": 2,
+            "gvk_index": {"v1:ConfigMap": "io.k8s.api.core.v1.ConfigMap"},
+            "schemas": {
+                "io.k8s.api.core.v1.ConfigMap": {
+                    "properties": {"data": {"type": "object", "additionalProperties": {"type": "integer"}}}
+                }
+            }
+        });
+        std::fs::write(k8s_dir.join("_schema.json"), store.to_string()).unwrap();
+        let err = session
+            .render(ENTRY, &filename, &SilentProgress)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HusakoError::Validation(_)));
+    }
+
+    #[tokio::test]
+    async fn records_dependencies_of_last_render() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let name_path = root.join("name.ts");
+        std::fs::write(&name_path, r#"export const name = "cm";"#).unwrap();
+        std::fs::write(root.join("unrelated.ts"), "export {};").unwrap();
+        let entry = root.join("entry.ts");
+        std::fs::write(&entry, ENTRY).unwrap();
+        let filename = entry.to_string_lossy();
+
+        let mut session = RenderSession::new(options(&root));
+        session
+            .render(ENTRY, &filename, &SilentProgress)
+            .await
+            .unwrap();
+        let deps = session.dependencies();
+        assert!(deps.contains(&entry));
+        assert!(deps.contains(&name_path));
+        assert!(deps.contains(&root.join(".husako/types/k8s/_schema.bin")));
+        assert!(!deps.contains(&root.join("unrelated.ts")));
+
+        // A failed render records what it read up to the failure
+        let broken = ENTRY.replace("./name", "./missing");
+        assert!(
+            session
+                .render(&broken, &filename, &SilentProgress)
+                .await
+                .is_err()
+        );
+        assert!(!session.dependencies().contains(&name_path));
+        assert!(session.dependencies().contains(&entry));
+    }
+
+    #[test]
+    fn generate_inputs_lists_local_sources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        assert_eq!(generate_inputs(root), vec![root.join("husako.toml")]);
+
+        std::fs::write(
+            root.join("husako.toml"),
+            r#"
+[resources]
+crds = { source = "file", path = "crds/" }
+k8s = { source = "release", version = "1.35" }
+
+[charts]
+local = { source = "chart", path = "charts/app" }
+
+[plugins]
+mine = { source = "path", path = "../plugin" }
+"#,
+        )
+        .unwrap();
+        let inputs = generate_inputs(root);
+        assert_eq!(inputs.len(), 4);
+        assert!(inputs.contains(&root.join("crds/")));
+        assert!(inputs.contains(&root.join("charts/app")));
+        assert!(inputs.contains(&root.join("../plugin")));
+    }
+}
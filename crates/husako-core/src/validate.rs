@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::path::Path;
+use std::sync::{Arc, OnceLock};
 
+use husako_dts::schema_bin::BinarySchemaStore;
 use serde_json::Value;
 
 use crate::quantity;
@@ -12,11 +14,34 @@ const MAX_DEPTH: usize = 64;
 // SchemaStore
 // ---------------------------------------------------------------------------
 
-/// Loaded `_schema.json` with resolved GVK index.
+/// Loaded `_schema.json` with resolved GVK index, or `_schema.bin` whose
+/// schemas are decoded as validation first reaches them.
 #[derive(Debug, Clone)]
 pub struct SchemaStore {
-    gvk_index: HashMap<String, String>,
-    schemas: HashMap<String, Value>,
+    source: Source,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Json {
+        gvk_index: HashMap<String, String>,
+        schemas: HashMap<String, Value>,
+    },
+    Binary(Arc<LazySchemas>),
+}
+
+#[derive(Debug)]
+struct LazySchemas {
+    store: BinarySchemaStore,
+    decoded: Vec<OnceLock<Option<Value>>>,
+}
+
+impl LazySchemas {
+    fn get(&self, idx: usize) -> Option<&Value> {
+        self.decoded[idx]
+            .get_or_init(|| self.store.decode(idx).ok())
+            .as_ref()
+    }
 }
 
 impl SchemaStore {
@@ -43,24 +68,54 @@ impl SchemaStore {
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
 
-        Some(Self { gvk_index, schemas })
+        Some(Self {
+            source: Source::Json { gvk_index, schemas },
+        })
+    }
+
+    /// Use an opened `_schema.bin`. Nothing is decoded until looked up.
+    pub fn from_binary(store: BinarySchemaStore) -> Self {
+        let decoded = (0..store.len()).map(|_| OnceLock::new()).collect();
+        Self {
+            source: Source::Binary(Arc::new(LazySchemas { store, decoded })),
+        }
     }
 
     fn schema_for_gvk(&self, api_version: &str, kind: &str) -> Option<&Value> {
         let key = format!("{api_version}:{kind}");
-        let schema_name = self.gvk_index.get(&key)?;
-        self.schemas.get(schema_name)
+        match &self.source {
+            Source::Json { gvk_index, schemas } => schemas.get(gvk_index.get(&key)?),
+            Source::Binary(lazy) => lazy.get(lazy.store.gvk(&key)?),
+        }
     }
 
     fn resolve_ref(&self, ref_name: &str) -> Option<&Value> {
-        self.schemas.get(ref_name)
+        match &self.source {
+            Source::Json { schemas, .. } => schemas.get(ref_name),
+            Source::Binary(lazy) => lazy.get(lazy.store.schema(ref_name)?),
+        }
     }
 }
 
-/// Load a `SchemaStore` from `.husako/types/k8s/_schema.json` if it exists.
+/// Load a `SchemaStore` from `.husako/types/k8s/`: `_schema.bin` when it is
+/// readable and not older than `_schema.json`, else `_schema.json` if it
+/// exists.
 pub fn load_schema_store(project_root: &Path) -> Option<SchemaStore> {
-    let path = project_root.join(".husako/types/k8s/_schema.json");
-    let content = std::fs::read_to_string(path).ok()?;
+    let dir = project_root.join(".husako/types/k8s");
+    let json_path = dir.join("_schema.json");
+    let bin_path = dir.join("_schema.bin");
+
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let bin_current = match (modified(&bin_path), modified(&json_path)) {
+        (Some(bin), Some(json)) => bin >= json,
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    if bin_current && let Ok(store) = BinarySchemaStore::open(&bin_path) {
+        return Some(SchemaStore::from_binary(store));
+    }
+
+    let content = std::fs::read_to_string(json_path).ok()?;
     let value: Value = serde_json::from_str(&content).ok()?;
     SchemaStore::from_json(&value)
 }
@@ -472,17 +527,29 @@ mod tests {
     use super::*;
     use serde_json::json;
 
-    fn make_store(schemas_json: Value, gvk_json: Value) -> SchemaStore {
-        let store_json = json!({
+    fn store_json(schemas_json: Value, gvk_json: Value) -> Value {
+        json!({
             "version": 2,
             "gvk_index": gvk_json,
             "schemas": schemas_json
-        });
-        SchemaStore::from_json(&store_json).unwrap()
+        })
+    }
+
+    fn make_store(schemas_json: Value, gvk_json: Value) -> SchemaStore {
+        SchemaStore::from_json(&store_json(schemas_json, gvk_json)).unwrap()
+    }
+
+    fn binary_store(value: &Value) -> SchemaStore {
+        let bytes = husako_dts::schema_bin::encode(value).unwrap();
+        SchemaStore::from_binary(BinarySchemaStore::from_bytes(bytes).unwrap())
     }
 
     fn simple_store() -> SchemaStore {
-        make_store(
+        SchemaStore::from_json(&simple_store_json()).unwrap()
+    }
+
+    fn simple_store_json() -> Value {
+        store_json(
             json!({
                 "io.k8s.api.apps.v1.Deployment": {
                     "properties": {
@@ -1106,6 +1173,89 @@ mod tests {
         assert!(store.resolve_ref("some.Schema").is_some());
     }
 
+    #[test]
+    fn binary_store_validates_like_json() {
+        let json_store = simple_store();
+        let bin_store = binary_store(&simple_store_json());
+        for doc in [
+            json!([{"apiVersion": "apps/v1", "kind": "Deployment", "spec": {"selector": {}, "replicas": "abc"}}]),
+            json!([{"apiVersion": "apps/v1", "kind": "Deployment", "spec": {"replicas": 3}}]),
+            json!([{"apiVersion": "apps/v1", "kind": "Deployment", "spec": {"selector": {}, "strategy": {"type": "bluegreen"}}}]),
+            json!([{"apiVersion": "apps/v1", "kind": "Deployment", "spec": {"selector": {}}}]),
+        ] {
+            let errors = |store: &SchemaStore| {
+                validate(&doc, Some(store))
+                    .err()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(errors(&bin_store), errors(&json_store));
+        }
+    }
+
+    #[test]
+    fn binary_store_decodes_only_reached_schemas() {
+        let store = binary_store(&simple_store_json());
+        let doc =
+            json!([{"apiVersion": "apps/v1", "kind": "Deployment", "spec": {"selector": {}}}]);
+        assert!(validate(&doc, Some(&store)).is_ok());
+
+        let Source::Binary(lazy) = &store.source else {
+            panic!("expected a binary store");
+        };
+        let decoded: Vec<&str> = (0..lazy.store.len())
+            .filter(|&i| lazy.decoded[i].get().is_some())
+            .map(|i| lazy.store.name(i))
+            .collect();
+        assert!(decoded.contains(&"io.k8s.api.apps.v1.Deployment"));
+        assert!(decoded.contains(&"io.k8s.api.apps.v1.DeploymentSpec"));
+        assert!(!decoded.contains(&"io.k8s.api.core.v1.ResourceRequirements"));
+    }
+
+    #[test]
+    fn load_schema_store_prefers_current_binary() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join(".husako/types/k8s");
+        std::fs::create_dir_all(&dir).unwrap();
+        let value = simple_store_json();
+        std::fs::write(dir.join("_schema.json"), value.to_string()).unwrap();
+        std::fs::write(
+            dir.join("_schema.bin"),
+            husako_dts::schema_bin::encode(&value).unwrap(),
+        )
+        .unwrap();
+        let store = load_schema_store(tmp.path()).unwrap();
+        assert!(matches!(store.source, Source::Binary(_)));
+
+        // A corrupt binary falls back to JSON
+        std::fs::write(dir.join("_schema.bin"), b"garbage").unwrap();
+        let store = load_schema_store(tmp.path()).unwrap();
+        assert!(matches!(store.source, Source::Json { .. }));
+        assert!(store.schema_for_gvk("apps/v1", "Deployment").is_some());
+    }
+
+    #[test]
+    fn load_schema_store_ignores_stale_binary() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join(".husako/types/k8s");
+        std::fs::create_dir_all(&dir).unwrap();
+        let value = simple_store_json();
+        let bin = dir.join("_schema.bin");
+        std::fs::write(&bin, husako_dts::schema_bin::encode(&value).unwrap()).unwrap();
+        std::fs::write(dir.join("_schema.json"), value.to_string()).unwrap();
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&bin)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let store = load_schema_store(tmp.path()).unwrap();
+        assert!(matches!(store.source, Source::Json { .. }));
+    }
+
     // --- Display ---
 
     #[test]
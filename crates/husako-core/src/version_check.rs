@@ -1,7 +1,13 @@
+use husako_config::FetchContext;
+
 use crate::HusakoError;
 
 const GITHUB_API_BASE: &str = "https://api.github.com";
 const ARTIFACTHUB_BASE: &str = "https://artifacthub.io";
+/// Page size of GitHub's tags API (its maximum).
+const GITHUB_TAGS_PER_PAGE: usize = 100;
+/// Upper bound on the tag pages read for Kubernetes releases.
+const GITHUB_MAX_TAG_PAGES: usize = 50;
 
 fn github_api_base() -> String {
     std::env::var("HUSAKO_GITHUB_API_URL").unwrap_or_else(|_| GITHUB_API_BASE.to_string())
@@ -23,6 +29,16 @@ pub fn version_matches_prefix(version: &str, prefix: &str) -> bool {
     v == p || v.starts_with(&format!("{p}."))
 }
 
+/// Version discovery always needs the network; fail fast in offline mode.
+fn require_network(what: &str, ctx: &FetchContext) -> Result<(), HusakoError> {
+    if ctx.offline {
+        return Err(HusakoError::Offline(format!(
+            "cannot {what} without network access"
+        )));
+    }
+    Ok(())
+}
+
 // TODO: use urlencoding instead
 fn percent_encode(s: &str) -> String {
     let mut out = String::with_capacity(s.len() * 3);
@@ -65,16 +81,20 @@ pub const ARTIFACTHUB_PAGE_SIZE: usize = 20;
 pub async fn search_artifacthub(
     query: &str,
     offset: usize,
+    ctx: &FetchContext,
 ) -> Result<ArtifactHubSearchResult, HusakoError> {
-    search_artifacthub_from(query, offset, &artifacthub_base()).await
+    require_network("search ArtifactHub", ctx)?;
+    search_artifacthub_from(query, offset, &artifacthub_base(), ctx).await
 }
 
 async fn search_artifacthub_from(
     query: &str,
     offset: usize,
     base_url: &str,
+    ctx: &FetchContext,
 ) -> Result<ArtifactHubSearchResult, HusakoError> {
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .user_agent("husako")
         .timeout(std::time::Duration::from_secs(10))
         .build()
@@ -87,7 +107,7 @@ async fn search_artifacthub_from(
     );
 
     let resp = client
-        .get(&url)
+        .get(ctx.rewrite_url(&url))
         .send()
         .await
         .map_err(|e| HusakoError::GenerateIo(format!("ArtifactHub search: {e}")))?;
@@ -116,32 +136,45 @@ async fn search_artifacthub_from(
 pub async fn discover_recent_releases(
     limit: usize,
     offset: usize,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HusakoError> {
-    discover_recent_releases_from(limit, offset, &github_api_base()).await
+    require_network("list Kubernetes releases", ctx)?;
+    discover_recent_releases_from(limit, offset, &github_api_base(), ctx).await
 }
 
 async fn discover_recent_releases_from(
     limit: usize,
     offset: usize,
     base_url: &str,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HusakoError> {
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .user_agent("husako")
         .build()
         .map_err(|e| HusakoError::GenerateIo(format!("HTTP client: {e}")))?;
 
-    let resp = client
-        .get(format!(
-            "{base_url}/repos/kubernetes/kubernetes/tags?per_page=100"
-        ))
-        .send()
-        .await
-        .map_err(|e| HusakoError::GenerateIo(format!("GitHub API: {e}")))?;
+    // The tags API is paged and not ordered by version, so every page is read
+    let mut tags: Vec<serde_json::Value> = Vec::new();
+    for page in 1..=GITHUB_MAX_TAG_PAGES {
+        let resp = client
+            .get(ctx.rewrite_url(&format!(
+                "{base_url}/repos/kubernetes/kubernetes/tags?per_page={GITHUB_TAGS_PER_PAGE}&page={page}"
+            )))
+            .send()
+            .await
+            .map_err(|e| HusakoError::GenerateIo(format!("GitHub API: {e}")))?;
 
-    let tags: Vec<serde_json::Value> = resp
-        .json()
-        .await
-        .map_err(|e| HusakoError::GenerateIo(format!("parse tags: {e}")))?;
+        let page_tags: Vec<serde_json::Value> = resp
+            .json()
+            .await
+            .map_err(|e| HusakoError::GenerateIo(format!("parse tags: {e}")))?;
+        let last = page_tags.len() < GITHUB_TAGS_PER_PAGE;
+        tags.extend(page_tags);
+        if last {
+            break;
+        }
+    }
 
     let mut versions: Vec<semver::Version> = Vec::new();
     let mut seen = std::collections::HashSet::new();
@@ -178,18 +211,24 @@ pub async fn discover_registry_versions(
     chart: &str,
     limit: usize,
     offset: usize,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HusakoError> {
+    require_network(&format!("list versions of {chart} in {repo}"), ctx)?;
     let url = format!("{}/index.yaml", repo.trim_end_matches('/'));
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .user_agent("husako")
         .build()
         .map_err(|e| HusakoError::GenerateIo(format!("HTTP client: {e}")))?;
 
-    let resp = client
-        .get(&url)
+    let request = husako_helm::auth::authorize(client.get(ctx.rewrite_url(&url)), repo, &url, ctx)?;
+    let resp = request
         .send()
         .await
         .map_err(|e| HusakoError::GenerateIo(format!("fetch registry index: {e}")))?;
+    if matches!(resp.status().as_u16(), 401 | 403) {
+        return Err(registry_denied(repo, resp.status()));
+    }
 
     let text = resp
         .text()
@@ -230,20 +269,25 @@ pub async fn discover_registry_versions(
 }
 
 /// Discover the latest stable Kubernetes release version from GitHub API.
-pub async fn discover_latest_release() -> Result<String, HusakoError> {
-    discover_latest_release_from(&github_api_base()).await
+pub async fn discover_latest_release(ctx: &FetchContext) -> Result<String, HusakoError> {
+    require_network("look up the latest Kubernetes release", ctx)?;
+    discover_latest_release_from(&github_api_base(), ctx).await
 }
 
-async fn discover_latest_release_from(base_url: &str) -> Result<String, HusakoError> {
-    let client = reqwest::Client::builder()
+async fn discover_latest_release_from(
+    base_url: &str,
+    ctx: &FetchContext,
+) -> Result<String, HusakoError> {
+    let client = ctx
+        .client_builder()
         .user_agent("husako")
         .build()
         .map_err(|e| HusakoError::GenerateIo(format!("HTTP client: {e}")))?;
 
     let resp = client
-        .get(format!(
+        .get(ctx.rewrite_url(&format!(
             "{base_url}/repos/kubernetes/kubernetes/tags?per_page=100"
-        ))
+        )))
         .send()
         .await
         .map_err(|e| HusakoError::GenerateIo(format!("GitHub API: {e}")))?;
@@ -277,6 +321,14 @@ async fn discover_latest_release_from(base_url: &str) -> Result<String, HusakoEr
         .ok_or_else(|| HusakoError::GenerateIo("no stable release tags found".to_string()))
 }
 
+fn registry_denied(repo: &str, status: reqwest::StatusCode) -> HusakoError {
+    HusakoError::Chart(husako_helm::HelmError::Auth(format!(
+        "{repo} returned {status}; add credentials for {} to [credentials] in husako.toml \
+         or to Helm's repositories.yaml",
+        husako_config::credentials::host_of(repo)
+    )))
+}
+
 /// Discover the latest version from a Helm chart registry's index.yaml.
 ///
 /// When `prefix` is `Some("16")`, returns the latest version whose numeric part starts with
@@ -285,18 +337,24 @@ pub async fn discover_latest_registry(
     repo: &str,
     chart: &str,
     prefix: Option<&str>,
+    ctx: &FetchContext,
 ) -> Result<String, HusakoError> {
+    require_network(&format!("look up the latest {chart} in {repo}"), ctx)?;
     let url = format!("{}/index.yaml", repo.trim_end_matches('/'));
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .user_agent("husako")
         .build()
         .map_err(|e| HusakoError::GenerateIo(format!("HTTP client: {e}")))?;
 
-    let resp = client
-        .get(&url)
+    let request = husako_helm::auth::authorize(client.get(ctx.rewrite_url(&url)), repo, &url, ctx)?;
+    let resp = request
         .send()
         .await
         .map_err(|e| HusakoError::GenerateIo(format!("fetch registry index: {e}")))?;
+    if matches!(resp.status().as_u16(), 401 | 403) {
+        return Err(registry_denied(repo, resp.status()));
+    }
 
     let text = resp
         .text()
@@ -351,26 +409,30 @@ pub async fn discover_latest_registry(
 pub async fn discover_latest_artifacthub(
     package: &str,
     prefix: Option<&str>,
+    ctx: &FetchContext,
 ) -> Result<String, HusakoError> {
-    discover_latest_artifacthub_from(package, prefix, &artifacthub_base()).await
+    require_network(&format!("look up the latest {package} on ArtifactHub"), ctx)?;
+    discover_latest_artifacthub_from(package, prefix, &artifacthub_base(), ctx).await
 }
 
 async fn discover_latest_artifacthub_from(
     package: &str,
     prefix: Option<&str>,
     base_url: &str,
+    ctx: &FetchContext,
 ) -> Result<String, HusakoError> {
     let url = format!(
         "{base_url}/api/v1/packages/helm/{}",
         package.trim_start_matches('/')
     );
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .user_agent("husako")
         .build()
         .map_err(|e| HusakoError::GenerateIo(format!("HTTP client: {e}")))?;
 
     let resp = client
-        .get(&url)
+        .get(ctx.rewrite_url(&url))
         .send()
         .await
         .map_err(|e| HusakoError::GenerateIo(format!("ArtifactHub API: {e}")))?;
@@ -407,8 +469,10 @@ pub async fn discover_artifacthub_versions(
     package: &str,
     limit: usize,
     offset: usize,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HusakoError> {
-    discover_artifacthub_versions_from(package, limit, offset, &artifacthub_base()).await
+    require_network(&format!("list versions of {package} on ArtifactHub"), ctx)?;
+    discover_artifacthub_versions_from(package, limit, offset, &artifacthub_base(), ctx).await
 }
 
 async fn discover_artifacthub_versions_from(
@@ -416,19 +480,21 @@ async fn discover_artifacthub_versions_from(
     limit: usize,
     offset: usize,
     base_url: &str,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HusakoError> {
     let url = format!(
         "{base_url}/api/v1/packages/helm/{}",
         package.trim_start_matches('/')
     );
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .user_agent("husako")
         .timeout(std::time::Duration::from_secs(10))
         .build()
         .map_err(|e| HusakoError::GenerateIo(format!("HTTP client: {e}")))?;
 
     let resp = client
-        .get(&url)
+        .get(ctx.rewrite_url(&url))
         .send()
         .await
         .map_err(|e| HusakoError::GenerateIo(format!("ArtifactHub API: {e}")))?;
@@ -469,7 +535,7 @@ fn parse_artifacthub_versions(
         .collect()
 }
 
-/// Discover the latest tag from a git repository using `git ls-remote --tags`.
+/// Discover the latest tag from a git repository.
 ///
 /// When `prefix` is `Some("v1")`, returns the latest tag whose version matches the prefix.
 /// When `None`, returns the overall latest stable tag.
@@ -479,44 +545,23 @@ fn parse_artifacthub_versions(
 pub fn discover_latest_git_tag(
     repo: &str,
     prefix: Option<&str>,
+    ctx: &FetchContext,
 ) -> Result<Option<String>, HusakoError> {
-    let output = std::process::Command::new("git")
-        .args(["ls-remote", "--tags", "--sort=-v:refname", repo])
-        .output()
-        .map_err(|e| HusakoError::GenerateIo(format!("git ls-remote: {e}")))?;
-
-    if !output.status.success() {
-        return Err(HusakoError::GenerateIo(format!(
-            "git ls-remote failed for '{repo}'"
-        )));
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
     let mut best: Option<(semver::Version, String)> = None;
 
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() < 2 {
-            continue;
-        }
-        let refname = parts[1];
-        let tag = refname
-            .strip_prefix("refs/tags/")
-            .unwrap_or(refname)
-            .trim_end_matches("^{}");
-
+    for tag in list_git_tags(repo, ctx)? {
         if let Some(pfx) = prefix
-            && !version_matches_prefix(tag, pfx)
+            && !version_matches_prefix(&tag, pfx)
         {
             continue;
         }
 
-        let stripped = tag.strip_prefix('v').unwrap_or(tag);
+        let stripped = tag.strip_prefix('v').unwrap_or(&tag);
         if let Ok(v) = semver::Version::parse(stripped)
             && v.pre.is_empty()
             && best.as_ref().is_none_or(|(b, _)| v > *b)
         {
-            best = Some((v, tag.to_string()));
+            best = Some((v, tag));
         }
     }
 
@@ -530,41 +575,16 @@ pub fn discover_git_tags(
     repo: &str,
     limit: usize,
     offset: usize,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HusakoError> {
-    let output = std::process::Command::new("git")
-        .args(["ls-remote", "--tags", "--sort=-v:refname", repo])
-        .output()
-        .map_err(|e| HusakoError::GenerateIo(format!("git ls-remote: {e}")))?;
-
-    if !output.status.success() {
-        return Err(HusakoError::GenerateIo(format!(
-            "git ls-remote failed for '{repo}'"
-        )));
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut seen = std::collections::HashSet::new();
-    let mut entries: Vec<(semver::Version, String)> = Vec::new();
-
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() < 2 {
-            continue;
-        }
-        let refname = parts[1];
-        let tag = refname
-            .strip_prefix("refs/tags/")
-            .unwrap_or(refname)
-            .trim_end_matches("^{}");
-
-        let stripped = tag.strip_prefix('v').unwrap_or(tag);
-        if let Ok(v) = semver::Version::parse(stripped)
-            && v.pre.is_empty()
-            && seen.insert(tag.to_string())
-        {
-            entries.push((v, tag.to_string()));
-        }
-    }
+    let mut entries: Vec<(semver::Version, String)> = list_git_tags(repo, ctx)?
+        .into_iter()
+        .filter_map(|tag| {
+            let stripped = tag.strip_prefix('v').unwrap_or(&tag);
+            let v = semver::Version::parse(stripped).ok()?;
+            v.pre.is_empty().then_some((v, tag))
+        })
+        .collect();
 
     entries.sort_by(|a, b| b.0.cmp(&a.0));
 
@@ -576,20 +596,30 @@ pub fn discover_git_tags(
         .collect())
 }
 
+fn list_git_tags(repo: &str, ctx: &FetchContext) -> Result<Vec<String>, HusakoError> {
+    require_network(&format!("list tags of {repo}"), ctx)?;
+    husako_git::list_tags(repo, ctx).map_err(|e| HusakoError::GenerateIo(e.to_string()))
+}
+
 /// Fetch up to `limit` available stable OCI tags for `reference`, starting at `offset`.
 pub async fn discover_oci_tags(
     reference: &str,
     limit: usize,
     offset: usize,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HusakoError> {
-    husako_helm::oci::list_tags(reference, limit, offset)
+    require_network(&format!("list tags of {reference}"), ctx)?;
+    husako_helm::oci::list_tags(reference, limit, offset, ctx)
         .await
         .map_err(|e| HusakoError::GenerateIo(e.to_string()))
 }
 
 /// Return the latest stable OCI tag for `reference`, or None if no tags found.
-pub async fn discover_latest_oci(reference: &str) -> Result<Option<String>, HusakoError> {
-    let tags = discover_oci_tags(reference, 1, 0).await?;
+pub async fn discover_latest_oci(
+    reference: &str,
+    ctx: &FetchContext,
+) -> Result<Option<String>, HusakoError> {
+    let tags = discover_oci_tags(reference, 1, 0, ctx).await?;
     Ok(tags.into_iter().next())
 }
 
@@ -898,9 +928,10 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let result = search_artifacthub_from("postgres", 0, &server.url())
-            .await
-            .unwrap();
+        let result =
+            search_artifacthub_from("postgres", 0, &server.url(), &FetchContext::default())
+                .await
+                .unwrap();
         assert_eq!(result.packages.len(), 2);
         assert_eq!(result.packages[0].name, "postgresql");
         assert!(!result.has_more);
@@ -919,9 +950,10 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let result = search_artifacthub_from("nonexistent", 0, &server.url())
-            .await
-            .unwrap();
+        let result =
+            search_artifacthub_from("nonexistent", 0, &server.url(), &FetchContext::default())
+                .await
+                .unwrap();
         assert!(result.packages.is_empty());
         assert!(!result.has_more);
     }
@@ -945,13 +977,57 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let versions = discover_recent_releases_from(10, 0, &server.url())
-            .await
-            .unwrap();
+        let versions =
+            discover_recent_releases_from(10, 0, &server.url(), &FetchContext::default())
+                .await
+                .unwrap();
         // v1.35.1 and v1.35.0 share the same minor → only one "1.35" entry
         assert_eq!(versions, vec!["1.35", "1.34"]);
     }
 
+    #[tokio::test]
+    async fn discover_recent_releases_reads_every_page() {
+        let mut server = mockito::Server::new_async().await;
+        // A full first page of patch and pre-release tags of recent minors,
+        // with older minors only on the second page
+        let first: Vec<serde_json::Value> = (0..100)
+            .map(|i| match i % 2 {
+                0 => serde_json::json!({"name": format!("v1.31.{i}")}),
+                _ => serde_json::json!({"name": format!("v1.30.{i}-rc.0")}),
+            })
+            .collect();
+        let second = serde_json::json!([
+            {"name": "v1.30.2"},
+            {"name": "v1.29.5"},
+            {"name": "v1.28.0"}
+        ]);
+        let page = |n: &str| mockito::Matcher::UrlEncoded("page".into(), n.into());
+        let _first = server
+            .mock("GET", "/repos/kubernetes/kubernetes/tags")
+            .match_query(page("1"))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::Value::from(first).to_string())
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/repos/kubernetes/kubernetes/tags")
+            .match_query(page("2"))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(second.to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let versions =
+            discover_recent_releases_from(usize::MAX, 0, &server.url(), &FetchContext::default())
+                .await
+                .unwrap();
+        assert_eq!(versions, vec!["1.31", "1.30", "1.29", "1.28"]);
+        second.assert_async().await;
+    }
+
     // ── discover_latest_release_from (mockito) ────────────────────────────────
 
     #[tokio::test]
@@ -971,7 +1047,9 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let version = discover_latest_release_from(&server.url()).await.unwrap();
+        let version = discover_latest_release_from(&server.url(), &FetchContext::default())
+            .await
+            .unwrap();
         assert_eq!(version, "1.34");
     }
 
@@ -993,9 +1071,14 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let version = discover_latest_artifacthub_from("bitnami/postgresql", None, &server.url())
-            .await
-            .unwrap();
+        let version = discover_latest_artifacthub_from(
+            "bitnami/postgresql",
+            None,
+            &server.url(),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(version, "16.4.0");
     }
 
@@ -1011,9 +1094,14 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let err = discover_latest_artifacthub_from("bitnami/postgresql", None, &server.url())
-            .await
-            .unwrap_err();
+        let err = discover_latest_artifacthub_from(
+            "bitnami/postgresql",
+            None,
+            &server.url(),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
         assert!(err.to_string().contains("no version field"));
     }
 
@@ -1038,9 +1126,10 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let versions = discover_registry_versions(&server.url(), "my-chart", 10, 0)
-            .await
-            .unwrap();
+        let versions =
+            discover_registry_versions(&server.url(), "my-chart", 10, 0, &FetchContext::default())
+                .await
+                .unwrap();
         assert_eq!(versions, vec!["3.0.0", "2.0.0", "1.0.0"]);
     }
 
@@ -1056,9 +1145,10 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .await;
 
         // Skip the first (5.0.0), take next 2 (4.0.0, 3.0.0)
-        let versions = discover_registry_versions(&server.url(), "my-chart", 2, 1)
-            .await
-            .unwrap();
+        let versions =
+            discover_registry_versions(&server.url(), "my-chart", 2, 1, &FetchContext::default())
+                .await
+                .unwrap();
         assert_eq!(versions, vec!["4.0.0", "3.0.0"]);
     }
 
@@ -1073,9 +1163,10 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let err = discover_registry_versions(&server.url(), "my-chart", 10, 0)
-            .await
-            .unwrap_err();
+        let err =
+            discover_registry_versions(&server.url(), "my-chart", 10, 0, &FetchContext::default())
+                .await
+                .unwrap_err();
         assert!(err.to_string().contains("not found"));
     }
 
@@ -1092,9 +1183,10 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let version = discover_latest_registry(&server.url(), "my-chart", None)
-            .await
-            .unwrap();
+        let version =
+            discover_latest_registry(&server.url(), "my-chart", None, &FetchContext::default())
+                .await
+                .unwrap();
         assert_eq!(version, "2.0.0");
     }
 
@@ -1109,9 +1201,10 @@ vwx234\trefs/tags/v1.7.0^{}\n";
             .create_async()
             .await;
 
-        let err = discover_latest_registry(&server.url(), "my-chart", None)
-            .await
-            .unwrap_err();
+        let err =
+            discover_latest_registry(&server.url(), "my-chart", None, &FetchContext::default())
+                .await
+                .unwrap_err();
         assert!(err.to_string().contains("no versions found"));
     }
 }
@@ -0,0 +1,67 @@
+//! SHA-256 digests in the `sha256:<hex>` form used by `husako.lock`, the
+//! shared cache and OCI registries, and the compile cache keys.
+
+/// Incremental SHA-256 for digests over several parts.
+pub struct Sha256(aws_lc_rs::digest::Context);
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Self(aws_lc_rs::digest::Context::new(&aws_lc_rs::digest::SHA256))
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Lowercase hex of the digest, without the `sha256:` prefix.
+    pub fn finish_hex(self) -> String {
+        self.0
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// The digest as `sha256:<hex>`.
+    pub fn finish(self) -> String {
+        format!("sha256:{}", self.finish_hex())
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `sha256:<hex>` digest of `data`.
+pub fn sha256_digest(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// Lowercase hex SHA-256 of `data`, as pinned by `sha256` in `husako.toml`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finish_hex()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digest_forms() {
+        let hex = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(sha256_hex(b"hello"), hex);
+        assert_eq!(sha256_digest(b"hello"), format!("sha256:{hex}"));
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"hel");
+        hasher.update(b"lo");
+        assert_eq!(hasher.finish(), sha256_digest(b"hello"));
+    }
+}
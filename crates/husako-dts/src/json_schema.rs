@@ -5,6 +5,13 @@ use crate::DtsError;
 use crate::emitter::format_ts_type;
 use crate::schema::{PropertyInfo, SchemaInfo, TsType, has_complex_property};
 
+/// Vendor extension `husako-helm` sets on a property holding a composed
+/// subchart's values schema; the value is the dependency's chart name. Type
+/// names inside a marked subtree are prefixed with the property's PascalCase
+/// name so subcharts don't collide with the parent (or each other) on common
+/// keys like `image` or `service`.
+pub const SUBCHART_KEY: &str = "x-husako-subchart";
+
 /// Generate `.d.ts` and `.js` content from a JSON Schema for a Helm chart.
 ///
 /// Returns `(dts_content, js_content)`.
@@ -14,7 +21,7 @@ pub fn generate_chart_types(
 ) -> Result<(String, String), DtsError> {
     let mut extracted = Vec::new();
     let root_name = to_pascal_case(chart_name);
-    extract_schemas(schema, &root_name, &mut extracted);
+    extract_schemas(schema, &root_name, "", &mut extracted);
 
     if extracted.is_empty() {
         return Err(DtsError::Schema(format!(
@@ -28,6 +35,85 @@ pub fn generate_chart_types(
     Ok((dts, js))
 }
 
+/// Generate `.d.ts` and `.js` helpers naming the objects a chart renders,
+/// appended to the chart's values module.
+///
+/// `fullname()` follows the `<chart>.fullname` helper that `helm create`
+/// writes into `_helpers.tpl` (and most published charts keep), honouring
+/// `nameOverride`/`fullnameOverride`. `chart` is the name from `Chart.yaml`.
+///
+/// Returns `(dts_content, js_content)`.
+pub fn generate_chart_refs(
+    chart: &str,
+    version: Option<&str>,
+    app_version: Option<&str>,
+) -> (String, String) {
+    let literal = |v: Option<&str>| {
+        v.map(|v| serde_json::Value::from(v).to_string())
+            .unwrap_or_else(|| "undefined".to_string())
+    };
+    let name = serde_json::Value::from(chart).to_string();
+    let (version, app_version) = (literal(version), literal(app_version));
+
+    let mut dts = String::new();
+    let _ = writeln!(dts, "/** Chart identity from `Chart.yaml`. */");
+    let _ = writeln!(
+        dts,
+        "export declare const chart: {{ readonly name: {name}; readonly version: {version}; readonly appVersion: {app_version} }};"
+    );
+    dts.push_str(
+        r#"
+/** Values that change the names of rendered objects. */
+export interface NameOverrides {
+  nameOverride?: string;
+  fullnameOverride?: string;
+}
+
+/** The chart's `name` helper: `nameOverride` or the chart name, truncated to 63 characters. */
+export function name(values?: NameOverrides | { _toJSON(): any }): string;
+/**
+ * The chart's `fullname` helper: `fullnameOverride`, or `<release>-<name>` (just `<release>`
+ * when it already contains the name), truncated to 63 characters.
+ */
+export function fullname(release: string, values?: NameOverrides | { _toJSON(): any }): string;
+/** Name of the chart's main Service, which charts name after `fullname`. */
+export function serviceName(release: string, values?: NameOverrides | { _toJSON(): any }): string;
+"#,
+    );
+
+    let mut js = String::new();
+    let _ = writeln!(
+        js,
+        "export const chart = {{ name: {name}, version: {version}, appVersion: {app_version} }};"
+    );
+    js.push_str(
+        r#"
+function _overrides(values) {
+  if (values && typeof values._toJSON === "function") return values._toJSON();
+  return values || {};
+}
+function _trunc63(s) {
+  s = s.slice(0, 63);
+  return s.endsWith("-") ? s.slice(0, -1) : s;
+}
+export function name(values) {
+  return _trunc63(_overrides(values).nameOverride || chart.name);
+}
+export function fullname(release, values) {
+  const v = _overrides(values);
+  if (v.fullnameOverride) return _trunc63(v.fullnameOverride);
+  const n = v.nameOverride || chart.name;
+  return _trunc63(release.includes(n) ? release : release + "-" + n);
+}
+export function serviceName(release, values) {
+  return fullname(release, values);
+}
+"#,
+    );
+
+    (dts, js)
+}
+
 /// A named schema extracted from the JSON Schema tree.
 struct ExtractedSchema {
     info: SchemaInfo,
@@ -35,7 +121,15 @@ struct ExtractedSchema {
 
 /// Extract schemas from a JSON Schema, creating separate named types for
 /// nested objects with their own properties (same pattern as CRD/OpenAPI).
-fn extract_schemas(schema: &serde_json::Value, name: &str, out: &mut Vec<ExtractedSchema>) {
+///
+/// `scope` prefixes the names of nested types; it is empty at the chart root
+/// and set to the subchart's type name inside a composed subchart.
+fn extract_schemas(
+    schema: &serde_json::Value,
+    name: &str,
+    scope: &str,
+    out: &mut Vec<ExtractedSchema>,
+) {
     let properties = match schema.get("properties").and_then(|p| p.as_object()) {
         Some(p) => p,
         None => return,
@@ -70,7 +164,7 @@ fn extract_schemas(schema: &serde_json::Value, name: &str, out: &mut Vec<Extract
     let mut props = Vec::new();
 
     for (prop_name, prop_schema) in properties {
-        let ts_type = resolve_json_schema_type(prop_name, prop_schema, &def_map, out);
+        let ts_type = resolve_json_schema_type(prop_name, prop_schema, scope, &def_map, out);
         let description = prop_schema
             .get("description")
             .and_then(|d| d.as_str())
@@ -87,12 +181,12 @@ fn extract_schemas(schema: &serde_json::Value, name: &str, out: &mut Vec<Extract
     // Extract definitions as their own schemas too
     if let Some(defs) = defs {
         for (def_name, def_schema) in defs {
-            let ts_name = to_pascal_case(def_name);
+            let ts_name = format!("{scope}{}", to_pascal_case(def_name));
             // Only extract if it's an object with properties and not already extracted
             if def_schema.get("properties").is_some()
                 && !out.iter().any(|e| e.info.ts_name == ts_name)
             {
-                extract_schemas(def_schema, &ts_name, out);
+                extract_schemas(def_schema, &ts_name, scope, out);
             }
         }
     }
@@ -118,7 +212,8 @@ fn extract_schemas(schema: &serde_json::Value, name: &str, out: &mut Vec<Extract
     let spec_props: Vec<PropertyInfo> = properties
         .iter()
         .map(|(prop_name, prop_schema)| {
-            let ts_type = resolve_json_schema_type_for_spec(prop_name, prop_schema, &def_map, out);
+            let ts_type =
+                resolve_json_schema_type_for_spec(prop_name, prop_schema, scope, &def_map, out);
             let description = prop_schema
                 .get("description")
                 .and_then(|d| d.as_str())
@@ -149,6 +244,7 @@ fn extract_schemas(schema: &serde_json::Value, name: &str, out: &mut Vec<Extract
 fn resolve_json_schema_type(
     prop_name: &str,
     schema: &serde_json::Value,
+    scope: &str,
     def_map: &HashMap<String, &serde_json::Value>,
     out: &mut Vec<ExtractedSchema>,
 ) -> TsType {
@@ -156,16 +252,16 @@ fn resolve_json_schema_type(
     if let Some(ref_str) = schema.get("$ref").and_then(|r| r.as_str()) {
         if let Some(def) = def_map.get(ref_str) {
             let ref_name = ref_str.rsplit('/').next().unwrap_or(ref_str);
-            let ts_name = to_pascal_case(ref_name);
+            let ts_name = format!("{scope}{}", to_pascal_case(ref_name));
             if def.get("properties").is_some() && !out.iter().any(|e| e.info.ts_name == ts_name) {
-                extract_schemas(def, &ts_name, out);
+                extract_schemas(def, &ts_name, scope, out);
             }
             if def.get("properties").is_some() {
                 return TsType::Ref(ts_name);
             }
         }
         let ref_name = ref_str.rsplit('/').next().unwrap_or(ref_str);
-        return TsType::Ref(to_pascal_case(ref_name));
+        return TsType::Ref(format!("{scope}{}", to_pascal_case(ref_name)));
     }
 
     // Handle enum (string literals)
@@ -189,7 +285,7 @@ fn resolve_json_schema_type(
         "array" => {
             let items_type = schema
                 .get("items")
-                .map(|items| resolve_json_schema_type(prop_name, items, def_map, out))
+                .map(|items| resolve_json_schema_type(prop_name, items, scope, def_map, out))
                 .unwrap_or(TsType::Any);
             TsType::Array(Box::new(items_type))
         }
@@ -198,14 +294,19 @@ fn resolve_json_schema_type(
                 if additional.is_boolean() {
                     return TsType::Map(Box::new(TsType::Any));
                 }
-                let val_type = resolve_json_schema_type(prop_name, additional, def_map, out);
+                let val_type = resolve_json_schema_type(prop_name, additional, scope, def_map, out);
                 return TsType::Map(Box::new(val_type));
             }
             if schema.get("properties").is_some() {
                 // Nested object with properties → extract as named schema
-                let ts_name = to_pascal_case(prop_name);
+                let ts_name = format!("{scope}{}", to_pascal_case(prop_name));
+                let child_scope = if schema.get(SUBCHART_KEY).is_some() {
+                    ts_name.as_str()
+                } else {
+                    scope
+                };
                 if !out.iter().any(|e| e.info.ts_name == ts_name) {
-                    extract_schemas(schema, &ts_name, out);
+                    extract_schemas(schema, &ts_name, child_scope, out);
                 }
                 return TsType::Ref(ts_name);
             }
@@ -221,13 +322,14 @@ fn resolve_json_schema_type(
 fn resolve_json_schema_type_for_spec(
     prop_name: &str,
     schema: &serde_json::Value,
+    scope: &str,
     def_map: &HashMap<String, &serde_json::Value>,
     out: &mut Vec<ExtractedSchema>,
 ) -> TsType {
     // Handle $ref
     if let Some(ref_str) = schema.get("$ref").and_then(|r| r.as_str()) {
         let ref_name = ref_str.rsplit('/').next().unwrap_or(ref_str);
-        let ts_name = to_pascal_case(ref_name);
+        let ts_name = format!("{scope}{}", to_pascal_case(ref_name));
         let spec_name = format!("{ts_name}Spec");
         if out.iter().any(|e| e.info.ts_name == spec_name) {
             return TsType::Ref(spec_name);
@@ -243,7 +345,9 @@ fn resolve_json_schema_type_for_spec(
         "array" => {
             let items_type = schema
                 .get("items")
-                .map(|items| resolve_json_schema_type_for_spec(prop_name, items, def_map, out))
+                .map(|items| {
+                    resolve_json_schema_type_for_spec(prop_name, items, scope, def_map, out)
+                })
                 .unwrap_or(TsType::Any);
             TsType::Array(Box::new(items_type))
         }
@@ -253,11 +357,11 @@ fn resolve_json_schema_type_for_spec(
                     return TsType::Map(Box::new(TsType::Any));
                 }
                 let val_type =
-                    resolve_json_schema_type_for_spec(prop_name, additional, def_map, out);
+                    resolve_json_schema_type_for_spec(prop_name, additional, scope, def_map, out);
                 return TsType::Map(Box::new(val_type));
             }
             if schema.get("properties").is_some() {
-                let ts_name = to_pascal_case(prop_name);
+                let ts_name = format!("{scope}{}", to_pascal_case(prop_name));
                 let spec_name = format!("{ts_name}Spec");
                 if out.iter().any(|e| e.info.ts_name == spec_name) {
                     return TsType::Ref(spec_name);
@@ -635,6 +739,55 @@ mod tests {
         assert!(dts.contains("optional?: string;"));
     }
 
+    #[test]
+    fn subchart_types_are_scoped() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "image": {
+                    "type": "object",
+                    "properties": { "tag": { "type": "string" } }
+                },
+                "global": {
+                    "type": "object",
+                    "properties": { "imageRegistry": { "type": "string" } }
+                },
+                "postgresql": {
+                    "type": "object",
+                    "x-husako-subchart": "postgresql",
+                    "properties": {
+                        "image": {
+                            "type": "object",
+                            "properties": { "digest": { "type": "string" } }
+                        },
+                        "auth": {
+                            "type": "object",
+                            "properties": { "database": { "type": "string" } }
+                        }
+                    }
+                }
+            }
+        });
+
+        let (dts, js) = generate_chart_types("app", &schema).unwrap();
+
+        // Parent and subchart `image` no longer collide
+        assert!(dts.contains("export interface ImageSpec"));
+        assert!(dts.contains("export interface PostgresqlImageSpec"));
+        assert!(dts.contains("digest?: string;"));
+        assert!(dts.contains("export interface PostgresqlAuthSpec"));
+
+        // Subchart root is a nested builder on the parent
+        assert!(dts.contains("postgresql(value: Postgresql): this;"));
+        assert!(dts.contains("export interface Postgresql extends _SchemaBuilder"));
+        assert!(dts.contains("image(value: PostgresqlImage): this;"));
+        assert!(js.contains("export function Postgresql() { return new _Postgresql(); }"));
+
+        // Shared global stays at the root scope
+        assert!(dts.contains("global(value: Global): this;"));
+        assert!(dts.contains("export interface GlobalSpec"));
+    }
+
     #[test]
     fn snapshot_basic_chart() {
         let schema = json!({
@@ -666,4 +819,18 @@ mod tests {
         insta::assert_snapshot!("chart_basic_dts", dts);
         insta::assert_snapshot!("chart_basic_js", js);
     }
+
+    #[test]
+    fn chart_refs_follow_fullname_convention() {
+        let (dts, js) = generate_chart_refs("ingress-nginx", Some("4.11.0"), None);
+        assert!(dts.contains(
+            r#"export declare const chart: { readonly name: "ingress-nginx"; readonly version: "4.11.0"; readonly appVersion: undefined };"#
+        ));
+        assert!(dts.contains("export function serviceName(release: string"));
+        assert!(js.contains(
+            r#"export const chart = { name: "ingress-nginx", version: "4.11.0", appVersion: undefined };"#
+        ));
+        assert!(js.contains("release.includes(n) ? release : release + \"-\" + n"));
+        assert!(js.contains("export function fullname(release, values)"));
+    }
 }
@@ -1,11 +1,13 @@
 mod emitter;
 pub mod json_schema;
 mod schema;
+pub mod schema_bin;
 pub mod schema_store;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use schema::{SchemaInfo, SchemaLocation};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, thiserror::Error)]
 pub enum DtsError {
@@ -26,23 +28,94 @@ pub struct GenerateOptions {
 pub struct GenerateResult {
     /// Map of relative file path to file content.
     pub files: HashMap<String, String>,
+    /// Every group-version (e.g. "apps/v1", "core/v1") in the specs, including
+    /// those whose files were not emitted.
+    pub group_versions: BTreeSet<String>,
+    /// `_schema.json` in the indexed binary form (see [`schema_bin`]), to be
+    /// written as `k8s/_schema.bin`.
+    pub schema_bin: Vec<u8>,
 }
 
-pub fn generate(options: &GenerateOptions) -> Result<GenerateResult, DtsError> {
-    let mut files = HashMap::new();
+/// What one schema source contributes to the k8s types. Kept between runs
+/// so that a changed source only costs its own group-versions and
+/// `_schema.json` fragment (see [`generate_from_sources`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceOutput {
+    /// Group-versions the source has schemas in.
+    pub group_versions: BTreeSet<String>,
+    /// Type names the source places in `_common`.
+    pub common: BTreeSet<String>,
+    /// The source's `_schema.json` fragment.
+    pub schema: serde_json::Value,
+    /// The source's specs, for re-emitting group-versions shared with another
+    /// source or after `_common` changed.
+    pub specs: HashMap<String, serde_json::Value>,
+}
 
-    // Parse all specs into SchemaInfo
-    let mut all_schemas: Vec<SchemaInfo> = Vec::new();
-    for spec in options.specs.values() {
-        all_schemas.extend(schema::parse_spec(spec));
+impl SourceOutput {
+    pub fn new(specs: HashMap<String, serde_json::Value>) -> Self {
+        let schemas = parse_schemas(specs.values());
+        Self {
+            group_versions: schemas.iter().filter_map(group_version).collect(),
+            common: schemas
+                .iter()
+                .filter(|s| s.location == SchemaLocation::Common)
+                .map(|s| s.ts_name.clone())
+                .collect(),
+            schema: schema_store::fragment(&specs),
+            specs,
+        }
+    }
+}
+
+pub fn generate(options: &GenerateOptions) -> Result<GenerateResult, DtsError> {
+    let all_schemas = parse_schemas(options.specs.values());
+    if all_schemas.is_empty() {
+        return Err(DtsError::NoSchemas(
+            "no schemas found in any provided spec".to_string(),
+        ));
     }
 
+    let mut result = emit(&all_schemas, None);
+    let schema_store = schema_store::generate_schema_store(&options.specs);
+    insert_schema_store(&mut result, &schema_store)?;
+    Ok(result)
+}
+
+/// Generate from the outputs of each schema source, merged in order: like
+/// [`generate`] on their combined specs, with later sources winning for the
+/// same discovery path.
+///
+/// With `only`, just those group-versions are emitted besides `_common` and
+/// `_schema.json`, for when the rest are unchanged on disk.
+pub fn generate_from_sources(
+    sources: &[&SourceOutput],
+    only: Option<&BTreeSet<String>>,
+) -> Result<GenerateResult, DtsError> {
+    let mut specs: HashMap<&String, &serde_json::Value> = HashMap::new();
+    for source in sources {
+        specs.extend(&source.specs);
+    }
+    let all_schemas = parse_schemas(specs.into_values());
     if all_schemas.is_empty() {
         return Err(DtsError::NoSchemas(
             "no schemas found in any provided spec".to_string(),
         ));
     }
 
+    let mut result = emit(&all_schemas, only);
+    let schema_store = schema_store::merge_fragments(sources.iter().map(|s| &s.schema));
+    insert_schema_store(&mut result, &schema_store)?;
+    Ok(result)
+}
+
+/// Parse, deduplicate and place the schemas of `specs`.
+fn parse_schemas<'a>(specs: impl Iterator<Item = &'a serde_json::Value>) -> Vec<SchemaInfo> {
+    let mut all_schemas: Vec<SchemaInfo> = Vec::new();
+    for spec in specs {
+        all_schemas.extend(schema::parse_spec(spec));
+    }
+
     // Deduplicate schemas by full_name. Each schema can appear in multiple
     // spec files (e.g. io.k8s.api.core.v1.PersistentVolumeClaim appears in
     // both api/v1 and apis/apps/v1 specs). Keep the entry that has a GVK when
@@ -82,6 +155,21 @@ pub fn generate(options: &GenerateOptions) -> Result<GenerateResult, DtsError> {
         }
     }
 
+    all_schemas
+}
+
+/// `"<group>/<version>"` of a schema placed in a group-version.
+fn group_version(schema: &SchemaInfo) -> Option<String> {
+    match &schema.location {
+        SchemaLocation::GroupVersion { group, version } => Some(format!("{group}/{version}")),
+        _ => None,
+    }
+}
+
+/// Emit `_common` and the group-version files (those in `only`, when set).
+fn emit(all_schemas: &[SchemaInfo], only: Option<&BTreeSet<String>>) -> GenerateResult {
+    let mut files = HashMap::new();
+
     // Separate common vs group-version schemas
     let common: Vec<&SchemaInfo> = all_schemas
         .iter()
@@ -109,7 +197,7 @@ pub fn generate(options: &GenerateOptions) -> Result<GenerateResult, DtsError> {
 
     // Group schemas by (group, version)
     let mut by_gv: HashMap<(String, String), Vec<&SchemaInfo>> = HashMap::new();
-    for schema in &all_schemas {
+    for schema in all_schemas {
         if let SchemaLocation::GroupVersion { group, version } = &schema.location {
             by_gv
                 .entry((group.clone(), version.clone()))
@@ -117,9 +205,16 @@ pub fn generate(options: &GenerateOptions) -> Result<GenerateResult, DtsError> {
                 .push(schema);
         }
     }
+    let group_versions = by_gv
+        .keys()
+        .map(|(group, version)| format!("{group}/{version}"))
+        .collect();
 
     // Emit per-group-version .d.ts and .js files
     for ((group, version), schemas) in &by_gv {
+        if only.is_some_and(|only| !only.contains(&format!("{group}/{version}"))) {
+            continue;
+        }
         let dts_content = emitter::emit_group_version(schemas, &common_names);
         let dts_path = format!("k8s/{group}/{version}.d.ts");
         files.insert(dts_path, dts_content);
@@ -134,13 +229,25 @@ pub fn generate(options: &GenerateOptions) -> Result<GenerateResult, DtsError> {
         }
     }
 
-    // Generate _schema.json
-    let schema_store = schema_store::generate_schema_store(&options.specs);
-    let schema_json = serde_json::to_string_pretty(&schema_store)
-        .map_err(|e| DtsError::Schema(format!("serialize _schema.json: {e}")))?;
-    files.insert("k8s/_schema.json".to_string(), schema_json);
+    GenerateResult {
+        files,
+        group_versions,
+        schema_bin: Vec::new(),
+    }
+}
 
-    Ok(GenerateResult { files })
+/// Add `_schema.json` and its binary form to the generated files.
+fn insert_schema_store(
+    result: &mut GenerateResult,
+    schema_store: &serde_json::Value,
+) -> Result<(), DtsError> {
+    let schema_json = serde_json::to_string_pretty(schema_store)
+        .map_err(|e| DtsError::Schema(format!("serialize _schema.json: {e}")))?;
+    result
+        .files
+        .insert("k8s/_schema.json".to_string(), schema_json);
+    result.schema_bin = schema_bin::encode(schema_store)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -286,4 +393,64 @@ mod tests {
         assert!(js.contains("class _Cluster"));
         assert!(js.contains("\"postgresql.cnpg.io/v1\""));
     }
+
+    #[test]
+    fn generate_from_sources_emits_only_selected_group_versions() {
+        let crd = json!({
+            "components": {
+                "schemas": {
+                    "io.cnpg.postgresql.v1.Cluster": {
+                        "properties": {"apiVersion": {"type": "string"}},
+                        "x-kubernetes-group-version-kind": [
+                            {"group": "postgresql.cnpg.io", "version": "v1", "kind": "Cluster"}
+                        ]
+                    }
+                }
+            }
+        });
+        let apps = SourceOutput::new(HashMap::from([(
+            "apis/apps/v1".to_string(),
+            mock_apps_v1_spec(),
+        )]));
+        let cnpg = SourceOutput::new(HashMap::from([(
+            "apis/postgresql.cnpg.io/v1".to_string(),
+            crd,
+        )]));
+        assert_eq!(apps.group_versions, BTreeSet::from(["apps/v1".to_string()]));
+        assert!(apps.common.contains("ObjectMeta"));
+        assert!(cnpg.common.is_empty());
+
+        let only = BTreeSet::from(["postgresql.cnpg.io/v1".to_string()]);
+        let result = generate_from_sources(&[&apps, &cnpg], Some(&only)).unwrap();
+        let mut paths: Vec<_> = result.files.keys().map(String::as_str).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "k8s/_common.d.ts",
+                "k8s/_schema.json",
+                "k8s/postgresql.cnpg.io/v1.d.ts",
+                "k8s/postgresql.cnpg.io/v1.js",
+            ]
+        );
+        assert_eq!(
+            result.group_versions,
+            BTreeSet::from(["apps/v1".to_string(), "postgresql.cnpg.io/v1".to_string()])
+        );
+        let schema: serde_json::Value =
+            serde_json::from_str(&result.files["k8s/_schema.json"]).unwrap();
+        assert!(schema["gvk_index"]["apps/v1:Deployment"].is_string());
+        assert!(schema["gvk_index"]["postgresql.cnpg.io/v1:Cluster"].is_string());
+
+        // Without a selection, the same files as generating from merged specs
+        let all = generate_from_sources(&[&apps, &cnpg], None).unwrap();
+        let specs = apps
+            .specs
+            .clone()
+            .into_iter()
+            .chain(cnpg.specs.clone())
+            .collect();
+        let merged = generate(&GenerateOptions { specs }).unwrap();
+        assert_eq!(all.files, merged.files);
+    }
 }
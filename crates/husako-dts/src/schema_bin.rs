@@ -0,0 +1,376 @@
+//! Indexed binary form of `_schema.json`, written next to it as
+//! `_schema.bin`.
+//!
+//! Reading `_schema.json` means parsing every schema of every group-version,
+//! while validating a build only needs the few its kinds reach. The binary
+//! form keeps each schema as its own minified JSON, behind sorted indexes of
+//! GVK keys and schema names, so a reader memory-maps the file and decodes
+//! schemas one at a time on lookup.
+//!
+//! Layout (integers are little-endian `u32`):
+//!
+//! ```text
+//! magic "HSKB" | format version | gvk count | schema count
+//! gvk entries, sorted by key:      key offset | key length | schema index
+//! schema entries, sorted by name:  name offset | name length | data offset | data length
+//! data: keys, names and schema JSON, offsets relative to its start
+//! ```
+
+use std::fs::File;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::DtsError;
+
+const MAGIC: &[u8; 4] = b"HSKB";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 16;
+const GVK_ENTRY_LEN: usize = 12;
+const SCHEMA_ENTRY_LEN: usize = 16;
+
+/// Encode `_schema.json` content (as built by
+/// [`crate::schema_store::merge_fragments`]) in the binary form.
+pub fn encode(schema_store: &Value) -> Result<Vec<u8>, DtsError> {
+    let empty = serde_json::Map::new();
+    let schemas = schema_store
+        .get("schemas")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let gvk_index = schema_store
+        .get("gvk_index")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    let mut names: Vec<&String> = schemas.keys().collect();
+    names.sort();
+    let mut keys: Vec<(&String, usize)> = gvk_index
+        .iter()
+        .filter_map(|(key, name)| {
+            let name = name.as_str()?;
+            let idx = names.binary_search_by(|n| n.as_str().cmp(name)).ok()?;
+            Some((key, idx))
+        })
+        .collect();
+    keys.sort();
+
+    let mut data = Vec::new();
+    let mut push = |bytes: &[u8]| -> Result<(u32, u32), DtsError> {
+        let range = (to_u32(data.len())?, to_u32(bytes.len())?);
+        data.extend_from_slice(bytes);
+        Ok(range)
+    };
+
+    let mut gvk_entries = Vec::with_capacity(keys.len() * GVK_ENTRY_LEN);
+    for (key, idx) in &keys {
+        let (off, len) = push(key.as_bytes())?;
+        for n in [off, len, to_u32(*idx)?] {
+            gvk_entries.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+
+    let mut schema_entries = Vec::with_capacity(names.len() * SCHEMA_ENTRY_LEN);
+    for name in &names {
+        let json = serde_json::to_vec(&schemas[name.as_str()])
+            .map_err(|e| DtsError::Schema(format!("serialize schema {name}: {e}")))?;
+        let (name_off, name_len) = push(name.as_bytes())?;
+        let (data_off, data_len) = push(&json)?;
+        for n in [name_off, name_len, data_off, data_len] {
+            schema_entries.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+
+    let mut out =
+        Vec::with_capacity(HEADER_LEN + gvk_entries.len() + schema_entries.len() + data.len());
+    out.extend_from_slice(MAGIC);
+    for n in [FORMAT_VERSION, to_u32(keys.len())?, to_u32(names.len())?] {
+        out.extend_from_slice(&n.to_le_bytes());
+    }
+    out.extend_from_slice(&gvk_entries);
+    out.extend_from_slice(&schema_entries);
+    out.extend_from_slice(&data);
+    Ok(out)
+}
+
+fn to_u32(n: usize) -> Result<u32, DtsError> {
+    u32::try_from(n).map_err(|_| DtsError::Schema("_schema.bin exceeds 4 GiB".to_string()))
+}
+
+enum Bytes {
+    Mapped(memmap2::Mmap),
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Mapped(map) => map,
+            Bytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// A `_schema.bin` opened for lookups. Nothing is decoded up front; the
+/// header and index bounds are checked once so lookups can't go out of
+/// range.
+pub struct BinarySchemaStore {
+    bytes: Bytes,
+    gvk_count: usize,
+    schema_count: usize,
+}
+
+impl std::fmt::Debug for BinarySchemaStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BinarySchemaStore")
+            .field("gvk_count", &self.gvk_count)
+            .field("schema_count", &self.schema_count)
+            .finish()
+    }
+}
+
+impl BinarySchemaStore {
+    /// Memory-map the file at `path`.
+    ///
+    /// `husako gen` replaces the file rather than writing into it, so a map
+    /// stays valid while types are regenerated.
+    pub fn open(path: &Path) -> Result<Self, DtsError> {
+        let file =
+            File::open(path).map_err(|e| DtsError::Io(format!("open {}: {e}", path.display())))?;
+        // SAFETY: the file is only ever replaced by rename, never modified in
+        // place, so the mapped pages don't change under us.
+        let map = unsafe { memmap2::Mmap::map(&file) }
+            .map_err(|e| DtsError::Io(format!("map {}: {e}", path.display())))?;
+        Self::new(Bytes::Mapped(map))
+    }
+
+    /// Read from bytes in memory, e.g. the output of [`encode`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, DtsError> {
+        Self::new(Bytes::Owned(bytes))
+    }
+
+    fn new(bytes: Bytes) -> Result<Self, DtsError> {
+        let invalid = |what: &str| DtsError::Schema(format!("invalid _schema.bin: {what}"));
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(invalid("bad header"));
+        }
+        let version = read_u32(&bytes, 4);
+        if version != FORMAT_VERSION {
+            return Err(invalid(&format!("unsupported format version {version}")));
+        }
+        let store = Self {
+            gvk_count: read_u32(&bytes, 8) as usize,
+            schema_count: read_u32(&bytes, 12) as usize,
+            bytes,
+        };
+
+        let data_start = store
+            .gvk_count
+            .checked_mul(GVK_ENTRY_LEN)
+            .and_then(|n| n.checked_add(store.schema_count.checked_mul(SCHEMA_ENTRY_LEN)?))
+            .and_then(|n| n.checked_add(HEADER_LEN))
+            .filter(|&n| n <= store.bytes.len())
+            .ok_or_else(|| invalid("truncated index"))?;
+        let data_len = store.bytes.len() - data_start;
+        let fits = |off: u32, len: u32| (off as usize).saturating_add(len as usize) <= data_len;
+        for i in 0..store.gvk_count {
+            let e = store.gvk_entry(i);
+            if !fits(read_u32(&store.bytes, e), read_u32(&store.bytes, e + 4))
+                || read_u32(&store.bytes, e + 8) as usize >= store.schema_count
+            {
+                return Err(invalid("gvk entry out of range"));
+            }
+        }
+        for i in 0..store.schema_count {
+            let e = store.schema_entry(i);
+            if !fits(read_u32(&store.bytes, e), read_u32(&store.bytes, e + 4))
+                || !fits(
+                    read_u32(&store.bytes, e + 8),
+                    read_u32(&store.bytes, e + 12),
+                )
+            {
+                return Err(invalid("schema entry out of range"));
+            }
+        }
+        Ok(store)
+    }
+
+    /// Number of schemas, i.e. the bound of the indexes [`Self::schema`]
+    /// returns.
+    pub fn len(&self) -> usize {
+        self.schema_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schema_count == 0
+    }
+
+    /// Index of the schema for a `"<apiVersion>:<kind>"` key.
+    pub fn gvk(&self, key: &str) -> Option<usize> {
+        let i = binary_search(self.gvk_count, |i| {
+            let e = self.gvk_entry(i);
+            self.data(read_u32(&self.bytes, e), read_u32(&self.bytes, e + 4))
+                .cmp(key.as_bytes())
+        })?;
+        Some(read_u32(&self.bytes, self.gvk_entry(i) + 8) as usize)
+    }
+
+    /// Index of the schema named `name`.
+    pub fn schema(&self, name: &str) -> Option<usize> {
+        binary_search(self.schema_count, |i| {
+            self.name_bytes(i).cmp(name.as_bytes())
+        })
+    }
+
+    /// Name of the schema at `idx`.
+    pub fn name(&self, idx: usize) -> &str {
+        std::str::from_utf8(self.name_bytes(idx)).unwrap_or_default()
+    }
+
+    /// Minified JSON of the schema at `idx`.
+    pub fn schema_bytes(&self, idx: usize) -> &[u8] {
+        let e = self.schema_entry(idx);
+        self.data(read_u32(&self.bytes, e + 8), read_u32(&self.bytes, e + 12))
+    }
+
+    /// Decode the schema at `idx`.
+    pub fn decode(&self, idx: usize) -> Result<Value, DtsError> {
+        serde_json::from_slice(self.schema_bytes(idx)).map_err(|e| {
+            DtsError::Schema(format!(
+                "decode schema {} from _schema.bin: {e}",
+                self.name(idx)
+            ))
+        })
+    }
+
+    fn name_bytes(&self, idx: usize) -> &[u8] {
+        let e = self.schema_entry(idx);
+        self.data(read_u32(&self.bytes, e), read_u32(&self.bytes, e + 4))
+    }
+
+    fn gvk_entry(&self, i: usize) -> usize {
+        HEADER_LEN + i * GVK_ENTRY_LEN
+    }
+
+    fn schema_entry(&self, i: usize) -> usize {
+        HEADER_LEN + self.gvk_count * GVK_ENTRY_LEN + i * SCHEMA_ENTRY_LEN
+    }
+
+    fn data(&self, off: u32, len: u32) -> &[u8] {
+        let start = HEADER_LEN
+            + self.gvk_count * GVK_ENTRY_LEN
+            + self.schema_count * SCHEMA_ENTRY_LEN
+            + off as usize;
+        &self.bytes[start..start + len as usize]
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Index in `0..len` for which `cmp` returns `Equal`, given entries sorted
+/// by it.
+fn binary_search(len: usize, cmp: impl Fn(usize) -> std::cmp::Ordering) -> Option<usize> {
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match cmp(mid) {
+            std::cmp::Ordering::Less => lo = mid + 1,
+            std::cmp::Ordering::Greater => hi = mid,
+            std::cmp::Ordering::Equal => return Some(mid),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({
+            "version": 2,
+            "gvk_index": {
+                "apps/v1:Deployment": "io.k8s.api.apps.v1.Deployment",
+                "v1:ConfigMap": "io.k8s.api.core.v1.ConfigMap",
+                "v1:Dangling": "io.k8s.api.core.v1.Missing"
+            },
+            "schemas": {
+                "io.k8s.api.apps.v1.Deployment": {
+                    "type": "object",
+                    "properties": {"spec": {"$ref": "io.k8s.api.apps.v1.DeploymentSpec"}}
+                },
+                "io.k8s.api.apps.v1.DeploymentSpec": {
+                    "type": "object",
+                    "properties": {"replicas": {"type": "integer"}}
+                },
+                "io.k8s.api.core.v1.ConfigMap": {"type": "object"}
+            }
+        })
+    }
+
+    #[test]
+    fn round_trip_lookups() {
+        let value = sample();
+        let store = BinarySchemaStore::from_bytes(encode(&value).unwrap()).unwrap();
+        assert_eq!(store.len(), 3);
+
+        let idx = store.gvk("apps/v1:Deployment").unwrap();
+        assert_eq!(store.name(idx), "io.k8s.api.apps.v1.Deployment");
+        assert_eq!(
+            store.decode(idx).unwrap(),
+            value["schemas"]["io.k8s.api.apps.v1.Deployment"]
+        );
+
+        let idx = store.schema("io.k8s.api.apps.v1.DeploymentSpec").unwrap();
+        assert_eq!(
+            store.decode(idx).unwrap(),
+            value["schemas"]["io.k8s.api.apps.v1.DeploymentSpec"]
+        );
+
+        assert!(store.gvk("v1:ConfigMap").is_some());
+        assert!(store.gvk("v1:Secret").is_none());
+        assert!(store.schema("io.k8s.api.core.v1.Secret").is_none());
+    }
+
+    #[test]
+    fn gvk_to_missing_schema_dropped() {
+        let store = BinarySchemaStore::from_bytes(encode(&sample()).unwrap()).unwrap();
+        assert!(store.gvk("v1:Dangling").is_none());
+    }
+
+    #[test]
+    fn empty_store() {
+        let store = BinarySchemaStore::from_bytes(encode(&json!({"version": 2})).unwrap()).unwrap();
+        assert!(store.is_empty());
+        assert!(store.gvk("v1:ConfigMap").is_none());
+    }
+
+    #[test]
+    fn open_mapped_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("_schema.bin");
+        std::fs::write(&path, encode(&sample()).unwrap()).unwrap();
+        let store = BinarySchemaStore::open(&path).unwrap();
+        assert!(store.gvk("apps/v1:Deployment").is_some());
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        assert!(BinarySchemaStore::from_bytes(b"JSON{}".to_vec()).is_err());
+        let mut bytes = encode(&sample()).unwrap();
+        bytes[4] = 9;
+        assert!(BinarySchemaStore::from_bytes(bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let bytes = encode(&sample()).unwrap();
+        for len in [HEADER_LEN, HEADER_LEN + 20, bytes.len() - 1] {
+            assert!(BinarySchemaStore::from_bytes(bytes[..len].to_vec()).is_err());
+        }
+    }
+}
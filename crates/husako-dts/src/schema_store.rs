@@ -11,10 +11,18 @@ const REF_PREFIX: &str = "#/components/schemas/";
 /// annotates the Quantity schema with `"format": "quantity"`, and builds a
 /// GVK index from `x-kubernetes-group-version-kind` annotations.
 pub fn generate_schema_store(specs: &HashMap<String, Value>) -> Value {
+    merge_fragments([&fragment(specs)])
+}
+
+/// The part of `_schema.json` contributed by one source's specs: its schemas
+/// with simplified `$ref` values and its GVK index entries.
+///
+/// Fragments are kept per schema source and combined with [`merge_fragments`],
+/// so a changed source only needs its own fragment rebuilt.
+pub fn fragment(specs: &HashMap<String, Value>) -> Value {
     let mut schemas = serde_json::Map::new();
     let mut gvk_index = serde_json::Map::new();
 
-    // 1. Collect all schemas from all specs
     for spec in specs.values() {
         if let Some(spec_schemas) = spec
             .get("components")
@@ -50,6 +58,29 @@ pub fn generate_schema_store(specs: &HashMap<String, Value>) -> Value {
         }
     }
 
+    serde_json::json!({
+        "gvk_index": gvk_index,
+        "schemas": schemas
+    })
+}
+
+/// Combine [`fragment`]s into `_schema.json` content. Later fragments win
+/// for schemas and GVKs defined more than once.
+pub fn merge_fragments<'a>(fragments: impl IntoIterator<Item = &'a Value>) -> Value {
+    let mut schemas = serde_json::Map::new();
+    let mut gvk_index = serde_json::Map::new();
+
+    // 1. Collect all schemas and GVK index entries from all fragments
+    for fragment in fragments {
+        for (field, merged) in [("schemas", &mut schemas), ("gvk_index", &mut gvk_index)] {
+            if let Some(entries) = fragment.get(field).and_then(Value::as_object) {
+                for (name, value) in entries {
+                    merged.insert(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+
     // 2. Annotate Quantity schema with format: "quantity"
     if let Some(quantity) = schemas.get_mut(QUANTITY_SCHEMA_NAME)
         && let Some(obj) = quantity.as_object_mut()
@@ -315,4 +346,40 @@ mod tests {
         let formatted = serde_json::to_string_pretty(&result).unwrap();
         insta::assert_snapshot!("schema_store_json", formatted);
     }
+
+    #[test]
+    fn merged_fragments_match_combined_specs() {
+        let deployments = HashMap::from([("apis/apps/v1".to_string(), deployment_spec())]);
+        let namespaces = HashMap::from([("api/v1".to_string(), namespace_spec())]);
+        let combined: HashMap<String, Value> = deployments
+            .clone()
+            .into_iter()
+            .chain(namespaces.clone())
+            .collect();
+
+        let merged = merge_fragments([&fragment(&deployments), &fragment(&namespaces)]);
+        let expected = generate_schema_store(&combined);
+        assert_eq!(merged["gvk_index"], expected["gvk_index"]);
+        let names = |store: &Value| -> Vec<String> {
+            store["schemas"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect()
+        };
+        assert_eq!(names(&merged), names(&expected));
+        assert_eq!(
+            merged["schemas"]["io.k8s.apimachinery.pkg.api.resource.Quantity"]["format"],
+            "quantity"
+        );
+        // Later fragments win for shared schemas
+        assert_eq!(
+            merged["schemas"]["io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta"]["properties"]
+                .as_object()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
 }
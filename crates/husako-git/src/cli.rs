@@ -0,0 +1,252 @@
+//! Transport through the `git` binary.
+
+use std::path::Path;
+
+use crate::{GitError, ProgressCb, Target, store};
+
+pub(crate) fn list_tags(repo: &str) -> Result<Vec<String>, GitError> {
+    let output = std::process::Command::new("git")
+        .args(["ls-remote", "--tags", repo])
+        .output()
+        .map_err(|e| GitError::Io(format!("git ls-remote: {e}")))?;
+    if !output.status.success() {
+        return Err(GitError::Failed(format!(
+            "git ls-remote failed for '{repo}': {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(parse_ls_remote(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Commit `tag` of `url` points to, from `git ls-remote`.
+pub(crate) fn tag_commit(url: &str, tag: &str) -> Result<Option<String>, GitError> {
+    let refname = format!("refs/tags/{tag}");
+    let output = std::process::Command::new("git")
+        .args(["ls-remote", url, &refname, &format!("{refname}^{{}}")])
+        .output()
+        .map_err(|e| GitError::Io(format!("git ls-remote: {e}")))?;
+    if !output.status.success() {
+        return Err(GitError::Failed(format!(
+            "git ls-remote failed for '{url}': {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(parse_tag_commit(
+        &String::from_utf8_lossy(&output.stdout),
+        &refname,
+    ))
+}
+
+/// The commit of `refname` in `git ls-remote` output: the peeled `^{}` line
+/// of an annotated tag, otherwise the ref itself.
+fn parse_tag_commit(stdout: &str, refname: &str) -> Option<String> {
+    let mut commit = None;
+    for line in stdout.lines() {
+        let Some((id, name)) = line.split_once('\t') else {
+            continue;
+        };
+        match name.strip_prefix(refname) {
+            Some("^{}") => return Some(id.to_string()),
+            Some("") => commit = Some(id.to_string()),
+            _ => {}
+        }
+    }
+    commit
+}
+
+/// Tag names from `git ls-remote --tags` output. Annotated tags are listed
+/// twice (the tag and its peeled `^{}` commit) but returned once.
+fn parse_ls_remote(stdout: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for line in stdout.lines() {
+        let Some((_, refname)) = line.split_once('\t') else {
+            continue;
+        };
+        let tag = refname
+            .strip_prefix("refs/tags/")
+            .unwrap_or(refname)
+            .trim_end_matches("^{}");
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Shallow-fetch `target` from `url` into the object store at `store_dir`.
+/// Returns the commit SHA.
+pub(crate) async fn fetch_into_store(
+    url: &str,
+    target: &Target,
+    store_dir: &Path,
+    on_progress: Option<&ProgressCb>,
+) -> Result<String, GitError> {
+    store::open_or_init(store_dir)?;
+    let git_dir = store_dir.to_string_lossy();
+    let refspec = target.refspec();
+    run_git(
+        &[
+            "--git-dir",
+            &git_dir,
+            "fetch",
+            "--depth",
+            "1",
+            "--progress",
+            "--no-tags",
+            url,
+            &refspec,
+        ],
+        on_progress,
+    )
+    .await?;
+    store::peel_ref(store_dir, &target.local_ref())
+}
+
+/// Run a git command, streaming `--progress` output to `on_progress`.
+/// Returns the collected stderr on failure.
+async fn run_git(args: &[&str], on_progress: Option<&ProgressCb>) -> Result<(), GitError> {
+    let mut child = tokio::process::Command::new("git")
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| GitError::Io(format!("git: {e}")))?;
+
+    // Stream stderr for real-time progress; collect for error reporting.
+    // git --progress uses \r to update in-place, so we split on both \r and \n.
+    let mut stderr_text = String::new();
+    if let Some(stderr) = child.stderr.take() {
+        use tokio::io::AsyncBufReadExt;
+        let mut lines = tokio::io::BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            for part in line.split('\r') {
+                let part = part.trim();
+                if part.is_empty() {
+                    continue;
+                }
+                stderr_text.push_str(part);
+                stderr_text.push('\n');
+                if let (Some(cb), Some((pct, bytes))) = (on_progress, parse_git_progress(part)) {
+                    cb(bytes, None, Some(pct));
+                }
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| GitError::Io(format!("git: {e}")))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GitError::Failed(stderr_text.trim_end().to_string()))
+    }
+}
+
+/// Parse a git progress line like "Receiving objects:  45% (24430/54286), 53.29 MiB | ..."
+/// Returns `(pct, bytes_received)`.
+fn parse_git_progress(line: &str) -> Option<(u8, u64)> {
+    let rest = line.strip_prefix("Receiving objects:")?.trim();
+    let pct_end = rest.find('%')?;
+    let pct: u8 = rest[..pct_end].trim().parse().ok()?;
+
+    // Try to extract MiB value: ", 53.29 MiB | ..."
+    let bytes = if let Some(mib_pos) = rest.find(" MiB") {
+        let before = rest[..mib_pos].trim_end();
+        let num_start = before
+            .rfind(|c: char| !c.is_ascii_digit() && c != '.')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        before[num_start..]
+            .parse::<f64>()
+            .ok()
+            .map(|mib| (mib * 1_048_576.0) as u64)
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    Some((pct, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::make_repo;
+
+    #[test]
+    fn ls_remote_output() {
+        let stdout = "aaa\trefs/tags/v1.0.0\n\
+                      bbb\trefs/tags/v1.0.0^{}\n\
+                      ccc\trefs/tags/v1.1.0\n";
+        assert_eq!(parse_ls_remote(stdout), ["v1.0.0", "v1.1.0"]);
+    }
+
+    #[test]
+    fn tag_commit_prefers_peeled_commit() {
+        let stdout = "aaa\trefs/tags/v1.0.0\n\
+                      bbb\trefs/tags/v1.0.0^{}\n\
+                      ccc\trefs/tags/v1.1.0\n";
+        assert_eq!(
+            parse_tag_commit(stdout, "refs/tags/v1.0.0").as_deref(),
+            Some("bbb")
+        );
+        assert_eq!(
+            parse_tag_commit(stdout, "refs/tags/v1.1.0").as_deref(),
+            Some("ccc")
+        );
+        assert_eq!(parse_tag_commit(stdout, "refs/tags/v1"), None);
+    }
+
+    #[tokio::test]
+    async fn fetch_into_store_at_refs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo.git");
+        let commits = make_repo(
+            &repo,
+            &[
+                ("v1", &[("crds/a.yaml", "one")]),
+                ("v2", &[("crds/a.yaml", "two")]),
+            ],
+        );
+        let url = format!("file://{}", repo.display());
+        assert_eq!(list_tags(&url).unwrap(), ["v1", "v2"]);
+        let store_dir = tmp.path().join("store.git");
+
+        for (target, expected) in [
+            (Target::Tag("v1".into()), &commits[0]),
+            (Target::Branch("main".into()), &commits[1]),
+            (Target::Rev(commits[0].clone()), &commits[0]),
+            (Target::Head, &commits[1]),
+        ] {
+            let commit = fetch_into_store(&url, &target, &store_dir, None)
+                .await
+                .unwrap();
+            assert_eq!(&commit, expected, "{target}");
+        }
+    }
+
+    #[test]
+    fn parse_git_progress_with_bytes() {
+        let (pct, bytes) =
+            parse_git_progress("Receiving objects:  45% (24430/54286), 53.29 MiB | 10.00 MiB/s")
+                .unwrap();
+        assert_eq!(pct, 45);
+        assert_eq!(bytes, (53.29 * 1_048_576.0) as u64);
+    }
+
+    #[test]
+    fn parse_git_progress_no_bytes() {
+        let (pct, bytes) = parse_git_progress("Receiving objects: 100% (54286/54286)").unwrap();
+        assert_eq!(pct, 100);
+        assert_eq!(bytes, 0);
+    }
+
+    #[test]
+    fn parse_git_progress_non_matching_line() {
+        assert!(parse_git_progress("remote: Counting objects: 100% (54286/54286)").is_none());
+        assert!(parse_git_progress("Resolving deltas:  25% (100/400)").is_none());
+        assert!(parse_git_progress("").is_none());
+    }
+}
@@ -0,0 +1,460 @@
+//! Git access for husako: tag listing and shallow fetching of a single tree
+//! path at a tag, branch or commit.
+//!
+//! The `git` binary is used when it can be run, so the user's credential
+//! helpers and SSH setup apply. Without it the same operations run in
+//! process: local repositories are read directly and remote ones are fetched
+//! over HTTP(S) or SSH with a depth of one.
+//!
+//! Fetched revisions are kept in a per-remote object store under the cache
+//! directory (see [`fetch`]), so every path read from one repository at one
+//! revision costs a single fetch.
+
+mod cli;
+mod native;
+mod store;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex, OnceLock};
+
+use husako_config::FetchContext;
+use husako_config::GitRef;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    #[error("{0}")]
+    Failed(String),
+    #[error("path '{0}' not found")]
+    PathNotFound(String),
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
+/// Callback type for fetch progress reporting: `(bytes_received, total_bytes, pct_override)`.
+pub type ProgressCb = dyn Fn(u64, Option<u64>, Option<u8>) + Sync;
+
+/// How git operations reach a repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// The `git` binary on `PATH`.
+    Cli,
+    /// In process, without the `git` binary.
+    Native,
+}
+
+/// The transport in use: the `git` binary when it can be run, otherwise the
+/// in-process one. Checked once per process.
+pub fn transport() -> Transport {
+    static GIT_AVAILABLE: OnceLock<bool> = OnceLock::new();
+    let available = *GIT_AVAILABLE.get_or_init(|| {
+        std::process::Command::new("git")
+            .arg("--version")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    });
+    if available {
+        Transport::Cli
+    } else {
+        Transport::Native
+    }
+}
+
+/// Owned form of the ref to fetch, so the fetch can run on a blocking thread.
+#[derive(Debug, Clone)]
+pub(crate) enum Target {
+    Head,
+    Tag(String),
+    Branch(String),
+    Rev(String),
+}
+
+impl From<Option<GitRef<'_>>> for Target {
+    fn from(git_ref: Option<GitRef<'_>>) -> Self {
+        match git_ref {
+            None => Self::Head,
+            Some(GitRef::Tag(tag)) => Self::Tag(tag.to_string()),
+            Some(GitRef::Branch(branch)) => Self::Branch(branch.to_string()),
+            Some(GitRef::Rev(rev)) => Self::Rev(rev.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Head => write!(f, "HEAD"),
+            Self::Tag(tag) => write!(f, "tag {tag}"),
+            Self::Branch(branch) => write!(f, "branch {branch}"),
+            Self::Rev(rev) => write!(f, "rev {rev}"),
+        }
+    }
+}
+
+impl Target {
+    /// Branches and `HEAD` move, so a stored commit for them may be stale.
+    fn is_moving(&self) -> bool {
+        matches!(self, Self::Head | Self::Branch(_))
+    }
+
+    /// The ref the fetched commit is stored under in the object store.
+    fn local_ref(&self) -> String {
+        match self {
+            Self::Head => "refs/husako/HEAD".to_string(),
+            Self::Tag(tag) => format!("refs/tags/{tag}"),
+            Self::Branch(branch) => format!("refs/heads/{branch}"),
+            Self::Rev(rev) => format!("refs/husako/revs/{rev}"),
+        }
+    }
+
+    /// Fetch refspec from the remote into [`Target::local_ref`].
+    fn refspec(&self) -> String {
+        let local = self.local_ref();
+        match self {
+            Self::Head => format!("+HEAD:{local}"),
+            Self::Tag(tag) => format!("+refs/tags/{tag}:{local}"),
+            Self::Branch(branch) => format!("+refs/heads/{branch}:{local}"),
+            // Fetching by commit needs the full SHA, as with `git fetch`
+            Self::Rev(rev) => format!("{rev}:{local}"),
+        }
+    }
+}
+
+/// Tag names of `repo`, without the `refs/tags/` prefix, each listed once.
+///
+/// Intentionally sync, like the version discovery functions that call it.
+pub fn list_tags(repo: &str, ctx: &FetchContext) -> Result<Vec<String>, GitError> {
+    let repo = ctx.rewrite_url(repo);
+    match transport() {
+        Transport::Cli => cli::list_tags(&repo),
+        Transport::Native => native::list_tags(&repo),
+    }
+}
+
+/// Commit of a tag on a remote, by remote URL and tag.
+type RemoteTags = HashMap<(String, String), Option<String>>;
+
+/// Commits tags resolved to on their remotes since the last
+/// [`forget_moving_refs`].
+static REMOTE_TAGS: LazyLock<Mutex<RemoteTags>> = LazyLock::new(Default::default);
+
+/// Commit `tag` of `repo` points to, with annotated tags peeled; `None` when
+/// the repository has no such tag.
+///
+/// Asks the remote rather than the object store, so a tag moved since it was
+/// fetched is seen; the answer is kept until [`forget_moving_refs`], and a
+/// later [`fetch`] of the tag fetches it again if it has moved. Intentionally
+/// sync, like [`list_tags`].
+pub fn tag_commit(repo: &str, tag: &str, ctx: &FetchContext) -> Result<Option<String>, GitError> {
+    let repo = ctx.rewrite_url(repo);
+    let key = (repo, tag.to_string());
+    if let Some(commit) = REMOTE_TAGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key)
+    {
+        return Ok(commit.clone());
+    }
+    let commit = match transport() {
+        Transport::Cli => cli::tag_commit(&key.0, tag)?,
+        Transport::Native => native::tag_commit(&key.0, tag)?,
+    };
+    REMOTE_TAGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key, commit.clone());
+    Ok(commit)
+}
+
+/// Whether [`tag_commit`] found `target`, a tag, at another commit than the
+/// `stored` one in this run.
+fn tag_moved(repo: &str, target: &Target, stored: &str) -> bool {
+    let Target::Tag(tag) = target else {
+        return false;
+    };
+    REMOTE_TAGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&(repo.to_string(), tag.clone()))
+        .is_some_and(|commit| commit.as_deref().is_some_and(|c| c != stored))
+}
+
+/// Fetch `repo` at `git_ref` (the default branch when `None`) with a depth of
+/// one and write the tree at `path` (everything when `None`) to the same
+/// place under `dest`, which must not exist yet or be empty.
+///
+/// The fetched objects are kept in `<cache_dir>/git-objects/`: tags and
+/// commits already there are not fetched again (unless [`tag_commit`] found
+/// the tag moved), and branches and `HEAD` are fetched once until
+/// [`forget_moving_refs`]. Nothing outside `path` is written and `dest` gets
+/// no `.git` directory. Returns the commit SHA.
+pub async fn fetch(
+    repo: &str,
+    git_ref: Option<GitRef<'_>>,
+    path: Option<&str>,
+    cache_dir: &Path,
+    dest: &Path,
+    on_progress: Option<&ProgressCb>,
+    ctx: &FetchContext,
+) -> Result<String, GitError> {
+    let repo = ctx.rewrite_url(repo);
+    let path = path.map(normalize_path).filter(|p| !p.is_empty());
+    let target = Target::from(git_ref);
+    let dest = dest.to_path_buf();
+
+    if transport() == Transport::Native
+        && let Some(dir) = native::local_path(&repo)
+    {
+        return blocking(move || native::fetch_local(&dir, &target, path.as_deref(), &dest)).await;
+    }
+
+    let store_dir = store::dir(cache_dir, &repo);
+    let _guard = store::lock(&store_dir).await;
+    let stored = store::cached(&store_dir, &target).filter(|c| !tag_moved(&repo, &target, c));
+    let commit = match stored {
+        Some(commit) => commit,
+        None => {
+            let commit = match transport() {
+                Transport::Cli => {
+                    cli::fetch_into_store(&repo, &target, &store_dir, on_progress).await?
+                }
+                Transport::Native => {
+                    let (target, store_dir) = (target.clone(), store_dir.clone());
+                    blocking(move || native::fetch_into_store(&repo, &target, &store_dir)).await?
+                }
+            };
+            store::remember(&store_dir, &target, &commit);
+            commit
+        }
+    };
+    blocking(move || store::write_path(&store_dir, &commit, path.as_deref(), &dest)).await
+}
+
+/// Forget the commits branches and `HEAD` were fetched at and the answers of
+/// [`tag_commit`], so the next [`fetch`] of them goes to the remote again.
+/// Called at the start of every generation, which may repeat within one
+/// process in watch mode.
+pub fn forget_moving_refs() {
+    store::forget_moving();
+    REMOTE_TAGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, GitError> + Send + 'static,
+) -> Result<T, GitError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| GitError::Failed(e.to_string()))?
+}
+
+/// `path` relative to the repository root: no leading `./` or `/`, no
+/// trailing `/`, and empty for the root itself.
+fn normalize_path(path: &str) -> String {
+    let path = path.trim_start_matches("./").trim_matches('/');
+    if path == "." {
+        String::new()
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Create a bare repository with one commit per `(tag, files)` step, on
+    /// branch `main`. Returns the commit SHA of each step.
+    pub(crate) fn make_repo(dir: &Path, steps: &[(&str, &[(&str, &str)])]) -> Vec<String> {
+        let work = dir.with_extension("work");
+        let git = |args: &[&str]| {
+            let out = std::process::Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(
+                out.status.success(),
+                "{}",
+                String::from_utf8_lossy(&out.stderr)
+            );
+            String::from_utf8_lossy(&out.stdout).trim().to_string()
+        };
+        let work_str = work.to_string_lossy().into_owned();
+        git(&["init", "--quiet", "--initial-branch", "main", &work_str]);
+        let mut commits = Vec::new();
+        for (tag, files) in steps {
+            for (path, content) in *files {
+                let file = work.join(path);
+                std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+                std::fs::write(file, content).unwrap();
+            }
+            git(&["-C", &work_str, "add", "-A"]);
+            git(&["-C", &work_str, "commit", "--quiet", "-m", tag]);
+            // Annotated, like most release tags
+            git(&["-C", &work_str, "tag", "-a", "-m", tag, tag]);
+            commits.push(git(&["-C", &work_str, "rev-parse", "HEAD"]));
+        }
+        git(&[
+            "clone",
+            "--quiet",
+            "--bare",
+            &work_str,
+            &dir.to_string_lossy(),
+        ]);
+        std::fs::remove_dir_all(&work).unwrap();
+        commits
+    }
+
+    /// Every file under `dir`, relative and sorted.
+    pub(crate) fn files(dir: &Path) -> Vec<String> {
+        fn walk(root: &Path, dir: &Path, out: &mut Vec<String>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    walk(root, &path, out);
+                } else {
+                    let rel = path.strip_prefix(root).unwrap();
+                    out.push(rel.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(dir, dir, &mut out);
+        out.sort();
+        out
+    }
+
+    #[tokio::test]
+    async fn fetch_paths_at_refs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ctx = FetchContext::default();
+        let repo = tmp.path().join("repo.git");
+        let commits = make_repo(
+            &repo,
+            &[
+                ("v1", &[("charts/app/values.yaml", "one"), ("README", "r")]),
+                ("v2", &[("charts/app/values.yaml", "two")]),
+            ],
+        );
+        let url = format!("file://{}", repo.display());
+        let cache = tmp.path().join("cache");
+
+        for (git_ref, expected, content) in [
+            (Some(GitRef::Tag("v1")), &commits[0], "one"),
+            (Some(GitRef::Branch("main")), &commits[1], "two"),
+            (Some(GitRef::Rev(&commits[0])), &commits[0], "one"),
+            (None, &commits[1], "two"),
+        ] {
+            let dest = tmp
+                .path()
+                .join(git_ref.map_or("head".into(), |r| r.cache_key()));
+            let commit = fetch(
+                &url,
+                git_ref,
+                Some("./charts/app/"),
+                &cache,
+                &dest,
+                None,
+                &ctx,
+            )
+            .await
+            .unwrap();
+            assert_eq!(&commit, expected);
+            assert_eq!(files(&dest), ["charts/app/values.yaml"]);
+            assert_eq!(
+                std::fs::read_to_string(dest.join("charts/app/values.yaml")).unwrap(),
+                content
+            );
+        }
+
+        let dest = tmp.path().join("missing");
+        let err = fetch(&url, None, Some("crds"), &cache, &dest, None, &ctx)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GitError::PathNotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn fetch_reuses_stored_revisions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ctx = FetchContext::default();
+        let repo = tmp.path().join("repo.git");
+        let commits = make_repo(&repo, &[("v1", &[("crds/a.yaml", "a"), ("charts/b", "b")])]);
+        let url = format!("file://{}", repo.display());
+        let cache = tmp.path().join("cache");
+        let dest = |name: &str| tmp.path().join(name);
+
+        fetch(
+            &url,
+            Some(GitRef::Tag("v1")),
+            Some("crds"),
+            &cache,
+            &dest("1"),
+            None,
+            &ctx,
+        )
+        .await
+        .unwrap();
+        fetch(
+            &url,
+            Some(GitRef::Branch("main")),
+            Some("crds"),
+            &cache,
+            &dest("2"),
+            None,
+            &ctx,
+        )
+        .await
+        .unwrap();
+
+        // Served from the store once the remote is gone
+        std::fs::remove_dir_all(&repo).unwrap();
+        for (git_ref, name) in [
+            (GitRef::Tag("v1"), "3"),
+            (GitRef::Rev(&commits[0]), "4"),
+            (GitRef::Branch("main"), "5"),
+        ] {
+            let commit = fetch(
+                &url,
+                Some(git_ref),
+                Some("charts"),
+                &cache,
+                &dest(name),
+                None,
+                &ctx,
+            )
+            .await
+            .unwrap();
+            assert_eq!(commit, commits[0]);
+            assert_eq!(files(&dest(name)), ["charts/b"]);
+        }
+
+        // Branches go to the remote again in the next run
+        forget_moving_refs();
+        let err = fetch(
+            &url,
+            Some(GitRef::Branch("main")),
+            None,
+            &cache,
+            &dest("6"),
+            None,
+            &ctx,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GitError::Failed(_)), "{err}");
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize_path("./charts/app/"), "charts/app");
+        assert_eq!(normalize_path("/crds"), "crds");
+        assert_eq!(normalize_path("."), "");
+        assert_eq!(normalize_path("./"), "");
+    }
+}
@@ -0,0 +1,269 @@
+//! In-process transport, for when the `git` binary is missing.
+//!
+//! Local repositories (plain paths and `file://` URLs) are read in place.
+//! Remote ones are fetched with a depth of one into the object store.
+
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use gix::bstr::ByteSlice;
+use gix::remote::Direction;
+
+use crate::store::{self, checkout, failed, open};
+use crate::{GitError, Target};
+
+pub(crate) fn list_tags(repo: &str) -> Result<Vec<String>, GitError> {
+    if let Some(dir) = local_path(repo) {
+        let repo = open(&dir)?;
+        let references = repo.references().map_err(failed)?;
+        return Ok(references
+            .tags()
+            .map_err(failed)?
+            .filter_map(Result::ok)
+            .map(|r| r.name().shorten().to_string())
+            .collect());
+    }
+
+    let scratch = tempfile::tempdir().map_err(|e| GitError::Io(format!("create temp dir: {e}")))?;
+    let local = gix::init_bare(scratch.path()).map_err(failed)?;
+    let (ref_map, _) = local
+        .remote_at(repo)
+        .map_err(failed)?
+        .with_fetch_tags(gix::remote::fetch::Tags::None)
+        .with_refspecs(["refs/tags/*:refs/tags/*"], Direction::Fetch)
+        .map_err(failed)?
+        .connect(Direction::Fetch)
+        .map_err(failed)?
+        .ref_map(gix::progress::Discard, Default::default())
+        .map_err(failed)?;
+
+    let mut tags: Vec<String> = Vec::new();
+    for remote_ref in &ref_map.remote_refs {
+        let (name, ..) = remote_ref.unpack();
+        if let Some(tag) = name.strip_prefix(b"refs/tags/") {
+            let tag = tag.to_str_lossy().into_owned();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    Ok(tags)
+}
+
+/// Commit `tag` of `repo` points to, read from its ref advertisement.
+pub(crate) fn tag_commit(repo: &str, tag: &str) -> Result<Option<String>, GitError> {
+    let refname = format!("refs/tags/{tag}");
+    if let Some(dir) = local_path(repo) {
+        let repo = open(&dir)?;
+        return repo
+            .try_find_reference(refname.as_str())
+            .map_err(failed)?
+            .map(|mut r| r.peel_to_id().map(|id| id.to_string()))
+            .transpose()
+            .map_err(failed);
+    }
+
+    let scratch = tempfile::tempdir().map_err(|e| GitError::Io(format!("create temp dir: {e}")))?;
+    let local = gix::init_bare(scratch.path()).map_err(failed)?;
+    let refspec = format!("{refname}:{refname}");
+    let (ref_map, _) = local
+        .remote_at(repo)
+        .map_err(failed)?
+        .with_fetch_tags(gix::remote::fetch::Tags::None)
+        .with_refspecs([refspec.as_str()], Direction::Fetch)
+        .map_err(failed)?
+        .connect(Direction::Fetch)
+        .map_err(failed)?
+        .ref_map(gix::progress::Discard, Default::default())
+        .map_err(failed)?;
+
+    Ok(ref_map.remote_refs.iter().find_map(|remote_ref| {
+        let (name, target, peeled) = remote_ref.unpack();
+        (name == refname.as_bytes())
+            .then(|| peeled.or(target).map(|id| id.to_string()))
+            .flatten()
+    }))
+}
+
+/// Write `path` of `target` in the local repository at `dir` under `dest`.
+pub(crate) fn fetch_local(
+    dir: &Path,
+    target: &Target,
+    path: Option<&str>,
+    dest: &Path,
+) -> Result<String, GitError> {
+    let local = open(dir)?;
+    let id = resolve_local(&local, target)
+        .map_err(|e| GitError::Failed(format!("{target} not found in {}: {e}", dir.display())))?;
+    checkout(&local, id, path, dest)
+}
+
+/// Shallow-fetch `target` from `url` into the object store at `store_dir`.
+/// Returns the commit SHA.
+pub(crate) fn fetch_into_store(
+    url: &str,
+    target: &Target,
+    store_dir: &Path,
+) -> Result<String, GitError> {
+    let store = store::open_or_init(store_dir)?;
+    let refspec = target.refspec();
+    let outcome = store
+        .remote_at(url)
+        .map_err(failed)?
+        .with_fetch_tags(gix::remote::fetch::Tags::None)
+        .with_refspecs([refspec.as_str()], Direction::Fetch)
+        .map_err(failed)?
+        .connect(Direction::Fetch)
+        .map_err(failed)?
+        .prepare_fetch(gix::progress::Discard, Default::default())
+        .map_err(failed)?
+        .with_shallow(gix::remote::fetch::Shallow::DepthAtRemote(NonZeroU32::MIN))
+        .receive(gix::progress::Discard, &AtomicBool::new(false))
+        .map_err(failed)?;
+
+    let id = outcome
+        .ref_map
+        .mappings
+        .iter()
+        .find_map(|mapping| mapping.remote.as_id())
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| GitError::Failed(format!("{target} not found in {url}")))?;
+    let commit = store
+        .find_object(id)
+        .map_err(failed)?
+        .peel_to_commit()
+        .map_err(failed)?;
+    Ok(commit.id.to_string())
+}
+
+/// The local directory of `repo`, for plain paths and `file://` URLs.
+pub(crate) fn local_path(repo: &str) -> Option<PathBuf> {
+    if let Some(path) = repo.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    }
+    (!repo.contains("://") && Path::new(repo).is_dir()).then(|| PathBuf::from(repo))
+}
+
+fn resolve_local(
+    repo: &gix::Repository,
+    target: &Target,
+) -> Result<gix::ObjectId, Box<dyn std::error::Error + Send + Sync>> {
+    let reference =
+        |name: String| -> Result<gix::ObjectId, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(repo.find_reference(name.as_str())?.peel_to_id()?.detach())
+        };
+    match target {
+        Target::Head => Ok(repo.head_id()?.detach()),
+        Target::Tag(tag) => reference(format!("refs/tags/{tag}")),
+        Target::Branch(branch) => reference(format!("refs/heads/{branch}")),
+        Target::Rev(rev) => Ok(gix::ObjectId::from_hex(rev.as_bytes())?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{files, make_repo};
+
+    #[test]
+    fn list_tags_of_local_and_remote_repos() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo.git");
+        make_repo(&repo, &[("v1", &[("a", "1")]), ("v2", &[("a", "2")])]);
+
+        let path = repo.to_string_lossy().into_owned();
+        assert_eq!(list_tags(&path).unwrap(), ["v1", "v2"]);
+        assert_eq!(list_tags(&format!("file://{path}")).unwrap(), ["v1", "v2"]);
+    }
+
+    #[test]
+    fn tag_commit_peels_annotated_tags() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo.git");
+        let commits = make_repo(&repo, &[("v1", &[("a", "1")]), ("v2", &[("a", "2")])]);
+
+        let path = repo.to_string_lossy().into_owned();
+        assert_eq!(tag_commit(&path, "v2").unwrap(), Some(commits[1].clone()));
+        assert_eq!(tag_commit(&path, "v3").unwrap(), None);
+    }
+
+    #[test]
+    fn fetch_local_path_at_refs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo.git");
+        let commits = make_repo(
+            &repo,
+            &[
+                ("v1", &[("charts/app/values.yaml", "one"), ("README", "r")]),
+                ("v2", &[("charts/app/values.yaml", "two")]),
+            ],
+        );
+
+        for (target, expected, content) in [
+            (Target::Tag("v1".into()), &commits[0], "one"),
+            (Target::Branch("main".into()), &commits[1], "two"),
+            (Target::Rev(commits[0].clone()), &commits[0], "one"),
+            (Target::Head, &commits[1], "two"),
+        ] {
+            let dest = tmp.path().join(target.to_string().replace(' ', "-"));
+            let commit = fetch_local(&repo, &target, Some("charts/app"), &dest).unwrap();
+            assert_eq!(&commit, expected);
+            assert_eq!(files(&dest), ["charts/app/values.yaml"]);
+            assert_eq!(
+                std::fs::read_to_string(dest.join("charts/app/values.yaml")).unwrap(),
+                content
+            );
+        }
+
+        let dest = tmp.path().join("all");
+        fetch_local(&repo, &Target::Tag("v1".into()), None, &dest).unwrap();
+        assert_eq!(files(&dest), ["README", "charts/app/values.yaml"]);
+
+        let err =
+            fetch_local(&repo, &Target::Head, Some("crds"), &tmp.path().join("x")).unwrap_err();
+        assert!(matches!(err, GitError::PathNotFound(_)), "{err}");
+        let err = fetch_local(
+            &repo,
+            &Target::Tag("v9".into()),
+            None,
+            &tmp.path().join("y"),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("tag v9 not found"), "{err}");
+    }
+
+    /// Goes through the wire protocol, with a local bare repository as the
+    /// remote.
+    #[test]
+    fn fetch_into_store_is_shallow() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo.git");
+        let commits = make_repo(
+            &repo,
+            &[
+                ("v1", &[("crds/a.yaml", "one"), ("README", "r")]),
+                ("v2", &[("crds/a.yaml", "two")]),
+            ],
+        );
+        let url = format!("file://{}", repo.display());
+        let store_dir = tmp.path().join("store.git");
+
+        for (target, expected, content) in [
+            (Target::Tag("v1".into()), &commits[0], "one"),
+            (Target::Branch("main".into()), &commits[1], "two"),
+            (Target::Rev(commits[0].clone()), &commits[0], "one"),
+            (Target::Head, &commits[1], "two"),
+        ] {
+            let dest = tmp.path().join(target.to_string().replace(' ', "-"));
+            let commit = fetch_into_store(&url, &target, &store_dir).unwrap();
+            assert_eq!(&commit, expected);
+            store::write_path(&store_dir, &commit, Some("crds"), &dest).unwrap();
+            assert_eq!(files(&dest), ["crds/a.yaml"]);
+            assert_eq!(
+                std::fs::read_to_string(dest.join("crds/a.yaml")).unwrap(),
+                content
+            );
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Shared git object store.
+//!
+//! Every remote gets one bare repository under `<cache_dir>/git-objects/`,
+//! and each revision is fetched into it once, with a depth of one. Paths are
+//! then written from the stored objects, so resources, charts and plugins
+//! that read different paths of one repository at one revision share a
+//! single fetch.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use gix::bstr::ByteSlice;
+use gix::object::tree::EntryKind;
+
+use crate::{GitError, Target};
+
+/// Directory of the object store for `url` under `cache_dir`.
+pub(crate) fn dir(cache_dir: &Path, url: &str) -> PathBuf {
+    cache_dir
+        .join("git-objects")
+        .join(format!("{}.git", djb2(url)))
+}
+
+/// Serialize fetches into one store, e.g. from charts resolved concurrently.
+pub(crate) async fn lock(store: &Path) -> tokio::sync::OwnedMutexGuard<()> {
+    static LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+        LazyLock::new(Default::default);
+    let lock = LOCKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(store.to_path_buf())
+        .or_default()
+        .clone();
+    lock.lock_owned().await
+}
+
+/// Commits branches and `HEAD` resolved to since the last
+/// [`crate::forget_moving_refs`], by store and target.
+static MOVING: LazyLock<Mutex<HashMap<(PathBuf, String), String>>> =
+    LazyLock::new(Default::default);
+
+pub(crate) fn forget_moving() {
+    MOVING.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// The commit `target` resolves to without fetching: tags and commits
+/// already in the store, and branches resolved earlier in this run.
+pub(crate) fn cached(store: &Path, target: &Target) -> Option<String> {
+    if target.is_moving() {
+        return MOVING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(store.to_path_buf(), target.to_string()))
+            .cloned();
+    }
+    let repo = gix::open(store).ok()?;
+    let id = match target {
+        Target::Tag(_) => repo
+            .find_reference(target.local_ref().as_str())
+            .ok()?
+            .peel_to_id()
+            .ok()?
+            .detach(),
+        Target::Rev(rev) => gix::ObjectId::from_hex(rev.as_bytes()).ok()?,
+        Target::Branch(_) | Target::Head => return None,
+    };
+    let commit = repo.find_object(id).ok()?.peel_to_commit().ok()?;
+    Some(commit.id.to_string())
+}
+
+/// Record the commit a fetch of `target` resolved to.
+pub(crate) fn remember(store: &Path, target: &Target, commit: &str) {
+    if target.is_moving() {
+        MOVING.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            (store.to_path_buf(), target.to_string()),
+            commit.to_string(),
+        );
+    }
+}
+
+/// The commit `name` points to in the repository at `repo_dir`.
+pub(crate) fn peel_ref(repo_dir: &Path, name: &str) -> Result<String, GitError> {
+    let repo = open(repo_dir)?;
+    let id = repo
+        .find_reference(name)
+        .map_err(failed)?
+        .peel_to_id()
+        .map_err(failed)?;
+    let commit = repo
+        .find_object(id)
+        .map_err(failed)?
+        .peel_to_commit()
+        .map_err(failed)?;
+    Ok(commit.id.to_string())
+}
+
+/// Write the tree at `path` (everything when `None`) of `commit` in the
+/// repository at `repo_dir` to the same place under `dest`.
+pub(crate) fn write_path(
+    repo_dir: &Path,
+    commit: &str,
+    path: Option<&str>,
+    dest: &Path,
+) -> Result<String, GitError> {
+    let repo = open(repo_dir)?;
+    let id = gix::ObjectId::from_hex(commit.as_bytes()).map_err(failed)?;
+    checkout(&repo, id, path, dest)
+}
+
+pub(crate) fn open(dir: &Path) -> Result<gix::Repository, GitError> {
+    gix::open(dir).map_err(|e| GitError::Failed(format!("open {}: {e}", dir.display())))
+}
+
+/// Open the store at `dir`, creating it when missing or unreadable.
+pub(crate) fn open_or_init(dir: &Path) -> Result<gix::Repository, GitError> {
+    if let Ok(repo) = gix::open(dir) {
+        return Ok(repo);
+    }
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir)
+        .map_err(|e| GitError::Io(format!("create dir {}: {e}", dir.display())))?;
+    gix::init_bare(dir).map_err(failed)
+}
+
+/// Write the tree at `path` of commit `id` (or the commit a tag `id` points
+/// to) under `dest`. Returns the commit SHA.
+pub(crate) fn checkout(
+    repo: &gix::Repository,
+    id: gix::ObjectId,
+    path: Option<&str>,
+    dest: &Path,
+) -> Result<String, GitError> {
+    let commit = repo
+        .find_object(id)
+        .map_err(failed)?
+        .peel_to_commit()
+        .map_err(failed)?;
+    let tree = commit.tree().map_err(failed)?;
+    std::fs::create_dir_all(dest)
+        .map_err(|e| GitError::Io(format!("create dir {}: {e}", dest.display())))?;
+    match path {
+        None => write_tree(repo, tree.id, dest)?,
+        Some(path) => {
+            let entry = tree
+                .lookup_entry_by_path(path)
+                .map_err(failed)?
+                .ok_or_else(|| GitError::PathNotFound(path.to_string()))?;
+            write_entry(
+                repo,
+                entry.mode().kind(),
+                entry.object_id(),
+                &dest.join(path),
+            )?;
+        }
+    }
+    Ok(commit.id.to_string())
+}
+
+fn write_tree(repo: &gix::Repository, id: gix::ObjectId, dir: &Path) -> Result<(), GitError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| GitError::Io(format!("create dir {}: {e}", dir.display())))?;
+    let tree = repo.find_tree(id).map_err(failed)?;
+    for entry in tree.iter() {
+        let entry = entry.map_err(failed)?;
+        let name = entry.filename().to_str_lossy();
+        if name == "." || name == ".." || name.contains(['/', '\\']) {
+            return Err(GitError::Failed(format!(
+                "invalid tree entry name '{name}'"
+            )));
+        }
+        write_entry(
+            repo,
+            entry.mode().kind(),
+            entry.oid().to_owned(),
+            &dir.join(name.as_ref()),
+        )?;
+    }
+    Ok(())
+}
+
+fn write_entry(
+    repo: &gix::Repository,
+    kind: EntryKind,
+    id: gix::ObjectId,
+    target: &Path,
+) -> Result<(), GitError> {
+    let io = |e: std::io::Error| GitError::Io(format!("write {}: {e}", target.display()));
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(io)?;
+    }
+    match kind {
+        EntryKind::Tree => write_tree(repo, id, target),
+        EntryKind::Blob | EntryKind::BlobExecutable => {
+            let blob = repo.find_blob(id).map_err(failed)?;
+            std::fs::write(target, &blob.data).map_err(io)?;
+            #[cfg(unix)]
+            if kind == EntryKind::BlobExecutable {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(target, std::fs::Permissions::from_mode(0o755))
+                    .map_err(io)?;
+            }
+            Ok(())
+        }
+        EntryKind::Link => {
+            let blob = repo.find_blob(id).map_err(failed)?;
+            let link = blob.data.to_str_lossy().into_owned();
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&link, target).map_err(io)?;
+            #[cfg(not(unix))]
+            std::fs::write(target, link).map_err(io)?;
+            Ok(())
+        }
+        // Submodules are not fetched, as with a plain clone
+        EntryKind::Commit => Ok(()),
+    }
+}
+
+pub(crate) fn failed(e: impl std::fmt::Display) -> GitError {
+    GitError::Failed(e.to_string())
+}
+
+/// djb2, as used for the other cache directory names.
+fn djb2(s: &str) -> String {
+    let mut hash: u64 = 5381;
+    for byte in s.bytes() {
+        hash = hash.wrapping_mul(33).wrapping_add(u64::from(byte));
+    }
+    format!("{hash:016x}")
+}
@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use husako_config::FetchContext;
+
 use crate::HelmError;
 
 const ARTIFACTHUB_BASE: &str = "https://artifacthub.io/api/v1/packages/helm";
@@ -9,7 +11,8 @@ const ARTIFACTHUB_BASE: &str = "https://artifacthub.io/api/v1/packages/helm";
 /// Flow:
 /// 1. Check cache
 /// 2. Fetch `https://artifacthub.io/api/v1/packages/helm/{package}/{version}`
-/// 3. Use `values_schema` if present — cache and return
+/// 3. Use `values_schema` if present — cache it, fetch the chart archive from
+///    `content_url` for rendering (best-effort) and return
 /// 4. Otherwise attempt a registry fallback using `repository.url`
 ///    (delegates to `registry::resolve`, which handles both HTTP and OCI)
 pub async fn resolve(
@@ -18,11 +21,12 @@ pub async fn resolve(
     version: &str,
     cache_dir: &Path,
     on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<serde_json::Value, HelmError> {
     let base = std::env::var("HUSAKO_ARTIFACTHUB_URL")
         .map(|u| format!("{u}/api/v1/packages/helm"))
         .unwrap_or_else(|_| ARTIFACTHUB_BASE.to_string());
-    resolve_from(name, package, version, cache_dir, &base, on_progress).await
+    resolve_from(name, package, version, cache_dir, &base, on_progress, ctx).await
 }
 
 async fn resolve_from(
@@ -32,6 +36,7 @@ async fn resolve_from(
     cache_dir: &Path,
     base_url: &str,
     on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<serde_json::Value, HelmError> {
     // Check cache
     let cache_key = crate::cache_hash(package);
@@ -48,11 +53,18 @@ async fn resolve_from(
         });
     }
 
+    if ctx.offline {
+        return Err(crate::offline_miss(name, &cache_path));
+    }
+
     // Fetch from ArtifactHub API
     let url = format!("{base_url}/{package}/{version}");
-    let client = reqwest::Client::new();
+    let client = ctx
+        .client_builder()
+        .build()
+        .map_err(|e| HelmError::Io(format!("chart '{name}': build HTTP client: {e}")))?;
     let resp = client
-        .get(&url)
+        .get(ctx.rewrite_url(&url))
         .send()
         .await
         .map_err(|e| HelmError::Io(format!("chart '{name}': fetch {url}: {e}")))?;
@@ -84,6 +96,14 @@ async fn resolve_from(
             serde_json::to_string_pretty(&schema).unwrap_or_default(),
         );
 
+        if let Some(content_url) = body.get("content_url").and_then(|v| v.as_str())
+            && let Ok(resp) = client.get(ctx.rewrite_url(content_url)).send().await
+            && resp.status().is_success()
+            && let Ok(bytes) = resp.bytes().await
+        {
+            crate::store_archive(&cache_path.with_extension("tgz"), &bytes);
+        }
+
         return Ok(schema);
     }
 
@@ -97,19 +117,29 @@ async fn resolve_from(
     let chart_name = package.rsplit('/').next().unwrap_or(package);
 
     if !repo_url.is_empty() {
-        return crate::registry::resolve(
+        let schema = crate::registry::resolve(
             name,
             repo_url,
             chart_name,
             version,
             cache_dir,
             on_progress,
+            ctx,
         )
-        .await
-        .map_err(|e| {
+        .await;
+        // Also when the chart has no values schema, so it can still be rendered
+        let registry_source = husako_config::ChartSource::Registry {
+            repo: repo_url.to_string(),
+            chart: chart_name.to_string(),
+            version: version.to_string(),
+        };
+        if let Some(registry_archive) = crate::archive_cache_path(&registry_source, cache_dir) {
+            crate::copy_archive(&registry_archive, &cache_path.with_extension("tgz"));
+        }
+        return schema.map_err(|e| {
             HelmError::NotFound(format!(
                 "chart '{name}': no values_schema on ArtifactHub; \
-                     registry fallback ({repo_url}) also failed: {e}"
+                 registry fallback ({repo_url}) also failed: {e}"
             ))
         });
     }
@@ -137,9 +167,16 @@ mod tests {
         )
         .unwrap();
 
-        let result = resolve("test", "my-org/my-chart", "1.0.0", cache_dir, None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            "my-org/my-chart",
+            "1.0.0",
+            cache_dir,
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["replicas"].is_object());
     }
@@ -153,9 +190,16 @@ mod tests {
         std::fs::create_dir_all(&cache_sub).unwrap();
         std::fs::write(cache_sub.join("1.0.0.json"), "not json").unwrap();
 
-        let err = resolve("test", "my-org/my-chart", "1.0.0", cache_dir, None)
-            .await
-            .unwrap_err();
+        let err = resolve(
+            "test",
+            "my-org/my-chart",
+            "1.0.0",
+            cache_dir,
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
         assert!(err.to_string().contains("parse cached schema"));
     }
 
@@ -182,6 +226,7 @@ mod tests {
             tmp.path(),
             &server.url(),
             None,
+            &FetchContext::default(),
         )
         .await
         .unwrap();
@@ -212,6 +257,7 @@ mod tests {
             tmp.path(),
             &server.url(),
             None,
+            &FetchContext::default(),
         )
         .await
         .unwrap_err();
@@ -259,6 +305,7 @@ mod tests {
             tmp.path(),
             &server.url(),
             None,
+            &FetchContext::default(),
         )
         .await
         .unwrap();
@@ -305,6 +352,7 @@ mod tests {
             tmp.path(),
             &server.url(),
             None,
+            &FetchContext::default(),
         )
         .await
         .unwrap();
@@ -0,0 +1,365 @@
+//! Credentials for private Helm repositories and OCI registries.
+//!
+//! Lookup order, first match wins:
+//!
+//! - HTTP repositories: `[credentials]` in `husako.toml`, then Helm's
+//!   `repositories.yaml`.
+//! - OCI registries: `[credentials]` in `husako.toml`, then Helm's registry
+//!   config, then Docker's `config.json` (`auths`, `credHelpers` and
+//!   `credsStore`).
+//!
+//! Without a match, requests are sent anonymously.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use husako_config::FetchContext;
+use husako_config::credentials::{self, host_of};
+use serde::Deserialize;
+
+use crate::HelmError;
+
+/// Credentials for an OCI registry.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RegistryCredentials {
+    Basic {
+        username: String,
+        password: String,
+    },
+    /// A refresh token from `docker login` to registries such as ACR,
+    /// exchanged at the token endpoint for an access token.
+    IdentityToken(String),
+}
+
+/// Attach credentials for `repo` to a request for `url`.
+///
+/// Credentials are only sent when `url` is on the repository's own host, so
+/// chart archives served from elsewhere never receive them.
+pub fn authorize(
+    request: reqwest::RequestBuilder,
+    repo: &str,
+    url: &str,
+    ctx: &FetchContext,
+) -> Result<reqwest::RequestBuilder, HelmError> {
+    if !host_of(url).eq_ignore_ascii_case(host_of(repo)) {
+        return Ok(request);
+    }
+    Ok(match repo_credentials(repo, ctx)? {
+        Some(creds) => request.basic_auth(creds.username, Some(creds.password)),
+        None => request,
+    })
+}
+
+/// Credentials for a Helm HTTP repository.
+fn repo_credentials(
+    repo: &str,
+    ctx: &FetchContext,
+) -> Result<Option<credentials::Credentials>, HelmError> {
+    if let Some(creds) = ctx
+        .credentials_for(host_of(repo))
+        .map_err(HelmError::Auth)?
+    {
+        return Ok(Some(creds));
+    }
+    Ok(helm_repositories_path().and_then(|path| helm_repository_credentials(&path, repo)))
+}
+
+/// Credentials for the OCI registry at `host`.
+pub(crate) fn registry_credentials(
+    host: &str,
+    ctx: &FetchContext,
+) -> Result<Option<RegistryCredentials>, HelmError> {
+    if let Some(creds) = ctx.credentials_for(host).map_err(HelmError::Auth)? {
+        return Ok(Some(RegistryCredentials::Basic {
+            username: creds.username,
+            password: creds.password,
+        }));
+    }
+    for path in [helm_registry_config_path(), docker_config_path()]
+        .into_iter()
+        .flatten()
+    {
+        if let Some(creds) = docker_config_credentials(&path, host) {
+            return Ok(Some(creds));
+        }
+    }
+    Ok(None)
+}
+
+// ── Helm repositories.yaml ──────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct HelmRepositories {
+    #[serde(default)]
+    repositories: Vec<HelmRepository>,
+}
+
+#[derive(Deserialize)]
+struct HelmRepository {
+    url: String,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    password: String,
+}
+
+/// `$HELM_REPOSITORY_CONFIG`, else `$XDG_CONFIG_HOME/helm/repositories.yaml`,
+/// else `~/.config/helm/repositories.yaml`.
+fn helm_repositories_path() -> Option<PathBuf> {
+    env_path("HELM_REPOSITORY_CONFIG")
+        .or_else(|| config_home().map(|d| d.join("helm/repositories.yaml")))
+}
+
+fn helm_repository_credentials(path: &Path, repo: &str) -> Option<credentials::Credentials> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let file: HelmRepositories = serde_yaml_ng::from_str(&content).ok()?;
+    let repo = repo.trim_end_matches('/');
+    file.repositories
+        .into_iter()
+        .find(|r| r.url.trim_end_matches('/') == repo && !r.username.is_empty())
+        .map(|r| credentials::Credentials {
+            username: r.username,
+            password: r.password,
+        })
+}
+
+// ── Docker config.json ──────────────────────────────────────────────────────
+
+#[derive(Deserialize, Default)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, DockerAuth>,
+    #[serde(default, rename = "credHelpers")]
+    cred_helpers: HashMap<String, String>,
+    #[serde(default, rename = "credsStore")]
+    creds_store: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct DockerAuth {
+    #[serde(default)]
+    auth: Option<String>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    identitytoken: Option<String>,
+}
+
+/// `$HELM_REGISTRY_CONFIG`, else `$XDG_CONFIG_HOME/helm/registry/config.json`,
+/// else `~/.config/helm/registry/config.json`.
+fn helm_registry_config_path() -> Option<PathBuf> {
+    env_path("HELM_REGISTRY_CONFIG")
+        .or_else(|| config_home().map(|d| d.join("helm/registry/config.json")))
+}
+
+/// `$DOCKER_CONFIG/config.json`, else `~/.docker/config.json`.
+fn docker_config_path() -> Option<PathBuf> {
+    env_path("DOCKER_CONFIG")
+        .map(|dir| dir.join("config.json"))
+        .or_else(|| home().map(|h| h.join(".docker/config.json")))
+}
+
+fn docker_config_credentials(path: &Path, host: &str) -> Option<RegistryCredentials> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let config: DockerConfig = serde_json::from_str(&content).ok()?;
+    let keys = docker_config_keys(host);
+
+    for key in &keys {
+        if let Some(helper) = config.cred_helpers.get(key) {
+            return credential_helper(helper, key);
+        }
+    }
+    for (server, auth) in &config.auths {
+        if keys
+            .iter()
+            .any(|key| host_of(server).eq_ignore_ascii_case(host_of(key)))
+            && let Some(creds) = decode_docker_auth(auth)
+        {
+            return Some(creds);
+        }
+    }
+    let store = config.creds_store.as_deref()?;
+    keys.iter().find_map(|key| credential_helper(store, key))
+}
+
+/// Keys Docker may have stored `host` under. Docker Hub is filed under its
+/// legacy index URL.
+fn docker_config_keys(host: &str) -> Vec<String> {
+    match host {
+        "registry-1.docker.io" | "docker.io" | "index.docker.io" => {
+            vec!["https://index.docker.io/v1/".to_string(), host.to_string()]
+        }
+        _ => vec![host.to_string()],
+    }
+}
+
+fn decode_docker_auth(auth: &DockerAuth) -> Option<RegistryCredentials> {
+    if let Some(token) = auth.identitytoken.as_ref().filter(|t| !t.is_empty()) {
+        return Some(RegistryCredentials::IdentityToken(token.clone()));
+    }
+    if let (Some(username), Some(password)) = (&auth.username, &auth.password) {
+        return Some(RegistryCredentials::Basic {
+            username: username.clone(),
+            password: password.clone(),
+        });
+    }
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(auth.auth.as_ref()?.trim())
+        .ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some(RegistryCredentials::Basic {
+        username: username.to_string(),
+        password: password.to_string(),
+    })
+}
+
+/// Ask `docker-credential-<helper>` for the credentials of `server`.
+fn credential_helper(helper: &str, server: &str) -> Option<RegistryCredentials> {
+    use std::io::Write;
+
+    let mut child = std::process::Command::new(format!("docker-credential-{helper}"))
+        .arg("get")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(server.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_helper_output(&output.stdout)
+}
+
+fn parse_helper_output(stdout: &[u8]) -> Option<RegistryCredentials> {
+    #[derive(Deserialize)]
+    struct HelperOutput {
+        #[serde(rename = "Username")]
+        username: String,
+        #[serde(rename = "Secret")]
+        secret: String,
+    }
+
+    let out: HelperOutput = serde_json::from_slice(stdout).ok()?;
+    if out.username == "<token>" {
+        Some(RegistryCredentials::IdentityToken(out.secret))
+    } else {
+        Some(RegistryCredentials::Basic {
+            username: out.username,
+            password: out.secret,
+        })
+    }
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+fn home() -> Option<PathBuf> {
+    env_path("HOME")
+}
+
+fn config_home() -> Option<PathBuf> {
+    env_path("XDG_CONFIG_HOME").or_else(|| home().map(|h| h.join(".config")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn basic(username: &str, password: &str) -> RegistryCredentials {
+        RegistryCredentials::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn docker_config_auths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.json");
+        let encoded = base64::engine::general_purpose::STANDARD.encode("robot$ci:s3cret");
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "auths": {
+                    "harbor.corp.example": { "auth": encoded },
+                    "https://index.docker.io/v1/": { "username": "hub", "password": "pw" },
+                    "acr.example.io": { "auth": "", "identitytoken": "refresh" }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            docker_config_credentials(&path, "harbor.corp.example"),
+            Some(basic("robot$ci", "s3cret"))
+        );
+        assert_eq!(
+            docker_config_credentials(&path, "registry-1.docker.io"),
+            Some(basic("hub", "pw"))
+        );
+        assert_eq!(
+            docker_config_credentials(&path, "acr.example.io"),
+            Some(RegistryCredentials::IdentityToken("refresh".to_string()))
+        );
+        assert_eq!(docker_config_credentials(&path, "ghcr.io"), None);
+        assert_eq!(
+            docker_config_credentials(&tmp.path().join("missing"), "ghcr.io"),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_credential_helper_is_no_credentials() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.json");
+        std::fs::write(
+            &path,
+            r#"{"credHelpers":{"ecr.example.com":"husako-test-missing-helper"}}"#,
+        )
+        .unwrap();
+        assert_eq!(docker_config_credentials(&path, "ecr.example.com"), None);
+    }
+
+    #[test]
+    fn helper_output() {
+        assert_eq!(
+            parse_helper_output(br#"{"ServerURL":"x","Username":"AWS","Secret":"pw"}"#),
+            Some(basic("AWS", "pw"))
+        );
+        assert_eq!(
+            parse_helper_output(br#"{"Username":"<token>","Secret":"refresh"}"#),
+            Some(RegistryCredentials::IdentityToken("refresh".to_string()))
+        );
+        assert_eq!(parse_helper_output(b"credentials not found"), None);
+    }
+
+    #[test]
+    fn helm_repositories_yaml() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("repositories.yaml");
+        std::fs::write(
+            &path,
+            "apiVersion: \"\"\nrepositories:\n\
+             - name: corp\n  url: https://charts.corp.example/stable/\n  username: ci\n  password: pw\n\
+             - name: public\n  url: https://charts.example.com\n",
+        )
+        .unwrap();
+
+        let creds =
+            helm_repository_credentials(&path, "https://charts.corp.example/stable").unwrap();
+        assert_eq!(creds.username, "ci");
+        assert_eq!(creds.password, "pw");
+        assert!(helm_repository_credentials(&path, "https://charts.example.com").is_none());
+        assert!(helm_repository_credentials(&path, "https://other.example.com").is_none());
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use crate::HelmError;
+use crate::subchart::ChartFiles;
+
+/// Resolve a local chart source: a chart directory or a packaged `.tgz`.
+///
+/// Flow:
+/// 1. Read the chart (directory on disk or archive) and locate its `Chart.yaml`
+/// 2. Infer a schema from `values.yaml` for any chart lacking `values.schema.json`
+/// 3. Compose the values schema with its subcharts (see [`crate::subchart`])
+pub fn resolve(name: &str, path: &str, project_root: &Path) -> Result<Value, HelmError> {
+    let (mut files, root) = read(name, path, project_root)?;
+
+    let roots: Vec<String> = files
+        .keys()
+        .filter_map(|k| k.strip_suffix("Chart.yaml"))
+        .filter(|r| r.is_empty() || r.ends_with('/'))
+        .map(String::from)
+        .collect();
+    for chart_root in roots {
+        let schema_path = format!("{chart_root}values.schema.json");
+        if files.contains_key(&schema_path) {
+            continue;
+        }
+        if let Some(values) = files.get(&format!("{chart_root}values.yaml")) {
+            let values: Value = serde_yaml_ng::from_slice(values).map_err(|e| {
+                HelmError::InvalidSchema(format!(
+                    "chart '{name}': parse {chart_root}values.yaml: {e}"
+                ))
+            })?;
+            let schema = infer_schema(&values);
+            files.insert(schema_path, schema.to_string().into_bytes());
+        }
+    }
+
+    crate::subchart::chart_values_schema(name, &files, &root)?.ok_or_else(|| {
+        HelmError::NotFound(format!(
+            "chart '{name}': {path} has neither values.schema.json nor values.yaml"
+        ))
+    })
+}
+
+/// Read the schema-relevant files of a local chart, returning them with the
+/// chart's root prefix (`""` for a directory, `"<chart>/"` for an archive).
+fn read(name: &str, path: &str, project_root: &Path) -> Result<(ChartFiles, String), HelmError> {
+    let resolved = project_root.join(path);
+    if resolved.is_dir() {
+        if !resolved.join("Chart.yaml").exists() {
+            return Err(HelmError::NotFound(format!(
+                "chart '{name}': no Chart.yaml in {}",
+                resolved.display()
+            )));
+        }
+        return Ok((crate::subchart::read_dir(name, &resolved)?, String::new()));
+    }
+    if !resolved.is_file() {
+        return Err(HelmError::NotFound(format!(
+            "chart '{name}': chart not found: {}",
+            resolved.display()
+        )));
+    }
+    let bytes = std::fs::read(&resolved)
+        .map_err(|e| HelmError::Io(format!("chart '{name}': read {}: {e}", resolved.display())))?;
+    let files = crate::subchart::read_archive(name, &bytes)?;
+    let root = crate::render::archive_root(&files).ok_or_else(|| {
+        HelmError::InvalidSchema(format!(
+            "chart '{name}': {} is not a chart archive (no Chart.yaml)",
+            resolved.display()
+        ))
+    })?;
+    Ok((files, root))
+}
+
+/// Infer a JSON Schema from default values: objects become typed
+/// properties, arrays take the type of their first item, and `null` or empty
+/// containers stay unconstrained.
+fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Object(map) if map.is_empty() => json!({ "type": "object" }),
+        Value::Object(map) => {
+            let properties: serde_json::Map<String, Value> = map
+                .iter()
+                .map(|(k, v)| (k.clone(), infer_schema(v)))
+                .collect();
+            json!({ "type": "object", "properties": properties })
+        }
+        Value::Array(items) => match items.first() {
+            Some(first) => json!({ "type": "array", "items": infer_schema(first) }),
+            None => json!({ "type": "array" }),
+        },
+        Value::String(_) => json!({ "type": "string" }),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(n) if n.is_f64() => json!({ "type": "number" }),
+        Value::Number(_) => json!({ "type": "integer" }),
+        Value::Null => json!({}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, content: &[u8]) {
+        let full = dir.join(path);
+        std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+        std::fs::write(full, content).unwrap();
+    }
+
+    fn build_tgz(entries: &[(&str, &str)]) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+        let tar_data = builder.into_inner().unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar_data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn directory_with_schema() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("charts/app");
+        write(
+            &dir,
+            "Chart.yaml",
+            b"apiVersion: v2\nname: app\nversion: 1.0.0\n",
+        );
+        write(
+            &dir,
+            "values.schema.json",
+            br#"{"type":"object","properties":{"replicas":{"type":"integer"}}}"#,
+        );
+        write(&dir, "values.yaml", b"replicas: 1\nignored: true\n");
+
+        let schema = resolve("app", "charts/app", tmp.path()).unwrap();
+        assert_eq!(schema["properties"]["replicas"]["type"], "integer");
+        assert!(schema["properties"].get("ignored").is_none());
+    }
+
+    #[test]
+    fn directory_falls_back_to_values_yaml() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("charts/app");
+        write(
+            &dir,
+            "Chart.yaml",
+            b"apiVersion: v2\nname: app\nversion: 1.0.0\n",
+        );
+        write(
+            &dir,
+            "values.yaml",
+            b"replicaCount: 1\nratio: 0.5\nimage:\n  repository: nginx\n  pullPolicy: IfNotPresent\nports:\n  - 80\nextraEnv: []\npodAnnotations: {}\ntolerations: ~\nenabled: true\n",
+        );
+
+        let schema = resolve("app", "charts/app", tmp.path()).unwrap();
+        let props = &schema["properties"];
+        assert_eq!(props["replicaCount"]["type"], "integer");
+        assert_eq!(props["ratio"]["type"], "number");
+        assert_eq!(props["image"]["properties"]["repository"]["type"], "string");
+        assert_eq!(props["ports"]["items"]["type"], "integer");
+        assert_eq!(props["extraEnv"], json!({ "type": "array" }));
+        assert_eq!(props["podAnnotations"], json!({ "type": "object" }));
+        assert_eq!(props["tolerations"], json!({}));
+        assert_eq!(props["enabled"]["type"], "boolean");
+    }
+
+    #[test]
+    fn packaged_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_tgz(&[
+            (
+                "app/Chart.yaml",
+                "apiVersion: v2\nname: app\nversion: 2.0.0\n",
+            ),
+            ("app/values.yaml", "service:\n  port: 80\n"),
+            ("app/templates/svc.yaml", "kind: Service\n"),
+        ]);
+        write(tmp.path(), "dist/app-2.0.0.tgz", &archive);
+
+        let schema = resolve("app", "dist/app-2.0.0.tgz", tmp.path()).unwrap();
+        assert_eq!(
+            schema["properties"]["service"]["properties"]["port"]["type"],
+            "integer"
+        );
+    }
+
+    #[test]
+    fn missing_chart_yaml_is_an_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("charts/app")).unwrap();
+        let err = resolve("app", "charts/app", tmp.path()).unwrap_err();
+        assert!(err.to_string().contains("no Chart.yaml"));
+
+        let err = resolve("app", "charts/missing", tmp.path()).unwrap_err();
+        assert!(err.to_string().contains("chart not found"));
+    }
+}
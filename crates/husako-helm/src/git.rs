@@ -1,26 +1,44 @@
 use std::path::Path;
 
+use husako_config::{FetchContext, GitRef};
+
 use crate::HelmError;
 
 /// Resolve a Helm chart from a git repository.
 ///
 /// Flow:
-/// 1. Check cache
-/// 2. Shallow-clone the repo at the specified tag
+/// 1. Check cache (never for branches, which move, or tags the remote now has
+///    at another commit)
+/// 2. Shallow-fetch the repo at the specified tag, branch or commit
 /// 3. Read `values.schema.json` from the specified path within the repo
-/// 4. Cache and return
+/// 4. Cache the schema, the resolved commit and a packaged copy of the chart
+///    (for rendering) and return
 pub async fn resolve(
     name: &str,
     repo: &str,
-    tag: &str,
+    git_ref: GitRef<'_>,
     path: &str,
     cache_dir: &Path,
     on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<serde_json::Value, HelmError> {
     // Check cache
     let cache_key = crate::cache_hash(&format!("{repo}/{path}"));
-    let cache_path = cache_dir.join(format!("helm/git/{cache_key}/{tag}.json"));
-    if cache_path.exists() {
+    let cache_path = cache_dir.join(format!("helm/git/{cache_key}/{}.json", git_ref.cache_key()));
+    // Branches move, so they are re-fetched unless offline; so is a tag the
+    // remote now has at another commit than the cached one
+    let moved = || match git_ref {
+        GitRef::Tag(tag) => {
+            std::fs::read_to_string(cache_path.with_extension("commit")).is_ok_and(|cached| {
+                husako_git::tag_commit(repo, tag, ctx)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|c| c != cached.trim())
+            })
+        }
+        _ => false,
+    };
+    if cache_path.exists() && (ctx.offline || !git_ref.is_mutable() && !moved()) {
         let content = std::fs::read_to_string(&cache_path).map_err(|e| {
             HelmError::Io(format!(
                 "chart '{name}': read cache {}: {e}",
@@ -32,68 +50,73 @@ pub async fn resolve(
         });
     }
 
-    // Clone repo at specific tag
-    let temp_dir = tempfile::tempdir()
-        .map_err(|e| HelmError::Io(format!("chart '{name}': create temp dir: {e}")))?;
-
-    let mut child = tokio::process::Command::new("git")
-        .args(["clone", "--depth", "1", "--branch", tag, "--progress", repo])
-        .arg(temp_dir.path())
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| HelmError::Io(format!("chart '{name}': git clone failed: {e}")))?;
-
-    // Stream stderr for real-time progress; collect for error reporting.
-    // git --progress uses \r to update in-place, so we split on both \r and \n.
-    let mut stderr_text = String::new();
-    if let Some(stderr) = child.stderr.take() {
-        use tokio::io::AsyncBufReadExt;
-        let mut lines = tokio::io::BufReader::new(stderr).lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            for part in line.split('\r') {
-                let part = part.trim();
-                if part.is_empty() {
-                    continue;
-                }
-                stderr_text.push_str(part);
-                stderr_text.push('\n');
-                if let (Some(cb), Some((pct, bytes))) = (on_progress, parse_git_progress(part)) {
-                    cb(bytes, None, Some(pct));
-                }
-            }
-        }
+    if ctx.offline {
+        return Err(crate::offline_miss(name, &cache_path));
     }
 
-    let status = child
-        .wait()
-        .await
-        .map_err(|e| HelmError::Io(format!("chart '{name}': git clone wait: {e}")))?;
+    // Fetch only the directory holding the schema (the chart directory, for
+    // packaging and vendored subcharts) at the requested ref
+    let temp_dir = tempfile::tempdir()
+        .map_err(|e| HelmError::Io(format!("chart '{name}': create temp dir: {e}")))?;
+    let chart_dir = Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned());
+    let not_found = || {
+        HelmError::NotFound(format!(
+            "chart '{name}': path '{path}' not found in repository {repo} at {git_ref}"
+        ))
+    };
+    let commit = husako_git::fetch(
+        repo,
+        Some(git_ref),
+        chart_dir.as_deref(),
+        cache_dir,
+        temp_dir.path(),
+        on_progress,
+        ctx,
+    )
+    .await
+    .map_err(|e| match e {
+        husako_git::GitError::PathNotFound(_) => not_found(),
+        e => HelmError::Io(format!(
+            "chart '{name}': git fetch {repo} at {git_ref} failed: {e}"
+        )),
+    })?;
 
-    if !status.success() {
-        return Err(HelmError::Io(format!(
-            "chart '{name}': git clone {repo} at tag {tag} failed: {stderr_text}"
-        )));
+    // Keep the chart for rendering, even if it has no values schema
+    let schema_path = temp_dir.path().join(path);
+    if let Some(chart_dir) = schema_path
+        .parent()
+        .filter(|d| d.join("Chart.yaml").exists())
+        && let Ok(archive) = package_chart_dir(chart_dir)
+    {
+        crate::store_archive(&cache_path.with_extension("tgz"), &archive);
+        let _ = std::fs::write(cache_path.with_extension("commit"), &commit);
     }
 
     // Read values.schema.json from the specified path
-    let schema_path = temp_dir.path().join(path);
     if !schema_path.exists() {
-        return Err(HelmError::NotFound(format!(
-            "chart '{name}': path '{path}' not found in repository {repo} at tag {tag}"
-        )));
+        return Err(not_found());
     }
 
-    let content = std::fs::read_to_string(&schema_path).map_err(|e| {
-        HelmError::Io(format!(
-            "chart '{name}': read {}: {e}",
-            schema_path.display()
-        ))
-    })?;
-
-    let schema: serde_json::Value = serde_json::from_str(&content).map_err(|e| {
-        HelmError::InvalidSchema(format!("chart '{name}': parse values.schema.json: {e}"))
-    })?;
+    let schema = match umbrella_chart_dir(&schema_path) {
+        // Chart directory: compose subchart schemas vendored under `charts/`
+        Some(chart_dir) => {
+            let files = crate::subchart::read_dir(name, chart_dir)?;
+            crate::subchart::chart_values_schema(name, &files, "")?.ok_or_else(not_found)?
+        }
+        None => {
+            let content = std::fs::read_to_string(&schema_path).map_err(|e| {
+                HelmError::Io(format!(
+                    "chart '{name}': read {}: {e}",
+                    schema_path.display()
+                ))
+            })?;
+            serde_json::from_str(&content).map_err(|e| {
+                HelmError::InvalidSchema(format!("chart '{name}': parse values.schema.json: {e}"))
+            })?
+        }
+    };
 
     // Cache
     if let Some(parent) = cache_path.parent() {
@@ -103,34 +126,41 @@ pub async fn resolve(
         &cache_path,
         serde_json::to_string_pretty(&schema).unwrap_or_default(),
     );
+    let _ = std::fs::write(cache_path.with_extension("commit"), &commit);
 
     Ok(schema)
 }
 
-/// Parse a git progress line like "Receiving objects:  45% (24430/54286), 53.29 MiB | ..."
-/// Returns `(pct, bytes_received)`.
-fn parse_git_progress(line: &str) -> Option<(u8, u64)> {
-    let rest = line.strip_prefix("Receiving objects:")?.trim();
-    let pct_end = rest.find('%')?;
-    let pct: u8 = rest[..pct_end].trim().parse().ok()?;
-
-    // Try to extract MiB value: ", 53.29 MiB | ..."
-    let bytes = if let Some(mib_pos) = rest.find(" MiB") {
-        let before = rest[..mib_pos].trim_end();
-        let num_start = before
-            .rfind(|c: char| !c.is_ascii_digit() && c != '.')
-            .map(|i| i + 1)
-            .unwrap_or(0);
-        before[num_start..]
-            .parse::<f64>()
-            .ok()
-            .map(|mib| (mib * 1_048_576.0) as u64)
-            .unwrap_or(0)
-    } else {
-        0
-    };
+/// Package a chart directory as a `.tgz` laid out like `helm package` output.
+fn package_chart_dir(chart_dir: &Path) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
 
-    Some((pct, bytes))
+    let root = chart_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "chart".to_string());
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.append_dir_all(&root, chart_dir)?;
+    let tar_data = builder.into_inner()?;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&tar_data)?;
+    encoder.finish()
+}
+
+/// Return the chart directory if `schema_path` is the `values.schema.json` of
+/// a chart that declares dependencies in its `Chart.yaml`.
+fn umbrella_chart_dir(schema_path: &Path) -> Option<&Path> {
+    if schema_path.file_name()? != "values.schema.json" {
+        return None;
+    }
+    let dir = schema_path.parent()?;
+    let chart_yaml = std::fs::read(dir.join("Chart.yaml")).ok()?;
+    let chart: serde_yaml_ng::Value = serde_yaml_ng::from_slice(&chart_yaml).ok()?;
+    chart
+        .get("dependencies")
+        .and_then(serde_yaml_ng::Value::as_sequence)
+        .is_some_and(|deps| !deps.is_empty())
+        .then_some(dir)
 }
 
 #[cfg(test)]
@@ -155,10 +185,11 @@ mod tests {
         let result = resolve(
             "test",
             "https://github.com/example/chart",
-            "v1.0.0",
+            GitRef::Tag("v1.0.0"),
             "charts/my-chart/values.schema.json",
             cache_dir,
             None,
+            &FetchContext::default(),
         )
         .await
         .unwrap();
@@ -180,36 +211,92 @@ mod tests {
         let err = resolve(
             "test",
             "https://github.com/example/chart",
-            "v1.0.0",
+            GitRef::Tag("v1.0.0"),
             "charts/my-chart/values.schema.json",
             cache_dir,
             None,
+            &FetchContext::default(),
         )
         .await
         .unwrap_err();
         assert!(err.to_string().contains("parse cached schema"));
     }
 
-    #[test]
-    fn parse_git_progress_with_bytes() {
-        let (pct, bytes) =
-            parse_git_progress("Receiving objects:  45% (24430/54286), 53.29 MiB | 10.00 MiB/s")
+    #[tokio::test]
+    async fn resolve_rev_records_commit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        std::fs::create_dir_all(repo.join("charts/app")).unwrap();
+        std::fs::write(
+            repo.join("charts/app/values.schema.json"),
+            r#"{"type":"object"}"#,
+        )
+        .unwrap();
+        let git = |args: &[&str]| {
+            let out = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
                 .unwrap();
-        assert_eq!(pct, 45);
-        assert_eq!(bytes, (53.29 * 1_048_576.0) as u64);
-    }
+            assert!(out.status.success());
+            String::from_utf8_lossy(&out.stdout).trim().to_string()
+        };
+        git(&["init", "--quiet"]);
+        git(&["add", "-A"]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        let commit = git(&["rev-parse", "HEAD"]);
 
-    #[test]
-    fn parse_git_progress_no_bytes() {
-        let (pct, bytes) = parse_git_progress("Receiving objects: 100% (54286/54286)").unwrap();
-        assert_eq!(pct, 100);
-        assert_eq!(bytes, 0);
+        let url = format!("file://{}", repo.display());
+        let cache_dir = tmp.path().join("cache");
+        let schema = resolve(
+            "app",
+            &url,
+            GitRef::Rev(&commit),
+            "charts/app/values.schema.json",
+            &cache_dir,
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(schema["type"], "object");
+
+        let source = husako_config::ChartSource::Git {
+            repo: url,
+            tag: String::new(),
+            branch: None,
+            rev: Some(commit.clone()),
+            path: "charts/app/values.schema.json".to_string(),
+        };
+        assert_eq!(crate::git_commit(&source, &cache_dir), Some(commit));
     }
 
     #[test]
-    fn parse_git_progress_non_matching_line() {
-        assert!(parse_git_progress("remote: Counting objects: 100% (54286/54286)").is_none());
-        assert!(parse_git_progress("Resolving deltas:  25% (100/400)").is_none());
-        assert!(parse_git_progress("").is_none());
+    fn umbrella_chart_dir_requires_dependencies() {
+        let tmp = tempfile::tempdir().unwrap();
+        let schema_path = tmp.path().join("values.schema.json");
+        std::fs::write(&schema_path, "{}").unwrap();
+        assert!(umbrella_chart_dir(&schema_path).is_none());
+
+        for chart_yaml in [
+            "name: app\n",
+            "name: app\ndependencies: []\n",
+            "name: app\ndependencies:\n",
+            // Inside a block scalar, not a `dependencies` key
+            "name: app\ndescription: |\n  dependencies:\n    - name: redis\n",
+        ] {
+            std::fs::write(tmp.path().join("Chart.yaml"), chart_yaml).unwrap();
+            assert!(umbrella_chart_dir(&schema_path).is_none(), "{chart_yaml}");
+        }
+
+        std::fs::write(
+            tmp.path().join("Chart.yaml"),
+            "name: app\n\"dependencies\":\n- name: redis\n",
+        )
+        .unwrap();
+        assert_eq!(umbrella_chart_dir(&schema_path), Some(tmp.path()));
+        assert!(umbrella_chart_dir(&tmp.path().join("other.json")).is_none());
     }
 }
@@ -1,13 +1,22 @@
 mod artifacthub;
+pub mod auth;
+mod chart;
 mod file;
 mod git;
+mod metadata;
 pub mod oci;
 mod registry;
+mod render;
+mod subchart;
+mod template;
+
+pub use metadata::{ChartMetadata, chart_metadata};
+pub use render::{RenderOptions, render, render_input};
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use husako_config::ChartSource;
+use husako_config::{ChartSource, FetchContext};
 
 #[derive(Debug, thiserror::Error)]
 pub enum HelmError {
@@ -17,6 +26,12 @@ pub enum HelmError {
     InvalidSchema(String),
     #[error("chart not found: {0}")]
     NotFound(String),
+    #[error("chart render error: {0}")]
+    Render(String),
+    #[error("offline mode: {0}")]
+    Offline(String),
+    #[error("authentication error: {0}")]
+    Auth(String),
 }
 
 /// Callback type for download progress reporting: `(bytes_received, total_bytes, pct_override)`.
@@ -31,6 +46,129 @@ pub(crate) fn cache_hash(s: &str) -> String {
     format!("{hash:016x}")
 }
 
+/// Error for a chart that would have to be downloaded in offline mode.
+pub(crate) fn offline_miss(name: &str, cache_path: &Path) -> HelmError {
+    HelmError::Offline(format!(
+        "chart '{name}' is not cached at {}",
+        cache_path.display()
+    ))
+}
+
+/// Path of the chart archive cached next to a remote chart's values schema.
+///
+/// Returns `None` for `file` and `chart` sources, which are read from the
+/// project tree.
+pub(crate) fn archive_cache_path(source: &ChartSource, cache_dir: &Path) -> Option<PathBuf> {
+    let (kind, key, version) = match source {
+        ChartSource::File { .. } | ChartSource::Chart { .. } => return None,
+        ChartSource::Registry {
+            repo,
+            chart,
+            version,
+        } if repo.starts_with("oci://") => ("oci", repo.clone(), version.clone()),
+        ChartSource::Registry {
+            repo,
+            chart,
+            version,
+        } => ("registry", format!("{repo}/{chart}"), version.clone()),
+        ChartSource::ArtifactHub { package, version } => {
+            ("artifacthub", package.clone(), version.clone())
+        }
+        ChartSource::Git { repo, path, .. } => (
+            "git",
+            format!("{repo}/{path}"),
+            source.git_ref()?.cache_key(),
+        ),
+        ChartSource::Oci { reference, version } => ("oci", reference.clone(), version.clone()),
+    };
+    Some(cache_dir.join(format!("helm/{kind}/{}/{version}.tgz", cache_hash(&key))))
+}
+
+/// Commit SHA a `git` chart source was last checked out at, as recorded
+/// next to its cached schema. `None` for other sources or before first fetch.
+pub fn git_commit(source: &ChartSource, cache_dir: &Path) -> Option<String> {
+    if !matches!(source, ChartSource::Git { .. }) {
+        return None;
+    }
+    let path = archive_cache_path(source, cache_dir)?.with_extension("commit");
+    let commit = std::fs::read_to_string(path).ok()?;
+    Some(commit.trim().to_string()).filter(|c| !c.is_empty())
+}
+
+/// SHA-256 digest of the chart archive cached for a remote chart source.
+/// `None` for local and `git` sources, or when no archive is cached.
+pub fn archive_digest(source: &ChartSource, cache_dir: &Path) -> Option<String> {
+    if matches!(source, ChartSource::Git { .. }) {
+        return None;
+    }
+    let bytes = std::fs::read(archive_cache_path(source, cache_dir)?).ok()?;
+    Some(husako_digest::sha256_digest(&bytes))
+}
+
+/// Whether `source` can be resolved and rendered from `cache_dir` alone.
+///
+/// Local `file` and `chart` sources always can; `git` sources that follow a
+/// branch never can, since they are re-fetched on every run.
+pub fn is_cached(source: &ChartSource, cache_dir: &Path, ctx: &FetchContext) -> bool {
+    if source.git_ref().is_some_and(|git_ref| git_ref.is_mutable()) {
+        return false;
+    }
+    match archive_cache_path(source, cache_dir) {
+        Some(archive) => {
+            archive.with_extension("json").exists()
+                || shared_key(source, cache_dir).is_some_and(|key| ctx.shared_contains(&key))
+        }
+        None => true,
+    }
+}
+
+/// Whether the archive [`fetch_archive`] keeps for `source` is in `cache_dir`
+/// (or the shared cache), whether or not the chart has a values schema.
+pub fn is_archive_cached(source: &ChartSource, cache_dir: &Path, ctx: &FetchContext) -> bool {
+    if source.git_ref().is_some_and(|git_ref| git_ref.is_mutable()) {
+        return false;
+    }
+    match archive_cache_path(source, cache_dir) {
+        Some(archive) => {
+            archive.exists()
+                || shared_key(source, cache_dir).is_some_and(|key| ctx.shared_contains(&key))
+        }
+        None => true,
+    }
+}
+
+/// Shared cache key of a remote chart: its cache path without extension,
+/// relative to the cache dir. `None` for local sources and branch refs.
+fn shared_key(source: &ChartSource, cache_dir: &Path) -> Option<String> {
+    if source.git_ref().is_some_and(|git_ref| git_ref.is_mutable()) {
+        return None;
+    }
+    let archive = archive_cache_path(source, cache_dir)?;
+    let rel = archive
+        .with_extension("")
+        .strip_prefix(cache_dir)
+        .ok()?
+        .to_owned();
+    Some(rel.to_string_lossy().replace('\\', "/"))
+}
+
+/// Cache a downloaded chart archive for later rendering. Best-effort: a
+/// failed write only means rendering will ask for the chart to be re-fetched.
+pub(crate) fn store_archive(path: &Path, bytes: &[u8]) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(path, bytes);
+}
+
+/// Copy a cached chart archive to the cache slot of another source that
+/// resolved through it (e.g. an ArtifactHub package served by a registry).
+pub(crate) fn copy_archive(from: &Path, to: &Path) {
+    if let Ok(bytes) = std::fs::read(from) {
+        store_archive(to, &bytes);
+    }
+}
+
 /// Resolve a single chart source to its `values.schema.json` content.
 pub async fn resolve(
     name: &str,
@@ -38,23 +176,88 @@ pub async fn resolve(
     project_root: &Path,
     cache_dir: &Path,
     on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
+) -> Result<serde_json::Value, HelmError> {
+    // Remote charts are consulted in the shared cache before downloading,
+    // and published there once cached locally.
+    let shared_key = shared_key(source, cache_dir);
+    if let Some(key) = &shared_key
+        && !cache_dir.join(format!("{key}.json")).exists()
+    {
+        ctx.restore_shared(cache_dir, key);
+    }
+
+    let schema = resolve_source(name, source, project_root, cache_dir, on_progress, ctx).await?;
+    if let Some(key) = &shared_key
+        && !ctx.shared_contains(key)
+    {
+        let files = ["json", "tgz", "commit"].map(|ext| format!("{key}.{ext}"));
+        ctx.publish_shared(cache_dir, key, &files.each_ref().map(String::as_str));
+    }
+    Ok(schema)
+}
+
+/// Make sure the archive of a remote chart is cached for [`render`].
+///
+/// Unlike [`resolve`], a chart without a `values.schema.json` is not an
+/// error: charts that only ship CRDs often have none. Local `file` and
+/// `chart` sources are read in place and need nothing cached.
+pub async fn fetch_archive(
+    name: &str,
+    source: &ChartSource,
+    project_root: &Path,
+    cache_dir: &Path,
+    ctx: &FetchContext,
+) -> Result<(), HelmError> {
+    let Some(archive) = archive_cache_path(source, cache_dir) else {
+        return Ok(());
+    };
+    let mutable = source.git_ref().is_some_and(|git_ref| git_ref.is_mutable());
+    if !mutable || ctx.offline {
+        if !archive.exists()
+            && let Some(key) = shared_key(source, cache_dir)
+        {
+            ctx.restore_shared(cache_dir, &key);
+        }
+        if archive.exists() {
+            return Ok(());
+        }
+    }
+
+    match resolve(name, source, project_root, cache_dir, None, ctx).await {
+        Ok(_) => Ok(()),
+        // Downloaded, but without a values schema
+        Err(HelmError::NotFound(_)) if archive.exists() => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+async fn resolve_source(
+    name: &str,
+    source: &ChartSource,
+    project_root: &Path,
+    cache_dir: &Path,
+    on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<serde_json::Value, HelmError> {
     match source {
         ChartSource::File { path } => file::resolve(name, path, project_root),
+        ChartSource::Chart { path } => chart::resolve(name, path, project_root),
         ChartSource::Registry {
             repo,
             chart,
             version,
-        } => registry::resolve(name, repo, chart, version, cache_dir, on_progress).await,
+        } => registry::resolve(name, repo, chart, version, cache_dir, on_progress, ctx).await,
         ChartSource::ArtifactHub { package, version } => {
-            artifacthub::resolve(name, package, version, cache_dir, on_progress).await
+            artifacthub::resolve(name, package, version, cache_dir, on_progress, ctx).await
         }
-        ChartSource::Git { repo, tag, path } => {
-            git::resolve(name, repo, tag, path, cache_dir, on_progress).await
+        ChartSource::Git { repo, path, .. } => {
+            let git_ref = source.git_ref().expect("git source has a git ref");
+            git::resolve(name, repo, git_ref, path, cache_dir, on_progress, ctx).await
         }
         ChartSource::Oci { reference, version } => {
             let chart = crate::oci::chart_name_from_reference(reference);
-            crate::oci::resolve(name, reference, chart, version, cache_dir, on_progress).await
+            crate::oci::resolve(name, reference, chart, version, cache_dir, on_progress, ctx).await
         }
     }
 }
@@ -66,6 +269,7 @@ pub async fn resolve_all(
     charts: &HashMap<String, ChartSource>,
     project_root: &Path,
     cache_dir: &Path,
+    ctx: &FetchContext,
 ) -> Result<HashMap<String, serde_json::Value>, HelmError> {
     if charts.is_empty() {
         return Ok(HashMap::new());
@@ -77,8 +281,9 @@ pub async fn resolve_all(
         let source = source.clone();
         let project_root = project_root.to_path_buf();
         let cache_dir = cache_dir.to_path_buf();
+        let ctx = ctx.clone();
         set.spawn(async move {
-            let schema = resolve(&name, &source, &project_root, &cache_dir, None).await?;
+            let schema = resolve(&name, &source, &project_root, &cache_dir, None, &ctx).await?;
             Ok::<_, HelmError>((name, schema))
         });
     }
@@ -134,9 +339,16 @@ mod tests {
             reference: reference.to_string(),
             version: "16.4.0".to_string(),
         };
-        let result = resolve("test", &source, tmp.path(), tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            &source,
+            tmp.path(),
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
     }
 
@@ -144,9 +356,14 @@ mod tests {
     async fn resolve_all_empty() {
         let charts = HashMap::new();
         let tmp = tempfile::tempdir().unwrap();
-        let result = resolve_all(&charts, tmp.path(), &tmp.path().join("cache"))
-            .await
-            .unwrap();
+        let result = resolve_all(
+            &charts,
+            tmp.path(),
+            &tmp.path().join("cache"),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert!(result.is_empty());
     }
 
@@ -167,10 +384,53 @@ mod tests {
             },
         );
 
-        let result = resolve_all(&charts, tmp.path(), &tmp.path().join("cache"))
-            .await
-            .unwrap();
+        let result = resolve_all(
+            &charts,
+            tmp.path(),
+            &tmp.path().join("cache"),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result.len(), 1);
         assert!(result.contains_key("my-chart"));
     }
+
+    #[test]
+    fn archive_digest_hashes_cached_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = ChartSource::Oci {
+            reference: "oci://ghcr.io/org/chart".to_string(),
+            version: "1.0.0".to_string(),
+        };
+        assert_eq!(archive_digest(&source, tmp.path()), None);
+
+        store_archive(&archive_cache_path(&source, tmp.path()).unwrap(), b"abc");
+        assert_eq!(
+            archive_digest(&source, tmp.path()).as_deref(),
+            Some("sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+    }
+
+    #[test]
+    fn is_cached_checks_schema_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = ChartSource::Oci {
+            reference: "oci://ghcr.io/org/chart".to_string(),
+            version: "1.0.0".to_string(),
+        };
+        assert!(!is_cached(&source, tmp.path(), &FetchContext::default()));
+        store_archive(
+            &archive_cache_path(&source, tmp.path())
+                .unwrap()
+                .with_extension("json"),
+            b"{}",
+        );
+        assert!(is_cached(&source, tmp.path(), &FetchContext::default()));
+
+        let local = ChartSource::File {
+            path: "values.schema.json".to_string(),
+        };
+        assert!(is_cached(&local, tmp.path(), &FetchContext::default()));
+    }
 }
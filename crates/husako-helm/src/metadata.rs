@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use husako_config::ChartSource;
+use serde::Deserialize;
+
+/// A chart's identity as declared in its `Chart.yaml`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ChartMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default, rename = "appVersion")]
+    pub app_version: Option<String>,
+}
+
+/// Read the `Chart.yaml` of a configured chart.
+///
+/// Remote charts are read from the archive cached by [`crate::resolve`],
+/// `chart` sources from their directory or archive, and `file` sources from
+/// the directory holding the configured schema. When no
+/// `Chart.yaml` is available, the name and version declared by the source
+/// itself are returned instead.
+pub fn chart_metadata(
+    name: &str,
+    source: &ChartSource,
+    project_root: &Path,
+    cache_dir: &Path,
+) -> ChartMetadata {
+    read_chart_yaml(source, project_root, cache_dir).unwrap_or_else(|| fallback(name, source))
+}
+
+fn read_chart_yaml(
+    source: &ChartSource,
+    project_root: &Path,
+    cache_dir: &Path,
+) -> Option<ChartMetadata> {
+    let bytes = match source {
+        ChartSource::File { path } => {
+            let resolved = project_root.join(path);
+            let dir = if resolved.is_dir() {
+                resolved.as_path()
+            } else {
+                resolved.parent()?
+            };
+            std::fs::read(dir.join("Chart.yaml")).ok()?
+        }
+        ChartSource::Chart { path } if project_root.join(path).is_dir() => {
+            std::fs::read(project_root.join(path).join("Chart.yaml")).ok()?
+        }
+        _ => {
+            let archive_path = match source {
+                ChartSource::Chart { path } => project_root.join(path),
+                _ => crate::archive_cache_path(source, cache_dir)?,
+            };
+            let archive = std::fs::read(archive_path).ok()?;
+            let mut files = crate::subchart::read_archive("", &archive).ok()?;
+            let root = crate::render::archive_root(&files)?;
+            files.remove(&format!("{root}Chart.yaml"))?
+        }
+    };
+    serde_yaml_ng::from_slice(&bytes).ok()
+}
+
+fn fallback(name: &str, source: &ChartSource) -> ChartMetadata {
+    let (chart, version) = match source {
+        ChartSource::Registry { chart, version, .. } => (chart.as_str(), Some(version)),
+        ChartSource::ArtifactHub { package, version } => {
+            (package.rsplit('/').next().unwrap_or(package), Some(version))
+        }
+        ChartSource::Oci { reference, version } => (
+            crate::oci::chart_name_from_reference(reference),
+            Some(version),
+        ),
+        ChartSource::Git { path, tag, .. } => {
+            let dir = path.strip_suffix("values.schema.json").unwrap_or(path);
+            let dir = dir.trim_end_matches('/');
+            (
+                dir.rsplit('/').next().unwrap_or(name),
+                Some(tag).filter(|t| !t.is_empty()),
+            )
+        }
+        ChartSource::File { .. } | ChartSource::Chart { .. } => (name, None),
+    };
+    ChartMetadata {
+        name: if chart.is_empty() { name } else { chart }.to_string(),
+        version: version.cloned(),
+        app_version: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_chart_yaml_next_to_file_schema() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("charts/web");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Chart.yaml"),
+            "apiVersion: v2\nname: web-app\nversion: 1.2.0\nappVersion: \"3.4\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("values.schema.json"), "{}").unwrap();
+
+        let source = ChartSource::File {
+            path: "charts/web/values.schema.json".to_string(),
+        };
+        let meta = chart_metadata("web", &source, tmp.path(), tmp.path());
+        assert_eq!(meta.name, "web-app");
+        assert_eq!(meta.version.as_deref(), Some("1.2.0"));
+        assert_eq!(meta.app_version.as_deref(), Some("3.4"));
+    }
+
+    #[test]
+    fn falls_back_to_source_without_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = ChartSource::Registry {
+            repo: "https://kubernetes.github.io/ingress-nginx".to_string(),
+            chart: "ingress-nginx".to_string(),
+            version: "4.11.0".to_string(),
+        };
+        let meta = chart_metadata("ingress", &source, tmp.path(), tmp.path());
+        assert_eq!(meta.name, "ingress-nginx");
+        assert_eq!(meta.version.as_deref(), Some("4.11.0"));
+        assert_eq!(meta.app_version, None);
+
+        let source = ChartSource::Git {
+            repo: "https://github.com/example/charts".to_string(),
+            tag: "v1.0.0".to_string(),
+            branch: None,
+            rev: None,
+            path: "charts/my-chart/values.schema.json".to_string(),
+        };
+        let meta = chart_metadata("mine", &source, tmp.path(), tmp.path());
+        assert_eq!(meta.name, "my-chart");
+
+        let source = ChartSource::File {
+            path: "schemas/values.schema.json".to_string(),
+        };
+        let meta = chart_metadata("local", &source, tmp.path(), tmp.path());
+        assert_eq!(meta.name, "local");
+        assert_eq!(meta.version, None);
+    }
+}
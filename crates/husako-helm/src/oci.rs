@@ -1,6 +1,10 @@
 use std::path::Path;
 
+use base64::Engine;
+use husako_config::FetchContext;
+
 use crate::HelmError;
+use crate::auth::RegistryCredentials;
 
 const MANIFEST_ACCEPT: &str = concat!(
     "application/vnd.oci.image.manifest.v1+json,",
@@ -16,12 +20,12 @@ const TAR_GZ_MEDIA_TYPE: &str = "application/tar+gzip";
 /// Flow:
 /// 1. Check cache
 /// 2. Parse reference → (host, repo, tag)
-/// 3. Authenticate (anonymous bearer token or no-auth)
+/// 3. Authenticate (configured credentials, anonymous bearer token or no-auth)
 /// 4. Fetch OCI manifest (handles image index by picking first manifest)
 /// 5. Find Helm chart content layer by media type
 /// 6. Download blob (.tgz)
 /// 7. Extract `values.schema.json` (reuses registry::extract_values_schema)
-/// 8. Cache and return
+/// 8. Cache the schema and the blob (for rendering) and return
 pub(crate) async fn resolve(
     name: &str,
     reference: &str,
@@ -29,6 +33,7 @@ pub(crate) async fn resolve(
     version: &str,
     cache_dir: &Path,
     on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<serde_json::Value, HelmError> {
     // Check cache
     let cache_key = crate::cache_hash(reference);
@@ -45,15 +50,20 @@ pub(crate) async fn resolve(
         });
     }
 
-    let (host, repo, ref_tag) = parse_oci_reference(reference)?;
+    if ctx.offline {
+        return Err(crate::offline_miss(name, &cache_path));
+    }
+
+    let (host, repo, ref_tag) = parse_oci_reference(&ctx.rewrite_url(reference))?;
     let tag = ref_tag.as_deref().unwrap_or(version);
 
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .timeout(std::time::Duration::from_secs(60))
         .build()
         .map_err(|e| HelmError::Io(format!("chart '{name}': build HTTP client: {e}")))?;
 
-    let token = get_token(&client, name, &host, &repo).await?;
+    let token = get_token(&client, name, &host, &repo, ctx).await?;
     let token_ref = token.as_deref();
 
     let manifest = fetch_manifest(
@@ -77,6 +87,7 @@ pub(crate) async fn resolve(
     )
     .await?;
 
+    crate::store_archive(&cache_path.with_extension("tgz"), &blob_bytes);
     let schema = crate::registry::extract_values_schema(name, chart, &blob_bytes)?;
 
     // Cache
@@ -106,24 +117,32 @@ pub(crate) fn chart_name_from_reference(reference: &str) -> &str {
 
 /// Fetch available stable semver tags from an OCI registry for the given reference.
 ///
-/// Uses the same anonymous bearer-token auth flow as `resolve()`.
+/// Uses the same auth flow as `resolve()`.
 /// Returns tags sorted descending by semver, up to `limit` starting at `offset`.
 pub async fn list_tags(
     reference: &str,
     limit: usize,
     offset: usize,
+    ctx: &FetchContext,
 ) -> Result<Vec<String>, HelmError> {
-    let (host, repo, _) = parse_oci_reference(reference)?;
+    if ctx.offline {
+        return Err(HelmError::Offline(format!(
+            "cannot list tags of {reference} without network access"
+        )));
+    }
+    let (host, repo, _) = parse_oci_reference(&ctx.rewrite_url(reference))?;
 
-    let client = reqwest::Client::builder()
+    let client = ctx
+        .client_builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()
         .map_err(|e| HelmError::Io(format!("list_tags: build HTTP client: {e}")))?;
 
-    let token = get_token(&client, "list_tags", &host, &repo)
-        .await
-        .ok()
-        .flatten();
+    let token = match get_token(&client, "list_tags", &host, &repo, ctx).await {
+        Ok(token) => token,
+        Err(e @ HelmError::Auth(_)) => return Err(e),
+        Err(_) => None,
+    };
 
     let url = format!(
         "{}://{host}/v2/{repo}/tags/list?n=200",
@@ -131,7 +150,7 @@ pub async fn list_tags(
     );
     let mut builder = client.get(&url).header("User-Agent", "husako");
     if let Some(ref tok) = token {
-        builder = builder.bearer_auth(tok);
+        builder = builder.header("Authorization", tok);
     }
 
     let resp = builder
@@ -238,14 +257,31 @@ fn registry_scheme(host: &str) -> &'static str {
     }
 }
 
-/// Attempt to obtain an anonymous bearer token for the registry.
+/// Authenticate against the registry, returning the `Authorization` header
+/// value to send with later requests.
 ///
 /// Returns `Ok(None)` when the registry accepts requests without authentication.
+/// Otherwise follows the registry's challenge: `Basic` sends the configured
+/// credentials as-is, `Bearer` exchanges them (or nothing, for anonymous
+/// pulls) for a token at the realm. See [`crate::auth`] for where credentials
+/// come from.
 async fn get_token(
     client: &reqwest::Client,
     name: &str,
     host: &str,
     repo: &str,
+    ctx: &FetchContext,
+) -> Result<Option<String>, HelmError> {
+    let credentials = crate::auth::registry_credentials(host, ctx)?;
+    authenticate(client, name, host, repo, credentials.as_ref()).await
+}
+
+async fn authenticate(
+    client: &reqwest::Client,
+    name: &str,
+    host: &str,
+    repo: &str,
+    credentials: Option<&RegistryCredentials>,
 ) -> Result<Option<String>, HelmError> {
     let scheme = registry_scheme(host);
     let ping_url = format!("{scheme}://{host}/v2/");
@@ -278,24 +314,76 @@ async fn get_token(
         })?
         .to_owned();
 
+    if www_auth.to_ascii_lowercase().starts_with("basic") {
+        return match credentials {
+            Some(RegistryCredentials::Basic { username, password }) => {
+                let encoded = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{username}:{password}"));
+                Ok(Some(format!("Basic {encoded}")))
+            }
+            _ => Err(missing_credentials(name, host)),
+        };
+    }
+
     let (realm, service, _scope) = parse_www_authenticate(&www_auth).ok_or_else(|| {
         HelmError::Io(format!(
             "chart '{name}': could not parse WWW-Authenticate: {www_auth}"
         ))
     })?;
 
-    let mut token_url = format!("{realm}?scope=repository:{repo}:pull");
-    if let Some(svc) = service {
-        token_url.push_str(&format!("&service={svc}"));
-    }
+    let scope = format!("repository:{repo}:pull");
+    let token_req = match credentials {
+        // OAuth2 refresh-token grant, as `docker login` does for identity tokens
+        Some(RegistryCredentials::IdentityToken(refresh_token)) => {
+            let mut form = vec![
+                ("grant_type", "refresh_token"),
+                ("client_id", "husako"),
+                ("scope", scope.as_str()),
+                ("refresh_token", refresh_token.as_str()),
+            ];
+            if let Some(svc) = &service {
+                form.push(("service", svc.as_str()));
+            }
+            let body = form
+                .iter()
+                .map(|(k, v)| format!("{k}={}", form_encode(v)))
+                .collect::<Vec<_>>()
+                .join("&");
+            client
+                .post(&realm)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(body)
+        }
+        _ => {
+            let mut token_url = format!("{realm}?scope={scope}");
+            if let Some(svc) = &service {
+                token_url.push_str(&format!("&service={svc}"));
+            }
+            let req = client.get(token_url);
+            match credentials {
+                Some(RegistryCredentials::Basic { username, password }) => {
+                    req.basic_auth(username, Some(password))
+                }
+                _ => req,
+            }
+        }
+    };
 
-    let token_resp = client
-        .get(&token_url)
+    let token_resp = token_req
         .header("User-Agent", "husako")
         .send()
         .await
-        .map_err(|e| HelmError::Io(format!("chart '{name}': OCI token fetch {token_url}: {e}")))?;
-
+        .map_err(|e| HelmError::Io(format!("chart '{name}': OCI token fetch {realm}: {e}")))?;
+
+    let status = token_resp.status().as_u16();
+    if status == 401 || status == 403 {
+        return Err(match credentials {
+            Some(_) => HelmError::Auth(format!(
+                "chart '{name}': OCI registry {host} rejected the configured credentials ({status})"
+            )),
+            None => missing_credentials(name, host),
+        });
+    }
     if !token_resp.status().is_success() {
         return Err(HelmError::Io(format!(
             "chart '{name}': OCI token fetch returned {}",
@@ -318,7 +406,27 @@ async fn get_token(
             ))
         })?;
 
-    Ok(Some(token.to_string()))
+    Ok(Some(format!("Bearer {token}")))
+}
+
+fn missing_credentials(name: &str, host: &str) -> HelmError {
+    HelmError::Auth(format!(
+        "chart '{name}': OCI registry {host} requires credentials; add them to [credentials] \
+         in husako.toml or log in with `helm registry login` / `docker login`"
+    ))
+}
+
+/// Percent-encode a value for an `application/x-www-form-urlencoded` body.
+fn form_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
 }
 
 /// Parse a `WWW-Authenticate: Bearer ...` header value.
@@ -389,7 +497,7 @@ fn fetch_manifest(
             .header("User-Agent", "husako");
 
         if let Some(ref tok) = token {
-            req = req.bearer_auth(tok);
+            req = req.header("Authorization", tok);
         }
 
         let resp = req
@@ -481,7 +589,7 @@ async fn download_blob(
     let mut req = client.get(&url).header("User-Agent", "husako");
 
     if let Some(tok) = token {
-        req = req.bearer_auth(tok);
+        req = req.header("Authorization", tok);
     }
 
     let resp = req
@@ -511,6 +619,16 @@ async fn download_blob(
         }
         buf.extend_from_slice(&chunk);
     }
+
+    // Content-addressed blobs must hash to the digest they were fetched by.
+    if digest.starts_with("sha256:") {
+        let actual = husako_digest::sha256_digest(&buf);
+        if actual != digest {
+            return Err(HelmError::Io(format!(
+                "chart '{name}': blob digest mismatch for {url}: expected {digest}, got {actual}"
+            )));
+        }
+    }
     Ok(buf)
 }
 
@@ -681,9 +799,17 @@ mod tests {
         )
         .unwrap();
 
-        let result = resolve("test", reference, "postgresql", "16.4.0", cache_dir, None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            reference,
+            "postgresql",
+            "16.4.0",
+            cache_dir,
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["replicaCount"].is_object());
     }
@@ -720,12 +846,14 @@ mod tests {
             .create_async()
             .await;
 
+        let blob = make_chart_tgz("mychart");
+        let blob_digest = husako_digest::sha256_digest(&blob);
         let manifest = serde_json::json!({
             "mediaType": "application/vnd.oci.image.manifest.v1+json",
             "schemaVersion": 2,
             "layers": [{
                 "mediaType": HELM_LAYER_MEDIA_TYPE,
-                "digest": "sha256:fakedigest",
+                "digest": blob_digest,
                 "size": 1234
             }]
         });
@@ -737,9 +865,11 @@ mod tests {
             .create_async()
             .await;
 
-        let blob = make_chart_tgz("mychart");
         let _m_blob = server
-            .mock("GET", "/v2/myorg/mychart/blobs/sha256:fakedigest")
+            .mock(
+                "GET",
+                format!("/v2/myorg/mychart/blobs/{blob_digest}").as_str(),
+            )
             .with_status(200)
             .with_body(blob)
             .create_async()
@@ -747,9 +877,17 @@ mod tests {
 
         let tmp = tempfile::tempdir().unwrap();
         let reference = format!("oci://{}/myorg/mychart", server.host_with_port());
-        let result = resolve("test", &reference, "mychart", "1.0.0", tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            &reference,
+            "mychart",
+            "1.0.0",
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["replicaCount"].is_object());
     }
@@ -786,12 +924,14 @@ mod tests {
             .create_async()
             .await;
 
+        let blob = make_chart_tgz("mychart");
+        let blob_digest = husako_digest::sha256_digest(&blob);
         let manifest = serde_json::json!({
             "mediaType": "application/vnd.oci.image.manifest.v1+json",
             "schemaVersion": 2,
             "layers": [{
                 "mediaType": HELM_LAYER_MEDIA_TYPE,
-                "digest": "sha256:authdigest",
+                "digest": blob_digest,
                 "size": 1234
             }]
         });
@@ -804,9 +944,11 @@ mod tests {
             .create_async()
             .await;
 
-        let blob = make_chart_tgz("mychart");
         let _m_blob = server
-            .mock("GET", "/v2/myorg/mychart/blobs/sha256:authdigest")
+            .mock(
+                "GET",
+                format!("/v2/myorg/mychart/blobs/{blob_digest}").as_str(),
+            )
             .match_header("authorization", "Bearer test-bearer-token")
             .with_status(200)
             .with_body(blob)
@@ -815,13 +957,125 @@ mod tests {
 
         let tmp = tempfile::tempdir().unwrap();
         let reference = format!("oci://{host}/myorg/mychart");
-        let result = resolve("test", &reference, "mychart", "2.0.0", tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            &reference,
+            "mychart",
+            "2.0.0",
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["replicaCount"].is_object());
     }
 
+    fn basic_credentials() -> RegistryCredentials {
+        RegistryCredentials::Basic {
+            username: "robot".to_string(),
+            password: "s3cret".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn authenticate_basic_challenge_sends_credentials() {
+        // ECR-style registries challenge with Basic and take the credentials directly
+        let mut server = mockito::Server::new_async().await;
+        let _m_ping = server
+            .mock("GET", "/v2/")
+            .with_status(401)
+            .with_header("www-authenticate", r#"Basic realm="registry""#)
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let host = server.host_with_port();
+        let auth = authenticate(
+            &client,
+            "test",
+            &host,
+            "org/app",
+            Some(&basic_credentials()),
+        )
+        .await
+        .unwrap();
+        // base64("robot:s3cret")
+        assert_eq!(auth.as_deref(), Some("Basic cm9ib3Q6czNjcmV0"));
+
+        let err = authenticate(&client, "test", &host, "org/app", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HelmError::Auth(_)), "{err}");
+        assert!(err.to_string().contains("requires credentials"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn authenticate_bearer_exchanges_credentials_for_token() {
+        let mut server = mockito::Server::new_async().await;
+        let host = server.host_with_port();
+        let _m_ping = server
+            .mock("GET", "/v2/")
+            .with_status(401)
+            .with_header(
+                "www-authenticate",
+                &format!(r#"Bearer realm="http://{host}/service/token",service="harbor""#),
+            )
+            .create_async()
+            .await;
+        let _m_token = server
+            .mock("GET", "/service/token")
+            .match_query(mockito::Matcher::Any)
+            .match_header("authorization", "Basic cm9ib3Q6czNjcmV0")
+            .with_status(200)
+            .with_body(r#"{"token":"private-token"}"#)
+            .create_async()
+            .await;
+        let _m_denied = server
+            .mock("GET", "/service/token")
+            .match_query(mockito::Matcher::Any)
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(401)
+            .create_async()
+            .await;
+        let _m_refresh = server
+            .mock("POST", "/service/token")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+                mockito::Matcher::UrlEncoded("refresh_token".into(), "refresh".into()),
+                mockito::Matcher::UrlEncoded("scope".into(), "repository:org/app:pull".into()),
+                mockito::Matcher::UrlEncoded("service".into(), "harbor".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"access_token":"refreshed-token"}"#)
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let auth = authenticate(
+            &client,
+            "test",
+            &host,
+            "org/app",
+            Some(&basic_credentials()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(auth.as_deref(), Some("Bearer private-token"));
+
+        let identity = RegistryCredentials::IdentityToken("refresh".to_string());
+        let auth = authenticate(&client, "test", &host, "org/app", Some(&identity))
+            .await
+            .unwrap();
+        assert_eq!(auth.as_deref(), Some("Bearer refreshed-token"));
+
+        let err = authenticate(&client, "test", &host, "org/app", None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("requires credentials"), "{err}");
+    }
+
     #[tokio::test]
     async fn list_tags_returns_sorted_semver_tags() {
         let mut server = mockito::Server::new_async().await;
@@ -846,7 +1100,9 @@ mod tests {
             .await;
 
         let reference = format!("oci://{}/myorg/mychart", server.host_with_port());
-        let tags = list_tags(&reference, 10, 0).await.unwrap();
+        let tags = list_tags(&reference, 10, 0, &FetchContext::default())
+            .await
+            .unwrap();
         // Sorted descending, only stable semver, max 10
         assert_eq!(tags, vec!["2.0.0", "1.5.0", "1.0.0"]);
     }
@@ -875,7 +1131,9 @@ mod tests {
             .await;
 
         let reference = format!("oci://{}/myorg/mychart", server.host_with_port());
-        let tags = list_tags(&reference, 1, 1).await.unwrap();
+        let tags = list_tags(&reference, 1, 1, &FetchContext::default())
+            .await
+            .unwrap();
         assert_eq!(tags, vec!["2.0.0"]);
     }
 
@@ -906,12 +1164,14 @@ mod tests {
             .await;
 
         // Second request (recurse with digest) returns the actual manifest
+        let blob = make_chart_tgz("multichart");
+        let blob_digest = husako_digest::sha256_digest(&blob);
         let manifest = serde_json::json!({
             "mediaType": "application/vnd.oci.image.manifest.v1+json",
             "schemaVersion": 2,
             "layers": [{
                 "mediaType": HELM_LAYER_MEDIA_TYPE,
-                "digest": "sha256:indexblob",
+                "digest": blob_digest,
                 "size": 100
             }]
         });
@@ -923,9 +1183,11 @@ mod tests {
             .create_async()
             .await;
 
-        let blob = make_chart_tgz("multichart");
         let _m_blob = server
-            .mock("GET", "/v2/myorg/multichart/blobs/sha256:indexblob")
+            .mock(
+                "GET",
+                format!("/v2/myorg/multichart/blobs/{blob_digest}").as_str(),
+            )
             .with_status(200)
             .with_body(blob)
             .create_async()
@@ -933,9 +1195,71 @@ mod tests {
 
         let tmp = tempfile::tempdir().unwrap();
         let reference = format!("oci://{}/myorg/multichart", server.host_with_port());
-        let result = resolve("test", &reference, "multichart", "3.0.0", tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            &reference,
+            "multichart",
+            "3.0.0",
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
     }
+
+    #[tokio::test]
+    async fn oci_blob_digest_mismatch_is_rejected() {
+        let mut server = mockito::Server::new_async().await;
+        let _m_ping = server
+            .mock("GET", "/v2/")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let blob = make_chart_tgz("mychart");
+        let blob_digest = husako_digest::sha256_digest(b"something else");
+        let manifest = serde_json::json!({
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "schemaVersion": 2,
+            "layers": [{
+                "mediaType": HELM_LAYER_MEDIA_TYPE,
+                "digest": blob_digest,
+                "size": 1234
+            }]
+        });
+        let _m_manifest = server
+            .mock("GET", "/v2/myorg/mychart/manifests/1.0.0")
+            .with_status(200)
+            .with_header("content-type", "application/vnd.oci.image.manifest.v1+json")
+            .with_body(manifest.to_string())
+            .create_async()
+            .await;
+        let _m_blob = server
+            .mock(
+                "GET",
+                format!("/v2/myorg/mychart/blobs/{blob_digest}").as_str(),
+            )
+            .with_status(200)
+            .with_body(blob)
+            .create_async()
+            .await;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let reference = format!("oci://{}/myorg/mychart", server.host_with_port());
+        let err = resolve(
+            "test",
+            &reference,
+            "mychart",
+            "1.0.0",
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("digest mismatch"), "{err}");
+        assert!(!tmp.path().join("helm").exists());
+    }
 }
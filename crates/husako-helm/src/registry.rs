@@ -1,6 +1,7 @@
-use std::io::Read;
 use std::path::Path;
 
+use husako_config::FetchContext;
+
 use crate::HelmError;
 
 /// Resolve a Helm chart from a Helm repository.
@@ -12,9 +13,9 @@ use crate::HelmError;
 /// 4. Find chart entry → match version → get archive URL
 /// 5. If archive URL is OCI (some registries list `oci://` in their index),
 ///    delegate to `oci::resolve`
-/// 6. Download `.tgz` archive
+/// 6. Download `.tgz` archive and cache it (for rendering)
 /// 7. Extract `values.schema.json` from archive
-/// 8. Cache and return
+/// 8. Cache the schema and return
 pub async fn resolve(
     name: &str,
     repo: &str,
@@ -22,10 +23,11 @@ pub async fn resolve(
     version: &str,
     cache_dir: &Path,
     on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<serde_json::Value, HelmError> {
     // Delegate OCI registries to the dedicated OCI resolver
     if repo.starts_with("oci://") {
-        return crate::oci::resolve(name, repo, chart, version, cache_dir, on_progress).await;
+        return crate::oci::resolve(name, repo, chart, version, cache_dir, on_progress, ctx).await;
     }
 
     // Check cache
@@ -43,22 +45,48 @@ pub async fn resolve(
         });
     }
 
-    let client = reqwest::Client::new();
+    if ctx.offline {
+        return Err(crate::offline_miss(name, &cache_path));
+    }
+
+    let client = ctx
+        .client_builder()
+        .build()
+        .map_err(|e| HelmError::Io(format!("chart '{name}': build HTTP client: {e}")))?;
 
     // Fetch index.yaml
     let index_url = format!("{}/index.yaml", repo.trim_end_matches('/'));
-    let index_yaml = fetch_url(&client, name, &index_url).await?;
+    let index_yaml = fetch_url(&client, name, repo, &index_url, ctx).await?;
     let archive_url = find_chart_archive_url(name, chart, version, &index_yaml)?;
 
     // Some Helm registry index.yaml files list OCI URLs as archive URLs
     // (e.g. Bitnami moved their HTTP registry to OCI). Delegate to oci::resolve.
     if archive_url.starts_with("oci://") {
-        return crate::oci::resolve(name, &archive_url, chart, version, cache_dir, on_progress)
-            .await;
+        let schema = crate::oci::resolve(
+            name,
+            &archive_url,
+            chart,
+            version,
+            cache_dir,
+            on_progress,
+            ctx,
+        )
+        .await;
+        // Also when the chart has no values schema, so it can still be rendered
+        let oci_source = husako_config::ChartSource::Oci {
+            reference: archive_url,
+            version: version.to_string(),
+        };
+        if let Some(oci_archive) = crate::archive_cache_path(&oci_source, cache_dir) {
+            crate::copy_archive(&oci_archive, &cache_path.with_extension("tgz"));
+        }
+        return schema;
     }
 
-    // Download and extract
-    let archive_bytes = fetch_url_bytes(&client, name, &archive_url, on_progress).await?;
+    // Download, keep the archive even if it has no values schema, and extract
+    let archive_bytes =
+        fetch_url_bytes(&client, name, repo, &archive_url, on_progress, ctx).await?;
+    crate::store_archive(&cache_path.with_extension("tgz"), &archive_bytes);
     let schema = extract_values_schema(name, chart, &archive_bytes)?;
 
     // Cache
@@ -73,19 +101,22 @@ pub async fn resolve(
     Ok(schema)
 }
 
-/// Fetch a URL as text.
-async fn fetch_url(client: &reqwest::Client, name: &str, url: &str) -> Result<String, HelmError> {
-    let resp = client
-        .get(url)
+/// Fetch a URL of repository `repo` as text.
+async fn fetch_url(
+    client: &reqwest::Client,
+    name: &str,
+    repo: &str,
+    url: &str,
+    ctx: &FetchContext,
+) -> Result<String, HelmError> {
+    let request = client.get(ctx.rewrite_url(url));
+    let resp = crate::auth::authorize(request, repo, url, ctx)?
         .send()
         .await
         .map_err(|e| HelmError::Io(format!("chart '{name}': fetch {url}: {e}")))?;
 
     if !resp.status().is_success() {
-        return Err(HelmError::Io(format!(
-            "chart '{name}': fetch {url} returned {}",
-            resp.status()
-        )));
+        return Err(fetch_error(name, repo, url, resp.status()));
     }
 
     resp.text()
@@ -93,24 +124,34 @@ async fn fetch_url(client: &reqwest::Client, name: &str, url: &str) -> Result<St
         .map_err(|e| HelmError::Io(format!("chart '{name}': read response from {url}: {e}")))
 }
 
+fn fetch_error(name: &str, repo: &str, url: &str, status: reqwest::StatusCode) -> HelmError {
+    if matches!(status.as_u16(), 401 | 403) {
+        return HelmError::Auth(format!(
+            "chart '{name}': fetch {url} returned {status}; add credentials for {} to \
+             [credentials] in husako.toml or to Helm's repositories.yaml",
+            husako_config::credentials::host_of(repo)
+        ));
+    }
+    HelmError::Io(format!("chart '{name}': fetch {url} returned {status}"))
+}
+
 /// Fetch a URL as bytes, streaming chunks and reporting progress.
 async fn fetch_url_bytes(
     client: &reqwest::Client,
     name: &str,
+    repo: &str,
     url: &str,
     on_progress: Option<&crate::ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<Vec<u8>, HelmError> {
-    let resp = client
-        .get(url)
+    let request = client.get(ctx.rewrite_url(url));
+    let resp = crate::auth::authorize(request, repo, url, ctx)?
         .send()
         .await
         .map_err(|e| HelmError::Io(format!("chart '{name}': fetch {url}: {e}")))?;
 
     if !resp.status().is_success() {
-        return Err(HelmError::Io(format!(
-            "chart '{name}': fetch {url} returned {}",
-            resp.status()
-        )));
+        return Err(fetch_error(name, repo, url, resp.status()));
     }
 
     let total_bytes = resp.content_length();
@@ -175,46 +216,20 @@ fn find_chart_archive_url(
 }
 
 /// Extract `values.schema.json` from a `.tgz` archive.
+///
+/// For umbrella charts, the schemas of subcharts vendored under `charts/` are
+/// composed into the result (see `subchart::chart_values_schema`).
 pub(crate) fn extract_values_schema(
     name: &str,
     chart: &str,
     archive_bytes: &[u8],
 ) -> Result<serde_json::Value, HelmError> {
-    let gz = flate2::read::GzDecoder::new(archive_bytes);
-    let mut archive = tar::Archive::new(gz);
-
-    let target_path = format!("{chart}/values.schema.json");
-
-    for entry in archive
-        .entries()
-        .map_err(|e| HelmError::Io(format!("chart '{name}': read archive entries: {e}")))?
-    {
-        let mut entry =
-            entry.map_err(|e| HelmError::Io(format!("chart '{name}': read archive entry: {e}")))?;
-
-        let path = entry
-            .path()
-            .map_err(|e| HelmError::Io(format!("chart '{name}': read entry path: {e}")))?
-            .to_string_lossy()
-            .to_string();
-
-        if path == target_path {
-            let mut content = String::new();
-            entry.read_to_string(&mut content).map_err(|e| {
-                HelmError::Io(format!(
-                    "chart '{name}': read values.schema.json from archive: {e}"
-                ))
-            })?;
-
-            return serde_json::from_str(&content).map_err(|e| {
-                HelmError::InvalidSchema(format!("chart '{name}': parse values.schema.json: {e}"))
-            });
-        }
-    }
-
-    Err(HelmError::NotFound(format!(
-        "chart '{name}': chart does not include values.schema.json"
-    )))
+    let files = crate::subchart::read_archive(name, archive_bytes)?;
+    crate::subchart::chart_values_schema(name, &files, &format!("{chart}/"))?.ok_or_else(|| {
+        HelmError::NotFound(format!(
+            "chart '{name}': chart does not include values.schema.json"
+        ))
+    })
 }
 
 #[cfg(test)]
@@ -224,17 +239,22 @@ mod tests {
     /// Build a minimal `.tgz` archive containing `{chart}/values.schema.json`
     /// with the given JSON content.
     fn build_tgz(chart: &str, schema_json: &str) -> Vec<u8> {
+        build_tgz_files(&[(&format!("{chart}/values.schema.json"), schema_json)])
+    }
+
+    /// Build a `.tgz` archive of `(path, content)` files.
+    fn build_tgz_files(files: &[(&str, &str)]) -> Vec<u8> {
         use flate2::write::GzEncoder;
         use std::io::Write;
 
         let mut builder = tar::Builder::new(Vec::new());
-        let mut header = tar::Header::new_gnu();
-        header
-            .set_path(format!("{chart}/values.schema.json"))
-            .unwrap();
-        header.set_size(schema_json.len() as u64);
-        header.set_cksum();
-        builder.append(&header, schema_json.as_bytes()).unwrap();
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
         let tar_data = builder.into_inner().unwrap();
 
         let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
@@ -260,9 +280,17 @@ mod tests {
         )
         .unwrap();
 
-        let result = resolve("test", oci_url, "my-chart", "1.0.0", tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            oci_url,
+            "my-chart",
+            "1.0.0",
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["replicas"].is_object());
     }
@@ -379,9 +407,17 @@ entries:
         )
         .unwrap();
 
-        let result = resolve("test", repo, chart, version, tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            repo,
+            chart,
+            version,
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["cached"].is_object());
     }
@@ -418,13 +454,109 @@ entries:
             .await;
 
         let tmp = tempfile::tempdir().unwrap();
-        let result = resolve("test", &host_url, chart, version, tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            &host_url,
+            chart,
+            version,
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["replicas"].is_object());
     }
 
+    /// A chart without values.schema.json has no schema to resolve, but its
+    /// archive is still cached so its CRDs can be rendered.
+    #[tokio::test]
+    async fn archive_cached_without_values_schema() {
+        let mut server = mockito::Server::new_async().await;
+        let host_url = server.url();
+        let archive_path = "/crds-only-1.0.0.tgz";
+        let index = format!(
+            "apiVersion: v1\nentries:\n  crds-only:\n    - version: \"1.0.0\"\n      urls:\n        - {host_url}{archive_path}\n"
+        );
+        let m_index = server
+            .mock("GET", "/index.yaml")
+            .with_status(200)
+            .with_body(index)
+            .expect(2)
+            .create_async()
+            .await;
+        let tgz = build_tgz_files(&[
+            (
+                "crds-only/Chart.yaml",
+                "apiVersion: v2\nname: crds-only\nversion: 1.0.0\n",
+            ),
+            (
+                "crds-only/crds/widget.yaml",
+                "kind: CustomResourceDefinition\n",
+            ),
+        ]);
+        let _m_archive = server
+            .mock("GET", archive_path)
+            .with_status(200)
+            .with_body(tgz.clone())
+            .create_async()
+            .await;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let source = husako_config::ChartSource::Registry {
+            repo: host_url.clone(),
+            chart: "crds-only".to_string(),
+            version: "1.0.0".to_string(),
+        };
+        let err = resolve(
+            "test",
+            &host_url,
+            "crds-only",
+            "1.0.0",
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, HelmError::NotFound(_)), "{err}");
+        let archive = crate::archive_cache_path(&source, tmp.path()).unwrap();
+        assert_eq!(std::fs::read(&archive).unwrap(), tgz);
+
+        std::fs::remove_file(&archive).unwrap();
+        assert!(!crate::is_archive_cached(
+            &source,
+            tmp.path(),
+            &FetchContext::default()
+        ));
+        crate::fetch_archive(
+            "test",
+            &source,
+            tmp.path(),
+            tmp.path(),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        assert!(crate::is_archive_cached(
+            &source,
+            tmp.path(),
+            &FetchContext::default()
+        ));
+        // Served from the cache from now on
+        crate::fetch_archive(
+            "test",
+            &source,
+            tmp.path(),
+            tmp.path(),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
+        m_index.assert_async().await;
+    }
+
     /// When index.yaml lists an `oci://` archive URL, resolve() delegates to oci::resolve.
     /// Pre-populate the OCI cache so no real OCI network call is made.
     #[tokio::test]
@@ -457,9 +589,17 @@ entries:
         )
         .unwrap();
 
-        let result = resolve("test", &host_url, chart, version, tmp.path(), None)
-            .await
-            .unwrap();
+        let result = resolve(
+            "test",
+            &host_url,
+            chart,
+            version,
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result["type"], "object");
         assert!(result["properties"]["ociField"].is_object());
     }
@@ -475,9 +615,17 @@ entries:
             .await;
 
         let tmp = tempfile::tempdir().unwrap();
-        let err = resolve("test", &server.url(), "my-chart", "1.0.0", tmp.path(), None)
-            .await
-            .unwrap_err();
+        let err = resolve(
+            "test",
+            &server.url(),
+            "my-chart",
+            "1.0.0",
+            tmp.path(),
+            None,
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap_err();
         assert!(err.to_string().contains("404"));
     }
 }
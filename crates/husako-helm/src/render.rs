@@ -0,0 +1,1033 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use husako_config::ChartSource;
+use serde::Deserialize;
+use serde_json::Value as Json;
+
+use crate::HelmError;
+use crate::subchart::ChartFiles;
+use crate::template::{Templates, Value};
+
+/// Kubernetes version reported by `.Capabilities` when none is given.
+const DEFAULT_KUBE_VERSION: &str = "v1.32.0";
+
+/// Group/versions reported by `.Capabilities.APIVersions` in addition to any
+/// passed in [`RenderOptions::api_versions`].
+const BUILTIN_API_VERSIONS: &[&str] = &[
+    "v1",
+    "admissionregistration.k8s.io/v1",
+    "apiextensions.k8s.io/v1",
+    "apiregistration.k8s.io/v1",
+    "apps/v1",
+    "authentication.k8s.io/v1",
+    "authorization.k8s.io/v1",
+    "autoscaling/v1",
+    "autoscaling/v2",
+    "batch/v1",
+    "certificates.k8s.io/v1",
+    "coordination.k8s.io/v1",
+    "discovery.k8s.io/v1",
+    "events.k8s.io/v1",
+    "flowcontrol.apiserver.k8s.io/v1",
+    "networking.k8s.io/v1",
+    "node.k8s.io/v1",
+    "policy/v1",
+    "rbac.authorization.k8s.io/v1",
+    "scheduling.k8s.io/v1",
+    "storage.k8s.io/v1",
+];
+
+/// Kinds in the order Helm installs them; other kinds sort after these.
+const INSTALL_ORDER: &[&str] = &[
+    "PriorityClass",
+    "Namespace",
+    "NetworkPolicy",
+    "ResourceQuota",
+    "LimitRange",
+    "PodSecurityPolicy",
+    "PodDisruptionBudget",
+    "ServiceAccount",
+    "Secret",
+    "SecretList",
+    "ConfigMap",
+    "StorageClass",
+    "PersistentVolume",
+    "PersistentVolumeClaim",
+    "CustomResourceDefinition",
+    "ClusterRole",
+    "ClusterRoleList",
+    "ClusterRoleBinding",
+    "ClusterRoleBindingList",
+    "Role",
+    "RoleList",
+    "RoleBinding",
+    "RoleBindingList",
+    "Service",
+    "DaemonSet",
+    "Pod",
+    "ReplicationController",
+    "ReplicaSet",
+    "Deployment",
+    "HorizontalPodAutoscaler",
+    "StatefulSet",
+    "Job",
+    "CronJob",
+    "IngressClass",
+    "Ingress",
+    "APIService",
+    "MutatingWebhookConfiguration",
+    "ValidatingWebhookConfiguration",
+];
+
+/// Release settings for [`render`], mirroring `helm template` flags.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// `.Release.Name`; defaults to the chart's name in `husako.toml`.
+    pub release_name: Option<String>,
+    /// `.Release.Namespace`; defaults to `"default"`.
+    pub namespace: Option<String>,
+    /// Prepend the chart's `crds/` files to the output (`--include-crds`).
+    pub include_crds: bool,
+    /// `.Capabilities.KubeVersion`; defaults to [`DEFAULT_KUBE_VERSION`].
+    pub kube_version: Option<String>,
+    /// Extra entries for `.Capabilities.APIVersions` (`--api-versions`).
+    pub api_versions: Vec<String>,
+}
+
+/// The subset of a `Chart.yaml` dependency entry used for rendering.
+#[derive(Debug, Clone, Deserialize)]
+struct Dependency {
+    name: String,
+    #[serde(default)]
+    alias: Option<String>,
+    #[serde(default)]
+    condition: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// A loaded chart with its vendored subcharts.
+struct Chart {
+    name: String,
+    metadata: Json,
+    defaults: Json,
+    dependencies: Vec<Dependency>,
+    /// `(path relative to the chart root, content)`, sorted by path.
+    templates: Vec<(String, String)>,
+    crds: Vec<(String, String)>,
+    files: Rc<BTreeMap<String, Vec<u8>>>,
+    subcharts: Vec<Chart>,
+}
+
+/// The chart directory or archive [`render`] reads for `source`, e.g. to
+/// re-render when it changes.
+pub fn render_input(source: &ChartSource, project_root: &Path, cache_dir: &Path) -> PathBuf {
+    match source {
+        ChartSource::File { path } => {
+            let resolved = project_root.join(path);
+            if resolved.is_dir() {
+                resolved
+            } else {
+                resolved.parent().unwrap_or(project_root).to_path_buf()
+            }
+        }
+        ChartSource::Chart { path } => project_root.join(path),
+        _ => {
+            crate::archive_cache_path(source, cache_dir).unwrap_or_else(|| cache_dir.to_path_buf())
+        }
+    }
+}
+
+/// Render the templates of chart `name` with `values`, like `helm template`.
+///
+/// Remote charts are read from the archive `husako gen` cached next to the
+/// chart's values schema, so rendering needs no network access. `chart`
+/// sources render the local directory or archive, and `file` sources the
+/// chart directory containing the configured `values.schema.json`.
+///
+/// Returns the rendered Kubernetes objects in Helm's install order. Chart
+/// tests (`helm.sh/hook: test`) and `NOTES.txt` are omitted.
+pub fn render(
+    name: &str,
+    source: &ChartSource,
+    project_root: &Path,
+    cache_dir: &Path,
+    values: &Json,
+    options: &RenderOptions,
+) -> Result<Vec<Json>, HelmError> {
+    let (files, root) = match source {
+        ChartSource::File { .. } => {
+            let dir = render_input(source, project_root, cache_dir);
+            if !dir.join("Chart.yaml").exists() {
+                return Err(HelmError::NotFound(format!(
+                    "chart '{name}': no Chart.yaml in {}; rendering needs the chart directory",
+                    dir.display()
+                )));
+            }
+            (read_dir_all(name, &dir)?, String::new())
+        }
+        ChartSource::Chart { path } if project_root.join(path).is_dir() => {
+            (read_dir_all(name, &project_root.join(path))?, String::new())
+        }
+        _ => {
+            let archive = match source {
+                ChartSource::Chart { path } => project_root.join(path),
+                _ => crate::archive_cache_path(source, cache_dir)
+                    .filter(|p| p.exists())
+                    .ok_or_else(|| {
+                        HelmError::NotFound(format!(
+                            "chart '{name}': chart archive is not cached; \
+                             run `husako clean --cache` then `husako gen --no-incremental` to download it"
+                        ))
+                    })?,
+            };
+            let bytes = std::fs::read(&archive).map_err(|e| {
+                HelmError::Io(format!("chart '{name}': read {}: {e}", archive.display()))
+            })?;
+            let files = read_archive_all(name, &bytes)?;
+            let root = archive_root(&files).ok_or_else(|| {
+                HelmError::InvalidSchema(format!("chart '{name}': archive has no Chart.yaml"))
+            })?;
+            (files, root)
+        }
+    };
+
+    let chart = load_chart(name, &files, &root)?;
+    let release = Release {
+        name: options
+            .release_name
+            .clone()
+            .unwrap_or_else(|| name.to_string()),
+        namespace: options
+            .namespace
+            .clone()
+            .unwrap_or_else(|| "default".to_string()),
+    };
+    let capabilities = capabilities(options);
+
+    let mut coalesced = chart.defaults.clone();
+    merge_values(&mut coalesced, values);
+
+    let mut templates = Templates::default();
+    let mut jobs = Vec::new();
+    let mut crds = Vec::new();
+    prepare(
+        name,
+        &chart,
+        coalesced,
+        &chart.name,
+        true,
+        &release,
+        &capabilities,
+        &mut templates,
+        &mut jobs,
+        &mut crds,
+    )?;
+
+    let mut docs = Vec::new();
+    if options.include_crds {
+        for (path, content) in &crds {
+            docs.extend(split_documents(name, path, content)?);
+        }
+    }
+
+    let mut rendered = Vec::new();
+    for (template_name, data) in &jobs {
+        let out = templates
+            .execute(template_name, data)
+            .map_err(|e| HelmError::Render(format!("chart '{name}': {e}")))?;
+        rendered.extend(split_documents(name, template_name, &out)?);
+    }
+    rendered.retain(|doc| !is_test_hook(doc));
+    rendered.sort_by_key(install_rank);
+    docs.extend(rendered);
+
+    Ok(docs)
+}
+
+struct Release {
+    name: String,
+    namespace: String,
+}
+
+/// Register the templates of `chart` (subcharts first, so the parent's
+/// `define`s win) and queue its renderable files with their data.
+#[allow(clippy::too_many_arguments)]
+fn prepare(
+    name: &str,
+    chart: &Chart,
+    mut values: Json,
+    path: &str,
+    is_root: bool,
+    release: &Release,
+    capabilities: &Value,
+    templates: &mut Templates,
+    jobs: &mut Vec<(String, Value)>,
+    crds: &mut Vec<(String, String)>,
+) -> Result<(), HelmError> {
+    for (sub, key) in enabled_subcharts(chart, &values) {
+        let mut sub_values = sub.defaults.clone();
+        let mut overrides = values.get(&key).cloned().unwrap_or(Json::Null);
+        if let Some(global) = values.get("global") {
+            inherit_global(&mut overrides, global);
+        }
+        merge_values(&mut sub_values, &overrides);
+
+        // Parent templates see the subchart's coalesced values too.
+        if let Some(obj) = values.as_object_mut() {
+            obj.insert(key.clone(), sub_values.clone());
+        }
+        prepare(
+            name,
+            sub,
+            sub_values,
+            &format!("{path}/charts/{}", sub.name),
+            false,
+            release,
+            capabilities,
+            templates,
+            jobs,
+            crds,
+        )?;
+    }
+
+    let values = Value::from_json(&values);
+    let chart_meta = chart_object(&chart.metadata, is_root);
+    for (file, content) in &chart.templates {
+        let template_name = format!("{path}/{file}");
+        templates
+            .add(&template_name, content)
+            .map_err(|e| HelmError::Render(format!("chart '{name}': {e}")))?;
+
+        let base = file.rsplit('/').next().unwrap_or(file);
+        if base.starts_with('_') || base == "NOTES.txt" {
+            continue;
+        }
+        let mut data = BTreeMap::new();
+        data.insert("Values".to_string(), values.clone());
+        data.insert("Chart".to_string(), chart_meta.clone());
+        data.insert("Release".to_string(), release_object(release));
+        data.insert("Capabilities".to_string(), capabilities.clone());
+        data.insert("Files".to_string(), Value::Files(chart.files.clone()));
+        data.insert(
+            "Template".to_string(),
+            Value::map(BTreeMap::from([
+                ("Name".to_string(), Value::str(template_name.as_str())),
+                (
+                    "BasePath".to_string(),
+                    Value::str(format!("{path}/templates")),
+                ),
+            ])),
+        );
+        jobs.push((template_name, Value::map(data)));
+    }
+    for (file, content) in &chart.crds {
+        crds.push((format!("{path}/{file}"), content.clone()));
+    }
+    Ok(())
+}
+
+/// Subcharts enabled by their dependency's `condition`/`tags`, with the
+/// values key (alias or name) each one reads.
+fn enabled_subcharts<'c>(chart: &'c Chart, values: &Json) -> Vec<(&'c Chart, String)> {
+    let mut result = Vec::new();
+    for sub in &chart.subcharts {
+        let deps: Vec<&Dependency> = chart
+            .dependencies
+            .iter()
+            .filter(|d| d.name == sub.name)
+            .collect();
+        if deps.is_empty() {
+            // Vendored but undeclared subcharts are always rendered.
+            result.push((sub, sub.name.clone()));
+            continue;
+        }
+        for dep in deps {
+            if dependency_enabled(dep, values) {
+                result.push((sub, dep.alias.clone().unwrap_or_else(|| dep.name.clone())));
+            }
+        }
+    }
+    result
+}
+
+/// Helm's rule: the first `condition` path that resolves to a boolean wins;
+/// otherwise the chart is enabled if any of its `tags` is true (or it has
+/// no tags set in values).
+fn dependency_enabled(dep: &Dependency, values: &Json) -> bool {
+    if let Some(condition) = &dep.condition {
+        for path in condition
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            let found = path
+                .split('.')
+                .try_fold(values, |v, key| v.get(key))
+                .and_then(Json::as_bool);
+            if let Some(enabled) = found {
+                return enabled;
+            }
+        }
+    }
+    let tags: Vec<bool> = dep
+        .tags
+        .iter()
+        .filter_map(|t| values.get("tags")?.get(t)?.as_bool())
+        .collect();
+    tags.is_empty() || tags.contains(&true)
+}
+
+/// Deep-merge `overlay` into `base`; `null` in the overlay deletes the key.
+fn merge_values(base: &mut Json, overlay: &Json) {
+    let (Some(base_obj), Some(overlay_obj)) = (base.as_object_mut(), overlay.as_object()) else {
+        if !overlay.is_null() {
+            *base = overlay.clone();
+        }
+        return;
+    };
+    for (k, v) in overlay_obj {
+        match (base_obj.get_mut(k), v) {
+            (_, Json::Null) => {
+                base_obj.remove(k);
+            }
+            (Some(existing), Json::Object(_)) if existing.is_object() => merge_values(existing, v),
+            _ => {
+                base_obj.insert(k.clone(), v.clone());
+            }
+        }
+    }
+}
+
+/// Copy the parent's `global` values into a subchart's overrides; the
+/// parent's settings win over anything already there.
+fn inherit_global(overrides: &mut Json, global: &Json) {
+    if !overrides.is_object() {
+        *overrides = Json::Object(serde_json::Map::new());
+    }
+    let obj = overrides.as_object_mut().expect("object");
+    let target = obj
+        .entry("global")
+        .or_insert_with(|| Json::Object(serde_json::Map::new()));
+    merge_values(target, global);
+}
+
+fn release_object(release: &Release) -> Value {
+    Value::map(BTreeMap::from([
+        ("Name".to_string(), Value::str(release.name.as_str())),
+        (
+            "Namespace".to_string(),
+            Value::str(release.namespace.as_str()),
+        ),
+        ("Service".to_string(), Value::str("Helm")),
+        ("IsInstall".to_string(), Value::Bool(true)),
+        ("IsUpgrade".to_string(), Value::Bool(false)),
+        ("Revision".to_string(), Value::Int(1)),
+    ]))
+}
+
+/// `.Chart`: `Chart.yaml` with Helm's Go field names (`appVersion` → `AppVersion`).
+fn chart_object(metadata: &Json, is_root: bool) -> Value {
+    let mut m = BTreeMap::new();
+    if let Some(obj) = metadata.as_object() {
+        for (k, v) in obj {
+            let key = match k.as_str() {
+                "apiVersion" => "APIVersion".to_string(),
+                other => {
+                    let mut chars = other.chars();
+                    chars
+                        .next()
+                        .map(|c| c.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                }
+            };
+            m.insert(key, Value::from_json(v));
+        }
+    }
+    m.insert("IsRoot".to_string(), Value::Bool(is_root));
+    Value::map(m)
+}
+
+fn capabilities(options: &RenderOptions) -> Value {
+    let git_version = options
+        .kube_version
+        .as_deref()
+        .map(|v| {
+            if v.starts_with('v') {
+                v.to_string()
+            } else {
+                format!("v{v}")
+            }
+        })
+        .unwrap_or_else(|| DEFAULT_KUBE_VERSION.to_string());
+    let mut parts = git_version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map(String::from);
+    let major = parts.next().unwrap_or_default();
+    let minor = parts.next().unwrap_or_default();
+
+    let mut api_versions: Vec<String> =
+        BUILTIN_API_VERSIONS.iter().map(|s| s.to_string()).collect();
+    api_versions.extend(options.api_versions.iter().cloned());
+
+    let kube = Value::map(BTreeMap::from([
+        ("Version".to_string(), Value::str(git_version.as_str())),
+        ("GitVersion".to_string(), Value::str(git_version.as_str())),
+        ("Major".to_string(), Value::Str(major)),
+        ("Minor".to_string(), Value::Str(minor)),
+    ]));
+    Value::map(BTreeMap::from([
+        ("KubeVersion".to_string(), kube),
+        (
+            "APIVersions".to_string(),
+            Value::ApiVersions(Rc::new(api_versions)),
+        ),
+        (
+            "HelmVersion".to_string(),
+            Value::map(BTreeMap::from([(
+                "Version".to_string(),
+                Value::str("v3.16.0"),
+            )])),
+        ),
+    ]))
+}
+
+/// Split rendered output into YAML documents and parse each one.
+fn split_documents(name: &str, template: &str, content: &str) -> Result<Vec<Json>, HelmError> {
+    let mut docs = Vec::new();
+    let mut current = String::new();
+    let mut flush = |current: &mut String| -> Result<(), HelmError> {
+        let text = std::mem::take(current);
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        let doc: Json = serde_yaml_ng::from_str(&text).map_err(|e| {
+            HelmError::Render(format!("chart '{name}': {template}: invalid YAML: {e}"))
+        })?;
+        match doc {
+            Json::Null => {}
+            Json::Object(_) => docs.push(doc),
+            _ => {
+                return Err(HelmError::Render(format!(
+                    "chart '{name}': {template}: rendered document is not a Kubernetes object"
+                )));
+            }
+        }
+        Ok(())
+    };
+    for line in content.lines() {
+        if line == "---" || line.starts_with("--- ") {
+            flush(&mut current)?;
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    flush(&mut current)?;
+    Ok(docs)
+}
+
+fn is_test_hook(doc: &Json) -> bool {
+    doc.pointer("/metadata/annotations/helm.sh~1hook")
+        .and_then(Json::as_str)
+        .is_some_and(|hooks| hooks.split(',').any(|h| h.trim().starts_with("test")))
+}
+
+fn install_rank(doc: &Json) -> usize {
+    let kind = doc.get("kind").and_then(Json::as_str).unwrap_or("");
+    INSTALL_ORDER
+        .iter()
+        .position(|k| *k == kind)
+        .unwrap_or(INSTALL_ORDER.len())
+}
+
+// --- Loading ---
+
+fn load_chart(name: &str, files: &ChartFiles, root: &str) -> Result<Chart, HelmError> {
+    let metadata: Json = match files.get(&format!("{root}Chart.yaml")) {
+        Some(bytes) => serde_yaml_ng::from_slice(bytes).map_err(|e| {
+            HelmError::InvalidSchema(format!("chart '{name}': parse {root}Chart.yaml: {e}"))
+        })?,
+        None => {
+            return Err(HelmError::NotFound(format!(
+                "chart '{name}': {root}Chart.yaml not found"
+            )));
+        }
+    };
+    let chart_name = metadata
+        .get("name")
+        .and_then(Json::as_str)
+        .unwrap_or(name)
+        .to_string();
+    let dependencies: Vec<Dependency> = metadata
+        .get("dependencies")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| {
+            HelmError::InvalidSchema(format!(
+                "chart '{name}': invalid dependencies in {root}Chart.yaml: {e}"
+            ))
+        })?
+        .unwrap_or_default();
+
+    let defaults = match files.get(&format!("{root}values.yaml")) {
+        Some(bytes) => serde_yaml_ng::from_slice::<Json>(bytes).map_err(|e| {
+            HelmError::InvalidSchema(format!("chart '{name}': parse {root}values.yaml: {e}"))
+        })?,
+        None => Json::Null,
+    };
+    let defaults = if defaults.is_null() {
+        Json::Object(serde_json::Map::new())
+    } else {
+        defaults
+    };
+
+    let mut templates = Vec::new();
+    let mut crds = Vec::new();
+    let mut chart_files = BTreeMap::new();
+    let mut subchart_dirs = Vec::new();
+    let mut packaged = Vec::new();
+
+    let mut paths: Vec<&String> = files.keys().filter(|k| k.starts_with(root)).collect();
+    paths.sort();
+    for path in paths {
+        let rel = &path[root.len()..];
+        let bytes = &files[path];
+        if let Some(sub) = rel.strip_prefix("charts/") {
+            match sub.split_once('/') {
+                Some((dir, "Chart.yaml")) => subchart_dirs.push(format!("{root}charts/{dir}/")),
+                None if sub.ends_with(".tgz") => packaged.push(bytes),
+                _ => {}
+            }
+        } else if rel.starts_with("templates/") {
+            templates.push((rel.to_string(), String::from_utf8_lossy(bytes).into_owned()));
+        } else if rel.starts_with("crds/") {
+            if rel.ends_with(".yaml") || rel.ends_with(".yml") || rel.ends_with(".json") {
+                crds.push((rel.to_string(), String::from_utf8_lossy(bytes).into_owned()));
+            }
+            chart_files.insert(rel.to_string(), bytes.clone());
+        } else if !matches!(
+            rel,
+            "Chart.yaml" | "Chart.lock" | "values.yaml" | "values.schema.json"
+        ) {
+            chart_files.insert(rel.to_string(), bytes.clone());
+        }
+    }
+
+    let mut subcharts = Vec::new();
+    for dir in subchart_dirs {
+        subcharts.push(load_chart(name, files, &dir)?);
+    }
+    for bytes in packaged {
+        let sub_files = read_archive_all(name, bytes)?;
+        if let Some(sub_root) = archive_root(&sub_files) {
+            subcharts.push(load_chart(name, &sub_files, &sub_root)?);
+        }
+    }
+
+    Ok(Chart {
+        name: chart_name,
+        metadata,
+        defaults,
+        dependencies,
+        templates,
+        crds,
+        files: Rc::new(chart_files),
+        subcharts,
+    })
+}
+
+fn read_archive_all(name: &str, bytes: &[u8]) -> Result<ChartFiles, HelmError> {
+    crate::subchart::read_archive_filtered(name, bytes, |path| !path.ends_with('/'))
+}
+
+/// The top-level directory of a packaged chart (`"<chart>/"`).
+pub(crate) fn archive_root(files: &ChartFiles) -> Option<String> {
+    files
+        .keys()
+        .filter_map(|k| k.strip_suffix("Chart.yaml"))
+        .filter(|r| r.matches('/').count() == 1)
+        .min()
+        .map(String::from)
+}
+
+/// Read every file of a chart directory (paths relative to `dir`).
+fn read_dir_all(name: &str, dir: &Path) -> Result<ChartFiles, HelmError> {
+    fn walk(name: &str, base: &Path, dir: &Path, files: &mut ChartFiles) -> Result<(), HelmError> {
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            HelmError::Io(format!("chart '{name}': read dir {}: {e}", dir.display()))
+        })?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                walk(name, base, &path, files)?;
+                continue;
+            }
+            let rel = path
+                .strip_prefix(base)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let content = std::fs::read(&path).map_err(|e| {
+                HelmError::Io(format!("chart '{name}': read {}: {e}", path.display()))
+            })?;
+            files.insert(rel, content);
+        }
+        Ok(())
+    }
+
+    let mut files = ChartFiles::new();
+    walk(name, dir, dir, &mut files)?;
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, content: &str) {
+        let full = dir.join(path);
+        std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+        std::fs::write(full, content).unwrap();
+    }
+
+    fn build_tgz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append(&header, *content).unwrap();
+        }
+        let tar_data = builder.into_inner().unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar_data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    const HELPERS: &str = r#"{{- define "web.fullname" -}}
+{{- printf "%s-%s" .Release.Name .Chart.Name | trunc 63 | trimSuffix "-" }}
+{{- end }}"#;
+
+    const DEPLOYMENT: &str = r#"apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: {{ include "web.fullname" . }}
+  namespace: {{ .Release.Namespace }}
+spec:
+  replicas: {{ .Values.replicaCount }}
+  template:
+    spec:
+      containers:
+        - name: web
+          image: "{{ .Values.image.repository }}:{{ .Values.image.tag | default .Chart.AppVersion }}"
+          {{- with .Values.resources }}
+          resources:
+            {{- toYaml . | nindent 12 }}
+          {{- end }}
+"#;
+
+    const SERVICE: &str = r#"{{- if .Values.service.enabled }}
+apiVersion: v1
+kind: Service
+metadata:
+  name: {{ include "web.fullname" . }}
+spec:
+  ports:
+    - port: {{ .Values.service.port }}
+{{- end }}
+"#;
+
+    fn write_web_chart(dir: &Path) {
+        write(
+            dir,
+            "Chart.yaml",
+            "apiVersion: v2\nname: web\nversion: 1.0.0\nappVersion: \"2.3.4\"\n",
+        );
+        write(
+            dir,
+            "values.yaml",
+            "replicaCount: 1\nimage:\n  repository: nginx\n  tag: \"\"\nservice:\n  enabled: true\n  port: 80\nresources: {}\n",
+        );
+        write(dir, "values.schema.json", r#"{"type":"object"}"#);
+        write(dir, "templates/_helpers.tpl", HELPERS);
+        write(dir, "templates/deployment.yaml", DEPLOYMENT);
+        write(dir, "templates/service.yaml", SERVICE);
+        write(dir, "templates/NOTES.txt", "Installed {{ .Release.Name }}");
+        write(
+            dir,
+            "templates/tests/test-connection.yaml",
+            "apiVersion: v1\nkind: Pod\nmetadata:\n  name: test\n  annotations:\n    \"helm.sh/hook\": test\n",
+        );
+    }
+
+    fn file_source() -> ChartSource {
+        ChartSource::File {
+            path: "chart/values.schema.json".to_string(),
+        }
+    }
+
+    #[test]
+    fn renders_local_chart_with_values() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_web_chart(&tmp.path().join("chart"));
+
+        let values = serde_json::json!({
+            "replicaCount": 3,
+            "resources": { "limits": { "cpu": "500m" } }
+        });
+        let options = RenderOptions {
+            release_name: Some("prod".to_string()),
+            namespace: Some("apps".to_string()),
+            ..Default::default()
+        };
+        let docs = render(
+            "web",
+            &file_source(),
+            tmp.path(),
+            &tmp.path().join("cache"),
+            &values,
+            &options,
+        )
+        .unwrap();
+
+        // Service sorts before Deployment; NOTES.txt and test hooks are dropped
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0]["kind"], "Service");
+        assert_eq!(docs[0]["metadata"]["name"], "prod-web");
+        assert_eq!(docs[0]["spec"]["ports"][0]["port"], 80);
+        let deploy = &docs[1];
+        assert_eq!(deploy["kind"], "Deployment");
+        assert_eq!(deploy["metadata"]["namespace"], "apps");
+        assert_eq!(deploy["spec"]["replicas"], 3);
+        let container = &deploy["spec"]["template"]["spec"]["containers"][0];
+        assert_eq!(container["image"], "nginx:2.3.4");
+        assert_eq!(container["resources"]["limits"]["cpu"], "500m");
+    }
+
+    #[test]
+    fn null_values_remove_defaults() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_web_chart(&tmp.path().join("chart"));
+
+        let values = serde_json::json!({ "service": { "enabled": false } });
+        let docs = render(
+            "web",
+            &file_source(),
+            tmp.path(),
+            &tmp.path().join("cache"),
+            &values,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0]["kind"], "Deployment");
+        assert_eq!(docs[0]["metadata"]["name"], "web-web");
+        assert_eq!(docs[0]["metadata"]["namespace"], "default");
+    }
+
+    #[test]
+    fn renders_cached_archive_with_subcharts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache_dir = tmp.path().join("cache");
+        let sub = build_tgz(&[
+            (
+                "db/Chart.yaml",
+                b"apiVersion: v2\nname: db\nversion: 0.1.0\n".as_slice(),
+            ),
+            ("db/values.yaml", b"enabled: true\nport: 5432\n"),
+            (
+                "db/templates/svc.yaml",
+                b"apiVersion: v1\nkind: Service\nmetadata:\n  name: {{ .Release.Name }}-db\n  labels:\n    region: {{ .Values.global.region }}\nspec:\n  ports:\n    - port: {{ .Values.port }}\n",
+            ),
+        ]);
+        let parent = build_tgz(&[
+            (
+                "app/Chart.yaml",
+                b"apiVersion: v2\nname: app\nversion: 1.0.0\ndependencies:\n  - name: db\n    alias: database\n    condition: database.enabled\n".as_slice(),
+            ),
+            ("app/values.yaml", b"global:\n  region: eu\n"),
+            (
+                "app/templates/cm.yaml",
+                b"apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: app\ndata:\n  dbPort: {{ .Values.database.port | quote }}\n",
+            ),
+            ("app/charts/db-0.1.0.tgz", &sub),
+        ]);
+
+        let source = ChartSource::Registry {
+            repo: "https://charts.example.com".to_string(),
+            chart: "app".to_string(),
+            version: "1.0.0".to_string(),
+        };
+        let archive = crate::archive_cache_path(&source, &cache_dir).unwrap();
+        std::fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        std::fs::write(&archive, parent).unwrap();
+
+        let values = serde_json::json!({ "database": { "port": 6432 } });
+        let docs = render(
+            "app",
+            &source,
+            tmp.path(),
+            &cache_dir,
+            &values,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0]["kind"], "ConfigMap");
+        assert_eq!(docs[0]["data"]["dbPort"], "6432");
+        assert_eq!(docs[1]["kind"], "Service");
+        assert_eq!(docs[1]["metadata"]["name"], "app-db");
+        assert_eq!(docs[1]["metadata"]["labels"]["region"], "eu");
+        assert_eq!(docs[1]["spec"]["ports"][0]["port"], 6432);
+
+        // The dependency condition disables the subchart
+        let values = serde_json::json!({ "database": { "enabled": false } });
+        let docs = render(
+            "app",
+            &source,
+            tmp.path(),
+            &cache_dir,
+            &values,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0]["kind"], "ConfigMap");
+    }
+
+    #[test]
+    fn missing_archive_reports_how_to_fetch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = ChartSource::Oci {
+            reference: "oci://ghcr.io/org/app".to_string(),
+            version: "1.0.0".to_string(),
+        };
+        let err = render(
+            "app",
+            &source,
+            tmp.path(),
+            tmp.path(),
+            &serde_json::json!({}),
+            &RenderOptions::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("chart archive is not cached"));
+    }
+
+    #[test]
+    fn template_errors_name_the_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("chart");
+        write(
+            &dir,
+            "Chart.yaml",
+            "apiVersion: v2\nname: bad\nversion: 1.0.0\n",
+        );
+        write(
+            &dir,
+            "templates/cm.yaml",
+            "{{ required \"config.name is required\" .Values.config.name }}",
+        );
+        let source = ChartSource::File {
+            path: "chart".to_string(),
+        };
+        let err = render(
+            "bad",
+            &source,
+            tmp.path(),
+            tmp.path(),
+            &serde_json::json!({ "config": {} }),
+            &RenderOptions::default(),
+        )
+        .unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("bad/templates/cm.yaml"), "{msg}");
+        assert!(msg.contains("config.name is required"), "{msg}");
+    }
+
+    #[test]
+    fn include_crds_prepends_crd_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("chart");
+        write_web_chart(&dir);
+        write(
+            &dir,
+            "crds/widget.yaml",
+            "apiVersion: apiextensions.k8s.io/v1\nkind: CustomResourceDefinition\nmetadata:\n  name: widgets.example.com\n",
+        );
+        let options = RenderOptions {
+            include_crds: true,
+            ..Default::default()
+        };
+        let docs = render(
+            "web",
+            &file_source(),
+            tmp.path(),
+            tmp.path(),
+            &serde_json::json!({}),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(docs[0]["kind"], "CustomResourceDefinition");
+        assert_eq!(docs.len(), 3);
+    }
+
+    #[test]
+    fn capabilities_follow_kube_version() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("chart");
+        write(
+            &dir,
+            "Chart.yaml",
+            "apiVersion: v2\nname: caps\nversion: 1.0.0\n",
+        );
+        write(
+            &dir,
+            "templates/cm.yaml",
+            r#"apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: caps
+data:
+  minor: {{ .Capabilities.KubeVersion.Minor | quote }}
+  policy: {{ .Capabilities.APIVersions.Has "policy/v1" | quote }}
+  custom: {{ .Capabilities.APIVersions.Has "example.com/v1" | quote }}
+  modern: {{ semverCompare ">=1.25-0" .Capabilities.KubeVersion.GitVersion | quote }}
+"#,
+        );
+        let options = RenderOptions {
+            kube_version: Some("1.24.3".to_string()),
+            api_versions: vec!["example.com/v1".to_string()],
+            ..Default::default()
+        };
+        let source = ChartSource::File {
+            path: "chart".to_string(),
+        };
+        let docs = render(
+            "caps",
+            &source,
+            tmp.path(),
+            tmp.path(),
+            &serde_json::json!({}),
+            &options,
+        )
+        .unwrap();
+        let data = &docs[0]["data"];
+        assert_eq!(data["minor"], "24");
+        assert_eq!(data["policy"], "true");
+        assert_eq!(data["custom"], "true");
+        assert_eq!(data["modern"], "false");
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+use husako_dts::json_schema::SUBCHART_KEY;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::HelmError;
+
+/// Files of an unpacked chart, keyed by `/`-separated path.
+///
+/// For schema composition only the files needed are kept: every `Chart.yaml`,
+/// `values.schema.json` and `values.yaml`, and every packaged subchart `.tgz`.
+pub(crate) type ChartFiles = HashMap<String, Vec<u8>>;
+
+/// The subset of `Chart.yaml` used for schema composition.
+#[derive(Debug, Deserialize)]
+struct ChartMeta {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    dependencies: Vec<ChartDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartDependency {
+    name: String,
+    #[serde(default)]
+    alias: Option<String>,
+}
+
+fn is_relevant(path: &str) -> bool {
+    path.ends_with("/Chart.yaml")
+        || path == "Chart.yaml"
+        || path.ends_with("values.schema.json")
+        || path.ends_with("values.yaml")
+        || (path.contains("charts/") && path.ends_with(".tgz"))
+}
+
+/// Read the files of a `.tgz` chart archive needed for schema composition.
+pub(crate) fn read_archive(name: &str, archive_bytes: &[u8]) -> Result<ChartFiles, HelmError> {
+    read_archive_filtered(name, archive_bytes, is_relevant)
+}
+
+/// Read the files of a `.tgz` chart archive accepted by `keep` into memory.
+pub(crate) fn read_archive_filtered(
+    name: &str,
+    archive_bytes: &[u8],
+    keep: fn(&str) -> bool,
+) -> Result<ChartFiles, HelmError> {
+    let gz = flate2::read::GzDecoder::new(archive_bytes);
+    let mut archive = tar::Archive::new(gz);
+    let mut files = ChartFiles::new();
+
+    for entry in archive
+        .entries()
+        .map_err(|e| HelmError::Io(format!("chart '{name}': read archive entries: {e}")))?
+    {
+        let mut entry =
+            entry.map_err(|e| HelmError::Io(format!("chart '{name}': read archive entry: {e}")))?;
+
+        let path = entry
+            .path()
+            .map_err(|e| HelmError::Io(format!("chart '{name}': read entry path: {e}")))?
+            .to_string_lossy()
+            .to_string();
+
+        if !keep(&path) {
+            continue;
+        }
+
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| HelmError::Io(format!("chart '{name}': read {path} from archive: {e}")))?;
+        files.insert(path, content);
+    }
+
+    Ok(files)
+}
+
+/// Read a chart directory on disk into memory (paths relative to `dir`).
+pub(crate) fn read_dir(name: &str, dir: &Path) -> Result<ChartFiles, HelmError> {
+    let mut files = ChartFiles::new();
+    collect_dir(name, dir, dir, &mut files)?;
+    Ok(files)
+}
+
+fn collect_dir(
+    name: &str,
+    base: &Path,
+    dir: &Path,
+    files: &mut ChartFiles,
+) -> Result<(), HelmError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| HelmError::Io(format!("chart '{name}': read dir {}: {e}", dir.display())))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            // Only descend into the chart's `charts/` subtree; templates are not needed.
+            let rel = path.strip_prefix(base).unwrap_or(&path);
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            if rel == "charts" || rel.contains("charts/") {
+                collect_dir(name, base, &path, files)?;
+            }
+            continue;
+        }
+        let rel = path
+            .strip_prefix(base)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if !is_relevant(&rel) {
+            continue;
+        }
+        let content = std::fs::read(&path)
+            .map_err(|e| HelmError::Io(format!("chart '{name}': read {}: {e}", path.display())))?;
+        files.insert(rel, content);
+    }
+    Ok(())
+}
+
+/// Resolve the values schema of the chart rooted at `root` (`""` or a
+/// `"<dir>/"` prefix), composing in the schemas of its subcharts.
+///
+/// Dependencies listed in `Chart.yaml` are looked up under `charts/`, either
+/// unpacked (`charts/<name>/`) or packaged (`charts/<name>-<version>.tgz`).
+/// Each subchart schema is placed under the dependency's alias or name, and
+/// every `global` section is merged into one shared `global` property on the
+/// parent. Dependencies whose subchart is absent or ships no schema are typed
+/// as free-form objects, unless the parent declares the property itself.
+///
+/// Returns `None` if neither the chart nor any of its subcharts has a schema.
+pub(crate) fn chart_values_schema(
+    name: &str,
+    files: &ChartFiles,
+    root: &str,
+) -> Result<Option<Value>, HelmError> {
+    let own = match files.get(&format!("{root}values.schema.json")) {
+        Some(bytes) => Some(serde_json::from_slice::<Value>(bytes).map_err(|e| {
+            HelmError::InvalidSchema(format!("chart '{name}': parse values.schema.json: {e}"))
+        })?),
+        None => None,
+    };
+
+    let meta = parse_chart_meta(name, files, root)?;
+    let dependencies = meta.map(|m| m.dependencies).unwrap_or_default();
+
+    let mut subcharts = Vec::new();
+    let mut free_form = Vec::new();
+    if !dependencies.is_empty() {
+        let packaged = packaged_subcharts(name, files, root)?;
+        for dep in &dependencies {
+            let unpacked_root = format!("{root}charts/{}/", dep.name);
+            let schema = if files.keys().any(|k| k.starts_with(&unpacked_root)) {
+                chart_values_schema(name, files, &unpacked_root)?
+            } else if let Some((sub_files, sub_root)) = packaged.get(&dep.name) {
+                chart_values_schema(name, sub_files, sub_root)?
+            } else {
+                None
+            };
+            let key = dep.alias.clone().unwrap_or_else(|| dep.name.clone());
+            match schema {
+                Some(schema) => subcharts.push((key, dep.name.clone(), schema)),
+                None => free_form.push(key),
+            }
+        }
+    }
+
+    if own.is_none() && subcharts.is_empty() {
+        return Ok(None);
+    }
+
+    let mut schema = own.unwrap_or_else(|| serde_json::json!({ "type": "object" }));
+    if !subcharts.is_empty() || !free_form.is_empty() {
+        if !schema.is_object() {
+            return Err(HelmError::InvalidSchema(format!(
+                "chart '{name}': {root}values.schema.json must be a JSON object"
+            )));
+        }
+        compose(&mut schema, subcharts);
+        let props = properties_mut(&mut schema);
+        for key in free_form {
+            props
+                .entry(key)
+                .or_insert_with(|| serde_json::json!({ "type": "object" }));
+        }
+    }
+    Ok(Some(schema))
+}
+
+fn parse_chart_meta(
+    name: &str,
+    files: &ChartFiles,
+    root: &str,
+) -> Result<Option<ChartMeta>, HelmError> {
+    let Some(bytes) = files.get(&format!("{root}Chart.yaml")) else {
+        return Ok(None);
+    };
+    serde_yaml_ng::from_slice(bytes)
+        .map(Some)
+        .map_err(|e| HelmError::Io(format!("chart '{name}': parse {root}Chart.yaml: {e}")))
+}
+
+/// Unpack every `charts/*.tgz` directly under `root`, keyed by the chart name
+/// declared in the packaged `Chart.yaml`. Values are `(files, root)`.
+fn packaged_subcharts(
+    name: &str,
+    files: &ChartFiles,
+    root: &str,
+) -> Result<HashMap<String, (ChartFiles, String)>, HelmError> {
+    let prefix = format!("{root}charts/");
+    let mut result = HashMap::new();
+
+    for (path, bytes) in files {
+        let Some(file_name) = path.strip_prefix(&prefix) else {
+            continue;
+        };
+        if file_name.contains('/') || !file_name.ends_with(".tgz") {
+            continue;
+        }
+
+        let sub_files = read_archive(name, bytes)?;
+        // A packaged chart has a single top-level directory holding Chart.yaml.
+        let sub_root = sub_files
+            .keys()
+            .filter_map(|k| k.strip_suffix("Chart.yaml"))
+            .filter(|r| r.matches('/').count() == 1)
+            .map(String::from)
+            .next();
+        let Some(sub_root) = sub_root else {
+            continue;
+        };
+        let Some(meta) = parse_chart_meta(name, &sub_files, &sub_root)? else {
+            continue;
+        };
+        result.insert(meta.name, (sub_files, sub_root));
+    }
+
+    Ok(result)
+}
+
+/// Place each `(key, chart, schema)` subchart under `properties[key]` of the
+/// parent and hoist every `global` section into a single shared property.
+fn compose(parent: &mut Value, subcharts: Vec<(String, String, Value)>) {
+    let mut global = take_global(parent);
+
+    for (key, chart, mut sub) in subcharts {
+        if let Some(sub_global) = take_global(&mut sub) {
+            merge_global(&mut global, sub_global);
+        }
+        if let Some(obj) = sub.as_object_mut() {
+            obj.insert(SUBCHART_KEY.to_string(), Value::String(chart));
+        }
+        properties_mut(parent).insert(key, sub);
+    }
+
+    if let Some(global) = global {
+        properties_mut(parent).insert("global".to_string(), global);
+    }
+}
+
+fn properties_mut(schema: &mut Value) -> &mut serde_json::Map<String, Value> {
+    if !schema.is_object() {
+        *schema = Value::Object(serde_json::Map::new());
+    }
+    let Value::Object(obj) = schema else {
+        unreachable!()
+    };
+    let props = obj
+        .entry("properties")
+        .or_insert_with(|| Value::Object(serde_json::Map::new()));
+    if !props.is_object() {
+        *props = Value::Object(serde_json::Map::new());
+    }
+    let Value::Object(props) = props else {
+        unreachable!()
+    };
+    props
+}
+
+/// Remove and return the `global` property of a schema (and drop it from `required`).
+fn take_global(schema: &mut Value) -> Option<Value> {
+    let obj = schema.as_object_mut()?;
+    if let Some(required) = obj.get_mut("required").and_then(Value::as_array_mut) {
+        required.retain(|r| r != "global");
+    }
+    obj.get_mut("properties")
+        .and_then(Value::as_object_mut)
+        .and_then(|p| p.remove("global"))
+}
+
+/// Merge the properties of `incoming` into `global`; properties already
+/// present (declared by the parent or an earlier subchart) are kept.
+fn merge_global(global: &mut Option<Value>, incoming: Value) {
+    let Some(target) = global else {
+        *global = Some(incoming);
+        return;
+    };
+    let Some(incoming_props) = incoming.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::json!({ "type": "object" });
+    }
+    let props = properties_mut(target);
+    for (k, v) in incoming_props {
+        props.entry(k.clone()).or_insert_with(|| v.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> ChartFiles {
+        entries
+            .iter()
+            .map(|(p, c)| (p.to_string(), c.as_bytes().to_vec()))
+            .collect()
+    }
+
+    fn build_tgz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append(&header, *content).unwrap();
+        }
+        let tar_data = builder.into_inner().unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar_data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    const UMBRELLA_CHART_YAML: &str = r#"
+apiVersion: v2
+name: app
+version: 1.0.0
+dependencies:
+  - name: postgresql
+    version: 16.4.0
+    repository: oci://registry-1.docker.io/bitnamicharts
+  - name: redis
+    alias: cache
+    version: 20.0.0
+    repository: oci://registry-1.docker.io/bitnamicharts
+"#;
+
+    #[test]
+    fn no_dependencies_returns_own_schema() {
+        let f = files(&[
+            ("app/Chart.yaml", "name: app\nversion: 1.0.0\n"),
+            (
+                "app/values.schema.json",
+                r#"{"type":"object","properties":{"replicas":{"type":"integer"}}}"#,
+            ),
+        ]);
+        let schema = chart_values_schema("test", &f, "app/").unwrap().unwrap();
+        assert_eq!(
+            schema,
+            serde_json::json!({"type":"object","properties":{"replicas":{"type":"integer"}}})
+        );
+    }
+
+    #[test]
+    fn no_schema_anywhere_returns_none() {
+        let f = files(&[("app/Chart.yaml", UMBRELLA_CHART_YAML)]);
+        assert!(chart_values_schema("test", &f, "app/").unwrap().is_none());
+    }
+
+    #[test]
+    fn unpacked_subcharts_are_composed_under_name_and_alias() {
+        let f = files(&[
+            ("app/Chart.yaml", UMBRELLA_CHART_YAML),
+            (
+                "app/values.schema.json",
+                r#"{"type":"object","properties":{"replicas":{"type":"integer"}}}"#,
+            ),
+            ("app/charts/postgresql/Chart.yaml", "name: postgresql\n"),
+            (
+                "app/charts/postgresql/values.schema.json",
+                r#"{"type":"object","properties":{"auth":{"type":"object","properties":{"database":{"type":"string"}}}}}"#,
+            ),
+            ("app/charts/redis/Chart.yaml", "name: redis\n"),
+            (
+                "app/charts/redis/values.schema.json",
+                r#"{"type":"object","properties":{"architecture":{"type":"string"}}}"#,
+            ),
+        ]);
+        let schema = chart_values_schema("test", &f, "app/").unwrap().unwrap();
+        let props = &schema["properties"];
+        assert_eq!(props["replicas"]["type"], "integer");
+        assert_eq!(
+            props["postgresql"]["properties"]["auth"]["properties"]["database"]["type"],
+            "string"
+        );
+        assert_eq!(props["postgresql"][SUBCHART_KEY], "postgresql");
+        assert_eq!(
+            props["cache"]["properties"]["architecture"]["type"],
+            "string"
+        );
+        assert_eq!(props["cache"][SUBCHART_KEY], "redis");
+        assert!(props.get("redis").is_none());
+    }
+
+    #[test]
+    fn subcharts_without_schema_are_free_form() {
+        let f = files(&[
+            ("app/Chart.yaml", UMBRELLA_CHART_YAML),
+            (
+                "app/values.schema.json",
+                r#"{"type":"object","properties":{"postgresql":{"type":"object","properties":{"enabled":{"type":"boolean"}}}}}"#,
+            ),
+            ("app/charts/redis/Chart.yaml", "name: redis\n"),
+        ]);
+        let schema = chart_values_schema("test", &f, "app/").unwrap().unwrap();
+        let props = &schema["properties"];
+        // Vendored without a schema
+        assert_eq!(props["cache"], serde_json::json!({ "type": "object" }));
+        // Not vendored, and declared by the parent: kept as declared
+        assert_eq!(
+            props["postgresql"]["properties"]["enabled"]["type"],
+            "boolean"
+        );
+    }
+
+    #[test]
+    fn packaged_subcharts_are_composed() {
+        let sub = build_tgz(&[
+            (
+                "postgresql/Chart.yaml",
+                b"name: postgresql\nversion: 16.4.0\n",
+            ),
+            (
+                "postgresql/values.schema.json",
+                br#"{"type":"object","properties":{"primary":{"type":"object"}}}"#,
+            ),
+        ]);
+        let mut f = files(&[("app/Chart.yaml", UMBRELLA_CHART_YAML)]);
+        f.insert("app/charts/postgresql-16.4.0.tgz".to_string(), sub);
+
+        // Parent ships no schema of its own — composition still yields one.
+        let schema = chart_values_schema("test", &f, "app/").unwrap().unwrap();
+        assert_eq!(schema["type"], "object");
+        assert_eq!(
+            schema["properties"]["postgresql"]["properties"]["primary"]["type"],
+            "object"
+        );
+        // redis (alias cache) is not vendored → free-form object
+        assert_eq!(
+            schema["properties"]["cache"],
+            serde_json::json!({ "type": "object" })
+        );
+    }
+
+    #[test]
+    fn globals_are_hoisted_and_merged() {
+        let f = files(&[
+            ("app/Chart.yaml", UMBRELLA_CHART_YAML),
+            (
+                "app/values.schema.json",
+                r#"{"type":"object","properties":{"global":{"type":"object","properties":{"env":{"type":"string"}}}}}"#,
+            ),
+            ("app/charts/postgresql/Chart.yaml", "name: postgresql\n"),
+            (
+                "app/charts/postgresql/values.schema.json",
+                r#"{"type":"object","required":["global"],"properties":{"global":{"type":"object","properties":{"env":{"type":"integer"},"storageClass":{"type":"string"}}},"port":{"type":"integer"}}}"#,
+            ),
+            ("app/charts/redis/Chart.yaml", "name: redis\n"),
+            (
+                "app/charts/redis/values.schema.json",
+                r#"{"type":"object","properties":{"global":{"type":"object","properties":{"imageRegistry":{"type":"string"}}}}}"#,
+            ),
+        ]);
+        let schema = chart_values_schema("test", &f, "app/").unwrap().unwrap();
+        let global = &schema["properties"]["global"]["properties"];
+        // Parent declaration wins on conflict
+        assert_eq!(global["env"]["type"], "string");
+        assert_eq!(global["storageClass"]["type"], "string");
+        assert_eq!(global["imageRegistry"]["type"], "string");
+
+        let pg = &schema["properties"]["postgresql"];
+        assert!(pg["properties"].get("global").is_none());
+        assert_eq!(pg["required"], serde_json::json!([]));
+        assert!(
+            schema["properties"]["cache"]["properties"]
+                .get("global")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn nested_subcharts_are_composed_recursively() {
+        let f = files(&[
+            (
+                "app/Chart.yaml",
+                "name: app\ndependencies:\n  - name: backend\n",
+            ),
+            (
+                "app/charts/backend/Chart.yaml",
+                "name: backend\ndependencies:\n  - name: postgresql\n",
+            ),
+            (
+                "app/charts/backend/charts/postgresql/Chart.yaml",
+                "name: postgresql\n",
+            ),
+            (
+                "app/charts/backend/charts/postgresql/values.schema.json",
+                r#"{"type":"object","properties":{"port":{"type":"integer"}}}"#,
+            ),
+        ]);
+        let schema = chart_values_schema("test", &f, "app/").unwrap().unwrap();
+        assert_eq!(
+            schema["properties"]["backend"]["properties"]["postgresql"]["properties"]["port"]["type"],
+            "integer"
+        );
+    }
+
+    #[test]
+    fn invalid_chart_yaml_returns_error() {
+        let f = files(&[("app/Chart.yaml", "dependencies: [unclosed")]);
+        let err = chart_values_schema("test", &f, "app/").unwrap_err();
+        assert!(err.to_string().contains("parse app/Chart.yaml"));
+    }
+
+    #[test]
+    fn read_archive_keeps_only_relevant_files() {
+        let tgz = build_tgz(&[
+            ("app/Chart.yaml", b"name: app\n"),
+            ("app/values.yaml", b"replicas: 1\n"),
+            ("app/templates/deployment.yaml", b"kind: Deployment\n"),
+            ("app/values.schema.json", b"{}"),
+            ("app/charts/sub-1.0.0.tgz", b"binary"),
+        ]);
+        let f = read_archive("test", &tgz).unwrap();
+        let mut keys: Vec<_> = f.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "app/Chart.yaml",
+                "app/charts/sub-1.0.0.tgz",
+                "app/values.schema.json",
+                "app/values.yaml"
+            ]
+        );
+    }
+
+    #[test]
+    fn read_dir_collects_chart_and_subcharts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::create_dir_all(root.join("charts/sub")).unwrap();
+        std::fs::create_dir_all(root.join("templates")).unwrap();
+        std::fs::write(root.join("Chart.yaml"), "name: app\n").unwrap();
+        std::fs::write(root.join("templates/Chart.yaml"), "ignored").unwrap();
+        std::fs::write(root.join("charts/sub/Chart.yaml"), "name: sub\n").unwrap();
+        std::fs::write(root.join("charts/sub/values.schema.json"), "{}").unwrap();
+
+        let f = read_dir("test", root).unwrap();
+        let mut keys: Vec<_> = f.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "Chart.yaml",
+                "charts/sub/Chart.yaml",
+                "charts/sub/values.schema.json"
+            ]
+        );
+    }
+}
@@ -0,0 +1,412 @@
+//! Evaluation of parsed templates.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::parse::{Arg, Cmd, List, Node, Pipe};
+use super::value::Value;
+use super::{TemplateError, Templates};
+
+/// Maximum nesting of `template`/`include`/`tpl` calls before giving up.
+const MAX_DEPTH: usize = 100;
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+}
+
+pub(crate) struct Exec<'a> {
+    templates: &'a Templates,
+    /// Templates defined at render time by `tpl` strings.
+    extra: HashMap<String, Rc<List>>,
+    vars: Vec<(String, Value)>,
+    /// Names of the templates being executed, innermost last.
+    stack: Vec<String>,
+}
+
+impl<'a> Exec<'a> {
+    pub(crate) fn new(templates: &'a Templates) -> Self {
+        Self {
+            templates,
+            extra: HashMap::new(),
+            vars: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    pub(crate) fn error(&self, msg: impl std::fmt::Display) -> TemplateError {
+        let name = self.stack.last().map(String::as_str).unwrap_or("");
+        TemplateError {
+            message: format!("template: {name}: {msg}"),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Rc<List>> {
+        self.extra
+            .get(name)
+            .or_else(|| self.templates.defs.get(name))
+            .cloned()
+    }
+
+    /// Execute the named template with `data` as dot.
+    pub(crate) fn execute(&mut self, name: &str, data: &Value) -> Result<String, TemplateError> {
+        let body = self
+            .lookup(name)
+            .ok_or_else(|| self.error(format!("no template {name:?} associated with template")))?;
+        self.run(name, &body, data)
+    }
+
+    /// Parse `src` (defining any templates it contains) and execute it, as
+    /// Helm's `tpl` function does.
+    pub(crate) fn execute_source(
+        &mut self,
+        name: &str,
+        src: &str,
+        data: &Value,
+    ) -> Result<String, TemplateError> {
+        let (body, defines) = super::parse::parse(name, src)?;
+        self.extra.extend(defines);
+        self.run(name, &Rc::new(body), data)
+    }
+
+    fn run(&mut self, name: &str, body: &List, data: &Value) -> Result<String, TemplateError> {
+        if self.stack.len() >= MAX_DEPTH {
+            return Err(self.error(format!(
+                "exceeded maximum template depth ({MAX_DEPTH}) calling {name:?}"
+            )));
+        }
+        let saved_vars = std::mem::replace(&mut self.vars, vec![("$".to_string(), data.clone())]);
+        self.stack.push(name.to_string());
+        let mut out = String::new();
+        let result = self.walk_list(body, data, &mut out);
+        self.stack.pop();
+        self.vars = saved_vars;
+        match result? {
+            Flow::Normal => Ok(out),
+            _ => Err(TemplateError {
+                message: format!("template: {name}: break or continue outside range"),
+            }),
+        }
+    }
+
+    fn walk_list(
+        &mut self,
+        list: &List,
+        dot: &Value,
+        out: &mut String,
+    ) -> Result<Flow, TemplateError> {
+        for node in list {
+            match self.walk(node, dot, out)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Walk a list in its own variable scope.
+    fn walk_scoped(
+        &mut self,
+        list: &List,
+        dot: &Value,
+        out: &mut String,
+    ) -> Result<Flow, TemplateError> {
+        let mark = self.vars.len();
+        let flow = self.walk_list(list, dot, out);
+        self.vars.truncate(mark);
+        flow
+    }
+
+    fn walk(&mut self, node: &Node, dot: &Value, out: &mut String) -> Result<Flow, TemplateError> {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Action(pipe) => {
+                let value = self.eval_pipe(dot, pipe)?;
+                if pipe.vars.is_empty() {
+                    out.push_str(&value.to_display());
+                }
+            }
+            Node::If(pipe, body, else_body) => {
+                let mark = self.vars.len();
+                let cond = self.eval_pipe(dot, pipe)?;
+                let branch = if cond.is_truthy() { body } else { else_body };
+                let flow = self.walk_list(branch, dot, out);
+                self.vars.truncate(mark);
+                return flow;
+            }
+            Node::With(pipe, body, else_body) => {
+                let mark = self.vars.len();
+                let value = self.eval_pipe(dot, pipe)?;
+                let flow = if value.is_truthy() {
+                    self.walk_list(body, &value, out)
+                } else {
+                    self.walk_list(else_body, dot, out)
+                };
+                self.vars.truncate(mark);
+                return flow;
+            }
+            Node::Range(pipe, body, else_body) => {
+                return self.walk_range(pipe, body, else_body, dot, out);
+            }
+            Node::Template(name, pipe) => {
+                let data = match pipe {
+                    Some(pipe) => self.eval_pipe(dot, pipe)?,
+                    None => Value::Nil,
+                };
+                let rendered = self.execute(name, &data)?;
+                out.push_str(&rendered);
+            }
+            Node::Break => return Ok(Flow::Break),
+            Node::Continue => return Ok(Flow::Continue),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn walk_range(
+        &mut self,
+        pipe: &Pipe,
+        body: &List,
+        else_body: &List,
+        dot: &Value,
+        out: &mut String,
+    ) -> Result<Flow, TemplateError> {
+        let value = self.eval_cmds(dot, pipe)?;
+        let entries: Vec<(Value, Value)> = match &value {
+            Value::Map(m) => m
+                .borrow()
+                .iter()
+                .map(|(k, v)| (Value::str(k.as_str()), v.clone()))
+                .collect(),
+            Value::Files(f) => f
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        Value::str(k.as_str()),
+                        Value::str(String::from_utf8_lossy(v)),
+                    )
+                })
+                .collect(),
+            Value::Int(n) => (0..*n).map(|i| (Value::Int(i), Value::Int(i))).collect(),
+            other => match other.items() {
+                Some(items) => items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, v)| (Value::Int(i as i64), v))
+                    .collect(),
+                None => {
+                    return Err(
+                        self.error(format!("range can't iterate over {}", other.to_display()))
+                    );
+                }
+            },
+        };
+
+        if entries.is_empty() {
+            return self.walk_scoped(else_body, dot, out);
+        }
+
+        for (key, elem) in entries {
+            let mark = self.vars.len();
+            match pipe.vars.as_slice() {
+                [] => {}
+                [v] => self.vars.push((v.clone(), elem.clone())),
+                [k, v, ..] => {
+                    self.vars.push((k.clone(), key));
+                    self.vars.push((v.clone(), elem.clone()));
+                }
+            }
+            let flow = self.walk_list(body, &elem, out);
+            self.vars.truncate(mark);
+            if let Flow::Break = flow? {
+                break;
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Evaluate a pipeline and apply its declaration or assignment.
+    fn eval_pipe(&mut self, dot: &Value, pipe: &Pipe) -> Result<Value, TemplateError> {
+        let value = self.eval_cmds(dot, pipe)?;
+        for var in &pipe.vars {
+            if pipe.assign {
+                match self.vars.iter_mut().rev().find(|(n, _)| n == var) {
+                    Some(slot) => slot.1 = value.clone(),
+                    None => return Err(self.error(format!("undefined variable: {var}"))),
+                }
+            } else {
+                self.vars.push((var.clone(), value.clone()));
+            }
+        }
+        Ok(value)
+    }
+
+    fn eval_cmds(&mut self, dot: &Value, pipe: &Pipe) -> Result<Value, TemplateError> {
+        let mut piped = None;
+        for cmd in &pipe.cmds {
+            piped = Some(self.eval_cmd(dot, cmd, piped)?);
+        }
+        Ok(piped.unwrap_or(Value::Nil))
+    }
+
+    fn eval_cmd(
+        &mut self,
+        dot: &Value,
+        cmd: &Cmd,
+        piped: Option<Value>,
+    ) -> Result<Value, TemplateError> {
+        let has_args = cmd.len() > 1 || piped.is_some();
+        match &cmd[0] {
+            Arg::Ident(name) => {
+                // `and`/`or` short-circuit like Go's builtins
+                if name == "and" || name == "or" {
+                    return self.eval_logic(name == "and", dot, &cmd[1..], piped);
+                }
+                let mut args = Vec::with_capacity(cmd.len());
+                for arg in &cmd[1..] {
+                    args.push(self.eval_arg(dot, arg)?);
+                }
+                args.extend(piped);
+                super::funcs::call(self, name, args)
+            }
+            Arg::Field(chain) => {
+                let args = self.eval_args(dot, &cmd[1..], piped)?;
+                self.eval_chain(dot.clone(), chain, args)
+            }
+            Arg::Var(name, chain) => {
+                let base = self.var(name)?;
+                let args = self.eval_args(dot, &cmd[1..], piped)?;
+                self.eval_chain(base, chain, args)
+            }
+            Arg::Sub(pipe, chain) => {
+                let base = self.eval_cmds(dot, pipe)?;
+                let args = self.eval_args(dot, &cmd[1..], piped)?;
+                self.eval_chain(base, chain, args)
+            }
+            other => {
+                if has_args {
+                    return Err(self.error("can't give argument to non-function"));
+                }
+                self.eval_arg(dot, other)
+            }
+        }
+    }
+
+    fn eval_logic(
+        &mut self,
+        is_and: bool,
+        dot: &Value,
+        args: &[Arg],
+        piped: Option<Value>,
+    ) -> Result<Value, TemplateError> {
+        if args.is_empty() && piped.is_none() {
+            return Err(self.error(format!(
+                "wrong number of args for {}: want at least 1 got 0",
+                if is_and { "and" } else { "or" }
+            )));
+        }
+        let mut last = Value::Nil;
+        for arg in args {
+            last = self.eval_arg(dot, arg)?;
+            if last.is_truthy() != is_and {
+                return Ok(last);
+            }
+        }
+        if let Some(p) = piped {
+            last = p;
+        }
+        Ok(last)
+    }
+
+    fn eval_args(
+        &mut self,
+        dot: &Value,
+        args: &[Arg],
+        piped: Option<Value>,
+    ) -> Result<Vec<Value>, TemplateError> {
+        let mut values = Vec::with_capacity(args.len() + 1);
+        for arg in args {
+            values.push(self.eval_arg(dot, arg)?);
+        }
+        values.extend(piped);
+        Ok(values)
+    }
+
+    fn eval_arg(&mut self, dot: &Value, arg: &Arg) -> Result<Value, TemplateError> {
+        Ok(match arg {
+            Arg::Dot => dot.clone(),
+            Arg::Field(chain) => self.eval_chain(dot.clone(), chain, Vec::new())?,
+            Arg::Var(name, chain) => {
+                let base = self.var(name)?;
+                self.eval_chain(base, chain, Vec::new())?
+            }
+            Arg::Sub(pipe, chain) => {
+                let base = self.eval_cmds(dot, pipe)?;
+                self.eval_chain(base, chain, Vec::new())?
+            }
+            Arg::Ident(name) => super::funcs::call(self, name, Vec::new())?,
+            Arg::Str(s) => Value::str(s.as_str()),
+            Arg::Int(n) => Value::Int(*n),
+            Arg::Float(f) => Value::Float(*f),
+            Arg::Bool(b) => Value::Bool(*b),
+            Arg::Nil => Value::Nil,
+        })
+    }
+
+    fn var(&self, name: &str) -> Result<Value, TemplateError> {
+        self.vars
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .ok_or_else(|| self.error(format!("undefined variable: {name}")))
+    }
+
+    /// Resolve a field chain on `value`. `args` are passed to the final
+    /// element, which must then be a method (`.Files.Get "x"`).
+    fn eval_chain(
+        &mut self,
+        mut value: Value,
+        chain: &[String],
+        args: Vec<Value>,
+    ) -> Result<Value, TemplateError> {
+        if chain.is_empty() && !args.is_empty() {
+            return Err(self.error("can't give argument to non-function"));
+        }
+        let mut args = Some(args);
+        for (i, field) in chain.iter().enumerate() {
+            let last = i + 1 == chain.len();
+            let call_args = if last {
+                args.take().unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            value = match &value {
+                Value::Map(m) => {
+                    if !call_args.is_empty() {
+                        return Err(self.error(format!(
+                            "{field} has arguments but cannot be invoked as function"
+                        )));
+                    }
+                    m.borrow().get(field).cloned().unwrap_or(Value::Nil)
+                }
+                Value::Files(files) => super::funcs::files_method(self, files, field, call_args)?,
+                Value::ApiVersions(versions) => {
+                    super::funcs::api_versions_method(self, versions, field, call_args)?
+                }
+                Value::Nil => {
+                    return Err(
+                        self.error(format!("nil pointer evaluating interface {{}}.{field}"))
+                    );
+                }
+                other => {
+                    return Err(self.error(format!(
+                        "can't evaluate field {field} in type {}",
+                        other.type_name()
+                    )));
+                }
+            };
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,1356 @@
+//! Template functions: Go's builtins, the Sprig functions charts commonly use
+//! and Helm's own additions (`include`, `tpl`, `toYaml`, `required`, ...).
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use base64::Engine;
+
+use super::TemplateError;
+use super::exec::Exec;
+use super::value::{Value, format_float, values_equal};
+
+type Result<T> = std::result::Result<T, TemplateError>;
+
+/// Call the template function `name`.
+pub(crate) fn call(exec: &mut Exec<'_>, name: &str, args: Vec<Value>) -> Result<Value> {
+    let n = args.len();
+    let want = |exec: &Exec<'_>, min: usize, max: usize| -> Result<()> {
+        if n < min || n > max {
+            let expected = if min == max {
+                min.to_string()
+            } else if max == usize::MAX {
+                format!("at least {min}")
+            } else {
+                format!("{min}-{max}")
+            };
+            return Err(exec.error(format!(
+                "wrong number of args for {name}: want {expected} got {n}"
+            )));
+        }
+        Ok(())
+    };
+    let mut args = args.into_iter();
+    let mut next = || args.next().unwrap_or(Value::Nil);
+
+    let value = match name {
+        // --- Helm ---
+        "include" => {
+            want(exec, 2, 2)?;
+            let tmpl = string(&next());
+            let data = next();
+            Value::Str(exec.execute(&tmpl, &data)?)
+        }
+        "tpl" => {
+            want(exec, 2, 2)?;
+            let src = string(&next());
+            let data = next();
+            Value::Str(exec.execute_source("tpl", &src, &data)?)
+        }
+        "required" => {
+            want(exec, 2, 2)?;
+            let msg = string(&next());
+            let v = next();
+            if v.is_nil() || matches!(&v, Value::Str(s) if s.is_empty()) {
+                return Err(exec.error(format!("execution error: {msg}")));
+            }
+            v
+        }
+        "fail" => {
+            want(exec, 1, 1)?;
+            return Err(exec.error(format!("execution error: {}", string(&next()))));
+        }
+        // No cluster access while rendering: behave like `helm template`.
+        "lookup" => {
+            want(exec, 4, 4)?;
+            Value::empty_map()
+        }
+        "toYaml" | "toYamlPretty" => {
+            want(exec, 1, 1)?;
+            Value::Str(to_yaml(&next()))
+        }
+        "fromYaml" => {
+            want(exec, 1, 1)?;
+            from_yaml(&string(&next()), false)
+        }
+        "fromYamlArray" => {
+            want(exec, 1, 1)?;
+            from_yaml(&string(&next()), true)
+        }
+        "toJson" | "toRawJson" | "mustToJson" | "mustToRawJson" => {
+            want(exec, 1, 1)?;
+            Value::Str(serde_json::to_string(&next().to_json()).unwrap_or_default())
+        }
+        "toPrettyJson" | "mustToPrettyJson" => {
+            want(exec, 1, 1)?;
+            Value::Str(serde_json::to_string_pretty(&next().to_json()).unwrap_or_default())
+        }
+        "fromJson" | "fromJsonArray" => {
+            want(exec, 1, 1)?;
+            match serde_json::from_str::<serde_json::Value>(&string(&next())) {
+                Ok(json) => Value::from_json(&json),
+                Err(e) => {
+                    let mut m = BTreeMap::new();
+                    m.insert("Error".to_string(), Value::str(e.to_string()));
+                    Value::map(m)
+                }
+            }
+        }
+
+        // --- Go builtins ---
+        "not" => {
+            want(exec, 1, 1)?;
+            Value::Bool(!next().is_truthy())
+        }
+        "len" => {
+            want(exec, 1, 1)?;
+            let v = next();
+            Value::Int(match &v {
+                Value::Str(s) => s.len() as i64,
+                Value::List(l) => l.len() as i64,
+                Value::Map(m) => m.borrow().len() as i64,
+                Value::Files(f) => f.len() as i64,
+                Value::ApiVersions(a) => a.len() as i64,
+                Value::Nil => 0,
+                other => return Err(exec.error(format!("len of type {}", other.type_name()))),
+            })
+        }
+        "index" => {
+            want(exec, 1, usize::MAX)?;
+            let mut v = next();
+            for key in args.by_ref() {
+                v = match (&v, &key) {
+                    (Value::Map(m), _) => {
+                        m.borrow().get(&string(&key)).cloned().unwrap_or(Value::Nil)
+                    }
+                    (Value::List(l), k) if k.as_f64().is_some() => {
+                        let i = k.to_int();
+                        if i < 0 || i as usize >= l.len() {
+                            return Err(exec.error(format!("index out of range: {i}")));
+                        }
+                        l[i as usize].clone()
+                    }
+                    (Value::Nil, _) => Value::Nil,
+                    (other, _) => {
+                        return Err(
+                            exec.error(format!("can't index item of type {}", other.type_name()))
+                        );
+                    }
+                };
+            }
+            v
+        }
+        "print" => Value::Str(go_print(&args.collect::<Vec<_>>())),
+        "println" => {
+            let items: Vec<String> = args.map(|v| v.to_display()).collect();
+            Value::Str(format!("{}\n", items.join(" ")))
+        }
+        "printf" => {
+            want(exec, 1, usize::MAX)?;
+            let format = string(&next());
+            Value::Str(sprintf(&format, &args.collect::<Vec<_>>()))
+        }
+        "eq" => {
+            want(exec, 2, usize::MAX)?;
+            let first = next();
+            Value::Bool(args.any(|v| values_equal(&first, &v)))
+        }
+        "ne" => {
+            want(exec, 2, 2)?;
+            Value::Bool(!values_equal(&next(), &next()))
+        }
+        "lt" | "le" | "gt" | "ge" => {
+            want(exec, 2, 2)?;
+            let (a, b) = (next(), next());
+            let ord = compare(&a, &b).ok_or_else(|| {
+                exec.error(format!(
+                    "incompatible types for comparison: {} and {}",
+                    a.type_name(),
+                    b.type_name()
+                ))
+            })?;
+            Value::Bool(match name {
+                "lt" => ord.is_lt(),
+                "le" => ord.is_le(),
+                "gt" => ord.is_gt(),
+                _ => ord.is_ge(),
+            })
+        }
+
+        // --- Defaults and flow ---
+        "default" => {
+            want(exec, 1, 2)?;
+            let default = next();
+            let given = next();
+            if is_empty(&given) { default } else { given }
+        }
+        "empty" => {
+            want(exec, 1, 1)?;
+            Value::Bool(is_empty(&next()))
+        }
+        "coalesce" => args.find(|v| !is_empty(v)).unwrap_or(Value::Nil),
+        "ternary" => {
+            want(exec, 3, 3)?;
+            let (yes, no, cond) = (next(), next(), next());
+            if cond.is_truthy() { yes } else { no }
+        }
+        "deepEqual" => {
+            want(exec, 2, 2)?;
+            Value::Bool(next().to_json() == next().to_json())
+        }
+        "kindOf" => {
+            want(exec, 1, 1)?;
+            Value::str(next().kind())
+        }
+        "kindIs" => {
+            want(exec, 2, 2)?;
+            let kind = string(&next());
+            Value::Bool(next().kind() == kind)
+        }
+        "typeOf" => {
+            want(exec, 1, 1)?;
+            Value::str(next().type_name())
+        }
+        "typeIs" => {
+            want(exec, 2, 2)?;
+            let ty = string(&next());
+            Value::Bool(next().type_name() == ty)
+        }
+
+        // --- Strings ---
+        "quote" => Value::Str(
+            args.filter(|v| !v.is_nil())
+                .map(|v| go_quote(&string(&v)))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        "squote" => Value::Str(
+            args.filter(|v| !v.is_nil())
+                .map(|v| format!("'{}'", string(&v)))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        "cat" => Value::Str(
+            args.filter(|v| !v.is_nil())
+                .map(|v| v.to_display())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        "toString" => {
+            want(exec, 1, 1)?;
+            Value::Str(string(&next()))
+        }
+        "toStrings" => {
+            want(exec, 1, 1)?;
+            let items = list_arg(exec, &next())?;
+            Value::list(items.iter().map(|v| Value::Str(string(v))).collect())
+        }
+        "indent" | "nindent" => {
+            want(exec, 2, 2)?;
+            let width = next().to_int().max(0) as usize;
+            let pad = " ".repeat(width);
+            let text = string(&next());
+            let indented = format!("{pad}{}", text.replace('\n', &format!("\n{pad}")));
+            Value::Str(if name == "nindent" {
+                format!("\n{indented}")
+            } else {
+                indented
+            })
+        }
+        "trim" => {
+            want(exec, 1, 1)?;
+            Value::str(string(&next()).trim())
+        }
+        "trimAll" => {
+            want(exec, 2, 2)?;
+            let cutset: Vec<char> = string(&next()).chars().collect();
+            Value::str(string(&next()).trim_matches(cutset.as_slice()))
+        }
+        "trimPrefix" => {
+            want(exec, 2, 2)?;
+            let prefix = string(&next());
+            let s = string(&next());
+            Value::str(s.strip_prefix(prefix.as_str()).unwrap_or(&s))
+        }
+        "trimSuffix" => {
+            want(exec, 2, 2)?;
+            let suffix = string(&next());
+            let s = string(&next());
+            Value::str(s.strip_suffix(suffix.as_str()).unwrap_or(&s))
+        }
+        "upper" => {
+            want(exec, 1, 1)?;
+            Value::Str(string(&next()).to_uppercase())
+        }
+        "lower" => {
+            want(exec, 1, 1)?;
+            Value::Str(string(&next()).to_lowercase())
+        }
+        "title" => {
+            want(exec, 1, 1)?;
+            Value::Str(map_words(&string(&next()), |w| capitalize(w, true)))
+        }
+        "untitle" => {
+            want(exec, 1, 1)?;
+            Value::Str(map_words(&string(&next()), |w| capitalize(w, false)))
+        }
+        "replace" => {
+            want(exec, 3, 3)?;
+            let (old, new, s) = (string(&next()), string(&next()), string(&next()));
+            Value::Str(s.replace(&old, &new))
+        }
+        "repeat" => {
+            want(exec, 2, 2)?;
+            let count = next().to_int().max(0) as usize;
+            Value::Str(string(&next()).repeat(count))
+        }
+        "substr" => {
+            want(exec, 3, 3)?;
+            let (start, end, s) = (next().to_int(), next().to_int(), string(&next()));
+            let len = s.len() as i64;
+            let start = start.clamp(0, len) as usize;
+            let end = if end < 0 || end > len { len } else { end } as usize;
+            Value::str(s.get(start..end.max(start)).unwrap_or(""))
+        }
+        "trunc" => {
+            want(exec, 2, 2)?;
+            let (count, s) = (next().to_int(), string(&next()));
+            let chars: Vec<char> = s.chars().collect();
+            let len = chars.len() as i64;
+            Value::Str(if count < 0 {
+                let skip = (len + count).max(0) as usize;
+                chars[skip..].iter().collect()
+            } else {
+                chars[..(count.min(len)) as usize].iter().collect()
+            })
+        }
+        "abbrev" => {
+            want(exec, 2, 2)?;
+            let (width, s) = (next().to_int().max(0) as usize, string(&next()));
+            Value::Str(if s.chars().count() <= width || width < 4 {
+                s
+            } else {
+                format!("{}...", s.chars().take(width - 3).collect::<String>())
+            })
+        }
+        "nospace" => {
+            want(exec, 1, 1)?;
+            Value::Str(
+                string(&next())
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect(),
+            )
+        }
+        "contains" => {
+            want(exec, 2, 2)?;
+            let needle = string(&next());
+            Value::Bool(string(&next()).contains(&needle))
+        }
+        "hasPrefix" => {
+            want(exec, 2, 2)?;
+            let prefix = string(&next());
+            Value::Bool(string(&next()).starts_with(&prefix))
+        }
+        "hasSuffix" => {
+            want(exec, 2, 2)?;
+            let suffix = string(&next());
+            Value::Bool(string(&next()).ends_with(&suffix))
+        }
+        "snakecase" => {
+            want(exec, 1, 1)?;
+            Value::Str(words(&string(&next())).join("_").to_lowercase())
+        }
+        "kebabcase" => {
+            want(exec, 1, 1)?;
+            Value::Str(words(&string(&next())).join("-").to_lowercase())
+        }
+        "camelcase" => {
+            want(exec, 1, 1)?;
+            Value::Str(
+                string(&next())
+                    .split('_')
+                    .map(|w| capitalize(w, true))
+                    .collect(),
+            )
+        }
+        "split" => {
+            want(exec, 2, 2)?;
+            let (sep, s) = (string(&next()), string(&next()));
+            Value::map(
+                s.split(sep.as_str())
+                    .enumerate()
+                    .map(|(i, part)| (format!("_{i}"), Value::str(part)))
+                    .collect(),
+            )
+        }
+        "splitList" => {
+            want(exec, 2, 2)?;
+            let (sep, s) = (string(&next()), string(&next()));
+            Value::list(s.split(sep.as_str()).map(Value::str).collect())
+        }
+        "splitn" => {
+            want(exec, 3, 3)?;
+            let (sep, count, s) = (
+                string(&next()),
+                next().to_int().max(1) as usize,
+                string(&next()),
+            );
+            Value::map(
+                s.splitn(count, sep.as_str())
+                    .enumerate()
+                    .map(|(i, part)| (format!("_{i}"), Value::str(part)))
+                    .collect(),
+            )
+        }
+        "join" => {
+            want(exec, 2, 2)?;
+            let sep = string(&next());
+            let v = next();
+            let items = match &v {
+                Value::Str(s) => vec![Value::str(s.as_str())],
+                other => list_arg(exec, other)?,
+            };
+            Value::Str(
+                items
+                    .iter()
+                    .filter(|v| !v.is_nil())
+                    .map(string)
+                    .collect::<Vec<_>>()
+                    .join(&sep),
+            )
+        }
+        "sortAlpha" => {
+            want(exec, 1, 1)?;
+            let mut items: Vec<String> = list_arg(exec, &next())?.iter().map(string).collect();
+            items.sort();
+            Value::list(items.into_iter().map(Value::Str).collect())
+        }
+        "b64enc" => {
+            want(exec, 1, 1)?;
+            Value::Str(base64::engine::general_purpose::STANDARD.encode(string(&next())))
+        }
+        "b64dec" => {
+            want(exec, 1, 1)?;
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(string(&next()))
+                .map(|b| String::from_utf8_lossy(&b).into_owned())
+                .unwrap_or_else(|e| e.to_string());
+            Value::Str(decoded)
+        }
+        "sha256sum" => {
+            want(exec, 1, 1)?;
+            Value::Str(husako_digest::sha256_hex(string(&next()).as_bytes()))
+        }
+        "sha1sum" => {
+            want(exec, 1, 1)?;
+            let digest = aws_lc_rs::digest::digest(
+                &aws_lc_rs::digest::SHA1_FOR_LEGACY_USE_ONLY,
+                string(&next()).as_bytes(),
+            );
+            Value::Str(hex(digest.as_ref()))
+        }
+        "randAlphaNum" | "randAlpha" | "randNumeric" | "randAscii" => {
+            want(exec, 1, 1)?;
+            Value::Str(random_string(name, next().to_int().max(0) as usize))
+        }
+        "uuidv4" => {
+            want(exec, 0, 0)?;
+            let hex = random_string("randHex", 32);
+            Value::Str(format!(
+                "{}-{}-4{}-a{}-{}",
+                &hex[..8],
+                &hex[8..12],
+                &hex[13..16],
+                &hex[17..20],
+                &hex[20..]
+            ))
+        }
+        "regexMatch" | "mustRegexMatch" => {
+            want(exec, 2, 2)?;
+            let re = regex(exec, &string(&next()))?;
+            Value::Bool(re.is_match(&string(&next())))
+        }
+        "regexFind" | "mustRegexFind" => {
+            want(exec, 2, 2)?;
+            let re = regex(exec, &string(&next()))?;
+            let s = string(&next());
+            Value::str(re.find(&s).map(|m| m.as_str()).unwrap_or(""))
+        }
+        "regexFindAll" | "mustRegexFindAll" => {
+            want(exec, 3, 3)?;
+            let re = regex(exec, &string(&next()))?;
+            let (s, limit) = (string(&next()), next().to_int());
+            let limit = if limit < 0 {
+                usize::MAX
+            } else {
+                limit as usize
+            };
+            Value::list(
+                re.find_iter(&s)
+                    .take(limit)
+                    .map(|m| Value::str(m.as_str()))
+                    .collect(),
+            )
+        }
+        "regexReplaceAll" | "mustRegexReplaceAll" => {
+            want(exec, 3, 3)?;
+            let re = regex(exec, &string(&next()))?;
+            let (s, repl) = (string(&next()), string(&next()));
+            Value::Str(re.replace_all(&s, repl.as_str()).into_owned())
+        }
+        "regexReplaceAllLiteral" | "mustRegexReplaceAllLiteral" => {
+            want(exec, 3, 3)?;
+            let re = regex(exec, &string(&next()))?;
+            let (s, repl) = (string(&next()), string(&next()));
+            Value::Str(re.replace_all(&s, regex::NoExpand(&repl)).into_owned())
+        }
+        "regexSplit" | "mustRegexSplit" => {
+            want(exec, 3, 3)?;
+            let re = regex(exec, &string(&next()))?;
+            let (s, limit) = (string(&next()), next().to_int());
+            let parts: Vec<Value> = if limit < 0 {
+                re.split(&s).map(Value::str).collect()
+            } else {
+                re.splitn(&s, limit as usize).map(Value::str).collect()
+            };
+            Value::list(parts)
+        }
+        "semverCompare" => {
+            want(exec, 2, 2)?;
+            let (constraint, version) = (string(&next()), string(&next()));
+            Value::Bool(semver_compare(exec, &constraint, &version)?)
+        }
+        "base" | "dir" | "ext" | "clean" => {
+            want(exec, 1, 1)?;
+            Value::Str(path_func(name, &string(&next())))
+        }
+
+        // --- Numbers ---
+        "atoi" => {
+            want(exec, 1, 1)?;
+            Value::Int(string(&next()).trim().parse().unwrap_or(0))
+        }
+        "int" | "int64" => {
+            want(exec, 1, 1)?;
+            Value::Int(next().to_int())
+        }
+        "float64" => {
+            want(exec, 1, 1)?;
+            Value::Float(next().to_float())
+        }
+        "add1" => {
+            want(exec, 1, 1)?;
+            Value::Int(next().to_int() + 1)
+        }
+        "add" | "mul" => {
+            let is_add = name == "add";
+            let init = if is_add { 0 } else { 1 };
+            Value::Int(args.fold(init, |acc, v| {
+                if is_add {
+                    acc + v.to_int()
+                } else {
+                    acc * v.to_int()
+                }
+            }))
+        }
+        "sub" | "div" | "mod" => {
+            want(exec, 2, 2)?;
+            let (a, b) = (next().to_int(), next().to_int());
+            if name != "sub" && b == 0 {
+                return Err(exec.error("integer divide by zero"));
+            }
+            Value::Int(match name {
+                "sub" => a - b,
+                "div" => a / b,
+                _ => a % b,
+            })
+        }
+        "addf" | "subf" | "mulf" | "divf" => {
+            want(exec, 1, usize::MAX)?;
+            let first = next().to_float();
+            Value::Float(args.fold(first, |acc, v| {
+                let v = v.to_float();
+                match name {
+                    "addf" => acc + v,
+                    "subf" => acc - v,
+                    "mulf" => acc * v,
+                    _ => acc / v,
+                }
+            }))
+        }
+        "max" | "min" => {
+            want(exec, 1, usize::MAX)?;
+            let first = next().to_int();
+            Value::Int(args.fold(first, |acc, v| {
+                if name == "max" {
+                    acc.max(v.to_int())
+                } else {
+                    acc.min(v.to_int())
+                }
+            }))
+        }
+        "floor" | "ceil" => {
+            want(exec, 1, 1)?;
+            let f = next().to_float();
+            Value::Float(if name == "floor" { f.floor() } else { f.ceil() })
+        }
+        "round" => {
+            want(exec, 2, 3)?;
+            let (f, places) = (next().to_float(), next().to_int());
+            let scale = 10f64.powi(places as i32);
+            Value::Float((f * scale).round() / scale)
+        }
+        "until" => {
+            want(exec, 1, 1)?;
+            let count = next().to_int();
+            Value::list((0..count.max(0)).map(Value::Int).collect())
+        }
+        "untilStep" => {
+            want(exec, 3, 3)?;
+            let (start, stop, step) = (next().to_int(), next().to_int(), next().to_int());
+            let mut items = Vec::new();
+            let mut i = start;
+            while step != 0 && ((step > 0 && i < stop) || (step < 0 && i > stop)) {
+                items.push(Value::Int(i));
+                i += step;
+            }
+            Value::list(items)
+        }
+
+        // --- Lists ---
+        "list" => Value::list(args.collect()),
+        "first" | "mustFirst" => {
+            want(exec, 1, 1)?;
+            list_arg(exec, &next())?
+                .into_iter()
+                .next()
+                .unwrap_or(Value::Nil)
+        }
+        "last" | "mustLast" => {
+            want(exec, 1, 1)?;
+            list_arg(exec, &next())?.pop().unwrap_or(Value::Nil)
+        }
+        "rest" | "mustRest" => {
+            want(exec, 1, 1)?;
+            Value::list(list_arg(exec, &next())?.into_iter().skip(1).collect())
+        }
+        "initial" | "mustInitial" => {
+            want(exec, 1, 1)?;
+            let mut items = list_arg(exec, &next())?;
+            items.pop();
+            Value::list(items)
+        }
+        "append" | "push" | "mustAppend" | "mustPush" => {
+            want(exec, 2, 2)?;
+            let mut items = list_arg(exec, &next())?;
+            items.push(next());
+            Value::list(items)
+        }
+        "prepend" | "mustPrepend" => {
+            want(exec, 2, 2)?;
+            let mut items = list_arg(exec, &next())?;
+            items.insert(0, next());
+            Value::list(items)
+        }
+        "concat" => {
+            let mut items = Vec::new();
+            for v in args {
+                items.extend(list_arg(exec, &v)?);
+            }
+            Value::list(items)
+        }
+        "reverse" | "mustReverse" => {
+            want(exec, 1, 1)?;
+            let mut items = list_arg(exec, &next())?;
+            items.reverse();
+            Value::list(items)
+        }
+        "uniq" | "mustUniq" => {
+            want(exec, 1, 1)?;
+            let mut items: Vec<Value> = Vec::new();
+            for v in list_arg(exec, &next())? {
+                if !items.iter().any(|i| values_equal(i, &v)) {
+                    items.push(v);
+                }
+            }
+            Value::list(items)
+        }
+        "without" | "mustWithout" => {
+            want(exec, 1, usize::MAX)?;
+            let items = list_arg(exec, &next())?;
+            let remove: Vec<Value> = args.collect();
+            Value::list(
+                items
+                    .into_iter()
+                    .filter(|v| !remove.iter().any(|r| values_equal(r, v)))
+                    .collect(),
+            )
+        }
+        "has" | "mustHas" => {
+            want(exec, 2, 2)?;
+            let needle = next();
+            Value::Bool(
+                list_arg(exec, &next())?
+                    .iter()
+                    .any(|v| values_equal(v, &needle)),
+            )
+        }
+        "compact" | "mustCompact" => {
+            want(exec, 1, 1)?;
+            Value::list(
+                list_arg(exec, &next())?
+                    .into_iter()
+                    .filter(|v| !is_empty(v))
+                    .collect(),
+            )
+        }
+        "slice" | "mustSlice" => {
+            want(exec, 1, 3)?;
+            let items = list_arg(exec, &next())?;
+            let len = items.len() as i64;
+            let start = args.next().map(|v| v.to_int()).unwrap_or(0).clamp(0, len) as usize;
+            let end = args.next().map(|v| v.to_int()).unwrap_or(len).clamp(0, len) as usize;
+            Value::list(items[start..end.max(start)].to_vec())
+        }
+
+        // --- Dicts ---
+        "dict" => {
+            let items: Vec<Value> = args.collect();
+            let mut m = BTreeMap::new();
+            for pair in items.chunks(2) {
+                let value = pair.get(1).cloned().unwrap_or(Value::Str(String::new()));
+                m.insert(string(&pair[0]), value);
+            }
+            Value::map(m)
+        }
+        "get" => {
+            want(exec, 2, 2)?;
+            let (d, key) = (next(), string(&next()));
+            match &d {
+                Value::Map(m) => m.borrow().get(&key).cloned().unwrap_or(Value::str("")),
+                _ => Value::str(""),
+            }
+        }
+        "set" => {
+            want(exec, 3, 3)?;
+            let (d, key, value) = (next(), string(&next()), next());
+            let Value::Map(m) = &d else {
+                return Err(exec.error(format!("set: expected map, got {}", d.type_name())));
+            };
+            m.borrow_mut().insert(key, value);
+            d
+        }
+        "unset" => {
+            want(exec, 2, 2)?;
+            let (d, key) = (next(), string(&next()));
+            let Value::Map(m) = &d else {
+                return Err(exec.error(format!("unset: expected map, got {}", d.type_name())));
+            };
+            m.borrow_mut().remove(&key);
+            d
+        }
+        "hasKey" => {
+            want(exec, 2, 2)?;
+            let (d, key) = (next(), string(&next()));
+            Value::Bool(matches!(&d, Value::Map(m) if m.borrow().contains_key(&key)))
+        }
+        "keys" => {
+            let mut keys = Vec::new();
+            for d in args {
+                if let Value::Map(m) = &d {
+                    keys.extend(m.borrow().keys().map(|k| Value::str(k.as_str())));
+                }
+            }
+            Value::list(keys)
+        }
+        "values" => {
+            want(exec, 1, 1)?;
+            match next() {
+                Value::Map(m) => Value::list(m.borrow().values().cloned().collect()),
+                _ => Value::list(Vec::new()),
+            }
+        }
+        "pluck" => {
+            want(exec, 1, usize::MAX)?;
+            let key = string(&next());
+            Value::list(
+                args.filter_map(|d| match &d {
+                    Value::Map(m) => m.borrow().get(&key).cloned(),
+                    _ => None,
+                })
+                .collect(),
+            )
+        }
+        "pick" | "omit" => {
+            want(exec, 1, usize::MAX)?;
+            let d = next();
+            let keys: Vec<String> = args.map(|k| string(&k)).collect();
+            let Value::Map(m) = &d else {
+                return Ok(Value::empty_map());
+            };
+            let pick = name == "pick";
+            Value::map(
+                m.borrow()
+                    .iter()
+                    .filter(|(k, _)| keys.contains(k) == pick)
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            )
+        }
+        "merge" | "mustMerge" | "mergeOverwrite" | "mustMergeOverwrite" => {
+            want(exec, 1, usize::MAX)?;
+            let dst = next();
+            let overwrite = name.ends_with("Overwrite");
+            if let Value::Map(_) = &dst {
+                for src in args {
+                    merge_into(&dst, &src, overwrite);
+                }
+            }
+            dst
+        }
+        "deepCopy" | "mustDeepCopy" => {
+            want(exec, 1, 1)?;
+            next().deep_copy()
+        }
+        "dig" => {
+            want(exec, 3, usize::MAX)?;
+            let mut rest: Vec<Value> = std::iter::once(next()).chain(args).collect();
+            let d = rest.pop().unwrap_or(Value::Nil);
+            let default = rest.pop().unwrap_or(Value::Nil);
+            let mut current = d;
+            for key in &rest {
+                current = match &current {
+                    Value::Map(m) => match m.borrow().get(&string(key)) {
+                        Some(v) => v.clone(),
+                        None => return Ok(default),
+                    },
+                    _ => return Ok(default),
+                };
+            }
+            current
+        }
+
+        _ => return Err(exec.error(format!("function {name:?} not defined"))),
+    };
+    Ok(value)
+}
+
+/// Methods of `.Files`.
+pub(crate) fn files_method(
+    exec: &Exec<'_>,
+    files: &Rc<BTreeMap<String, Vec<u8>>>,
+    method: &str,
+    args: Vec<Value>,
+) -> Result<Value> {
+    let arg = |i: usize| args.get(i).map(string).unwrap_or_default();
+    let text = |path: &str| {
+        files
+            .get(path)
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .unwrap_or_default()
+    };
+    Ok(match method {
+        "Get" | "GetBytes" => Value::Str(text(&arg(0))),
+        "Lines" => Value::list(
+            text(&arg(0))
+                .split('\n')
+                .map(|l| Value::str(l.trim_end_matches('\r')))
+                .collect(),
+        ),
+        "Glob" => {
+            let pattern = arg(0);
+            Value::Files(Rc::new(
+                files
+                    .iter()
+                    .filter(|(path, _)| glob_match(&pattern, path))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            ))
+        }
+        "AsConfig" | "AsSecrets" => {
+            let entries: serde_json::Map<String, serde_json::Value> = files
+                .iter()
+                .map(|(path, bytes)| {
+                    let key = path.rsplit('/').next().unwrap_or(path).to_string();
+                    let value = if method == "AsSecrets" {
+                        base64::engine::general_purpose::STANDARD.encode(bytes)
+                    } else {
+                        String::from_utf8_lossy(bytes).into_owned()
+                    };
+                    (key, serde_json::Value::String(value))
+                })
+                .collect();
+            Value::Str(to_yaml(&Value::from_json(&serde_json::Value::Object(
+                entries,
+            ))))
+        }
+        other => {
+            return Err(exec.error(format!("can't evaluate field {other} in type chart.Files")));
+        }
+    })
+}
+
+/// Methods of `.Capabilities.APIVersions`.
+pub(crate) fn api_versions_method(
+    exec: &Exec<'_>,
+    versions: &Rc<Vec<String>>,
+    method: &str,
+    args: Vec<Value>,
+) -> Result<Value> {
+    match method {
+        "Has" => {
+            let wanted = args.first().map(string).unwrap_or_default();
+            Ok(Value::Bool(versions.contains(&wanted)))
+        }
+        other => Err(exec.error(format!(
+            "can't evaluate field {other} in type chartutil.VersionSet"
+        ))),
+    }
+}
+
+/// Stringify a value the way Sprig's `toString` does.
+fn string(v: &Value) -> String {
+    v.to_display()
+}
+
+fn list_arg(exec: &Exec<'_>, v: &Value) -> Result<Vec<Value>> {
+    v.items()
+        .ok_or_else(|| exec.error(format!("expected a list, got {}", v.type_name())))
+}
+
+/// Sprig's notion of emptiness (used by `default`, `empty`, `coalesce`, `compact`).
+fn is_empty(v: &Value) -> bool {
+    !v.is_truthy()
+}
+
+fn compare(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (Value::Str(x), Value::Str(y)) => Some(x.cmp(y)),
+        (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+        _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    }
+}
+
+fn merge_into(dst: &Value, src: &Value, overwrite: bool) {
+    let (Value::Map(d), Value::Map(s)) = (dst, src) else {
+        return;
+    };
+    if Rc::ptr_eq(d, s) {
+        return;
+    }
+    for (k, v) in s.borrow().iter() {
+        let existing = d.borrow().get(k).cloned();
+        match existing {
+            Some(cur @ Value::Map(_)) if matches!(v, Value::Map(_)) => {
+                merge_into(&cur, v, overwrite)
+            }
+            Some(_) if !overwrite => {}
+            _ => {
+                d.borrow_mut().insert(k.clone(), v.clone());
+            }
+        }
+    }
+}
+
+/// Go's `fmt.Sprint`: spaces are added between operands when neither is a string.
+fn go_print(args: &[Value]) -> String {
+    let mut out = String::new();
+    for (i, v) in args.iter().enumerate() {
+        if i > 0 && !matches!(v, Value::Str(_)) && !matches!(args[i - 1], Value::Str(_)) {
+            out.push(' ');
+        }
+        out.push_str(&v.to_display());
+    }
+    out
+}
+
+/// A subset of Go's `fmt.Sprintf`: flags `-+ 0#`, width, precision and the
+/// verbs `v s d q f F e g t x X o b c T %`.
+fn sprintf(format: &str, args: &[Value]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let mut flags = String::new();
+        while let Some(&f) = chars.peek() {
+            if "-+ 0#".contains(f) {
+                flags.push(f);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        let mut width = String::new();
+        while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+            width.push(d);
+            chars.next();
+        }
+        let mut precision = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut p = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                p.push(d);
+                chars.next();
+            }
+            precision = Some(p.parse::<usize>().unwrap_or(0));
+        }
+        let Some(verb) = chars.next() else {
+            out.push_str("%!(NOVERB)");
+            break;
+        };
+        if verb == '%' {
+            out.push('%');
+            continue;
+        }
+        let Some(arg) = args.next() else {
+            out.push_str(&format!("%!{verb}(MISSING)"));
+            continue;
+        };
+
+        let body = match verb {
+            'v' | 's' => {
+                let s = arg.to_display();
+                match precision {
+                    Some(p) if verb == 's' => s.chars().take(p).collect(),
+                    _ => s,
+                }
+            }
+            'd' => match arg {
+                Value::Int(_) | Value::Float(_) | Value::Bool(_) => {
+                    let n = arg.to_int();
+                    if flags.contains('+') && n >= 0 {
+                        format!("+{n}")
+                    } else {
+                        n.to_string()
+                    }
+                }
+                other => format!("%!d({}={})", other.type_name(), other.to_display()),
+            },
+            'f' | 'F' => format!("{:.*}", precision.unwrap_or(6), arg.to_float()),
+            'e' => format!("{:.*e}", precision.unwrap_or(6), arg.to_float()),
+            'g' => format_float(arg.to_float()),
+            'q' => go_quote(&arg.to_display()),
+            't' => arg.is_truthy().to_string(),
+            'x' | 'X' | 'o' | 'b' => {
+                let s = match arg {
+                    Value::Str(s) if verb == 'x' || verb == 'X' => {
+                        s.bytes().map(|b| format!("{b:02x}")).collect()
+                    }
+                    _ => {
+                        let n = arg.to_int();
+                        match verb {
+                            'x' | 'X' => format!("{n:x}"),
+                            'o' => format!("{n:o}"),
+                            _ => format!("{n:b}"),
+                        }
+                    }
+                };
+                if verb == 'X' { s.to_uppercase() } else { s }
+            }
+            'c' => char::from_u32(arg.to_int() as u32)
+                .map(String::from)
+                .unwrap_or_default(),
+            'T' => arg.type_name().to_string(),
+            other => format!("%!{other}({})", arg.to_display()),
+        };
+
+        let width = width.parse::<usize>().unwrap_or(0);
+        let len = body.chars().count();
+        if len >= width {
+            out.push_str(&body);
+        } else if flags.contains('-') {
+            out.push_str(&body);
+            out.push_str(&" ".repeat(width - len));
+        } else if flags.contains('0') && matches!(verb, 'd' | 'f' | 'F' | 'x' | 'X' | 'o' | 'b') {
+            let (sign, digits) = match body.strip_prefix('-') {
+                Some(d) => ("-", d),
+                None => ("", body.as_str()),
+            };
+            out.push_str(sign);
+            out.push_str(&"0".repeat(width - len));
+            out.push_str(digits);
+        } else {
+            out.push_str(&" ".repeat(width - len));
+            out.push_str(&body);
+        }
+    }
+
+    let extra: Vec<String> = args
+        .map(|v| format!("{}={}", v.type_name(), v.to_display()))
+        .collect();
+    if !extra.is_empty() {
+        out.push_str(&format!("%!(EXTRA {})", extra.join(", ")));
+    }
+    out
+}
+
+/// Quote a string like Go's `strconv.Quote`.
+fn go_quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 || c == '\x7f' => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Serialize a value as YAML like Helm's `toYaml` (no trailing newline).
+pub(crate) fn to_yaml(v: &Value) -> String {
+    serde_yaml_ng::to_string(&v.to_json())
+        .unwrap_or_default()
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+fn from_yaml(s: &str, array: bool) -> Value {
+    match serde_yaml_ng::from_str::<serde_json::Value>(s) {
+        Ok(serde_json::Value::Null) if array => Value::list(Vec::new()),
+        Ok(serde_json::Value::Null) => Value::empty_map(),
+        Ok(json) => Value::from_json(&json),
+        Err(e) => {
+            let mut m = BTreeMap::new();
+            m.insert("Error".to_string(), Value::str(e.to_string()));
+            if array {
+                Value::list(vec![Value::str(e.to_string())])
+            } else {
+                Value::map(m)
+            }
+        }
+    }
+}
+
+fn regex(exec: &Exec<'_>, pattern: &str) -> Result<regex::Regex> {
+    regex::Regex::new(pattern)
+        .map_err(|e| exec.error(format!("invalid regular expression {pattern:?}: {e}")))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn random_string(kind: &str, len: usize) -> String {
+    use rand::Rng;
+
+    const ALPHA: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const NUMERIC: &[u8] = b"0123456789";
+    const HEX: &[u8] = b"0123456789abcdef";
+    let alphabet: Vec<u8> = match kind {
+        "randAlpha" => ALPHA.to_vec(),
+        "randNumeric" => NUMERIC.to_vec(),
+        "randAscii" => (b' '..=b'~').collect(),
+        "randHex" => HEX.to_vec(),
+        _ => [ALPHA, NUMERIC].concat(),
+    };
+    let mut rng = rand::rng();
+    (0..len)
+        .map(|_| alphabet[rng.random_range(0..alphabet.len())] as char)
+        .collect()
+}
+
+fn capitalize(word: &str, upper: bool) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) if upper => first.to_uppercase().chain(chars).collect(),
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn map_words(s: &str, f: impl Fn(&str) -> String) -> String {
+    s.split(' ').map(f).collect::<Vec<_>>().join(" ")
+}
+
+/// Split an identifier into words at case changes and separators.
+fn words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn path_func(name: &str, path: &str) -> String {
+    let p = std::path::Path::new(path);
+    match name {
+        "base" => p
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_else(|| ".".to_string()),
+        "dir" => match p.parent().map(|d| d.to_string_lossy().into_owned()) {
+            Some(d) if !d.is_empty() => d,
+            _ => ".".to_string(),
+        },
+        "ext" => p
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default(),
+        _ => {
+            let mut parts: Vec<&str> = Vec::new();
+            for part in path.split('/') {
+                match part {
+                    "" | "." => {}
+                    ".." if parts.last().is_some_and(|l| *l != "..") => {
+                        parts.pop();
+                    }
+                    other => parts.push(other),
+                }
+            }
+            let joined = parts.join("/");
+            match (path.starts_with('/'), joined.is_empty()) {
+                (true, _) => format!("/{joined}"),
+                (false, true) => ".".to_string(),
+                (false, false) => joined,
+            }
+        }
+    }
+}
+
+/// Match a path against a glob with `*` (within a segment), `**` and `?`.
+fn glob_match(pattern: &str, path: &str) -> bool {
+    fn go(p: &[u8], s: &[u8]) -> bool {
+        match p.first() {
+            None => s.is_empty(),
+            Some(b'*') if p.get(1) == Some(&b'*') => {
+                let rest = p[2..].strip_prefix(b"/").unwrap_or(&p[2..]);
+                (0..=s.len()).any(|i| go(rest, &s[i..]))
+            }
+            Some(b'*') => (0..=s.len())
+                .take_while(|&i| i == 0 || s[i - 1] != b'/')
+                .any(|i| go(&p[1..], &s[i..])),
+            Some(b'?') => !s.is_empty() && s[0] != b'/' && go(&p[1..], &s[1..]),
+            Some(c) => s.first() == Some(c) && go(&p[1..], &s[1..]),
+        }
+    }
+    go(pattern.as_bytes(), path.as_bytes())
+}
+
+/// Evaluate a Masterminds-style semver constraint (`>=1.19-0`, `~1.2`,
+/// `^2`, `1.x`, `>1 <3`, `a || b`). Pre-release and build suffixes are
+/// ignored when comparing, so `v1.30.2-eks-1` satisfies `>=1.19-0`.
+fn semver_compare(exec: &Exec<'_>, constraint: &str, version: &str) -> Result<bool> {
+    let Some(v) = parse_version(version) else {
+        return Err(exec.error(format!("invalid semantic version {version:?}")));
+    };
+    for alternative in constraint.split("||") {
+        let mut all = true;
+        for part in alternative
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|p| !p.is_empty())
+        {
+            let op_len = part
+                .find(|c: char| c.is_ascii_digit() || c == 'v' || c == 'x' || c == 'X' || c == '*')
+                .unwrap_or(part.len());
+            let (op, target) = part.split_at(op_len);
+            let parts = parse_constraint_version(target);
+            if !satisfies(op.trim(), &parts, v) {
+                all = false;
+                break;
+            }
+        }
+        if all {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn parse_version(s: &str) -> Option<(u64, u64, u64)> {
+    let core = s.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut nums = core.split('.').map(|p| p.parse::<u64>());
+    let major = nums.next()?.ok()?;
+    let minor = nums.next().unwrap_or(Ok(0)).ok()?;
+    let patch = nums.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// Constraint version components; `None` for missing or wildcard parts.
+fn parse_constraint_version(s: &str) -> [Option<u64>; 3] {
+    let core = s.trim_start_matches('v');
+    let core = core.split(['-', '+']).next().unwrap_or("");
+    let mut parts = [None; 3];
+    for (i, p) in core.split('.').take(3).enumerate() {
+        parts[i] = p.parse().ok();
+    }
+    parts
+}
+
+fn satisfies(op: &str, parts: &[Option<u64>; 3], v: (u64, u64, u64)) -> bool {
+    let lower = (
+        parts[0].unwrap_or(0),
+        parts[1].unwrap_or(0),
+        parts[2].unwrap_or(0),
+    );
+    // Exclusive upper bound for wildcard/partial versions (`1.2` ⇒ <1.3.0)
+    let partial_upper = match parts {
+        [None, ..] => None,
+        [Some(m), None, _] => Some((m + 1, 0, 0)),
+        [Some(m), Some(n), None] => Some((*m, n + 1, 0)),
+        _ => None,
+    };
+    let in_partial = |v| v >= lower && partial_upper.is_none_or(|u| v < u);
+    match op {
+        "" | "=" => in_partial(v),
+        "!=" => !in_partial(v),
+        ">" => match partial_upper {
+            Some(u) => v >= u,
+            None => v > lower,
+        },
+        ">=" => v >= lower,
+        "<" => v < lower,
+        "<=" => match partial_upper {
+            Some(u) => v < u,
+            None => v <= lower,
+        },
+        "~" | "~>" => {
+            let upper = match parts {
+                [Some(m), None, _] => (m + 1, 0, 0),
+                _ => (lower.0, lower.1 + 1, 0),
+            };
+            v >= lower && v < upper
+        }
+        "^" => {
+            let upper = if lower.0 > 0 || parts[1].is_none() {
+                (lower.0 + 1, 0, 0)
+            } else if lower.1 > 0 || parts[2].is_none() {
+                (0, lower.1 + 1, 0)
+            } else {
+                (0, 0, lower.2 + 1)
+            };
+            v >= lower && v < upper
+        }
+        _ => false,
+    }
+}
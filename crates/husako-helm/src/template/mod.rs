@@ -0,0 +1,210 @@
+//! A Go `text/template` engine with the Sprig and Helm function sets, used to
+//! render chart templates without the `helm` binary.
+
+mod exec;
+mod funcs;
+mod parse;
+mod value;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub(crate) use value::Value;
+
+#[derive(Debug)]
+pub(crate) struct TemplateError {
+    pub(crate) message: String,
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A set of named templates sharing `define`d partials, like a Go
+/// `*template.Template` with several files parsed into it.
+#[derive(Default)]
+pub(crate) struct Templates {
+    defs: HashMap<String, Rc<parse::List>>,
+}
+
+impl Templates {
+    /// Parse `src` and register it as `name`, along with every template it
+    /// defines. Later definitions replace earlier ones.
+    pub(crate) fn add(&mut self, name: &str, src: &str) -> Result<(), TemplateError> {
+        let (body, defines) = parse::parse(name, src)?;
+        self.defs.extend(defines);
+        self.defs.insert(name.to_string(), Rc::new(body));
+        Ok(())
+    }
+
+    /// Execute the template `name` with `data` as dot.
+    pub(crate) fn execute(&self, name: &str, data: &Value) -> Result<String, TemplateError> {
+        exec::Exec::new(self).execute(name, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(src: &str, data: serde_json::Value) -> String {
+        try_render(src, data).unwrap()
+    }
+
+    fn try_render(src: &str, data: serde_json::Value) -> Result<String, TemplateError> {
+        let mut t = Templates::default();
+        t.add("test", src)?;
+        t.execute("test", &Value::from_json(&data))
+    }
+
+    #[test]
+    fn fields_and_text() {
+        let out = render(
+            "name: {{ .Values.name }}\nmissing: {{ .Values.nope }}",
+            serde_json::json!({"Values": {"name": "web"}}),
+        );
+        assert_eq!(out, "name: web\nmissing: ");
+    }
+
+    #[test]
+    fn nil_pointer_on_missing_parent() {
+        let err = try_render("{{ .Values.a.b }}", serde_json::json!({"Values": {}})).unwrap_err();
+        assert!(
+            err.message
+                .contains("nil pointer evaluating interface {}.b")
+        );
+    }
+
+    #[test]
+    fn if_else_with_range() {
+        let src = r#"{{- if .on }}on{{ else }}off{{ end }}
+{{- with .obj }} {{ .k }}{{ end }}
+{{- range $i, $v := .list }} {{ $i }}={{ $v }}{{ end }}
+{{- range $k, $v := .map }} {{ $k }}:{{ $v }}{{ end }}
+{{- range .empty }}x{{ else }} none{{ end }}"#;
+        let out = render(
+            src,
+            serde_json::json!({
+                "on": false,
+                "obj": {"k": "v"},
+                "list": ["a", "b"],
+                "map": {"z": 1, "a": 2},
+                "empty": []
+            }),
+        );
+        assert_eq!(out, "off v 0=a 1=b a:2 z:1 none");
+    }
+
+    #[test]
+    fn variables_scope_and_assignment() {
+        let src = r#"{{ $x := 1 }}{{ if true }}{{ $x = 2 }}{{ $y := 3 }}{{ end }}{{ $x }}{{ range .l }}{{ $.top }}{{ end }}"#;
+        let out = render(src, serde_json::json!({"l": [1, 2], "top": "t"}));
+        assert_eq!(out, "2tt");
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let src = "{{ range .l }}{{ if eq . 2 }}{{ continue }}{{ end }}{{ if eq . 4 }}{{ break }}{{ end }}{{ . }}{{ end }}";
+        assert_eq!(render(src, serde_json::json!({"l": [1, 2, 3, 4, 5]})), "13");
+    }
+
+    #[test]
+    fn include_with_nindent_and_define() {
+        let src = r#"{{- define "labels" -}}
+app: {{ .name }}
+tier: web
+{{- end -}}
+metadata:
+  labels:
+    {{- include "labels" . | nindent 4 }}"#;
+        let out = render(src, serde_json::json!({"name": "api"}));
+        assert_eq!(out, "metadata:\n  labels:\n    app: api\n    tier: web");
+    }
+
+    #[test]
+    fn pipelines_and_defaults() {
+        let src =
+            r#"{{ .a | default "x" | upper | quote }} {{ default 3 .b }} {{ .c | default "d" }}"#;
+        let out = render(src, serde_json::json!({"a": "", "b": 0, "c": "set"}));
+        assert_eq!(out, r#""X" 3 set"#);
+    }
+
+    #[test]
+    fn to_yaml_and_dicts() {
+        let src = r#"{{- $d := dict "b" 2 "a" (list 1 "two") -}}
+{{- $_ := set $d "c" true -}}
+{{ toYaml $d }}
+{{ hasKey $d "c" }} {{ len $d }} {{ keys $d | sortAlpha | join "," }}"#;
+        let out = render(src, serde_json::json!({}));
+        assert_eq!(out, "a:\n- 1\n- two\nb: 2\nc: true\ntrue 3 a,b,c");
+    }
+
+    #[test]
+    fn printf_and_strings() {
+        let src = r#"{{ printf "%s-%d-%05.1f-%v" "a" 7 3.14159 true }} {{ trunc 5 "abcdefgh" }} {{ "Hello World" | lower | replace " " "-" }} {{ trimSuffix "-" "name-" }}"#;
+        let out = render(src, serde_json::json!({}));
+        assert_eq!(out, "a-7-003.1-true abcde hello-world name");
+    }
+
+    #[test]
+    fn and_or_short_circuit() {
+        let src = r#"{{ and .a .a.b }}|{{ or .x "fallback" }}"#;
+        let out = render(src, serde_json::json!({"a": null, "x": ""}));
+        assert_eq!(out, "|fallback");
+    }
+
+    #[test]
+    fn tpl_renders_strings() {
+        let src = r#"{{ tpl .Values.tmpl . }}"#;
+        let out = render(
+            src,
+            serde_json::json!({"Values": {"tmpl": "{{ .Values.name }}-svc", "name": "web"}}),
+        );
+        assert_eq!(out, "web-svc");
+    }
+
+    #[test]
+    fn required_and_fail_report_errors() {
+        let err = try_render(
+            r#"{{ required "image.tag is required" .tag }}"#,
+            serde_json::json!({}),
+        )
+        .unwrap_err();
+        assert!(err.message.contains("image.tag is required"));
+        let err = try_render(r#"{{ fail "boom" }}"#, serde_json::json!({})).unwrap_err();
+        assert!(err.message.contains("boom"));
+    }
+
+    #[test]
+    fn semver_compare_constraints() {
+        let src = r#"{{ semverCompare ">=1.19-0" "v1.30.2-eks-1" }} {{ semverCompare "<1.19" "1.30.0" }} {{ semverCompare "~1.2" "1.2.9" }} {{ semverCompare "^1.2.0" "2.0.0" }}"#;
+        assert_eq!(render(src, serde_json::json!({})), "true false true false");
+    }
+
+    #[test]
+    fn sha256sum_and_b64() {
+        let src = r#"{{ "abc" | sha256sum }} {{ "hi" | b64enc }} {{ "aGk=" | b64dec }}"#;
+        assert_eq!(
+            render(src, serde_json::json!({})),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad aGk= hi"
+        );
+    }
+
+    #[test]
+    fn unknown_function_is_an_error() {
+        let err = try_render("{{ nope 1 }}", serde_json::json!({})).unwrap_err();
+        assert!(err.message.contains(r#"function "nope" not defined"#));
+    }
+
+    #[test]
+    fn numbers_print_like_go() {
+        let src = "{{ .i }} {{ .f }} {{ add .i 1 }} {{ div 7 2 }} {{ .big }}";
+        let out = render(
+            src,
+            serde_json::json!({"i": 3, "f": 1.5, "big": 3000000000.0_f64}),
+        );
+        assert_eq!(out, "3 1.5 4 3 3000000000");
+    }
+}
@@ -0,0 +1,759 @@
+//! Lexer and parser for Go `text/template` syntax.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::TemplateError;
+
+/// A parsed template body.
+pub(crate) type List = Vec<Node>;
+
+#[derive(Debug)]
+pub(crate) enum Node {
+    Text(String),
+    Action(Pipe),
+    If(Pipe, List, List),
+    Range(Pipe, List, List),
+    With(Pipe, List, List),
+    Template(String, Option<Pipe>),
+    Break,
+    Continue,
+}
+
+/// A pipeline with optional variable declaration (`$x := ...`) or
+/// assignment (`$x = ...`).
+#[derive(Debug)]
+pub(crate) struct Pipe {
+    pub(crate) vars: Vec<String>,
+    pub(crate) assign: bool,
+    pub(crate) cmds: Vec<Cmd>,
+}
+
+/// One pipeline stage: a function or operand followed by arguments.
+pub(crate) type Cmd = Vec<Arg>;
+
+#[derive(Debug)]
+pub(crate) enum Arg {
+    /// `.`
+    Dot,
+    /// `.A.B` — a field chain on dot.
+    Field(Vec<String>),
+    /// `$x.A.B` — a variable with an optional field chain.
+    Var(String, Vec<String>),
+    /// A function name.
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Nil,
+    /// `( pipeline ).A.B`
+    Sub(Box<Pipe>, Vec<String>),
+}
+
+/// Parse `src` into its main body plus every `define`/`block` it contains.
+pub(crate) fn parse(
+    name: &str,
+    src: &str,
+) -> Result<(List, HashMap<String, Rc<List>>), TemplateError> {
+    let items = lex(name, src)?;
+    let mut parser = Parser {
+        name,
+        items,
+        pos: 0,
+        defines: HashMap::new(),
+    };
+    let (list, end) = parser.parse_list()?;
+    if let Some(end) = end {
+        return Err(parser.error(&format!("unexpected {{{{{}}}}}", end.keyword())));
+    }
+    Ok((list, parser.defines))
+}
+
+// --- Lexer ---
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Dot,
+    Field(String),
+    Var(String),
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    LParen,
+    RParen,
+    Pipe,
+    Declare,
+    Assign,
+    Comma,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    /// Whether whitespace preceded the token; `.a.b` is one operand,
+    /// `.a .b` two.
+    spaced: bool,
+}
+
+#[derive(Debug)]
+enum Item {
+    Text(String),
+    Action(Vec<Token>, usize),
+}
+
+fn lex(name: &str, src: &str) -> Result<Vec<Item>, TemplateError> {
+    let mut items = Vec::new();
+    let mut rest = src;
+    let mut line = 1;
+    let mut trim_next = false;
+
+    loop {
+        let Some(start) = rest.find("{{") else {
+            push_text(&mut items, rest, trim_next);
+            break;
+        };
+        let mut text = &rest[..start];
+        let mut inner = &rest[start + 2..];
+        line += text.matches('\n').count();
+
+        // `{{- ` trims whitespace before the action
+        if let Some(stripped) = inner.strip_prefix('-')
+            && stripped.starts_with(|c: char| c.is_ascii_whitespace())
+        {
+            text = text.trim_end_matches(|c: char| c.is_ascii_whitespace());
+            inner = stripped;
+        }
+        push_text(&mut items, text, trim_next);
+
+        // Comments: `{{/* ... */}}`
+        let trimmed = inner.trim_start();
+        if trimmed.starts_with("/*") {
+            let end = trimmed.find("*/").ok_or_else(|| TemplateError {
+                message: format!("template: {name}:{line}: unclosed comment"),
+            })?;
+            let after = trimmed[end + 2..].trim_start();
+            let (after, trim) = if let Some(a) = after.strip_prefix("-}}") {
+                (a, true)
+            } else if let Some(a) = after.strip_prefix("}}") {
+                (a, false)
+            } else {
+                return Err(TemplateError {
+                    message: format!(
+                        "template: {name}:{line}: comment ends before closing delimiter"
+                    ),
+                });
+            };
+            line += trimmed[..end].matches('\n').count();
+            trim_next = trim;
+            rest = after;
+            continue;
+        }
+
+        let (tokens, consumed, trim) = lex_action(name, line, inner)?;
+        line += inner[..consumed].matches('\n').count();
+        items.push(Item::Action(tokens, line));
+        trim_next = trim;
+        rest = &inner[consumed..];
+    }
+
+    Ok(items)
+}
+
+fn push_text(items: &mut Vec<Item>, text: &str, trim_start: bool) {
+    let text = if trim_start {
+        text.trim_start_matches(|c: char| c.is_ascii_whitespace())
+    } else {
+        text
+    };
+    if !text.is_empty() {
+        items.push(Item::Text(text.to_string()));
+    }
+}
+
+/// Tokenize an action body. Returns the tokens, the number of bytes consumed
+/// (including the closing delimiter) and whether it ended with `-}}`.
+fn lex_action(
+    name: &str,
+    line: usize,
+    src: &str,
+) -> Result<(Vec<Token>, usize, bool), TemplateError> {
+    let err = |msg: &str| TemplateError {
+        message: format!("template: {name}:{line}: {msg}"),
+    };
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut spaced = true;
+
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+
+        if c.is_ascii_whitespace() {
+            // ` -}}` trims whitespace after the action
+            if src[i..].trim_start().starts_with("-}}") {
+                let dash = i + src[i..].find("-}}").unwrap_or(0);
+                return Ok((tokens, dash + 3, true));
+            }
+            i += 1;
+            spaced = true;
+            continue;
+        }
+        if src[i..].starts_with("}}") {
+            return Ok((tokens, i + 2, false));
+        }
+
+        let start = i;
+        let tok = match c {
+            '(' => {
+                i += 1;
+                Tok::LParen
+            }
+            ')' => {
+                i += 1;
+                Tok::RParen
+            }
+            '|' => {
+                i += 1;
+                Tok::Pipe
+            }
+            ',' => {
+                i += 1;
+                Tok::Comma
+            }
+            ':' if src[i..].starts_with(":=") => {
+                i += 2;
+                Tok::Declare
+            }
+            '=' => {
+                i += 1;
+                Tok::Assign
+            }
+            '"' => {
+                let (s, len) =
+                    lex_quoted(&src[i..]).ok_or_else(|| err("unterminated quoted string"))?;
+                i += len;
+                Tok::Str(s)
+            }
+            '`' => {
+                let end = src[i + 1..]
+                    .find('`')
+                    .ok_or_else(|| err("unterminated raw quoted string"))?;
+                let s = src[i + 1..i + 1 + end].to_string();
+                i += end + 2;
+                Tok::Str(s)
+            }
+            '\'' => {
+                let end = src[i + 1..]
+                    .find('\'')
+                    .ok_or_else(|| err("unterminated character constant"))?;
+                let ch = unescape(&src[i + 1..i + 1 + end])
+                    .and_then(|s| s.chars().next())
+                    .ok_or_else(|| err("invalid character constant"))?;
+                i += end + 2;
+                Tok::Int(ch as i64)
+            }
+            '.' if !src[i + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+                i += 1;
+                let len = ident_len(&src[i..]);
+                if len == 0 {
+                    Tok::Dot
+                } else {
+                    let field = src[i..i + len].to_string();
+                    i += len;
+                    Tok::Field(field)
+                }
+            }
+            '$' => {
+                i += 1;
+                let len = ident_len(&src[i..]);
+                let var = format!("${}", &src[i..i + len]);
+                i += len;
+                Tok::Var(var)
+            }
+            c if c.is_ascii_digit()
+                || ((c == '-' || c == '+' || c == '.') && next_is_digit(src, i)) =>
+            {
+                let len = number_len(&src[i..]);
+                let text = &src[i..i + len];
+                i += len;
+                parse_number(text).ok_or_else(|| err(&format!("bad number syntax: {text:?}")))?
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let len = ident_len(&src[i..]);
+                let ident = src[i..i + len].to_string();
+                i += len;
+                Tok::Ident(ident)
+            }
+            other => return Err(err(&format!("unexpected {other:?} in command"))),
+        };
+        debug_assert!(i > start);
+        tokens.push(Token { tok, spaced });
+        spaced = false;
+    }
+
+    Err(err("unclosed action"))
+}
+
+fn ident_len(s: &str) -> usize {
+    s.char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map(|(i, _)| i)
+        .unwrap_or(s.len())
+}
+
+fn next_is_digit(s: &str, i: usize) -> bool {
+    s[i + 1..].starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn number_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut prev = ' ';
+    for (i, c) in s.char_indices() {
+        let ok = c.is_ascii_alphanumeric()
+            || c == '.'
+            || c == '_'
+            || (i == 0 && (c == '-' || c == '+'))
+            || ((c == '-' || c == '+') && (prev == 'e' || prev == 'E'));
+        if !ok {
+            break;
+        }
+        len = i + c.len_utf8();
+        prev = c;
+    }
+    len
+}
+
+fn parse_number(text: &str) -> Option<Tok> {
+    let clean = text.replace('_', "");
+    let (neg, digits) = match clean.strip_prefix('-') {
+        Some(d) => (true, d),
+        None => (false, clean.strip_prefix('+').unwrap_or(&clean)),
+    };
+    let radix = |prefix: &str, radix: u32| {
+        digits
+            .strip_prefix(prefix)
+            .and_then(|d| i64::from_str_radix(d, radix).ok())
+    };
+    let int = radix("0x", 16)
+        .or_else(|| radix("0X", 16))
+        .or_else(|| radix("0o", 8))
+        .or_else(|| radix("0b", 2))
+        .or_else(|| digits.parse::<i64>().ok());
+    if let Some(n) = int {
+        return Some(Tok::Int(if neg { -n } else { n }));
+    }
+    clean.parse::<f64>().ok().map(Tok::Float)
+}
+
+/// Lex a double-quoted Go string starting at `s[0] == '"'`.
+/// Returns the unescaped string and its byte length including quotes.
+fn lex_quoted(s: &str) -> Option<(String, usize)> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some((unescape(&s[1..i])?, i + 1)),
+            b'\n' => return None,
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            'r' => out.push('\r'),
+            'a' => out.push('\x07'),
+            'b' => out.push('\x08'),
+            'f' => out.push('\x0c'),
+            'v' => out.push('\x0b'),
+            '\\' => out.push('\\'),
+            '"' => out.push('"'),
+            '\'' => out.push('\''),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.push(char::from(u8::from_str_radix(&hex, 16).ok()?));
+            }
+            'u' => {
+                let hex: String = chars.by_ref().take(4).collect();
+                out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            'U' => {
+                let hex: String = chars.by_ref().take(8).collect();
+                out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+// --- Parser ---
+
+/// What ended a list: `{{end}}` or `{{else ...}}` (with the tokens after
+/// `else`, e.g. `if .x` for `{{else if .x}}`).
+enum ListEnd {
+    End,
+    Else(Vec<Token>),
+}
+
+impl ListEnd {
+    fn keyword(&self) -> &'static str {
+        match self {
+            ListEnd::End => "end",
+            ListEnd::Else(_) => "else",
+        }
+    }
+}
+
+struct Parser<'a> {
+    name: &'a str,
+    items: Vec<Item>,
+    pos: usize,
+    defines: HashMap<String, Rc<List>>,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> TemplateError {
+        let line = self.items[..self.pos.min(self.items.len())]
+            .iter()
+            .rev()
+            .find_map(|i| match i {
+                Item::Action(_, line) => Some(*line),
+                Item::Text(_) => None,
+            })
+            .unwrap_or(1);
+        TemplateError {
+            message: format!("template: {}:{line}: {msg}", self.name),
+        }
+    }
+
+    /// Parse nodes until `{{end}}`, `{{else}}` or end of input.
+    fn parse_list(&mut self) -> Result<(List, Option<ListEnd>), TemplateError> {
+        let mut list = Vec::new();
+        while self.pos < self.items.len() {
+            let item = std::mem::replace(&mut self.items[self.pos], Item::Text(String::new()));
+            self.pos += 1;
+            let tokens = match item {
+                Item::Text(text) => {
+                    list.push(Node::Text(text));
+                    continue;
+                }
+                Item::Action(tokens, _) => tokens,
+            };
+
+            let keyword = match tokens.first().map(|t| &t.tok) {
+                Some(Tok::Ident(k)) => k.as_str(),
+                None => return Err(self.error("missing value for command")),
+                _ => "",
+            };
+            let rest = tokens[1.min(tokens.len())..].to_vec();
+            match keyword {
+                "end" => return Ok((list, Some(ListEnd::End))),
+                "else" => return Ok((list, Some(ListEnd::Else(rest)))),
+                "if" | "range" | "with" => list.push(self.parse_control(keyword, rest)?),
+                "define" => {
+                    let name = self.template_name(&rest)?;
+                    let (body, end) = self.parse_list()?;
+                    if !matches!(end, Some(ListEnd::End)) {
+                        return Err(self.error("unexpected EOF in define"));
+                    }
+                    self.defines.insert(name, Rc::new(body));
+                }
+                "block" => {
+                    let name = self.template_name(&rest)?;
+                    let pipe = self.parse_pipe(&rest[1..], true)?;
+                    let (body, end) = self.parse_list()?;
+                    if !matches!(end, Some(ListEnd::End)) {
+                        return Err(self.error("unexpected EOF in block"));
+                    }
+                    self.defines.insert(name.clone(), Rc::new(body));
+                    list.push(Node::Template(name, Some(pipe)));
+                }
+                "template" => {
+                    let name = self.template_name(&rest)?;
+                    let pipe = if rest.len() > 1 {
+                        Some(self.parse_pipe(&rest[1..], true)?)
+                    } else {
+                        None
+                    };
+                    list.push(Node::Template(name, pipe));
+                }
+                "break" => list.push(Node::Break),
+                "continue" => list.push(Node::Continue),
+                _ => list.push(Node::Action(self.parse_pipe(&tokens, true)?)),
+            }
+        }
+        Ok((list, None))
+    }
+
+    fn template_name(&self, tokens: &[Token]) -> Result<String, TemplateError> {
+        match tokens.first().map(|t| &t.tok) {
+            Some(Tok::Str(name)) => Ok(name.clone()),
+            _ => Err(self.error("expected quoted template name")),
+        }
+    }
+
+    /// Parse `if`/`range`/`with` including `else` chains and the closing `end`.
+    fn parse_control(&mut self, keyword: &str, tokens: Vec<Token>) -> Result<Node, TemplateError> {
+        let pipe = self.parse_pipe(&tokens, true)?;
+        let (body, end) = self.parse_list()?;
+        let else_body = match end {
+            Some(ListEnd::End) => Vec::new(),
+            Some(ListEnd::Else(rest)) if rest.is_empty() => {
+                let (else_body, end) = self.parse_list()?;
+                if !matches!(end, Some(ListEnd::End)) {
+                    return Err(self.error(&format!("expected end; found {}", describe(&end))));
+                }
+                else_body
+            }
+            // `{{else if ...}}` / `{{else with ...}}` chain into a nested
+            // control that shares this one's `{{end}}`.
+            Some(ListEnd::Else(rest)) => match rest.first().map(|t| &t.tok) {
+                Some(Tok::Ident(k)) if k == "if" || k == "with" => {
+                    let k = k.clone();
+                    vec![self.parse_control(&k, rest[1..].to_vec())?]
+                }
+                _ => return Err(self.error("unexpected tokens after else")),
+            },
+            None => return Err(self.error(&format!("unexpected EOF in {keyword}"))),
+        };
+        Ok(match keyword {
+            "if" => Node::If(pipe, body, else_body),
+            "range" => Node::Range(pipe, body, else_body),
+            _ => Node::With(pipe, body, else_body),
+        })
+    }
+
+    fn parse_pipe(&self, tokens: &[Token], allow_decl: bool) -> Result<Pipe, TemplateError> {
+        let mut vars = Vec::new();
+        let mut assign = false;
+        let mut pos = 0;
+
+        // `$x :=`, `$i, $v :=` or `$x =`
+        if allow_decl {
+            let mut i = 0;
+            let mut names = Vec::new();
+            while let Some(Tok::Var(v)) = tokens.get(i).map(|t| &t.tok) {
+                names.push(v.clone());
+                match tokens.get(i + 1).map(|t| &t.tok) {
+                    Some(Tok::Comma) if names.len() == 1 => i += 2,
+                    Some(Tok::Declare) => {
+                        vars = names;
+                        pos = i + 2;
+                        break;
+                    }
+                    Some(Tok::Assign) => {
+                        vars = names;
+                        assign = true;
+                        pos = i + 2;
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        let mut cmds = Vec::new();
+        let mut cmd = Vec::new();
+        let mut i = pos;
+        while i < tokens.len() {
+            match &tokens[i].tok {
+                Tok::Pipe => {
+                    if cmd.is_empty() {
+                        return Err(self.error("missing command before |"));
+                    }
+                    cmds.push(std::mem::take(&mut cmd));
+                    i += 1;
+                }
+                _ => {
+                    let (arg, next) = self.parse_operand(tokens, i)?;
+                    cmd.push(arg);
+                    i = next;
+                }
+            }
+        }
+        if cmd.is_empty() {
+            return Err(self.error("missing value for command"));
+        }
+        cmds.push(cmd);
+        Ok(Pipe { vars, assign, cmds })
+    }
+
+    /// Parse one operand starting at `tokens[i]`, including any field chain
+    /// directly attached to it. Returns the operand and the next position.
+    fn parse_operand(&self, tokens: &[Token], i: usize) -> Result<(Arg, usize), TemplateError> {
+        let mut next = i + 1;
+        let arg = match &tokens[i].tok {
+            Tok::Dot => Arg::Dot,
+            Tok::Field(f) => {
+                let mut chain = vec![f.clone()];
+                next = collect_fields(tokens, next, &mut chain);
+                return Ok((Arg::Field(chain), next));
+            }
+            Tok::Var(v) => {
+                let mut chain = Vec::new();
+                next = collect_fields(tokens, next, &mut chain);
+                return Ok((Arg::Var(v.clone(), chain), next));
+            }
+            Tok::Ident(name) => match name.as_str() {
+                "true" => Arg::Bool(true),
+                "false" => Arg::Bool(false),
+                "nil" => Arg::Nil,
+                _ => Arg::Ident(name.clone()),
+            },
+            Tok::Str(s) => Arg::Str(s.clone()),
+            Tok::Int(n) => Arg::Int(*n),
+            Tok::Float(f) => Arg::Float(*f),
+            Tok::LParen => {
+                let mut depth = 1;
+                let mut j = i + 1;
+                while j < tokens.len() {
+                    match tokens[j].tok {
+                        Tok::LParen => depth += 1,
+                        Tok::RParen => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    j += 1;
+                }
+                if depth != 0 {
+                    return Err(self.error("unclosed left paren"));
+                }
+                let inner = self.parse_pipe(&tokens[i + 1..j], false)?;
+                let mut chain = Vec::new();
+                next = collect_fields(tokens, j + 1, &mut chain);
+                return Ok((Arg::Sub(Box::new(inner), chain), next));
+            }
+            Tok::RParen => return Err(self.error("unexpected right paren")),
+            Tok::Declare | Tok::Assign => return Err(self.error("unexpected declaration")),
+            Tok::Comma => return Err(self.error("unexpected comma")),
+            Tok::Pipe => return Err(self.error("unexpected |")),
+        };
+        Ok((arg, next))
+    }
+}
+
+/// Append `.A.B` fields that directly follow an operand (no whitespace).
+fn collect_fields(tokens: &[Token], mut i: usize, chain: &mut Vec<String>) -> usize {
+    while let Some(Token {
+        tok: Tok::Field(f),
+        spaced: false,
+    }) = tokens.get(i)
+    {
+        chain.push(f.clone());
+        i += 1;
+    }
+    i
+}
+
+fn describe(end: &Option<ListEnd>) -> &'static str {
+    match end {
+        Some(e) => e.keyword(),
+        None => "EOF",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trim_markers_strip_whitespace() {
+        let items = lex("t", "a  \n{{- 1 -}}\n  b").unwrap();
+        assert!(matches!(&items[0], Item::Text(t) if t == "a"));
+        assert!(matches!(&items[2], Item::Text(t) if t == "b"));
+    }
+
+    #[test]
+    fn comments_are_dropped() {
+        let items = lex("t", "a{{/* note */}}b{{- /* x */ -}} c").unwrap();
+        let texts: Vec<_> = items
+            .iter()
+            .map(|i| match i {
+                Item::Text(t) => t.as_str(),
+                Item::Action(..) => "?",
+            })
+            .collect();
+        assert_eq!(texts, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn field_chains_bind_without_spaces() {
+        let (list, _) = parse("t", "{{ .Values.a .b $x.c (.d).e }}").unwrap();
+        let Node::Action(pipe) = &list[0] else {
+            panic!("expected action")
+        };
+        let cmd = &pipe.cmds[0];
+        assert!(matches!(&cmd[0], Arg::Field(c) if c == &["Values", "a"]));
+        assert!(matches!(&cmd[1], Arg::Field(c) if c == &["b"]));
+        assert!(matches!(&cmd[2], Arg::Var(v, c) if v == "$x" && c == &["c"]));
+        assert!(matches!(&cmd[3], Arg::Sub(_, c) if c == &["e"]));
+    }
+
+    #[test]
+    fn declarations_and_pipes() {
+        let (list, _) = parse("t", r#"{{ $i, $v := .x | default "y" }}"#).unwrap();
+        let Node::Action(pipe) = &list[0] else {
+            panic!("expected action")
+        };
+        assert_eq!(pipe.vars, vec!["$i", "$v"]);
+        assert!(!pipe.assign);
+        assert_eq!(pipe.cmds.len(), 2);
+    }
+
+    #[test]
+    fn else_if_chains_share_end() {
+        let (list, _) = parse("t", "{{if .a}}A{{else if .b}}B{{else}}C{{end}}").unwrap();
+        assert_eq!(list.len(), 1);
+        let Node::If(_, _, else_body) = &list[0] else {
+            panic!("expected if")
+        };
+        assert!(matches!(&else_body[0], Node::If(_, _, e) if e.len() == 1));
+    }
+
+    #[test]
+    fn defines_are_collected() {
+        let (list, defines) =
+            parse("t", r#"{{ define "x" }}X{{ end }}{{ template "x" . }}"#).unwrap();
+        assert!(defines.contains_key("x"));
+        assert!(matches!(&list[0], Node::Template(n, Some(_)) if n == "x"));
+    }
+
+    #[test]
+    fn numbers_and_strings() {
+        let (list, _) = parse("t", r#"{{ f -1 0x10 1.5 "a\"b" `raw` 'c' }}"#).unwrap();
+        let Node::Action(pipe) = &list[0] else {
+            panic!("expected action")
+        };
+        let cmd = &pipe.cmds[0];
+        assert!(matches!(cmd[1], Arg::Int(-1)));
+        assert!(matches!(cmd[2], Arg::Int(16)));
+        assert!(matches!(cmd[3], Arg::Float(f) if f == 1.5));
+        assert!(matches!(&cmd[4], Arg::Str(s) if s == "a\"b"));
+        assert!(matches!(&cmd[5], Arg::Str(s) if s == "raw"));
+        assert!(matches!(cmd[6], Arg::Int(99)));
+    }
+
+    #[test]
+    fn unclosed_if_is_an_error() {
+        let err = parse("t", "{{ if .a }}x").unwrap_err();
+        assert!(err.message.contains("unexpected EOF in if"));
+    }
+}
@@ -0,0 +1,315 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// A dynamically typed template value.
+///
+/// Maps are shared and mutable (like Go maps) so `set`/`unset` on a dict are
+/// visible through every reference to it; lists are immutable like the
+/// slices Sprig returns.
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Rc<Vec<Value>>),
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+    /// `.Files` of a chart: path → contents.
+    Files(Rc<BTreeMap<String, Vec<u8>>>),
+    /// `.Capabilities.APIVersions`.
+    ApiVersions(Rc<Vec<String>>),
+}
+
+impl Value {
+    pub(crate) fn str(s: impl Into<String>) -> Self {
+        Value::Str(s.into())
+    }
+
+    pub(crate) fn list(items: Vec<Value>) -> Self {
+        Value::List(Rc::new(items))
+    }
+
+    pub(crate) fn map(entries: BTreeMap<String, Value>) -> Self {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub(crate) fn empty_map() -> Self {
+        Value::map(BTreeMap::new())
+    }
+
+    /// Go template truthiness: false, 0, nil and empty strings, lists and
+    /// maps are false.
+    pub(crate) fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(b) => *b,
+            Value::Int(n) => *n != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::Str(s) => !s.is_empty(),
+            Value::List(l) => !l.is_empty(),
+            Value::Map(m) => !m.borrow().is_empty(),
+            Value::Files(f) => !f.is_empty(),
+            Value::ApiVersions(_) => true,
+        }
+    }
+
+    pub(crate) fn is_nil(&self) -> bool {
+        matches!(self, Value::Nil)
+    }
+
+    /// Go reflect kind, as reported by Sprig's `kindOf`.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Value::Nil => "invalid",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int64",
+            Value::Float(_) => "float64",
+            Value::Str(_) => "string",
+            Value::List(_) => "slice",
+            Value::Map(_) | Value::Files(_) => "map",
+            Value::ApiVersions(_) => "slice",
+        }
+    }
+
+    /// Go type name, as reported by Sprig's `typeOf`.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "<nil>",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int64",
+            Value::Float(_) => "float64",
+            Value::Str(_) => "string",
+            Value::List(_) => "[]interface {}",
+            Value::Map(_) => "map[string]interface {}",
+            Value::Files(_) => "chart.Files",
+            Value::ApiVersions(_) => "chartutil.VersionSet",
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Convert to an integer the way Sprig's `int` does (strings are parsed,
+    /// floats truncated, anything else is 0).
+    pub(crate) fn to_int(&self) -> i64 {
+        match self {
+            Value::Int(n) => *n,
+            Value::Float(f) => *f as i64,
+            Value::Bool(b) => *b as i64,
+            Value::Str(s) => {
+                let s = s.trim();
+                s.parse::<i64>()
+                    .ok()
+                    .or_else(|| s.parse::<f64>().ok().map(|f| f as i64))
+                    .unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    pub(crate) fn to_float(&self) -> f64 {
+        match self {
+            Value::Int(n) => *n as f64,
+            Value::Float(f) => *f,
+            Value::Bool(b) => *b as i64 as f64,
+            Value::Str(s) => s.trim().parse().unwrap_or(0.0),
+            _ => 0.0,
+        }
+    }
+
+    /// Items of a list-like value (`nil` is an empty list).
+    pub(crate) fn items(&self) -> Option<Vec<Value>> {
+        match self {
+            Value::Nil => Some(Vec::new()),
+            Value::List(l) => Some(l.as_ref().clone()),
+            Value::ApiVersions(v) => Some(v.iter().map(|s| Value::str(s.as_str())).collect()),
+            _ => None,
+        }
+    }
+
+    /// Build a template value from JSON.
+    pub(crate) fn from_json(json: &serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Nil,
+            serde_json::Value::Bool(b) => Value::Bool(*b),
+            // Integral floats (e.g. large numbers from JavaScript) stay integers
+            // so they don't print in exponent notation.
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => match n.as_f64().unwrap_or(0.0) {
+                    f if f.fract() == 0.0 && f.abs() < 9e15 => Value::Int(f as i64),
+                    f => Value::Float(f),
+                },
+            },
+            serde_json::Value::String(s) => Value::str(s.as_str()),
+            serde_json::Value::Array(a) => Value::list(a.iter().map(Value::from_json).collect()),
+            serde_json::Value::Object(o) => Value::map(
+                o.iter()
+                    .map(|(k, v)| (k.clone(), Value::from_json(v)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Nil => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Int(n) => serde_json::Value::from(*n),
+            Value::Float(f) => {
+                if f.fract() == 0.0 && f.abs() < 1e15 {
+                    serde_json::Value::from(*f as i64)
+                } else {
+                    serde_json::Number::from_f64(*f)
+                        .map(serde_json::Value::Number)
+                        .unwrap_or(serde_json::Value::Null)
+                }
+            }
+            Value::Str(s) => serde_json::Value::String(s.clone()),
+            Value::List(l) => serde_json::Value::Array(l.iter().map(Value::to_json).collect()),
+            Value::Map(m) => serde_json::Value::Object(
+                m.borrow()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_json()))
+                    .collect(),
+            ),
+            Value::Files(f) => serde_json::Value::Object(
+                f.iter()
+                    .map(|(k, v)| {
+                        (
+                            k.clone(),
+                            serde_json::Value::String(String::from_utf8_lossy(v).into_owned()),
+                        )
+                    })
+                    .collect(),
+            ),
+            Value::ApiVersions(v) => serde_json::Value::Array(
+                v.iter()
+                    .map(|s| serde_json::Value::String(s.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Recursively copy a value so later `set` calls don't affect the original.
+    pub(crate) fn deep_copy(&self) -> Value {
+        match self {
+            Value::List(l) => Value::list(l.iter().map(Value::deep_copy).collect()),
+            Value::Map(m) => Value::map(
+                m.borrow()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.deep_copy()))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    /// Format like Go's `%v` verb (and `print`). Helm renders missing values
+    /// as an empty string rather than `<no value>`.
+    pub(crate) fn to_display(&self) -> String {
+        let mut out = String::new();
+        self.write_display(&mut out, true);
+        out
+    }
+
+    fn write_display(&self, out: &mut String, top: bool) {
+        match self {
+            Value::Nil => {
+                if !top {
+                    out.push_str("<nil>");
+                }
+            }
+            Value::Bool(b) => {
+                let _ = write!(out, "{b}");
+            }
+            Value::Int(n) => {
+                let _ = write!(out, "{n}");
+            }
+            Value::Float(f) => out.push_str(&format_float(*f)),
+            Value::Str(s) => out.push_str(s),
+            Value::List(_) | Value::ApiVersions(_) => {
+                out.push('[');
+                for (i, item) in self.items().unwrap_or_default().iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    item.write_display(out, false);
+                }
+                out.push(']');
+            }
+            Value::Map(m) => {
+                out.push_str("map[");
+                for (i, (k, v)) in m.borrow().iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    out.push_str(k);
+                    out.push(':');
+                    v.write_display(out, false);
+                }
+                out.push(']');
+            }
+            Value::Files(f) => {
+                out.push_str("map[");
+                for (i, k) in f.keys().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    out.push_str(k);
+                }
+                out.push(']');
+            }
+        }
+    }
+}
+
+/// Format a float like Go's `%v`: integral values print without a fraction,
+/// very large or small magnitudes use exponent notation.
+pub(crate) fn format_float(f: f64) -> String {
+    if f.is_nan() {
+        return "NaN".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "+Inf" } else { "-Inf" }.to_string();
+    }
+    let abs = f.abs();
+    if abs != 0.0 && !(1e-4..1e21).contains(&abs) {
+        let s = format!("{f:e}");
+        // Rust prints `1e21`, Go prints `1e+21`
+        return match s.split_once('e') {
+            Some((m, e)) if !e.starts_with('-') => format!("{m}e+{e:0>2}"),
+            Some((m, e)) => format!("{m}e-{:0>2}", &e[1..]),
+            None => s,
+        };
+    }
+    if f.fract() == 0.0 {
+        format!("{}", f as i128)
+    } else {
+        format!("{f}")
+    }
+}
+
+/// Loose equality used by `eq`/`ne`: numbers compare by value regardless of
+/// int/float representation; lists and maps compare structurally.
+pub(crate) fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Nil, Value::Nil) => true,
+        (Value::Bool(x), Value::Bool(y)) => x == y,
+        (Value::Str(x), Value::Str(y)) => x == y,
+        (Value::Int(x), Value::Int(y)) => x == y,
+        (x, y) if x.as_f64().is_some() && y.as_f64().is_some() => x.as_f64() == y.as_f64(),
+        (Value::List(_), Value::List(_)) | (Value::Map(_), Value::Map(_)) => {
+            a.to_json() == b.to_json()
+        }
+        _ => false,
+    }
+}
@@ -37,8 +37,18 @@ fn specs_dir(base: &Path, key: &str) -> PathBuf {
     cache_dir(base, key).join("specs")
 }
 
+/// Path of a server's cached discovery index.
+pub fn discovery_path(cache_dir_path: &Path, key: &str) -> PathBuf {
+    cache_dir(cache_dir_path, key).join("discovery.json")
+}
+
+/// Path of a cached group-version spec.
+pub fn spec_path(cache_dir_path: &Path, key: &str, group_version: &str) -> PathBuf {
+    specs_dir(cache_dir_path, key).join(spec_filename(group_version))
+}
+
 pub fn read_discovery(cache_dir_path: &Path, key: &str) -> Result<DiscoveryIndex, OpenApiError> {
-    let path = cache_dir(cache_dir_path, key).join("discovery.json");
+    let path = discovery_path(cache_dir_path, key);
     let data = std::fs::read_to_string(&path)
         .map_err(|e| OpenApiError::Cache(format!("read {}: {e}", path.display())))?;
     serde_json::from_str(&data)
@@ -91,7 +101,7 @@ pub fn read_spec(
     key: &str,
     group_version: &str,
 ) -> Result<serde_json::Value, OpenApiError> {
-    let path = specs_dir(cache_dir_path, key).join(spec_filename(group_version));
+    let path = spec_path(cache_dir_path, key, group_version);
     let data = std::fs::read_to_string(&path)
         .map_err(|e| OpenApiError::Cache(format!("read {}: {e}", path.display())))?;
     serde_json::from_str(&data)
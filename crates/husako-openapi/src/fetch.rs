@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 use reqwest::Client;
 
 use crate::{DiscoveryIndex, OpenApiError};
@@ -59,6 +61,37 @@ pub async fn fetch_spec(
         .map_err(|e| OpenApiError::Parse(format!("parse spec from {url}: {e}")))
 }
 
+/// Fetch the single OpenAPI v2 document served at `/openapi/v2`.
+pub async fn fetch_v2(client: &Client, base_url: &str) -> Result<serde_json::Value, OpenApiError> {
+    fetch_spec(client, base_url, "/openapi/v2").await
+}
+
+/// Group-versions the API server serves, keyed like the `/openapi/v3`
+/// discovery paths (`api/v1`, `apis/apps/v1`), read from the `/api` and
+/// `/apis` discovery documents. Unlike `/openapi/v3`, these also list
+/// aggregated APIs that publish no v3 document.
+pub async fn fetch_served_group_versions(
+    client: &Client,
+    base_url: &str,
+) -> Result<BTreeSet<String>, OpenApiError> {
+    let core = fetch_spec(client, base_url, "/api").await?;
+    let groups = fetch_spec(client, base_url, "/apis").await?;
+    let core = core["versions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .map(|v| format!("api/{v}"));
+    let groups = groups["groups"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|g| g["versions"].as_array().into_iter().flatten())
+        .filter_map(|v| v["groupVersion"].as_str())
+        .map(|gv| format!("apis/{gv}"));
+    Ok(core.chain(groups).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,12 +1,15 @@
 mod cache;
 pub mod crd;
 mod fetch;
+pub mod kubeconfig;
 pub mod release;
+pub mod v2;
 
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use husako_config::FetchContext;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, thiserror::Error)]
@@ -19,23 +22,34 @@ pub enum OpenApiError {
     Parse(String),
     #[error("group-version not found: {0}")]
     NotFound(String),
-    #[error("no cached data available for offline use")]
-    NoCachedData,
+    #[error("no cached data available for offline use: {0} not found")]
+    NoCachedData(String),
     #[error("CRD parse error: {0}")]
     Crd(String),
     #[error("GitHub release error: {0}")]
     Release(String),
+    #[error("kubeconfig error: {0}")]
+    Kubeconfig(String),
 }
 
 pub enum OpenApiSource {
-    Url { base_url: String },
+    Url {
+        base_url: String,
+    },
+    /// A cluster reached through a kubeconfig context; `path` and `context`
+    /// default to `$KUBECONFIG`/`~/.kube/config` and its current context.
+    Kubeconfig {
+        path: Option<PathBuf>,
+        context: Option<String>,
+    },
     Directory(PathBuf),
 }
 
 pub struct FetchOptions {
     pub source: OpenApiSource,
     pub cache_dir: PathBuf,
-    pub offline: bool,
+    /// Network settings; with `offline`, specs are served from `cache_dir` only.
+    pub context: FetchContext,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +63,9 @@ pub struct DiscoveryPath {
     pub server_relative_url: String,
 }
 
+/// Cache slot of a server's OpenAPI v2 document.
+const V2_CACHE_NAME: &str = "openapi/v2";
+
 pub struct OpenApiClient {
     http_client: Option<reqwest::Client>,
     base_url: Option<String>,
@@ -61,7 +78,9 @@ impl OpenApiClient {
     pub fn new(options: FetchOptions) -> Result<Self, OpenApiError> {
         match options.source {
             OpenApiSource::Url { base_url } => {
-                let client = reqwest::Client::builder()
+                let client = options
+                    .context
+                    .client_builder()
                     .timeout(Duration::from_secs(30))
                     .build()
                     .map_err(|e| OpenApiError::Http(format!("failed to build HTTP client: {e}")))?;
@@ -70,7 +89,17 @@ impl OpenApiClient {
                     base_url: Some(base_url),
                     directory: None,
                     cache_dir: options.cache_dir,
-                    offline: options.offline,
+                    offline: options.context.offline,
+                })
+            }
+            OpenApiSource::Kubeconfig { path, context } => {
+                let connection = kubeconfig::load(path.as_deref(), context.as_deref())?;
+                Ok(Self {
+                    http_client: Some(connection.http_client(&options.context)?),
+                    base_url: Some(connection.server),
+                    directory: None,
+                    cache_dir: options.cache_dir,
+                    offline: options.context.offline,
                 })
             }
             OpenApiSource::Directory(path) => Ok(Self {
@@ -92,8 +121,13 @@ impl OpenApiClient {
         let key = cache::server_key(base_url);
 
         if self.offline {
-            return cache::read_discovery(&self.cache_dir, &key)
-                .map_err(|_| OpenApiError::NoCachedData);
+            return cache::read_discovery(&self.cache_dir, &key).map_err(|_| {
+                OpenApiError::NoCachedData(
+                    cache::discovery_path(&self.cache_dir, &key)
+                        .display()
+                        .to_string(),
+                )
+            });
         }
 
         let client = self.http_client.as_ref().unwrap();
@@ -119,8 +153,13 @@ impl OpenApiClient {
         let key = cache::server_key(base_url);
 
         if self.offline {
-            return cache::read_spec(&self.cache_dir, &key, group_version)
-                .map_err(|_| OpenApiError::NoCachedData);
+            return cache::read_spec(&self.cache_dir, &key, group_version).map_err(|_| {
+                OpenApiError::NoCachedData(
+                    cache::spec_path(&self.cache_dir, &key, group_version)
+                        .display()
+                        .to_string(),
+                )
+            });
         }
 
         // Get discovery to find the server-relative URL and current hash
@@ -165,7 +204,14 @@ impl OpenApiClient {
     pub async fn fetch_all_specs(
         &self,
     ) -> Result<HashMap<String, serde_json::Value>, OpenApiError> {
-        let index = self.discover().await?;
+        let index = match self.discover().await {
+            Ok(index) => index,
+            // Servers without `/openapi/v3` (older clusters) only serve v2
+            Err(e) if self.directory.is_none() => {
+                return self.fetch_v2_specs().await.map_err(|_| e);
+            }
+            Err(e) => return Err(e),
+        };
 
         // For directory or offline sources: sequential (no network I/O to parallelize)
         if self.directory.is_some() || self.offline {
@@ -173,6 +219,11 @@ impl OpenApiClient {
             for gv in index.paths.keys() {
                 specs.insert(gv.clone(), self.fetch_spec(gv).await?);
             }
+            if self.directory.is_some() {
+                // Spec directories may hold a v2 `swagger.json` instead of per-group-version files
+                return Ok(v2::expand(specs));
+            }
+            self.merge_v2_specs(&mut specs).await;
             return Ok(specs);
         }
 
@@ -236,9 +287,70 @@ impl OpenApiClient {
             let _ = cache::write_hashes(&cache_dir, &server_key, &hashes);
         }
 
+        self.merge_v2_specs(&mut specs).await;
         Ok(specs)
     }
 
+    /// Add the group-versions `/openapi/v2` serves but `/openapi/v3` does not
+    /// (e.g. from aggregated API servers without a v3 document) to `specs`.
+    /// Best-effort: servers without v2 keep the v3 specs as they are.
+    ///
+    /// The v2 document can be many megabytes, so it is only downloaded when
+    /// the server lists group-versions missing from `specs`. Offline, a cached
+    /// copy is used when one was stored.
+    async fn merge_v2_specs(&self, specs: &mut HashMap<String, serde_json::Value>) {
+        if !self.offline {
+            let client = self.http_client.as_ref().unwrap();
+            let base_url = self.base_url.as_deref().unwrap();
+            if let Ok(served) = fetch::fetch_served_group_versions(client, base_url).await
+                && served.iter().all(|gv| specs.contains_key(gv))
+            {
+                // Keep offline runs from merging a document this run skipped
+                let key = cache::server_key(base_url);
+                let _ =
+                    std::fs::remove_file(cache::spec_path(&self.cache_dir, &key, V2_CACHE_NAME));
+                return;
+            }
+        }
+        if let Ok(v2_specs) = self.fetch_v2_specs().await {
+            for (key, spec) in v2_specs {
+                specs.entry(key).or_insert(spec);
+            }
+        }
+    }
+
+    /// Fetch `/openapi/v2` and convert it to per-group-version specs,
+    /// falling back to (or, offline, reading) the cached document.
+    async fn fetch_v2_specs(&self) -> Result<HashMap<String, serde_json::Value>, OpenApiError> {
+        let base_url = self.base_url.as_deref().unwrap();
+        let key = cache::server_key(base_url);
+
+        let swagger = if self.offline {
+            cache::read_spec(&self.cache_dir, &key, V2_CACHE_NAME).map_err(|_| {
+                OpenApiError::NoCachedData(
+                    cache::spec_path(&self.cache_dir, &key, V2_CACHE_NAME)
+                        .display()
+                        .to_string(),
+                )
+            })?
+        } else {
+            let client = self.http_client.as_ref().unwrap();
+            match fetch::fetch_v2(client, base_url).await {
+                Ok(swagger) => {
+                    let _ = cache::write_spec(&self.cache_dir, &key, V2_CACHE_NAME, &swagger);
+                    swagger
+                }
+                Err(e) => cache::read_spec(&self.cache_dir, &key, V2_CACHE_NAME).map_err(|_| e)?,
+            }
+        };
+        if !v2::is_v2(&swagger) {
+            return Err(OpenApiError::Parse(format!(
+                "{base_url}/openapi/v2 did not return a swagger document"
+            )));
+        }
+        Ok(v2::convert(&swagger))
+    }
+
     fn discover_from_directory(
         &self,
         dir: &std::path::Path,
@@ -318,6 +430,34 @@ mod tests {
         })
     }
 
+    /// Mock `/api` and `/apis` listing `group_versions` (e.g. `apps/v1`) as
+    /// the named groups the server serves.
+    async fn mock_served_groups(server: &mut mockito::Server, group_versions: &[&str]) {
+        server
+            .mock("GET", "/api")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({ "versions": [] }).to_string())
+            .create_async()
+            .await;
+        let groups: Vec<_> = group_versions
+            .iter()
+            .map(|gv| {
+                serde_json::json!({
+                    "name": gv.split('/').next().unwrap(),
+                    "versions": [{ "groupVersion": gv }]
+                })
+            })
+            .collect();
+        server
+            .mock("GET", "/apis")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({ "groups": groups }).to_string())
+            .create_async()
+            .await;
+    }
+
     fn mock_spec_json() -> serde_json::Value {
         serde_json::json!({
             "openapi": "3.0.0",
@@ -343,7 +483,7 @@ mod tests {
                 base_url: server.url(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: false,
+            context: FetchContext::default(),
         })
         .unwrap();
 
@@ -354,6 +494,249 @@ mod tests {
         mock.assert_async().await;
     }
 
+    #[tokio::test]
+    async fn discover_through_kubeconfig_sends_token() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/openapi/v3")
+            .match_header("authorization", "Bearer s3cret")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_discovery_json().to_string())
+            .create_async()
+            .await;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let kubeconfig = tmp.path().join("config");
+        std::fs::write(
+            &kubeconfig,
+            format!(
+                "current-context: mock\nclusters:\n  - name: mock\n    cluster:\n      server: {}\n\
+                 contexts:\n  - name: mock\n    context: {{ cluster: mock, user: bot }}\n\
+                 users:\n  - name: bot\n    user: {{ token: s3cret }}\n",
+                server.url()
+            ),
+        )
+        .unwrap();
+
+        let client = OpenApiClient::new(FetchOptions {
+            source: OpenApiSource::Kubeconfig {
+                path: Some(kubeconfig),
+                context: None,
+            },
+            cache_dir: tmp.path().join("cache"),
+            context: FetchContext::default(),
+        })
+        .unwrap();
+
+        let index = client.discover().await.unwrap();
+        assert_eq!(index.paths.len(), 2);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_openapi_v2() {
+        let mut server = mockito::Server::new_async().await;
+        let _v3 = server
+            .mock("GET", "/openapi/v3")
+            .with_status(404)
+            .create_async()
+            .await;
+        let swagger = serde_json::json!({
+            "swagger": "2.0",
+            "definitions": {
+                "io.k8s.api.core.v1.ConfigMap": {
+                    "type": "object",
+                    "x-kubernetes-group-version-kind": [
+                        { "group": "", "kind": "ConfigMap", "version": "v1" }
+                    ]
+                }
+            }
+        });
+        let v2 = server
+            .mock("GET", "/openapi/v2")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(swagger.to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let options = |offline| FetchOptions {
+            source: OpenApiSource::Url {
+                base_url: server.url(),
+            },
+            cache_dir: tmp.path().to_path_buf(),
+            context: FetchContext {
+                offline,
+                ..Default::default()
+            },
+        };
+
+        let specs = OpenApiClient::new(options(false))
+            .unwrap()
+            .fetch_all_specs()
+            .await
+            .unwrap();
+        assert!(
+            specs["api/v1"]["components"]["schemas"]
+                .get("io.k8s.api.core.v1.ConfigMap")
+                .is_some()
+        );
+
+        // Offline runs convert the cached v2 document
+        let specs = OpenApiClient::new(options(true))
+            .unwrap()
+            .fetch_all_specs()
+            .await
+            .unwrap();
+        assert!(specs.contains_key("api/v1"));
+        v2.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn merges_group_versions_missing_from_v3() {
+        let mut server = mockito::Server::new_async().await;
+        let _discovery = server
+            .mock("GET", "/openapi/v3")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "paths": {
+                        "apis/apps/v1": {
+                            "serverRelativeURL": "/openapi/v3/apis/apps/v1?hash=AAA"
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let v3_apps = serde_json::json!({
+            "openapi": "3.0.0",
+            "components": { "schemas": { "io.k8s.api.apps.v1.Deployment": { "type": "object" } } }
+        });
+        let _apps = server
+            .mock("GET", "/openapi/v3/apis/apps/v1")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(v3_apps.to_string())
+            .create_async()
+            .await;
+        // v2 also serves apps/v1 (ignored in favour of v3) and metrics.k8s.io,
+        // which is missing from v3
+        let swagger = serde_json::json!({
+            "swagger": "2.0",
+            "definitions": {
+                "io.k8s.api.apps.v1.Deployment": {
+                    "type": "object",
+                    "description": "from v2",
+                    "x-kubernetes-group-version-kind": [
+                        { "group": "apps", "kind": "Deployment", "version": "v1" }
+                    ]
+                },
+                "io.k8s.metrics.pkg.apis.metrics.v1beta1.NodeMetrics": {
+                    "type": "object",
+                    "x-kubernetes-group-version-kind": [
+                        { "group": "metrics.k8s.io", "kind": "NodeMetrics", "version": "v1beta1" }
+                    ]
+                }
+            }
+        });
+        mock_served_groups(&mut server, &["apps/v1", "metrics.k8s.io/v1beta1"]).await;
+        let v2 = server
+            .mock("GET", "/openapi/v2")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(swagger.to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let options = |offline| FetchOptions {
+            source: OpenApiSource::Url {
+                base_url: server.url(),
+            },
+            cache_dir: tmp.path().to_path_buf(),
+            context: FetchContext {
+                offline,
+                ..Default::default()
+            },
+        };
+
+        for offline in [false, true] {
+            let specs = OpenApiClient::new(options(offline))
+                .unwrap()
+                .fetch_all_specs()
+                .await
+                .unwrap();
+            assert_eq!(specs.len(), 2, "offline: {offline}");
+            assert_eq!(specs["apis/apps/v1"], v3_apps);
+            assert!(
+                specs["apis/metrics.k8s.io/v1beta1"]["components"]["schemas"]
+                    .get("io.k8s.metrics.pkg.apis.metrics.v1beta1.NodeMetrics")
+                    .is_some()
+            );
+        }
+        v2.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn skips_v2_when_v3_covers_every_group_version() {
+        let mut server = mockito::Server::new_async().await;
+        let _discovery = server
+            .mock("GET", "/openapi/v3")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "paths": {
+                        "apis/apps/v1": {
+                            "serverRelativeURL": "/openapi/v3/apis/apps/v1?hash=AAA"
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let _apps = server
+            .mock("GET", "/openapi/v3/apis/apps/v1")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_spec_json().to_string())
+            .create_async()
+            .await;
+        mock_served_groups(&mut server, &["apps/v1"]).await;
+        let v2 = server
+            .mock("GET", "/openapi/v2")
+            .with_status(200)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let specs = OpenApiClient::new(FetchOptions {
+            source: OpenApiSource::Url {
+                base_url: server.url(),
+            },
+            cache_dir: tmp.path().to_path_buf(),
+            context: FetchContext::default(),
+        })
+        .unwrap()
+        .fetch_all_specs()
+        .await
+        .unwrap();
+        assert_eq!(specs.len(), 1);
+        assert!(specs.contains_key("apis/apps/v1"));
+        v2.assert_async().await;
+    }
+
     #[tokio::test]
     async fn fetch_spec_from_server() {
         let mut server = mockito::Server::new_async().await;
@@ -382,7 +765,7 @@ mod tests {
                 base_url: server.url(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: false,
+            context: FetchContext::default(),
         })
         .unwrap();
 
@@ -420,7 +803,7 @@ mod tests {
                 base_url: server.url(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: false,
+            context: FetchContext::default(),
         })
         .unwrap();
 
@@ -477,7 +860,7 @@ mod tests {
                 base_url: server.url(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: false,
+            context: FetchContext::default(),
         })
         .unwrap();
 
@@ -540,7 +923,10 @@ mod tests {
                 base_url: "https://localhost:6443".to_string(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: true,
+            context: FetchContext {
+                offline: true,
+                ..Default::default()
+            },
         })
         .unwrap();
 
@@ -559,12 +945,17 @@ mod tests {
                 base_url: "https://localhost:6443".to_string(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: true,
+            context: FetchContext {
+                offline: true,
+                ..Default::default()
+            },
         })
         .unwrap();
 
         let err = client.discover().await.unwrap_err();
-        assert!(matches!(err, OpenApiError::NoCachedData));
+        assert!(
+            matches!(&err, OpenApiError::NoCachedData(path) if path.ends_with("discovery.json"))
+        );
     }
 
     #[tokio::test]
@@ -597,7 +988,7 @@ mod tests {
                 base_url: server.url(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: false,
+            context: FetchContext::default(),
         })
         .unwrap();
 
@@ -634,7 +1025,7 @@ mod tests {
                 base_url: server.url(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: false,
+            context: FetchContext::default(),
         })
         .unwrap();
 
@@ -657,7 +1048,10 @@ mod tests {
         let client = OpenApiClient::new(FetchOptions {
             source: OpenApiSource::Directory(dir.to_path_buf()),
             cache_dir: cache_tmp.path().to_path_buf(),
-            offline: true,
+            context: FetchContext {
+                offline: true,
+                ..Default::default()
+            },
         })
         .unwrap();
 
@@ -704,7 +1098,7 @@ mod tests {
                 base_url: server.url(),
             },
             cache_dir: tmp.path().to_path_buf(),
-            offline: false,
+            context: FetchContext::default(),
         })
         .unwrap();
 
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::path::Path;
 
+use husako_config::FetchContext;
 use serde_json::Value;
 
 use crate::OpenApiError;
@@ -18,10 +19,11 @@ pub async fn fetch_release_specs(
     version: &str,
     cache_dir: &Path,
     on_progress: Option<&ProgressCb>,
+    ctx: &FetchContext,
 ) -> Result<HashMap<String, Value>, OpenApiError> {
     let base = std::env::var("HUSAKO_GITHUB_API_URL")
         .unwrap_or_else(|_| "https://api.github.com".to_string());
-    fetch_release_specs_from(version, cache_dir, on_progress, &base).await
+    fetch_release_specs_from(version, cache_dir, on_progress, &base, ctx).await
 }
 
 async fn fetch_release_specs_from(
@@ -29,6 +31,7 @@ async fn fetch_release_specs_from(
     cache_dir: &Path,
     on_progress: Option<&ProgressCb>,
     api_base: &str,
+    ctx: &FetchContext,
 ) -> Result<HashMap<String, Value>, OpenApiError> {
     let tag = version_to_tag(version);
     let tag_cache = cache_dir.join(format!("release/{tag}"));
@@ -39,12 +42,12 @@ async fn fetch_release_specs_from(
     }
 
     // List spec files from the GitHub API
-    let client = build_http_client()?;
+    let client = build_http_client(ctx)?;
     let contents_url =
         format!("{api_base}/repos/kubernetes/kubernetes/contents/api/openapi-spec/v3?ref={tag}");
 
     let resp = client
-        .get(&contents_url)
+        .get(ctx.rewrite_url(&contents_url))
         .header("User-Agent", "husako")
         .header("Accept", "application/vnd.github.v3+json")
         .send()
@@ -95,7 +98,7 @@ async fn fetch_release_specs_from(
             .ok_or_else(|| release_err(format!("no download_url for {}", entry.name)))?;
 
         let spec_resp = client
-            .get(download_url)
+            .get(ctx.rewrite_url(download_url))
             .header("User-Agent", "husako")
             .send()
             .await
@@ -218,8 +221,8 @@ fn load_cached_specs(tag_cache: &Path) -> Result<HashMap<String, Value>, OpenApi
     Ok(specs)
 }
 
-fn build_http_client() -> Result<reqwest::Client, OpenApiError> {
-    reqwest::Client::builder()
+fn build_http_client(ctx: &FetchContext) -> Result<reqwest::Client, OpenApiError> {
+    ctx.client_builder()
         .timeout(std::time::Duration::from_secs(60))
         .build()
         .map_err(|e| release_err(format!("build HTTP client: {e}")))
@@ -337,9 +340,15 @@ mod tests {
             .await;
 
         let tmp = tempfile::tempdir().unwrap();
-        let result = fetch_release_specs_from("1.35", tmp.path(), None, &server.url())
-            .await
-            .unwrap();
+        let result = fetch_release_specs_from(
+            "1.35",
+            tmp.path(),
+            None,
+            &server.url(),
+            &FetchContext::default(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(result.len(), 1);
         assert!(result.contains_key("apis/apps/v1"));
@@ -363,7 +372,14 @@ mod tests {
             .await;
 
         let tmp = tempfile::tempdir().unwrap();
-        let result = fetch_release_specs_from("1.35", tmp.path(), None, &server.url()).await;
+        let result = fetch_release_specs_from(
+            "1.35",
+            tmp.path(),
+            None,
+            &server.url(),
+            &FetchContext::default(),
+        )
+        .await;
 
         assert!(result.is_err());
         let msg = result.unwrap_err().to_string();
@@ -393,7 +409,9 @@ mod tests {
         .unwrap();
 
         // This should hit cache and NOT make any network requests
-        let result = fetch_release_specs("1.35", cache_dir, None).await.unwrap();
+        let result = fetch_release_specs("1.35", cache_dir, None, &FetchContext::default())
+            .await
+            .unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result["api/v1"]["info"]["title"], "cached");
     }
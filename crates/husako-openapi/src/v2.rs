@@ -0,0 +1,214 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde_json::{Map, Value};
+
+/// Returns `true` if `spec` is an OpenAPI v2 (swagger) document.
+pub fn is_v2(spec: &Value) -> bool {
+    spec.get("swagger").is_some()
+        || (spec.get("definitions").is_some() && spec.get("components").is_none())
+}
+
+/// Convert an OpenAPI v2 document into discovery-keyed v3 specs.
+///
+/// Every definition carrying `x-kubernetes-group-version-kind` is placed in
+/// the spec of each group-version it lists (`api/v1`, `apis/apps/v1`), along
+/// with the definitions it references transitively — the same shape the
+/// `/openapi/v3/<group-version>` endpoints return. `$ref`s are rewritten from
+/// `#/definitions/` to `#/components/schemas/`.
+pub fn convert(swagger: &Value) -> HashMap<String, Value> {
+    let Some(definitions) = swagger.get("definitions").and_then(Value::as_object) else {
+        return HashMap::new();
+    };
+
+    let mut roots: HashMap<String, BTreeSet<&str>> = HashMap::new();
+    for (name, schema) in definitions {
+        let Some(gvks) = schema
+            .get("x-kubernetes-group-version-kind")
+            .and_then(Value::as_array)
+        else {
+            continue;
+        };
+        for gvk in gvks {
+            let group = gvk["group"].as_str().unwrap_or("");
+            let Some(version) = gvk["version"].as_str().filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            let key = if group.is_empty() {
+                format!("api/{version}")
+            } else {
+                format!("apis/{group}/{version}")
+            };
+            roots.entry(key).or_default().insert(name.as_str());
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|(key, names)| {
+            let mut schemas = Map::new();
+            let mut pending: Vec<&str> = names.into_iter().collect();
+            while let Some(name) = pending.pop() {
+                if schemas.contains_key(name) {
+                    continue;
+                }
+                let Some(schema) = definitions.get(name) else {
+                    continue;
+                };
+                let mut refs = Vec::new();
+                collect_refs(schema, &mut refs);
+                pending.extend(refs);
+                schemas.insert(name.to_string(), rewrite_refs(schema));
+            }
+            (
+                key,
+                serde_json::json!({ "components": { "schemas": schemas } }),
+            )
+        })
+        .collect()
+}
+
+/// Replace v2 documents in a spec map with their converted group-versions.
+/// Specs already present in v3 form take precedence.
+pub fn expand(specs: HashMap<String, Value>) -> HashMap<String, Value> {
+    let (v2, mut v3): (HashMap<_, _>, HashMap<_, _>) =
+        specs.into_iter().partition(|(_, spec)| is_v2(spec));
+    for spec in v2.values() {
+        for (key, converted) in convert(spec) {
+            v3.entry(key).or_insert(converted);
+        }
+    }
+    v3
+}
+
+fn collect_refs<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(name) = map
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|r| r.strip_prefix("#/definitions/"))
+            {
+                out.push(name);
+            }
+            map.values().for_each(|v| collect_refs(v, out));
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_refs(v, out)),
+        _ => {}
+    }
+}
+
+fn rewrite_refs(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let v = match (k.as_str(), v.as_str()) {
+                        ("$ref", Some(r)) => match r.strip_prefix("#/definitions/") {
+                            Some(name) => Value::String(format!("#/components/schemas/{name}")),
+                            None => v.clone(),
+                        },
+                        _ => rewrite_refs(v),
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(rewrite_refs).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn swagger() -> Value {
+        json!({
+            "swagger": "2.0",
+            "info": { "title": "Kubernetes", "version": "v1.16.0" },
+            "definitions": {
+                "io.k8s.api.apps.v1.Deployment": {
+                    "type": "object",
+                    "properties": {
+                        "metadata": { "$ref": "#/definitions/io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta" },
+                        "spec": { "$ref": "#/definitions/io.k8s.api.apps.v1.DeploymentSpec" }
+                    },
+                    "x-kubernetes-group-version-kind": [
+                        { "group": "apps", "kind": "Deployment", "version": "v1" }
+                    ]
+                },
+                "io.k8s.api.apps.v1.DeploymentSpec": {
+                    "type": "object",
+                    "properties": { "replicas": { "type": "integer" } }
+                },
+                "io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta": {
+                    "type": "object",
+                    "properties": { "name": { "type": "string" } }
+                },
+                "io.k8s.api.core.v1.ConfigMap": {
+                    "type": "object",
+                    "properties": {
+                        "metadata": { "$ref": "#/definitions/io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta" }
+                    },
+                    "x-kubernetes-group-version-kind": [
+                        { "group": "", "kind": "ConfigMap", "version": "v1" }
+                    ]
+                },
+                "io.k8s.metrics.pkg.apis.metrics.v1beta1.PodMetrics": {
+                    "type": "object",
+                    "x-kubernetes-group-version-kind": [
+                        { "group": "metrics.k8s.io", "kind": "PodMetrics", "version": "v1beta1" }
+                    ]
+                },
+                "io.k8s.api.core.v1.Unreferenced": { "type": "object" }
+            }
+        })
+    }
+
+    #[test]
+    fn groups_definitions_by_gvk_with_references() {
+        let specs = convert(&swagger());
+        let mut keys: Vec<&String> = specs.keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            ["api/v1", "apis/apps/v1", "apis/metrics.k8s.io/v1beta1"]
+        );
+
+        let apps = specs["apis/apps/v1"]["components"]["schemas"]
+            .as_object()
+            .unwrap();
+        assert_eq!(apps.len(), 3);
+        assert_eq!(
+            apps["io.k8s.api.apps.v1.Deployment"]["properties"]["spec"]["$ref"],
+            "#/components/schemas/io.k8s.api.apps.v1.DeploymentSpec"
+        );
+        assert!(apps.contains_key("io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta"));
+
+        let core = specs["api/v1"]["components"]["schemas"]
+            .as_object()
+            .unwrap();
+        assert!(core.contains_key("io.k8s.api.core.v1.ConfigMap"));
+        assert!(!core.contains_key("io.k8s.api.core.v1.Unreferenced"));
+    }
+
+    #[test]
+    fn expand_keeps_v3_specs() {
+        let v3 = json!({ "components": { "schemas": { "v3": {} } } });
+        let specs = expand(HashMap::from([
+            ("swagger".to_string(), swagger()),
+            ("apis/apps/v1".to_string(), v3.clone()),
+        ]));
+        assert!(!specs.contains_key("swagger"));
+        assert_eq!(specs["apis/apps/v1"], v3);
+        assert!(specs.contains_key("api/v1"));
+    }
+
+    #[test]
+    fn detects_v2_documents() {
+        assert!(is_v2(&swagger()));
+        assert!(is_v2(&json!({ "definitions": {} })));
+        assert!(!is_v2(&json!({ "openapi": "3.0.0", "components": {} })));
+    }
+}
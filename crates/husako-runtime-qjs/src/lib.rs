@@ -1,16 +1,20 @@
 mod loader;
+mod module_cache;
 mod resolver;
 
 use std::cell::{Cell, RefCell};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use rquickjs::loader::{BuiltinLoader, BuiltinResolver};
-use rquickjs::{Context, Ctx, Error, Function, Module, Promise, Runtime, Value};
+use rquickjs::{Context, Ctx, Error, Exception, Function, Module, Promise, Runtime, Value};
 
+use husako_compile_oxc::CompileCache;
 use loader::HusakoFileLoader;
+pub use module_cache::ModuleCache;
 use resolver::{HusakoFileResolver, HusakoK8sResolver, PluginResolver};
 
 #[derive(Debug, thiserror::Error)]
@@ -41,6 +45,49 @@ pub struct ExecuteOptions {
     pub generated_types_dir: Option<PathBuf>,
     /// Plugin module mappings: import specifier → absolute `.js` path.
     pub plugin_modules: std::collections::HashMap<String, PathBuf>,
+    /// Backs `helmTemplate()`; scripts calling it fail when `None`.
+    pub chart_renderer: Option<ChartRenderer>,
+    /// Directory of compiled `.ts` modules kept between runs (see
+    /// [`husako_compile_oxc::CompileCache`]); modules are compiled afresh
+    /// when `None`.
+    pub compile_cache_dir: Option<PathBuf>,
+    /// File modules loaded by earlier executions (see [`ModuleCache`]);
+    /// every module is loaded from its file when `None`.
+    pub module_cache: Option<ModuleCache>,
+    /// Records the files the execution reads, when set.
+    pub loaded_files: Option<LoadedFiles>,
+}
+
+/// Renders a configured chart: `(chart name, values, options)` → array of
+/// Kubernetes objects, or an error message thrown into the script.
+pub type ChartRenderer = Arc<
+    dyn Fn(&str, serde_json::Value, serde_json::Value) -> Result<serde_json::Value, String>
+        + Send
+        + Sync,
+>;
+
+/// Files an execution read: every file module it loaded, and whatever host
+/// functions such as the [`ChartRenderer`] record. `husako render --watch`
+/// re-renders only when one of these changes.
+#[derive(Debug, Clone, Default)]
+pub struct LoadedFiles(Arc<Mutex<BTreeSet<PathBuf>>>);
+
+impl LoadedFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, path: impl Into<PathBuf>) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.into());
+    }
+
+    /// The files recorded so far, leaving none.
+    pub fn take(&self) -> BTreeSet<PathBuf> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
 }
 
 /// Same fields as `ExecuteOptions`; separate name for clarity in the test runner path.
@@ -73,6 +120,65 @@ fn execution_error(ctx: &Ctx<'_>, err: Error) -> RuntimeError {
     RuntimeError::Execution(err.to_string())
 }
 
+/// Install `__husako_helm_template(chart, valuesJson, optionsJson)`, the host
+/// side of the SDK's `helmTemplate()`. Values and options cross as JSON text;
+/// the rendered documents come back as JS values.
+fn install_chart_renderer<'js>(
+    ctx: &Ctx<'js>,
+    options: &ExecuteOptions,
+) -> Result<(), RuntimeError> {
+    let renderer = options.chart_renderer.clone();
+    let render_fn = Function::new(
+        ctx.clone(),
+        move |ctx: Ctx<'js>, chart: String, values: String, opts: String| {
+            let Some(renderer) = &renderer else {
+                return Err(Exception::throw_message(
+                    &ctx,
+                    "helmTemplate() is not available in this context",
+                ));
+            };
+            let parse = |s: &str| serde_json::from_str(s).unwrap_or(serde_json::Value::Null);
+            match renderer(&chart, parse(&values), parse(&opts)) {
+                Ok(docs) => json_to_js(&ctx, &docs),
+                Err(msg) => Err(Exception::throw_message(&ctx, &msg)),
+            }
+        },
+    )
+    .map_err(|e| RuntimeError::Init(e.to_string()))?;
+    ctx.globals()
+        .set("__husako_helm_template", render_fn)
+        .map_err(|e| RuntimeError::Init(e.to_string()))
+}
+
+/// Build a JS value from rendered chart output. `JSON.parse` would turn every
+/// number into a float, so integers are created as JS ints and render back as
+/// `8080` rather than `8080.0`.
+fn json_to_js<'js>(ctx: &Ctx<'js>, value: &serde_json::Value) -> rquickjs::Result<Value<'js>> {
+    Ok(match value {
+        serde_json::Value::Null => Value::new_null(ctx.clone()),
+        serde_json::Value::Bool(b) => Value::new_bool(ctx.clone(), *b),
+        serde_json::Value::Number(n) => match n.as_i64().and_then(|i| i32::try_from(i).ok()) {
+            Some(i) => Value::new_int(ctx.clone(), i),
+            None => Value::new_float(ctx.clone(), n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => rquickjs::String::from_str(ctx.clone(), s)?.into_value(),
+        serde_json::Value::Array(items) => {
+            let array = rquickjs::Array::new(ctx.clone())?;
+            for (i, item) in items.iter().enumerate() {
+                array.set(i, json_to_js(ctx, item)?)?;
+            }
+            array.into_value()
+        }
+        serde_json::Value::Object(map) => {
+            let object = rquickjs::Object::new(ctx.clone())?;
+            for (key, item) in map {
+                object.set(key.as_str(), json_to_js(ctx, item)?)?;
+            }
+            object.into_value()
+        }
+    })
+}
+
 /// Synchronous QuickJS execution — runs on a blocking thread via [`execute`].
 fn execute_sync(
     js_source: &str,
@@ -116,7 +222,11 @@ fn execute_sync(
         BuiltinLoader::default()
             .with_module("husako", husako_sdk::HUSAKO_MODULE)
             .with_module("husako/_base", husako_sdk::HUSAKO_BASE),
-        HusakoFileLoader::new(),
+        HusakoFileLoader::new(
+            options.compile_cache_dir.clone().map(CompileCache::new),
+            options.module_cache.clone(),
+            options.loaded_files.clone(),
+        ),
     );
     rt.set_loader(resolver, loader);
 
@@ -150,6 +260,7 @@ fn execute_sync(
         ctx.globals()
             .set("__husako_build", build_fn)
             .map_err(|e| RuntimeError::Init(e.to_string()))?;
+        install_chart_renderer(&ctx, options)?;
 
         let promise = Module::evaluate(ctx.clone(), "main", js_source)
             .map_err(|e| execution_error(&ctx, e))?;
@@ -236,11 +347,16 @@ fn execute_tests_sync(
             .with_module("husako", husako_sdk::HUSAKO_MODULE)
             .with_module("husako/_base", husako_sdk::HUSAKO_BASE)
             .with_module("husako/test", husako_sdk::HUSAKO_TEST_MODULE),
-        HusakoFileLoader::new(),
+        HusakoFileLoader::new(
+            options.compile_cache_dir.clone().map(CompileCache::new),
+            options.module_cache.clone(),
+            options.loaded_files.clone(),
+        ),
     );
     rt.set_loader(resolver, loader);
 
     let json_str: String = ctx.with(|ctx| {
+        install_chart_renderer(&ctx, options)?;
         let promise = Module::evaluate(ctx.clone(), "main", js_source)
             .map_err(|e| execution_error(&ctx, e))?;
         promise
@@ -409,6 +525,10 @@ mod tests {
             max_heap_mb: None,
             generated_types_dir: None,
             plugin_modules: std::collections::HashMap::new(),
+            chart_renderer: None,
+            compile_cache_dir: None,
+            module_cache: None,
+            loaded_files: None,
         }
     }
 
@@ -423,6 +543,48 @@ mod tests {
         assert_eq!(result[0]["kind"], "Namespace");
     }
 
+    #[tokio::test]
+    async fn helm_template_renders_through_host() {
+        let mut opts = test_options();
+        opts.chart_renderer = Some(Arc::new(|chart, values, options| {
+            if chart != "web" {
+                return Err(format!("chart '{chart}' is not configured"));
+            }
+            Ok(serde_json::json!([{
+                "apiVersion": "v1",
+                "kind": "Service",
+                "metadata": {
+                    "name": options["releaseName"],
+                    "namespace": "default"
+                },
+                "spec": { "ports": [{ "port": values["port"] }], "ratio": 0.5 }
+            }]))
+        }));
+        let js = r#"
+            import { build, helmTemplate } from "husako";
+            const values = { _toJSON() { return { port: 8080 }; } };
+            const docs = helmTemplate("web", values, { releaseName: "prod" });
+            docs[0].metadata.labels = { tier: "web" };
+            build(docs);
+        "#;
+        let result = execute(js, &opts).await.unwrap();
+        assert_eq!(result[0]["metadata"]["name"], "prod");
+        assert_eq!(result[0]["metadata"]["labels"]["tier"], "web");
+        assert_eq!(result[0]["spec"]["ports"][0]["port"], 8080);
+        assert!(result[0]["spec"]["ports"][0]["port"].is_i64());
+        assert_eq!(result[0]["spec"]["ratio"], 0.5);
+
+        let js = r#"
+            import { build, helmTemplate } from "husako";
+            build(helmTemplate("db", {}));
+        "#;
+        let err = execute(js, &opts).await.unwrap_err();
+        assert!(err.to_string().contains("chart 'db' is not configured"));
+
+        let err = execute(js, &test_options()).await.unwrap_err();
+        assert!(err.to_string().contains("helmTemplate() is not available"));
+    }
+
     #[tokio::test]
     async fn no_build_call() {
         let js = r#"
@@ -552,6 +714,10 @@ export class ConfigMap extends _ResourceBuilder {
             max_heap_mb: None,
             generated_types_dir: Some(dir.path().to_path_buf()),
             plugin_modules: std::collections::HashMap::new(),
+            chart_renderer: None,
+            compile_cache_dir: None,
+            module_cache: None,
+            loaded_files: None,
         };
         (dir, opts)
     }
@@ -833,6 +999,10 @@ export function values() { return new Values(); }
             max_heap_mb: None,
             generated_types_dir: Some(dir.path().to_path_buf()),
             plugin_modules: std::collections::HashMap::new(),
+            chart_renderer: None,
+            compile_cache_dir: None,
+            module_cache: None,
+            loaded_files: None,
         };
 
         let js = r#"
@@ -857,6 +1027,10 @@ export function values() { return new Values(); }
             max_heap_mb: None,
             generated_types_dir: None,
             plugin_modules: std::collections::HashMap::new(),
+            chart_renderer: None,
+            compile_cache_dir: None,
+            module_cache: None,
+            loaded_files: None,
         }
     }
 
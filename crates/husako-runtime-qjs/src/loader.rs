@@ -1,17 +1,31 @@
 use std::collections::HashMap;
 use std::path::Path;
 
+use husako_compile_oxc::CompileCache;
 use rquickjs::module::Declared;
-use rquickjs::{Ctx, Error, Module, Result};
+use rquickjs::{Ctx, Error, Module, Result, WriteOptions};
+
+use crate::LoadedFiles;
+use crate::module_cache::{self, ModuleCache};
 
 pub struct HusakoFileLoader {
     cache: HashMap<String, Vec<u8>>,
+    compile_cache: Option<CompileCache>,
+    module_cache: Option<ModuleCache>,
+    loaded_files: Option<LoadedFiles>,
 }
 
 impl HusakoFileLoader {
-    pub fn new() -> Self {
+    pub fn new(
+        compile_cache: Option<CompileCache>,
+        module_cache: Option<ModuleCache>,
+        loaded_files: Option<LoadedFiles>,
+    ) -> Self {
         Self {
             cache: HashMap::new(),
+            compile_cache,
+            module_cache,
+            loaded_files,
         }
     }
 }
@@ -24,6 +38,22 @@ impl rquickjs::loader::Loader for HusakoFileLoader {
         }
 
         let path = Path::new(name);
+        if let Some(loaded) = &self.loaded_files {
+            loaded.record(path);
+        }
+
+        // Reuse the bytecode of an earlier execution while the file is unchanged
+        let stamp = self
+            .module_cache
+            .as_ref()
+            .and_then(|_| module_cache::stamp(path));
+        if let (Some(modules), Some(stamp)) = (&self.module_cache, stamp)
+            && let Some(bytecode) = modules.get(name, stamp)
+        {
+            // SAFETY: the bytecode was written by `Module::write` in this
+            // process, i.e. by the same QuickJS build that reads it.
+            return unsafe { Module::load(ctx.clone(), &bytecode) };
+        }
 
         // Read the file
         let source = std::fs::read_to_string(path)
@@ -31,15 +61,24 @@ impl rquickjs::loader::Loader for HusakoFileLoader {
 
         // Compile TypeScript if needed
         let js = if matches!(path.extension().and_then(|e| e.to_str()), Some("ts")) {
-            husako_compile_oxc::compile(&source, name)
-                .map_err(|e| Error::new_loading_message(name, e.to_string()))?
+            match &self.compile_cache {
+                Some(cache) => cache.compile(&source, name),
+                None => husako_compile_oxc::compile(&source, name),
+            }
+            .map_err(|e| Error::new_loading_message(name, e.to_string()))?
         } else {
             source
         };
 
         let bytes = js.into_bytes();
         self.cache.insert(name.to_string(), bytes.clone());
-        Module::declare(ctx.clone(), name, bytes)
+        let module = Module::declare(ctx.clone(), name, bytes)?;
+        if let (Some(modules), Some(stamp)) = (&self.module_cache, stamp)
+            && let Ok(bytecode) = module.write(WriteOptions::default())
+        {
+            modules.insert(name, stamp, bytecode);
+        }
+        Ok(module)
     }
 }
 
@@ -54,7 +93,7 @@ mod tests {
         let ts_path = dir.path().join("helper.ts");
         std::fs::write(&ts_path, "export const x: number = 42;").unwrap();
 
-        let mut loader = HusakoFileLoader::new();
+        let mut loader = HusakoFileLoader::new(None, None, None);
         let rt = rquickjs::Runtime::new().unwrap();
         let ctx = rquickjs::Context::full(&rt).unwrap();
         ctx.with(|ctx| {
@@ -70,7 +109,7 @@ mod tests {
         let js_path = dir.path().join("helper.js");
         std::fs::write(&js_path, "export const x = 42;").unwrap();
 
-        let mut loader = HusakoFileLoader::new();
+        let mut loader = HusakoFileLoader::new(None, None, None);
         let rt = rquickjs::Runtime::new().unwrap();
         let ctx = rquickjs::Context::full(&rt).unwrap();
         ctx.with(|ctx| {
@@ -86,7 +125,7 @@ mod tests {
         let ts_path = dir.path().join("cached.ts");
         std::fs::write(&ts_path, "export const x: number = 1;").unwrap();
 
-        let mut loader = HusakoFileLoader::new();
+        let mut loader = HusakoFileLoader::new(None, None, None);
         let name = ts_path.to_str().unwrap();
 
         let rt = rquickjs::Runtime::new().unwrap();
@@ -97,4 +136,75 @@ mod tests {
 
         assert!(loader.cache.contains_key(name));
     }
+
+    #[test]
+    fn compiles_through_compile_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let ts_path = dir.path().join("helper.ts");
+        std::fs::write(&ts_path, "export const x: number = 42;").unwrap();
+        let cache_dir = dir.path().join("compile");
+
+        let mut loader = HusakoFileLoader::new(Some(CompileCache::new(&cache_dir)), None, None);
+        let rt = rquickjs::Runtime::new().unwrap();
+        let ctx = rquickjs::Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            assert!(loader.load(&ctx, ts_path.to_str().unwrap()).is_ok());
+        });
+
+        assert!(cache_dir.exists());
+    }
+
+    #[test]
+    fn reuses_module_bytecode_until_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let ts_path = dir.path().join("shared.ts");
+        std::fs::write(&ts_path, "export const x: number = 1;").unwrap();
+        let name = ts_path.to_str().unwrap();
+        let modules = ModuleCache::new();
+
+        let eval = |modules: &ModuleCache| -> i32 {
+            let mut loader = HusakoFileLoader::new(None, Some(modules.clone()), None);
+            let rt = rquickjs::Runtime::new().unwrap();
+            let ctx = rquickjs::Context::full(&rt).unwrap();
+            ctx.with(|ctx| {
+                let (module, promise) = loader.load(&ctx, name).unwrap().eval().unwrap();
+                promise.finish::<()>().unwrap();
+                module.get("x").unwrap()
+            })
+        };
+
+        assert_eq!(eval(&modules), 1);
+        assert_eq!(modules.len(), 1);
+        // A fresh runtime reads the kept bytecode
+        assert_eq!(eval(&modules), 1);
+
+        // A changed file is not served from the cache
+        std::fs::write(&ts_path, "export const x: number = 22;").unwrap();
+        assert_eq!(eval(&modules), 22);
+
+        modules.invalidate(&ts_path);
+        assert!(modules.is_empty());
+    }
+
+    #[test]
+    fn records_loaded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let ts_path = dir.path().join("helper.ts");
+        std::fs::write(&ts_path, "export const x: number = 1;").unwrap();
+        let name = ts_path.to_str().unwrap();
+        let loaded = LoadedFiles::new();
+        let modules = ModuleCache::new();
+
+        for _ in 0..2 {
+            let mut loader =
+                HusakoFileLoader::new(None, Some(modules.clone()), Some(loaded.clone()));
+            let rt = rquickjs::Runtime::new().unwrap();
+            let ctx = rquickjs::Context::full(&rt).unwrap();
+            ctx.with(|ctx| {
+                assert!(loader.load(&ctx, name).is_ok());
+            });
+            // Recorded whether read from the file or from the module cache
+            assert_eq!(loaded.take(), [ts_path.clone()].into());
+        }
+    }
 }
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// File modules loaded by earlier executions, kept as QuickJS bytecode.
+///
+/// Every execution still gets a fresh runtime, so module state never leaks
+/// between renders, but a module whose file is unchanged is neither read,
+/// compiled nor parsed again. That matters for the generated `k8s/*`
+/// modules, which are large and rarely change.
+///
+/// An entry is dropped by [`ModuleCache::invalidate`] and, for changes the
+/// caller didn't see (e.g. `husako gen` rewriting `.husako/types/`), when
+/// the file's modification time or size no longer match.
+#[derive(Clone, Default)]
+pub struct ModuleCache {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+struct Entry {
+    stamp: Stamp,
+    bytecode: Arc<[u8]>,
+}
+
+/// Modification time and size of a module file when it was loaded.
+pub(crate) type Stamp = (SystemTime, u64);
+
+impl std::fmt::Debug for ModuleCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModuleCache")
+            .field("modules", &self.len())
+            .finish()
+    }
+}
+
+impl ModuleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the module loaded from `path`, e.g. after a watcher saw it
+    /// change.
+    pub fn invalidate(&self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.lock().remove(path.to_string_lossy().as_ref());
+    }
+
+    /// Number of modules kept.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn get(&self, name: &str, stamp: Stamp) -> Option<Arc<[u8]>> {
+        let mut entries = self.lock();
+        match entries.get(name) {
+            Some(entry) if entry.stamp == stamp => Some(entry.bytecode.clone()),
+            Some(_) => {
+                entries.remove(name);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, name: &str, stamp: Stamp, bytecode: Vec<u8>) {
+        self.lock().insert(
+            name.to_string(),
+            Entry {
+                stamp,
+                bytecode: bytecode.into(),
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The [`Stamp`] of the file at `path`.
+pub(crate) fn stamp(path: &Path) -> Option<Stamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
@@ -57,5 +57,39 @@ export function limits(rl: ResourceListFragment): ResourceRequirementsFragment;
 export function merge(items: MetadataFragment[]): MetadataFragment;
 export function merge(items: ResourceListFragment[]): ResourceListFragment;
 
+/** Release settings for `helmTemplate()`, mirroring `helm template` flags. */
+export interface HelmTemplateOptions {
+  /** `.Release.Name`. Defaults to the chart's name in husako.toml. */
+  releaseName?: string;
+  /** `.Release.Namespace`. Defaults to `"default"`. */
+  namespace?: string;
+  /** Also emit the chart's `crds/` files (`--include-crds`). */
+  includeCrds?: boolean;
+  /** `.Capabilities.KubeVersion`. Defaults to the `release` resource version, if any. */
+  kubeVersion?: string;
+  /** Extra `.Capabilities.APIVersions` entries (`--api-versions`). */
+  apiVersions?: string[];
+}
+
+/** A Kubernetes object rendered from a chart template. */
+export interface RenderedObject {
+  apiVersion: string;
+  kind: string;
+  metadata: { name?: string; namespace?: string; [key: string]: any };
+  [key: string]: any;
+  _render(): any;
+}
+
+/**
+ * Render a chart from `[charts]` in husako.toml, like `helm template`.
+ * `values` may be the chart's generated values builder or a plain object.
+ * Runs offline against the chart archive cached by `husako gen`.
+ */
+export function helmTemplate(
+  chart: string,
+  values?: { _toJSON(): any } | Record<string, any>,
+  options?: HelmTemplateOptions,
+): RenderedObject[];
+
 /** Submit resources to husako for rendering. Must be called exactly once. */
 export function build(input: { _render(): any } | { _render(): any }[]): void;
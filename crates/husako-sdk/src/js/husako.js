@@ -175,6 +175,29 @@ export function merge(items) {
   return items[items.length - 1];
 }
 
+// --- helmTemplate ---
+
+const renderedObjectProto = {
+  _render() {
+    return Object.assign({}, this);
+  },
+};
+
+export function helmTemplate(chart, values, options) {
+  let plain = values;
+  if (values && typeof values._toJSON === "function") {
+    plain = values._toJSON();
+  }
+  const docs = __husako_helm_template(
+    chart,
+    JSON.stringify(plain === undefined ? {} : plain),
+    JSON.stringify(options || {})
+  );
+  return docs.map(function(doc) {
+    return Object.assign(Object.create(renderedObjectProto), doc);
+  });
+}
+
 // --- build ---
 
 export function build(input) {